@@ -173,7 +173,7 @@ fn bench_rebuild(c: &mut Criterion) {
     c.bench_function("rebuild_after_article_change", |b| {
         b.iter(|| {
             archival
-                .site
+                .site()
                 .invalidate_file(Path::new("objects/articles/article-0.toml"));
             archival.build(BuildOptions::default()).unwrap()
         })
@@ -55,6 +55,12 @@ export type FieldValue = ({
 } | {
     "Oneof": [string, FieldValue | null];
 } | {
+
+    /**
+     * The values of a list field, see [`FieldType::List`].
+     */
+    "List": FieldValue[];
+} | {
     "Boolean": boolean;
 } | {
     "File": File;
@@ -112,6 +118,45 @@ export type RemoveChildEvent = {
     "path": ValuePath;
     "source": (string | null);
 };
+
+/**
+ * Adds a value to a list field. `path` points at the list, e.g. `tags` or
+ * `links.0.tags`.
+ */
+export type AddListValueEvent = {
+    "object": string;
+    "filename": string;
+    "path": ValuePath;
+    "value": FieldValue;
+
+    /**
+     * If not provided, this will just append to the end of the list.
+     */
+    "index": (Usize | null);
+};
+
+/**
+ * Removes a value from a list field. `path` points at the value, e.g.
+ * `tags.2`.
+ */
+export type RemoveListValueEvent = {
+    "object": string;
+    "filename": string;
+    "path": ValuePath;
+    "source": (string | null);
+};
+
+/**
+ * Moves the value at index `from` of the list at `path` to index `to`.
+ */
+export type MoveListValueEvent = {
+    "object": string;
+    "filename": string;
+    "path": ValuePath;
+    "from": Usize;
+    "to": Usize;
+    "source": (string | null);
+};
 export type ArchivalEvent = ({
     "RenameObject": RenameObjectEvent;
 } | {
@@ -128,4 +173,42 @@ export type ArchivalEvent = ({
     "AddChild": AddChildEvent;
 } | {
     "RemoveChild": RemoveChildEvent;
+} | {
+    "AddListValue": AddListValueEvent;
+} | {
+    "RemoveListValue": RemoveListValueEvent;
+} | {
+    "MoveListValue": MoveListValueEvent;
+});
+
+/**
+ * What a [`CheckedEvent`] expects its object to hold.
+ */
+export type ExpectedVersion = ({
+
+    /**
+     * The version of the whole object, as returned by [`Object::version`].
+     * Any change to the object conflicts.
+     */
+    "Version": string;
+} | {
+
+    /**
+     * The previous value of what the event changes, or `None` if it was
+     * unset: the field for an edit, the list for list events, and the list of
+     * children for child events. Changes elsewhere in the object don't
+     * conflict. Other events can only expect a version.
+     */
+    "Value": (FieldValue | null);
 });
+
+/**
+ * An event that is rejected with a [`ConflictError`] unless its object is
+ * still as the sender last saw it, so that changes made from a stale copy of
+ * an object don't silently overwrite newer ones. See
+ * [`Archival::send_checked_event`].
+ */
+export type CheckedEvent = {
+    "event": ArchivalEvent;
+    "expected": ExpectedVersion;
+};
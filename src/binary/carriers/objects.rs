@@ -4,19 +4,16 @@
 use crate::{
     fields::FieldValue,
     file_system::FileSystemAPI,
+    filters::liquid_to_json,
     object::{to_liquid::ToLiquidOptions, ObjectEntry},
     site::Site,
     Object,
 };
 use anyhow::Result;
-use liquid_core::{
-    model::{Scalar, Value},
-    ValueView,
-};
+use liquid_core::model::Value;
 use serde::Serialize;
-use serde_json::{Map, Number};
+use serde_json::Map;
 use std::collections::BTreeSet;
-use time::{format_description::well_known::Rfc3339, macros::format_description, UtcOffset};
 
 const CARRIER_OPTIONS: ToLiquidOptions = ToLiquidOptions {
     include_secrets: true,
@@ -47,7 +44,7 @@ pub(crate) fn build_payload<F: FileSystemAPI>(
 ) -> Result<CarrierPayload> {
     let mut objects = Map::new();
     let mut uploads = BTreeSet::new();
-    for (name, entry) in site.get_objects_sorted(fs, Some(order_of))? {
+    for (name, entry) in site.get_objects(fs)? {
         let definition = site
             .object_definitions
             .get(&name)
@@ -75,12 +72,6 @@ pub(crate) fn build_payload<F: FileSystemAPI>(
     })
 }
 
-fn order_of(a: &Object, b: &Object) -> std::cmp::Ordering {
-    a.order
-        .partial_cmp(&b.order)
-        .unwrap_or(std::cmp::Ordering::Equal)
-}
-
 /// Walks an object for uploaded files. `UPLOADS.list()` cannot be derived from
 /// the serialized objects: a file and a `meta` table holding `sha`/`filename`
 /// keys are indistinguishable once both are plain JSON.
@@ -112,57 +103,12 @@ fn collect_uploads_in_value(value: &FieldValue, into: &mut BTreeSet<UploadEntry>
                 collect_uploads_in_value(inner, into);
             }
         }
-        _ => {}
-    }
-}
-
-/// Liquid values derive `Serialize`, but a `DateTime` serializes through
-/// liquid's own display format ("2022-12-22 00:00:00 +0000"). Carriers are
-/// typed against ISO 8601 dates, so the conversion is written out by hand.
-pub(crate) fn liquid_to_json(value: &Value) -> serde_json::Value {
-    match value {
-        Value::Nil | Value::State(_) => serde_json::Value::Null,
-        Value::Array(values) => {
-            serde_json::Value::Array(values.iter().map(liquid_to_json).collect())
+        FieldValue::List(items) => {
+            for item in items {
+                collect_uploads_in_value(item, into);
+            }
         }
-        Value::Object(object) => serde_json::Value::Object(
-            object
-                .iter()
-                .map(|(k, v)| (k.to_string(), liquid_to_json(v)))
-                .collect(),
-        ),
-        Value::Scalar(scalar) => scalar_to_json(scalar),
-    }
-}
-
-fn scalar_to_json(scalar: &Scalar) -> serde_json::Value {
-    // Discriminating by type_name is deliberate: to_integer and to_date_time
-    // parse string values, so a "42" field would silently become a number.
-    match scalar.type_name() {
-        "whole number" => scalar
-            .to_integer()
-            .map(|i| serde_json::Value::Number(i.into()))
-            .unwrap_or(serde_json::Value::Null),
-        "fractional number" => scalar
-            .to_float()
-            .and_then(Number::from_f64)
-            .map(serde_json::Value::Number)
-            .unwrap_or(serde_json::Value::Null),
-        "boolean" => scalar
-            .to_bool()
-            .map(serde_json::Value::Bool)
-            .unwrap_or(serde_json::Value::Null),
-        "date time" => scalar
-            .to_date_time()
-            .and_then(|dt| dt.to_offset(UtcOffset::UTC).format(&Rfc3339).ok())
-            .map(serde_json::Value::String)
-            .unwrap_or(serde_json::Value::Null),
-        "date" => scalar
-            .to_date()
-            .and_then(|d| d.format(format_description!("[year]-[month]-[day]")).ok())
-            .map(serde_json::Value::String)
-            .unwrap_or(serde_json::Value::Null),
-        _ => serde_json::Value::String(scalar.to_kstr().into_string()),
+        _ => {}
     }
 }
 
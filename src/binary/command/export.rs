@@ -0,0 +1,521 @@
+use super::BinaryCommand;
+use crate::{
+    binary::{
+        command::{add_args, command_root, CommandConfig},
+        ExitStatus,
+    },
+    fields::ObjectValues,
+    file_system_stdlib,
+    object::{Object, ValuePath},
+    Archival, FieldConfig, FieldType, FieldValue, ObjectDefinition,
+};
+use anyhow::Result;
+use clap::{arg, value_parser, ArgMatches};
+use ordermap::OrderMap;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    sync::{atomic::AtomicBool, Arc},
+};
+use thiserror::Error;
+
+/// The column holding the name of each exported object, which `archival
+/// import --name filename` reads back.
+const FILENAME_COLUMN: &str = "filename";
+/// The columns a file field is flattened to, as `<field>.<key>`.
+const FILE_COLUMNS: [&str; 6] = ["url", "sha", "filename", "mime", "name", "description"];
+
+#[derive(Error, Debug, Clone)]
+pub enum ExportError {
+    #[error("either --format or a file path must be provided")]
+    FormatOrFileRequired,
+    #[error("no file extension or format provided")]
+    NoExtension,
+    #[error("invalid object type '{0}'")]
+    InvalidObjectType(String),
+    #[error("invalid child {0}")]
+    InvalidChild(String),
+    #[error("failed writing export: {0}")]
+    WriteError(String),
+}
+
+#[derive(Debug, Clone)]
+enum ExportFormat {
+    #[cfg(feature = "import-csv")]
+    Csv,
+    Json,
+    Ndjson,
+}
+
+impl From<&str> for ExportFormat {
+    fn from(value: &str) -> Self {
+        match &value.to_lowercase()[..] {
+            #[cfg(feature = "import-csv")]
+            "csv" => Self::Csv,
+            "json" => Self::Json,
+            "ndjson" | "jsonl" => Self::Ndjson,
+            _ => panic!("Unsupported format {}", value),
+        }
+    }
+}
+
+/// A row of cells by column. Structured values (lists, meta and oneofs) are
+/// json values, unset values null, and everything else a string.
+type Row = OrderMap<String, serde_json::Value>;
+
+/// Csv cells are all text, so structured values are written as json.
+#[cfg(feature = "import-csv")]
+fn csv_cell(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.to_string(),
+        serde_json::Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+impl ExportFormat {
+    fn write<W: Write>(
+        &self,
+        // Only csv has a header row.
+        #[cfg_attr(not(feature = "import-csv"), allow(unused_variables))] columns: &[String],
+        rows: &[Row],
+        output: &mut W,
+    ) -> Result<(), ExportError> {
+        let error = |e: &dyn std::fmt::Display| ExportError::WriteError(e.to_string());
+        match self {
+            #[cfg(feature = "import-csv")]
+            ExportFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(output);
+                writer.write_record(columns).map_err(|e| error(&e))?;
+                for row in rows {
+                    writer
+                        .write_record(columns.iter().map(|c| csv_cell(&row[c])))
+                        .map_err(|e| error(&e))?;
+                }
+                writer.flush().map_err(|e| error(&e))?;
+            }
+            ExportFormat::Json => {
+                serde_json::to_writer_pretty(&mut *output, rows).map_err(|e| error(&e))?;
+                writeln!(output).map_err(|e| error(&e))?;
+            }
+            ExportFormat::Ndjson => {
+                for row in rows {
+                    serde_json::to_writer(&mut *output, row).map_err(|e| error(&e))?;
+                    writeln!(output).map_err(|e| error(&e))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Options for flattening objects into rows.
+#[derive(Debug, Clone, Copy, Default)]
+struct ExportOptions {
+    /// Like `ToLiquidOptions::include_secrets`, secret fields are left out
+    /// unless this is set.
+    include_secrets: bool,
+}
+
+fn is_exported(field_type: &FieldType, options: ExportOptions) -> bool {
+    options.include_secrets || !field_type.is_secret()
+}
+
+/// The columns of an export of objects with the given definition. Files take
+/// a column per key, and other fields a single column.
+fn columns(definition: &ObjectDefinition, options: ExportOptions) -> Vec<String> {
+    let mut columns = vec![FILENAME_COLUMN.to_string()];
+    for (name, field) in &definition.fields {
+        if !is_exported(&field.r#type, options) {
+            continue;
+        }
+        if field.r#type.base_type().is_uploadable() {
+            columns.extend(FILE_COLUMNS.iter().map(|key| format!("{name}.{key}")));
+        } else {
+            columns.push(name.to_string());
+        }
+    }
+    columns
+}
+
+/// Converts toml to json for structured cells, which `archival import` reads
+/// back with the field's type.
+fn toml_to_json(value: toml::Value) -> serde_json::Value {
+    match value {
+        toml::Value::String(s) => serde_json::Value::String(s),
+        toml::Value::Integer(i) => i.into(),
+        toml::Value::Float(f) => f.into(),
+        toml::Value::Boolean(b) => b.into(),
+        toml::Value::Datetime(d) => serde_json::Value::String(d.to_string()),
+        toml::Value::Array(a) => a.into_iter().map(toml_to_json).collect(),
+        toml::Value::Table(t) => t.into_iter().map(|(k, v)| (k, toml_to_json(v))).collect(),
+    }
+}
+
+/// Formats a field value as a cell that `FieldValue::from_string` (or json,
+/// for lists, meta and oneofs) reads back as the same value.
+fn cell(value: &FieldValue) -> serde_json::Value {
+    let text = match value {
+        FieldValue::String(s)
+        | FieldValue::Secret(s)
+        | FieldValue::Enum(s)
+        | FieldValue::Markdown(s) => s.to_string(),
+        FieldValue::Number(n) => n.to_string(),
+        FieldValue::Boolean(b) => b.to_string(),
+        FieldValue::Date(d) => {
+            let d = d.as_liquid_datetime();
+            format!(
+                "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+                d.year(),
+                d.month(),
+                d.day(),
+                d.hour(),
+                d.minute(),
+                d.second()
+            )
+        }
+        FieldValue::Meta(m) => return serde_json::Value::from(m),
+        FieldValue::Null => return serde_json::Value::Null,
+        value => {
+            return Option::<toml::Value>::from(value)
+                .map(toml_to_json)
+                .unwrap_or_default()
+        }
+    };
+    serde_json::Value::String(text)
+}
+
+/// Flattens the fields of an object or child into a row.
+fn row(
+    filename: &str,
+    values: &ObjectValues,
+    definition: &ObjectDefinition,
+    field_config: &FieldConfig,
+    options: ExportOptions,
+) -> Row {
+    let mut row = Row::new();
+    row.insert(FILENAME_COLUMN.to_string(), filename.into());
+    for (name, field) in &definition.fields {
+        if !is_exported(&field.r#type, options) {
+            continue;
+        }
+        if field.r#type.base_type().is_uploadable() {
+            let file = match values.get(name) {
+                Some(FieldValue::File(file)) => file.clone().into_map(Some(field_config)),
+                _ => OrderMap::new(),
+            };
+            for key in FILE_COLUMNS {
+                let value = file.get(key).cloned().unwrap_or_default();
+                row.insert(format!("{name}.{key}"), value.into());
+            }
+        } else {
+            row.insert(
+                name.to_string(),
+                values.get(name).map(cell).unwrap_or_default(),
+            );
+        }
+    }
+    row
+}
+
+/// Flattens objects, or the children at `child` in each of them, into rows.
+fn export_rows(
+    objects: &[&Object],
+    definition: &ObjectDefinition,
+    child: Option<&ValuePath>,
+    field_config: &FieldConfig,
+    options: ExportOptions,
+) -> Result<(Vec<String>, Vec<Row>), ExportError> {
+    let Some(child) = child else {
+        let rows = objects
+            .iter()
+            .map(|o| row(&o.filename, &o.values, definition, field_config, options))
+            .collect();
+        return Ok((columns(definition, options), rows));
+    };
+    let child_def = child
+        .get_definition(definition)
+        .map_err(|_| ExportError::InvalidChild(child.to_string()))?;
+    let mut rows = vec![];
+    for object in objects {
+        if let Some(FieldValue::Objects(children)) = child.get_in_object(object) {
+            for values in children {
+                rows.push(row(
+                    &object.filename,
+                    values,
+                    child_def,
+                    field_config,
+                    options,
+                ));
+            }
+        }
+    }
+    Ok((columns(child_def, options), rows))
+}
+
+pub struct Command {}
+impl BinaryCommand for Command {
+    fn name(&self) -> &str {
+        "export"
+    }
+    fn cli(&self, cmd: clap::Command) -> clap::Command {
+        add_args(cmd.about("export site objects as csv, json or ndjson")
+            .arg(
+                arg!([object] "The object type to export.")
+                    .required(true)
+                    .value_parser(value_parser!(String)),
+            )
+            .arg(
+                arg!(-c --child <child> "Export the children at this path in each object, instead of the objects themselves. Objects are exported without their children, so export each child list separately with this.")
+                    .value_parser(value_parser!(ValuePath)),
+            )
+            .arg(
+                arg!(-f --format <"csv|json|ndjson"> "The format to export. Inferred from the file extension if exporting to a file.")
+                    .value_parser(value_parser!(ExportFormat)),
+            )
+            .arg(
+                arg!(--"include-secrets" "Include the values of secret fields.")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                arg!([file] "The file to write the export to. If not provided, will write to stdout (and --format is required).")
+                    .default_value("-")
+                    .value_parser(value_parser!(String)),
+            ), CommandConfig::no_build())
+    }
+    fn handler(
+        &self,
+        args: &ArgMatches,
+        _quit: Arc<AtomicBool>,
+    ) -> Result<crate::binary::ExitStatus> {
+        let root_dir = command_root(args);
+        let file_path = args.get_one::<String>("file").filter(|p| *p != "-");
+        let format = if let Some(format) = args.get_one::<ExportFormat>("format") {
+            format.to_owned()
+        } else if let Some(file_path) = file_path {
+            ExportFormat::from(
+                PathBuf::from(file_path)
+                    .extension()
+                    .ok_or(ExportError::NoExtension)?
+                    .to_string_lossy()
+                    .as_ref(),
+            )
+        } else {
+            return Err(ExportError::FormatOrFileRequired.into());
+        };
+        let object_type = args.get_one::<String>("object").unwrap();
+        let fs = file_system_stdlib::NativeFileSystem::new(&root_dir);
+        let archival = Archival::new(fs)?;
+        let site = archival.site();
+        let definition = site
+            .object_definitions
+            .get(object_type)
+            .ok_or_else(|| ExportError::InvalidObjectType(object_type.to_owned()))?;
+        let all_objects = archival.get_objects()?;
+        let objects: Vec<&Object> = all_objects
+            .get(object_type)
+            .map(|entry| entry.into_iter().collect())
+            .unwrap_or_default();
+        let child = args.get_one::<ValuePath>("child");
+        if child.is_none() && !definition.children.is_empty() {
+            let names: Vec<&str> = definition.children.keys().map(|k| k.as_str()).collect();
+            eprintln!(
+                "{} children ({}) are not exported with their objects; export them with --child.",
+                object_type,
+                names.join(", ")
+            );
+        }
+        let (columns, rows) = export_rows(
+            &objects,
+            definition,
+            child,
+            &site.field_config,
+            ExportOptions {
+                include_secrets: args.get_flag("include-secrets"),
+            },
+        )?;
+        match file_path {
+            Some(path) => {
+                let mut writer = BufWriter::new(File::create(path)?);
+                format.write(&columns, &rows, &mut writer)?;
+                eprintln!("exported {} rows to {}.", rows.len(), path);
+            }
+            None => format.write(&columns, &rows, &mut io::stdout().lock())?,
+        }
+        Ok(ExitStatus::Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::{MANIFEST_FILE_NAME, OBJECT_DEFINITION_FILE_NAME},
+        FileSystemAPI, MemoryFileSystem,
+    };
+
+    fn archival() -> Archival<MemoryFileSystem> {
+        let mut fs = MemoryFileSystem::default();
+        fs.write_str(MANIFEST_FILE_NAME, "upload_prefix = \"\"\n".to_string())
+            .unwrap();
+        fs.write_str(
+            OBJECT_DEFINITION_FILE_NAME,
+            "[post]\ntitle = \"string\"\ntoken = \"secret\"\ndate = \"date\"\ntags = [\"string\"]\ncover = \"image\"\n[post.links]\nurl = \"string\"\n"
+                .to_string(),
+        )
+        .unwrap();
+        fs.write_str(
+            "objects/post/hello.toml",
+            "title = \"Hello, world\"\ntoken = \"shh\"\ndate = 2024-01-02T10:00:00\ntags = [\"a\", \"b\"]\n[cover]\nsha = \"abc\"\nfilename = \"c.png\"\nmime = \"image/png\"\n[[links]]\nurl = \"/one\"\n[[links]]\nurl = \"/two\"\n"
+                .to_string(),
+        )
+        .unwrap();
+        Archival::new(fs).unwrap()
+    }
+
+    fn export(
+        archival: &Archival<MemoryFileSystem>,
+        format: ExportFormat,
+        child: Option<&ValuePath>,
+        options: ExportOptions,
+    ) -> String {
+        let objects = archival.get_objects().unwrap();
+        let posts: Vec<&Object> = objects.get("post").unwrap().into_iter().collect();
+        let (columns, rows) = export_rows(
+            &posts,
+            archival.site().object_definitions.get("post").unwrap(),
+            child,
+            &archival.site().field_config,
+            options,
+        )
+        .unwrap();
+        let mut output = vec![];
+        format.write(&columns, &rows, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    #[cfg(feature = "import-csv")]
+    fn exports_flattened_csv() {
+        let archival = archival();
+        let csv = export(&archival, ExportFormat::Csv, None, ExportOptions::default());
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("filename,title,date,tags,cover.url,cover.sha,cover.filename,cover.mime,cover.name,cover.description")
+        );
+        let row = lines.next().unwrap();
+        assert!(
+            row.starts_with(
+                "hello,\"Hello, world\",2024-01-02 10:00:00,\"[\"\"a\"\",\"\"b\"\"]\","
+            ),
+            "{row}"
+        );
+        assert!(row.ends_with(",abc,c.png,image/png,,"), "{row}");
+        assert!(!csv.contains("shh"));
+    }
+
+    #[test]
+    fn exports_children_and_secrets_as_ndjson() {
+        let archival = archival();
+        let ndjson = export(
+            &archival,
+            ExportFormat::Ndjson,
+            Some(&ValuePath::from_string("links")),
+            ExportOptions::default(),
+        );
+        assert_eq!(
+            ndjson,
+            "{\"filename\":\"hello\",\"url\":\"/one\"}\n{\"filename\":\"hello\",\"url\":\"/two\"}\n"
+        );
+        let json = export(
+            &archival,
+            ExportFormat::Json,
+            None,
+            ExportOptions {
+                include_secrets: true,
+            },
+        );
+        let rows: Vec<Row> = serde_json::from_str(&json).unwrap();
+        assert_eq!(rows[0]["token"], "shh");
+        assert_eq!(rows[0]["tags"], serde_json::json!(["a", "b"]));
+    }
+
+    #[test]
+    fn exports_import_back_to_the_same_objects() -> Result<()> {
+        use crate::{binary::command::import, file_system_stdlib::NativeFileSystem};
+        use tempfile::tempdir;
+
+        let write_site = |root: &std::path::Path, posts: bool| -> Result<()> {
+            std::fs::write(root.join(MANIFEST_FILE_NAME), "upload_prefix = \"\"\n")?;
+            std::fs::write(
+                root.join(OBJECT_DEFINITION_FILE_NAME),
+                "[post]\ntitle = \"string\"\ntoken = \"secret\"\ndate = \"date\"\ncount = \"number\"\ntags = [\"string\"]\ncover = \"image\"\n",
+            )?;
+            if posts {
+                std::fs::create_dir_all(root.join("objects/post"))?;
+                std::fs::write(
+                    root.join("objects/post/hello.toml"),
+                    "title = \"Hello, world\"\ntoken = \"shh\"\ndate = 2024-01-02T10:00:00\ncount = 3\ntags = [\"a\", \"b\"]\n[cover]\nsha = \"abc\"\nfilename = \"c.png\"\nmime = \"image/png\"\n",
+                )?;
+                std::fs::write(
+                    root.join("objects/post/empty.toml"),
+                    "title = \"Empty\"\ntags = []\n",
+                )?;
+            }
+            Ok(())
+        };
+        let source = tempdir()?;
+        let dest = tempdir()?;
+        let out = tempdir()?;
+        write_site(source.path(), true)?;
+        write_site(dest.path(), false)?;
+        let quit = Arc::new(AtomicBool::new(false));
+        let site = |dir: &tempfile::TempDir| dir.path().to_string_lossy().to_string();
+        let export = |dir: &tempfile::TempDir, file: &str| -> Result<String> {
+            let matches = Command {}
+                .cli(clap::Command::new("export"))
+                .try_get_matches_from(["export", "post", "--include-secrets", file, &site(dir)])?;
+            Command {}.handler(&matches, quit.clone())?;
+            Ok(std::fs::read_to_string(file)?)
+        };
+        for format in ["json", "ndjson"] {
+            let file = out.path().join(format!("posts.{format}"));
+            let file = file.to_string_lossy();
+            let exported = export(&source, &file)?;
+            let import = import::Command {};
+            let matches = import
+                .cli(clap::Command::new("import"))
+                .try_get_matches_from([
+                    "import",
+                    "post",
+                    "--name",
+                    "filename",
+                    "--mode",
+                    "upsert",
+                    &file,
+                    &site(&dest),
+                ])?;
+            import.handler(&matches, quit.clone())?;
+
+            let archival = Archival::new(NativeFileSystem::new(dest.path()))?;
+            let hello = archival.get_object("post", Some("hello"))?;
+            assert_eq!(
+                hello.values.get("tags"),
+                Some(&FieldValue::List(vec![
+                    FieldValue::String("a".to_string()),
+                    FieldValue::String("b".to_string())
+                ])),
+                "{format}"
+            );
+            assert_eq!(hello.values.get("count"), Some(&FieldValue::Number(3.0)));
+            // Unset fields stay unset, rather than becoming empty values.
+            let empty = archival.get_object("post", Some("empty"))?;
+            assert_eq!(empty.values.get("count"), None, "{format}");
+            assert_eq!(empty.values.get("token"), None, "{format}");
+            assert_eq!(export(&dest, &file)?, exported, "{format}");
+        }
+        Ok(())
+    }
+}
@@ -16,8 +16,9 @@ use std::sync::{atomic::AtomicBool, Arc};
 impl Archival<file_system_stdlib::NativeFileSystem> {
     fn format_objects(&self) -> Result<()> {
         self.fs_mutex.with_fs(|fs| {
-            let all_objects = self.site.get_objects(fs)?;
-            let definitions = &self.site.object_definitions;
+            let site = self.site();
+            let all_objects = site.get_objects(fs)?;
+            let definitions = &site.object_definitions;
             for (obj_type, objects) in &all_objects {
                 for object in objects {
                     let path = self.object_path_impl(obj_type, &object.filename, fs)?;
@@ -34,7 +35,7 @@ impl Archival<file_system_stdlib::NativeFileSystem> {
     fn format_manifest(&self) -> Result<()> {
         self.fs_mutex.with_fs(|fs| {
             let manifest_path = Manifest::path_in(Path::new(""), fs)?;
-            fs.write_str(manifest_path, self.site.manifest.to_toml()?)
+            fs.write_str(manifest_path, self.site().manifest.to_toml()?)
         })
     }
 }
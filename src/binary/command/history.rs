@@ -0,0 +1,68 @@
+use super::BinaryCommand;
+use crate::{
+    binary::{
+        command::{add_args, command_root, CommandConfig},
+        ExitStatus,
+    },
+    file_system_stdlib, Archival,
+};
+use anyhow::Result;
+use clap::{arg, ArgMatches};
+use std::sync::{atomic::AtomicBool, Arc};
+
+/// Splits an object argument like `post/hello` into its type and filename.
+/// Root objects are named by their type alone.
+pub(super) fn object_arg(arg: &str) -> (&str, &str) {
+    arg.split_once('/').unwrap_or((arg, arg))
+}
+
+pub struct Command {}
+impl BinaryCommand for Command {
+    fn name(&self) -> &str {
+        "history"
+    }
+    fn cli(&self, cmd: clap::Command) -> clap::Command {
+        add_args(
+            cmd.about("lists the previous versions of an object")
+                .long_about(
+                    "Lists the commits in this site's git repository that changed an \
+                     object, newest first, with the fields each one changed.",
+                )
+                .arg(arg!(<object> "the object, e.g. post/hello, or the name of a root object."))
+                .arg(arg!(--json "print the history as JSON.").required(false)),
+            CommandConfig::no_build(),
+        )
+    }
+    fn handler(&self, args: &ArgMatches, _quit: Arc<AtomicBool>) -> Result<ExitStatus> {
+        let root_dir = command_root(args);
+        let fs = file_system_stdlib::NativeFileSystem::new(&root_dir);
+        let archival = Archival::new(fs)?;
+        let (obj_type, filename) = object_arg(args.get_one::<String>("object").unwrap());
+        let history = archival.object_history(obj_type, filename)?;
+        if args.get_flag("json") {
+            println!("{}", serde_json::to_string_pretty(&history)?);
+            return Ok(ExitStatus::Ok);
+        }
+        if history.is_empty() {
+            println!("No history for {obj_type}/{filename}.");
+        }
+        for version in &history {
+            let revision = &version.revision;
+            println!(
+                "{} {} {}: {}",
+                &revision.id[..revision.id.len().min(8)],
+                revision.date,
+                revision.author,
+                revision.summary
+            );
+            if version.object.is_none() {
+                println!("    (deleted)");
+            } else {
+                for change in &version.changes {
+                    println!("    {change}");
+                }
+            }
+        }
+        Ok(ExitStatus::Ok)
+    }
+}
@@ -1,24 +1,30 @@
+#[cfg(feature = "import-markdown")]
+use super::import_markdown;
+#[cfg(feature = "import-wxr")]
+use super::import_wxr;
+use super::upload::{self, UploadError, UploadTarget};
 use super::BinaryCommand;
 use crate::{
     binary::{
         command::{add_args, command_root, CommandConfig},
         ExitStatus,
     },
-    events::{AddChildEvent, AddObjectEvent, ArchivalEvent, ArchivalEventResponse, EditFieldEvent},
+    events::{AddChildEvent, AddObjectEvent, ArchivalEvent, DeleteObjectEvent, EditFieldEvent},
+    fields::File,
     file_system_stdlib,
+    history::diff_objects,
     object::{ObjectEntry, ValuePath},
-    Archival, FieldValue, FileSystemAPI, ObjectDefinition,
+    Archival, FieldType, FieldValue, FileSystemAPI, ObjectDefinition, ObjectPreview,
 };
 use anyhow::Result;
 use clap::{arg, value_parser, ArgMatches};
 use indicatif::{ProgressBar, ProgressStyle};
-use liquid::model::ArrayView;
 use std::{
     collections::HashMap,
     fmt::Debug,
-    fs::{self, File},
-    io::{self, BufReader, Read},
-    path::PathBuf,
+    fs,
+    io::{self, BufRead, BufReader, Read},
+    path::{Path, PathBuf},
     sync::{atomic::AtomicBool, Arc},
 };
 use thiserror::Error;
@@ -49,10 +55,22 @@ pub enum ImportError {
     MissingNameField,
     #[error("failed parsing file {0}")]
     ParseError(String),
-    #[error("failed writing {0}/{1}: {2}")]
-    WriteError(String, String, String),
     #[error("couldn't find specified name field {0} in {1:?}")]
     MissingName(String, HashMap<String, String>),
+    #[error("{0}/{1} already exists. use --mode update or upsert to update existing objects")]
+    ObjectExists(String, String),
+    #[error("--mode and --delete-missing only apply when generating objects")]
+    ModeRequiresName,
+    #[error("failed importing {0}: {1}")]
+    ImportFailed(String, String),
+    #[error(transparent)]
+    Upload(#[from] UploadError),
+    #[cfg(feature = "import-markdown")]
+    #[error("invalid front matter in {0}: {1}")]
+    InvalidFrontMatter(String, String),
+    #[cfg(feature = "import-markdown")]
+    #[error("markdown can only be imported from files, as new objects")]
+    UnsupportedMarkdownImport,
 }
 
 #[derive(Debug, Clone)]
@@ -83,11 +101,141 @@ impl From<&str> for FieldMap {
     }
 }
 
+/// How rows are matched to existing objects, by the name generated from
+/// their `--name` field.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(super) enum ImportMode {
+    /// Only create objects, failing if one already exists.
+    #[default]
+    Create,
+    /// Only update existing objects, skipping rows without one.
+    Update,
+    /// Update existing objects and create the rest.
+    Upsert,
+}
+
+impl From<&str> for ImportMode {
+    fn from(value: &str) -> Self {
+        match &value.to_lowercase()[..] {
+            "create" => Self::Create,
+            "update" => Self::Update,
+            "upsert" => Self::Upsert,
+            _ => panic!("Unsupported mode {}", value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct ImportOptions {
+    pub(super) mode: ImportMode,
+    /// Delete objects that no row names.
+    pub(super) delete_missing: bool,
+    /// Work out the changes, but don't write them.
+    pub(super) dry_run: bool,
+}
+
+/// What importing a row does to the object it names.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum RowAction {
+    Create,
+    Update,
+    Skip,
+}
+
+impl ImportOptions {
+    /// What to do with the `object` named `filename` by a row, given whether
+    /// it already exists.
+    pub(super) fn row_action(
+        &self,
+        object: &str,
+        filename: &str,
+        exists: bool,
+    ) -> Result<RowAction, ImportError> {
+        match self.mode {
+            ImportMode::Create if exists => Err(ImportError::ObjectExists(
+                object.to_string(),
+                filename.to_string(),
+            )),
+            ImportMode::Update if !exists => Ok(RowAction::Skip),
+            _ if exists => Ok(RowAction::Update),
+            _ => Ok(RowAction::Create),
+        }
+    }
+
+    /// Sends `events`, after deleting the `existing` objects that weren't
+    /// `imported` if `delete_missing` is set. With `dry_run`, returns what
+    /// they would change instead.
+    pub(super) fn write_events<F: FileSystemAPI + Debug + Clone>(
+        &self,
+        object: &str,
+        mut events: Vec<ArchivalEvent>,
+        existing: &[String],
+        imported: &[String],
+        archival: &Archival<F>,
+        progress: &impl Fn(&str, u64, u64),
+    ) -> Result<Vec<ObjectPreview>, ImportError> {
+        if self.delete_missing {
+            for filename in existing.iter().filter(|f| !imported.contains(f)) {
+                events.push(ArchivalEvent::DeleteObject(DeleteObjectEvent {
+                    object: object.to_string(),
+                    filename: filename.to_string(),
+                    source: None,
+                }));
+            }
+        }
+        let write_error =
+            |e: anyhow::Error| ImportError::ImportFailed(object.to_string(), e.to_string());
+        if self.dry_run {
+            progress("checking changes...", 0, 0);
+            archival.preview_events(events).map_err(write_error)
+        } else {
+            progress("writing changes...", 0, 0);
+            archival.send_events(events, None).map_err(write_error)?;
+            Ok(vec![])
+        }
+    }
+}
+
+/// The filenames of the existing `object` objects.
+pub(super) fn existing_objects<F: FileSystemAPI + Debug + Clone>(
+    archival: &Archival<F>,
+    object: &str,
+) -> Result<Vec<String>, ImportError> {
+    Ok(archival
+        .get_objects()
+        .map_err(|e| ImportError::ParseError(e.to_string()))?
+        .get(object)
+        .map(|entry| entry.into_iter().map(|o| o.filename.to_owned()).collect())
+        .unwrap_or_default())
+}
+
+#[derive(Debug, Default)]
+pub(super) struct ImportResult {
+    pub(super) attachments: Vec<String>,
+    /// Rows that `ImportMode::Update` found no object for.
+    pub(super) skipped: Vec<String>,
+    /// With `dry_run`, each object the import would change.
+    pub(super) previews: Vec<ObjectPreview>,
+}
+
 #[derive(Debug, Clone)]
 enum ImportFormat {
     #[cfg(feature = "import-csv")]
     Csv,
     Json,
+    Ndjson,
+    #[cfg(feature = "import-markdown")]
+    Markdown,
+    #[cfg(feature = "import-wxr")]
+    Wxr,
+}
+
+/// Rows read from a source file, and the URLs of any media it references that
+/// should be uploaded separately.
+#[derive(Debug, Default)]
+struct ImportData {
+    rows: Vec<HashMap<String, String>>,
+    attachments: Vec<String>,
 }
 
 impl From<&str> for ImportFormat {
@@ -96,6 +244,11 @@ impl From<&str> for ImportFormat {
             #[cfg(feature = "import-csv")]
             "csv" => Self::Csv,
             "json" => Self::Json,
+            "ndjson" | "jsonl" => Self::Ndjson,
+            #[cfg(feature = "import-markdown")]
+            "md" | "markdown" => Self::Markdown,
+            #[cfg(feature = "import-wxr")]
+            "wxr" | "xml" => Self::Wxr,
             _ => panic!("Unsupported format {}", value),
         }
     }
@@ -129,22 +282,123 @@ impl ImportFormat {
         }
         Ok(output)
     }
+    /// Json rows may hold any json values, which are read as text: strings as
+    /// they are, and everything else (e.g. the lists `archival export` writes)
+    /// as json. Null values are left out, like fields missing from the row.
+    fn json_row(row: HashMap<String, serde_json::Value>) -> HashMap<String, String> {
+        row.into_iter()
+            .filter_map(|(k, v)| match v {
+                serde_json::Value::Null => None,
+                serde_json::Value::String(s) => Some((k, s)),
+                v => Some((k, v.to_string())),
+            })
+            .collect()
+    }
     fn parse_json<R: Read>(
         input: &mut BufReader<R>,
     ) -> Result<Vec<HashMap<String, String>>, ImportError> {
         let json = io::read_to_string(input).map_err(|e| ImportError::ParseError(e.to_string()))?;
-        let map: Vec<HashMap<String, String>> = serde_json::from_str(&json).unwrap();
-        Ok(map)
+        let rows: Vec<HashMap<String, serde_json::Value>> =
+            serde_json::from_str(&json).map_err(|e| ImportError::ParseError(e.to_string()))?;
+        Ok(rows.into_iter().map(Self::json_row).collect())
     }
-    pub fn parse<R: Read>(
-        &self,
+    fn parse_ndjson<R: Read>(
         input: &mut BufReader<R>,
     ) -> Result<Vec<HashMap<String, String>>, ImportError> {
-        match self {
+        input
+            .lines()
+            .filter(|line| !line.as_ref().is_ok_and(|l| l.trim().is_empty()))
+            .map(|line| {
+                let line = line.map_err(|e| ImportError::ParseError(e.to_string()))?;
+                serde_json::from_str(&line)
+                    .map(Self::json_row)
+                    .map_err(|e| ImportError::ParseError(e.to_string()))
+            })
+            .collect()
+    }
+    pub fn parse<R: Read>(&self, input: &mut BufReader<R>) -> Result<ImportData, ImportError> {
+        let rows = match self {
             #[cfg(feature = "import-csv")]
-            ImportFormat::Csv => Self::parse_csv(input),
-            ImportFormat::Json => Self::parse_json(input),
+            ImportFormat::Csv => Self::parse_csv(input)?,
+            ImportFormat::Json => Self::parse_json(input)?,
+            ImportFormat::Ndjson => Self::parse_ndjson(input)?,
+            // Markdown files are read by `import_markdown`.
+            #[cfg(feature = "import-markdown")]
+            ImportFormat::Markdown => return Err(ImportError::UnsupportedMarkdownImport),
+            #[cfg(feature = "import-wxr")]
+            ImportFormat::Wxr => {
+                let export = import_wxr::parse(input)?;
+                return Ok(ImportData {
+                    rows: export.rows,
+                    attachments: export.attachments,
+                });
+            }
+        };
+        Ok(ImportData {
+            rows,
+            attachments: vec![],
+        })
+    }
+    /// The field that names generated objects when --name isn't given.
+    fn default_name_field(&self) -> Option<&'static str> {
+        match self {
+            #[cfg(feature = "import-wxr")]
+            ImportFormat::Wxr => Some("slug"),
+            _ => None,
+        }
+    }
+    /// Converts a source value to a value of the given field type, or `None`
+    /// if it's empty and the type has no empty value.
+    fn field_value(
+        &self,
+        name: &String,
+        field_type: &FieldType,
+        value: &str,
+    ) -> Result<Option<FieldValue>, ImportError> {
+        let parse_error = |e: &dyn std::fmt::Display| ImportError::ParseError(e.to_string());
+        #[cfg(feature = "import-wxr")]
+        if let ImportFormat::Wxr = self {
+            // WordPress content is HTML, and categories and tags are lists.
+            match field_type.base_type() {
+                FieldType::Markdown => {
+                    return Ok(Some(FieldValue::Markdown(import_wxr::html_to_markdown(
+                        value,
+                    ))))
+                }
+                FieldType::List(_) => {
+                    let items = value
+                        .split(import_wxr::LIST_SEPARATOR)
+                        .filter(|item| !item.is_empty())
+                        .map(|item| toml::Value::String(item.to_string()))
+                        .collect();
+                    return FieldValue::from_toml(name, field_type, &toml::Value::Array(items))
+                        .map(Some)
+                        .map_err(|e| parse_error(&e));
+                }
+                _ => {}
+            }
         }
+        match field_type.base_type() {
+            FieldType::String
+            | FieldType::Secret
+            | FieldType::Markdown
+            | FieldType::Number
+            | FieldType::Boolean => {}
+            _ if value.is_empty() => return Ok(None),
+            // Structured values are json, as `archival export` writes them.
+            FieldType::List(_) | FieldType::Meta | FieldType::Oneof(_) => {
+                let json: serde_json::Value =
+                    serde_json::from_str(value).map_err(|e| parse_error(&e))?;
+                let value = toml::Value::try_from(json).map_err(|e| parse_error(&e))?;
+                return FieldValue::from_toml(name, field_type, &value)
+                    .map(Some)
+                    .map_err(|e| parse_error(&e));
+            }
+            _ => {}
+        }
+        FieldValue::from_string(name, field_type, value.to_string())
+            .map(Some)
+            .map_err(|e| parse_error(&e))
     }
 }
 
@@ -165,17 +419,41 @@ impl BinaryCommand for Command {
                     .value_parser(value_parser!(ValuePath)),
             )
             .arg(
-                arg!(-n --name <field_name> "If generating objects, the data field to use to generate object file names.")
+                arg!(-n --name <field_name> "If generating objects, the data field to use to generate object file names. Defaults to slug for wxr.")
                     .value_parser(value_parser!(String)),
             )
             .arg(
-                arg!(-f --format <"csv|json"> "The format of the source data. Inferred from the file extension if importing from a file.")
+                arg!(-f --format <"csv|json|ndjson|markdown|wxr"> "The format of the source data. Inferred from the file extension if importing from a file, and markdown if importing from a directory.")
                     .value_parser(value_parser!(ImportFormat)),
             )
             .arg(
                 arg!(-m --map <"from:to"> ... "map a source field name to a destination field name.")
                     .value_parser(value_parser!(FieldMap)),
             )
+            .arg(
+                arg!(--mode <"create|update|upsert"> "If generating objects, whether to create new objects, update existing ones with the same name, or both. Defaults to create.")
+                    .value_parser(value_parser!(ImportMode)),
+            )
+            .arg(
+                arg!(--"delete-missing" "If generating objects, delete existing objects that aren't in the source data.")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                arg!(--"dry-run" "Print the changes the import would make without making them.")
+                    .action(clap::ArgAction::SetTrue),
+            )
+            .arg(
+                arg!(--uploads <dir> "Copy the local files named in upload fields into this directory, instead of uploading them to archival.")
+                    .value_parser(value_parser!(PathBuf)),
+            )
+            .arg(
+                arg!(-r --repo <repo_name> "A repo name (e.g. github/jesseditson/blog) to upload the local files named in upload fields for. If not provided, will be inferred from the first git remote.")
+                    .value_parser(value_parser!(String)),
+            )
+            .arg(
+                arg!(-b --body <field_name> "If importing markdown, the field to put the text after each file's front matter in.")
+                    .value_parser(value_parser!(String)),
+            )
             .arg(
                 arg!([file] "The file containing data to import. If not provided, will read from stdin (and --format is required).")
                     .default_value("-")
@@ -195,6 +473,10 @@ impl BinaryCommand for Command {
         } else if let Some(file_path) = file_path {
             let metadata = fs::metadata(file_path)
                 .map_err(|_| ImportError::FileNotExists(file_path.to_owned()))?;
+            #[cfg(feature = "import-markdown")]
+            if metadata.is_dir() {
+                return self.import_markdown(args, &root_dir, std::path::Path::new(file_path));
+            }
             if !metadata.is_file() {
                 return Err(ImportError::FileNotExists(file_path.to_owned()).into());
             }
@@ -208,9 +490,18 @@ impl BinaryCommand for Command {
         } else {
             return Err(ImportError::FormatOrFileRequired.into());
         };
+        #[cfg(feature = "import-markdown")]
+        if let ImportFormat::Markdown = file_format {
+            return match file_path {
+                Some(path) if path != "-" => {
+                    self.import_markdown(args, &root_dir, std::path::Path::new(path))
+                }
+                _ => Err(ImportError::UnsupportedMarkdownImport.into()),
+            };
+        }
         let file_reader = match file_path {
             Some(path) if path == "-" => None,
-            Some(path) => Some(BufReader::new(File::open(path)?)),
+            Some(path) => Some(BufReader::new(fs::File::open(path)?)),
             None => None,
         };
         let object = args.get_one::<PathBuf>("object").unwrap();
@@ -235,8 +526,8 @@ impl BinaryCommand for Command {
         let fs = file_system_stdlib::NativeFileSystem::new(&root_dir);
         let archival = Archival::new(fs)?;
         // Find the specified object definition
-        let obj_def = archival
-            .site
+        let site = archival.site();
+        let obj_def = site
             .object_definitions
             .get(&object_type)
             .ok_or_else(|| ImportError::InvalidObjectType(object_type.to_owned()))?;
@@ -278,25 +569,29 @@ impl BinaryCommand for Command {
             // generate filenames from.
             if let Some(name_field) = name_field {
                 ImportName::Field(name_field.to_owned())
+            } else if let Some(name_field) = file_format.default_name_field() {
+                ImportName::Field(name_field.to_string())
             } else {
                 return Err(ImportError::MissingNameField.into());
             }
         };
-        let field_map_args = args.get_many::<FieldMap>("map");
-        let mut field_map = HashMap::new();
-        if let Some(fm) = field_map_args {
-            for fm in fm {
-                field_map.insert(fm.to.to_owned(), fm.from.to_owned());
-            }
-        }
+        let field_map = field_map(args);
         let mapped_type = if let Some(child_def) = child_def {
             child_def
         } else {
             obj_def
         };
+        let options = import_options(args);
+        // Upload fields name local files, relative to the source file.
+        let source_dir = file_path
+            .filter(|path| *path != "-")
+            .and_then(|path| Path::new(path).parent())
+            .unwrap_or(Path::new(""));
+        let mut upload = uploader(args, &root_dir, &archival, options.dry_run);
+        let mut read_file = |value: &str| upload(&source_dir.join(value));
         let bar = ProgressBar::new_spinner();
         bar.set_style(ProgressStyle::with_template("{msg} {spinner}").unwrap());
-        Command::parse(
+        let result = Command::parse(
             file_reader,
             &object_type,
             field_map,
@@ -308,6 +603,8 @@ impl BinaryCommand for Command {
             },
             mapped_type,
             &file_format,
+            options,
+            &mut read_file,
             &archival,
             |msg, p, t| {
                 bar.set_message(msg.to_string());
@@ -315,10 +612,119 @@ impl BinaryCommand for Command {
                 bar.set_position(p);
             },
         )?;
+        bar.finish_and_clear();
+        print_result(result);
         Ok(ExitStatus::Ok)
     }
 }
 
+fn import_options(args: &ArgMatches) -> ImportOptions {
+    ImportOptions {
+        mode: args
+            .get_one::<ImportMode>("mode")
+            .copied()
+            .unwrap_or_default(),
+        delete_missing: args.get_flag("delete-missing"),
+        dry_run: args.get_flag("dry-run"),
+    }
+}
+
+/// Reads the local file at a path and uploads it, to `--uploads` if given.
+/// Only connects to the upload backend once there's a file to upload, and
+/// with `dry_run` only reads the file.
+fn uploader<'a, F: FileSystemAPI + Debug + Clone>(
+    args: &'a ArgMatches,
+    root_dir: &'a Path,
+    archival: &'a Archival<F>,
+    dry_run: bool,
+) -> impl FnMut(&Path) -> Result<File, ImportError> + 'a {
+    let mut upload_target = None;
+    move |path| {
+        let (file, data) = upload::read_file(path)?;
+        if dry_run {
+            return Ok(file);
+        }
+        if upload_target.is_none() {
+            upload_target = Some(match args.get_one::<PathBuf>("uploads") {
+                Some(dir) => UploadTarget::Directory {
+                    dir: dir.to_owned(),
+                    upload_prefix: archival.site().field_config.upload_prefix.to_owned(),
+                },
+                None => UploadTarget::archival(
+                    root_dir,
+                    args.get_one::<String>("repo").map(|r| r.as_str()),
+                )?,
+            });
+        }
+        upload_target.as_ref().unwrap().upload(&file, data)?;
+        Ok(file)
+    }
+}
+
+/// Prints what an import skipped, changed with `--dry-run`, and left to upload.
+fn print_result(result: ImportResult) {
+    if !result.skipped.is_empty() {
+        println!("no existing objects to update for:");
+        for filename in result.skipped {
+            println!("  {filename}");
+        }
+    }
+    for preview in &result.previews {
+        let path = format!("{}/{}", preview.object, preview.filename);
+        match (&preview.before, &preview.after) {
+            (None, _) => println!("+ {path}"),
+            (_, None) => println!("- {path}"),
+            (before, after) => {
+                let changes = diff_objects(before.as_ref(), after.as_ref());
+                if !changes.is_empty() {
+                    println!("~ {path}");
+                    for change in changes {
+                        println!("    {change}");
+                    }
+                }
+            }
+        }
+    }
+    if !result.attachments.is_empty() {
+        println!("these attachments should be uploaded:");
+        for url in result.attachments {
+            println!("  {url}");
+        }
+    }
+}
+
+/// The `--map` arguments, from destination to source field name.
+fn field_map(args: &ArgMatches) -> HashMap<String, String> {
+    let mut field_map = HashMap::new();
+    if let Some(fm) = args.get_many::<FieldMap>("map") {
+        for fm in fm {
+            field_map.insert(fm.to.to_owned(), fm.from.to_owned());
+        }
+    }
+    field_map
+}
+
+/// The value of a file field from `<field>.<key>` columns, which is how
+/// `archival export` writes files.
+fn file_columns(
+    row: &HashMap<String, String>,
+    field: &str,
+    field_type: &FieldType,
+) -> Option<toml::Table> {
+    if !field_type.base_type().is_uploadable() {
+        return None;
+    }
+    let file: toml::Table = ["sha", "filename", "mime", "name", "description"]
+        .into_iter()
+        .filter_map(|key| {
+            row.get(&format!("{field}.{key}"))
+                .filter(|v| !v.is_empty())
+                .map(|v| (key.to_string(), toml::Value::String(v.to_string())))
+        })
+        .collect();
+    file.contains_key("sha").then_some(file)
+}
+
 #[derive(Debug)]
 enum ImportName {
     File(String),
@@ -326,6 +732,65 @@ enum ImportName {
 }
 
 impl Command {
+    /// Imports a markdown file, or a directory of them, as objects.
+    #[cfg(feature = "import-markdown")]
+    fn import_markdown(
+        &self,
+        args: &ArgMatches,
+        root_dir: &std::path::Path,
+        path: &std::path::Path,
+    ) -> Result<ExitStatus> {
+        let object = args.get_one::<PathBuf>("object").unwrap();
+        if object.extension().is_some() {
+            return Err(ImportError::UnsupportedMarkdownImport.into());
+        }
+        let object_type = object.to_string_lossy().to_string();
+        let fs = file_system_stdlib::NativeFileSystem::new(root_dir);
+        let archival = Archival::new(fs)?;
+        let site = archival.site();
+        let obj_def = site
+            .object_definitions
+            .get(&object_type)
+            .ok_or_else(|| ImportError::InvalidObjectType(object_type.to_owned()))?;
+        // Markdown is mapped from front matter keys to fields.
+        let field_map = field_map(args)
+            .into_iter()
+            .map(|(to, from)| (from, to))
+            .collect();
+        let files = import_markdown::read_files(path)?;
+        let options = import_options(args);
+        let mut upload = uploader(args, root_dir, &archival, options.dry_run);
+        let bar = ProgressBar::new_spinner();
+        bar.set_style(ProgressStyle::with_template("{msg} {spinner}").unwrap());
+        let (result, unmapped) = import_markdown::import(
+            &files,
+            &object_type,
+            &field_map,
+            args.get_one::<String>("body").map(|b| b.as_str()),
+            obj_def,
+            options,
+            &mut upload,
+            &archival,
+            |msg, p, t| {
+                bar.set_message(msg.to_string());
+                bar.set_length(t);
+                bar.set_position(p);
+            },
+        )?;
+        bar.finish_and_clear();
+        if !options.dry_run {
+            println!("imported {} files.", files.len() - result.skipped.len());
+        }
+        print_result(result);
+        if !unmapped.is_empty() {
+            println!("these front matter keys were not imported:");
+            for key in unmapped {
+                println!("  {key}");
+            }
+        }
+        Ok(ExitStatus::Ok)
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn parse<R: Read, F: FileSystemAPI + Debug + Clone>(
         reader: Option<BufReader<R>>,
@@ -335,37 +800,68 @@ impl Command {
         root_path: ValuePath,
         mapped_type: &ObjectDefinition,
         file_format: &ImportFormat,
+        options: ImportOptions,
+        read_file: &mut dyn FnMut(&str) -> Result<File, ImportError>,
         archival: &Archival<F>,
         progress: impl Fn(&str, u64, u64),
-    ) -> Result<(), ImportError> {
+    ) -> Result<ImportResult, ImportError> {
+        if let ImportName::File(_) = import_name {
+            if options.mode != ImportMode::Create || options.delete_missing {
+                return Err(ImportError::ModeRequiresName);
+            }
+        }
         // Generate a list of rows from our input data
         progress("parsing file...", 0, 0);
         let inverted_field_map: HashMap<&String, &String> =
             field_map.iter().map(|(k, v)| (v, k)).collect();
-        let parsed = if let Some(mut reader) = reader {
+        let ImportData { rows, attachments } = if let Some(mut reader) = reader {
             file_format.parse(&mut reader)?
         } else {
             file_format.parse(&mut BufReader::new(io::stdin().lock()))?
         };
-        if let ImportName::Field(f) = &import_name {
-            progress("creating object...", 0, 0);
-            archival
-                .send_event(
-                    ArchivalEvent::AddObject(AddObjectEvent {
-                        object: object.to_string(),
-                        filename: f.to_string(),
-                        order: Some(0.),
-                        values: vec![],
-                    }),
-                    None,
-                )
-                .map_err(|e| {
-                    ImportError::WriteError(object.to_string(), f.to_string(), e.to_string())
-                })?;
-        }
+        let mut result = ImportResult {
+            attachments,
+            ..Default::default()
+        };
+        let existing: Vec<String> = match &import_name {
+            ImportName::File(_) => vec![],
+            ImportName::Field(_) => existing_objects(archival, object)?,
+        };
+        // New children are appended, after any the object already has.
+        let mut child_count = match &import_name {
+            ImportName::File(f) => archival
+                .get_object(object, Some(f))
+                .ok()
+                .and_then(|o| match root_path.get_in_object(&o) {
+                    Some(FieldValue::Objects(children)) => Some(children.len()),
+                    _ => None,
+                })
+                .unwrap_or_default(),
+            ImportName::Field(_) => 0,
+        };
+        let edit = |filename: &str, path: ValuePath, field: String, value: FieldValue| {
+            ArchivalEvent::EditField(EditFieldEvent {
+                object: object.to_string(),
+                filename: filename.to_string(),
+                path,
+                value: Some(value),
+                field,
+                source: None,
+            })
+        };
+        // Upload fields name a local file to upload.
+        let mut field_value = |name: &String, field_type: &FieldType, value: &str| {
+            if field_type.base_type().is_uploadable() && !value.is_empty() {
+                read_file(value).map(|file| Some(FieldValue::File(file)))
+            } else {
+                file_format.field_value(name, field_type, value)
+            }
+        };
+        let mut events = vec![];
+        let mut imported: Vec<String> = vec![];
         let mut idx = 0;
-        let total = parsed.size();
-        for row in parsed {
+        let total = rows.len();
+        for row in rows {
             idx += 1;
             progress(
                 &format!("importing {} of {} rows...", idx, total),
@@ -375,29 +871,15 @@ impl Command {
             let mut current_path = root_path.clone();
             let filename = match &import_name {
                 ImportName::File(f) => {
-                    let r = archival
-                        .send_event(
-                            ArchivalEvent::AddChild(AddChildEvent {
-                                object: object.to_string(),
-                                filename: f.to_string(),
-                                path: current_path.to_owned(),
-                                values: vec![],
-                                index: None,
-                            }),
-                            None,
-                        )
-                        .map_err(|e| {
-                            ImportError::WriteError(
-                                object.to_string(),
-                                f.to_string(),
-                                e.to_string(),
-                            )
-                        })?;
-                    if let ArchivalEventResponse::Index(i) = r {
-                        current_path = current_path.append(ValuePath::index(i));
-                    } else {
-                        panic!("archival did not return an index for an inserted child");
-                    }
+                    events.push(ArchivalEvent::AddChild(AddChildEvent {
+                        object: object.to_string(),
+                        filename: f.to_string(),
+                        path: current_path.to_owned(),
+                        values: vec![],
+                        index: None,
+                    }));
+                    current_path = current_path.append(ValuePath::index(child_count));
+                    child_count += 1;
                     f.to_owned()
                 }
                 ImportName::Field(f) => {
@@ -405,23 +887,27 @@ impl Command {
                         .get(f)
                         .ok_or(ImportError::MissingName(f.to_owned(), row.clone()))
                         .map(|s| s.split_whitespace().collect::<Vec<_>>().join("-"))?;
-                    archival
-                        .send_event(
-                            ArchivalEvent::AddObject(AddObjectEvent {
+                    // Rows repeating a name update the object the first one
+                    // imported.
+                    let exists = existing.contains(&file_name) || imported.contains(&file_name);
+                    match options.row_action(object, &file_name, exists)? {
+                        RowAction::Skip => {
+                            result.skipped.push(file_name);
+                            continue;
+                        }
+                        RowAction::Create => {
+                            events.push(ArchivalEvent::AddObject(AddObjectEvent {
                                 object: object.to_string(),
                                 filename: file_name.to_string(),
                                 order: Some(0.),
                                 values: vec![],
-                            }),
-                            None,
-                        )
-                        .map_err(|e| {
-                            ImportError::WriteError(
-                                object.to_string(),
-                                f.to_string(),
-                                e.to_string(),
-                            )
-                        })?;
+                            }));
+                        }
+                        RowAction::Update => {}
+                    }
+                    if !imported.contains(&file_name) {
+                        imported.push(file_name.to_string());
+                    }
                     file_name
                 }
             };
@@ -433,32 +919,26 @@ impl Command {
                 } else {
                     name
                 };
-                if let Some(value) = row.get(from_name) {
-                    // Validate type
-                    let value = FieldValue::from_string(name, field_type, value.to_string())
-                        .map_err(|e| ImportError::ParseError(e.to_string()))?;
-                    archival
-                        .send_event(
-                            ArchivalEvent::EditField(EditFieldEvent {
-                                object: object.to_string(),
-                                filename: filename.to_string(),
-                                path: current_path.clone(),
-                                value: Some(value),
-                                field: name.to_string(),
-                                source: None,
-                            }),
-                            None,
-                        )
-                        .map_err(|e| {
-                            ImportError::WriteError(
-                                object.to_string(),
-                                filename.to_string(),
-                                e.to_string(),
-                            )
-                        })?;
+                // Validate type
+                let value = if let Some(value) = row.get(from_name) {
                     unused_cols.remove(from_name);
+                    field_value(name, field_type, value)?
+                } else if let Some(file) = file_columns(&row, from_name, field_type) {
+                    Some(
+                        FieldValue::from_toml(name, field_type, &toml::Value::Table(file))
+                            .map_err(|e| ImportError::ParseError(e.to_string()))?,
+                    )
                 } else {
                     println!("field '{}' not found in row: {:?}", from_name, row);
+                    continue;
+                };
+                if let Some(value) = value {
+                    events.push(edit(
+                        &filename,
+                        current_path.clone(),
+                        name.to_string(),
+                        value,
+                    ));
                 }
             }
             // We support pathing in csv column names, so if the name begins
@@ -479,32 +959,237 @@ impl Command {
                     let col_field = col_field.unwrap().to_string().trim().to_string();
                     if let Some(field_type) = found_type.field_type(&col_field) {
                         // Validate type
-                        let value = FieldValue::from_string(&name, field_type, value.to_string())
-                            .map_err(|e| ImportError::ParseError(e.to_string()))?;
-                        archival
-                            .send_event(
-                                ArchivalEvent::EditField(EditFieldEvent {
-                                    object: object.to_string(),
-                                    filename: filename.to_string(),
-                                    path: current_path.clone().concat(col_path),
-                                    value: Some(value),
-                                    field: col_field,
-                                    source: None,
-                                }),
-                                None,
-                            )
-                            .map_err(|e| {
-                                ImportError::WriteError(
-                                    object.to_string(),
-                                    filename.to_string(),
-                                    e.to_string(),
-                                )
-                            })?;
+                        let Some(value) = field_value(&name, field_type, &value)? else {
+                            continue;
+                        };
+                        events.push(edit(
+                            &filename,
+                            current_path.clone().concat(col_path),
+                            col_field,
+                            value,
+                        ));
                     }
                 }
             }
         }
-        Ok(())
+        result.previews =
+            options.write_events(object, events, &existing, &imported, archival, &progress)?;
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod json_tests {
+    use super::{
+        upload::{self, UploadTarget},
+        Command, ImportError, ImportFormat, ImportMode, ImportName, ImportOptions,
+    };
+    use crate::{
+        constants::{MANIFEST_FILE_NAME, OBJECT_DEFINITION_FILE_NAME},
+        object::ValuePath,
+        sha_for_data, FieldValue, FileSystemAPI, MemoryFileSystem,
+    };
+    use std::collections::HashMap;
+    use std::io::BufReader;
+    use tempfile::tempdir;
+
+    #[test]
+    fn parse_exported_ndjson_to_files() {
+        let mut fs = MemoryFileSystem::default();
+        fs.write_str(MANIFEST_FILE_NAME, "upload_prefix = \"\"\n".to_string())
+            .unwrap();
+        fs.write_str(
+            OBJECT_DEFINITION_FILE_NAME,
+            "[post]\ntitle = \"string\"\ndate = \"date\"\ntags = [\"string\"]\ncover = \"image\"\n"
+                .to_string(),
+        )
+        .unwrap();
+        let archival = crate::Archival::new(fs).unwrap();
+        let site = archival.site();
+        let obj_def = site.object_definitions.get("post").unwrap();
+        // As written by `archival export --format ndjson`, and with lists as
+        // json text like older exports.
+        let ndjson = r#"{"filename":"hello","title":"Hello","date":"2024-01-02 10:00:00","tags":["a","b"],"cover.url":"https://example.com/abc/c.png","cover.sha":"abc","cover.filename":"c.png","cover.mime":"image/png","cover.name":"","cover.description":""}
+{"filename":"undated","title":"Undated","date":"","tags":"[]","cover.url":"","cover.sha":"","cover.filename":"","cover.mime":"","cover.name":"","cover.description":""}"#;
+        Command::parse(
+            Some(BufReader::new(ndjson.as_bytes())),
+            "post",
+            HashMap::new(),
+            ImportName::Field("filename".to_string()),
+            ValuePath::default(),
+            obj_def,
+            &ImportFormat::Ndjson,
+            ImportOptions::default(),
+            &mut |_| unreachable!(),
+            &archival,
+            |m, p, t| println!("{} ({}/{})", m, p, t),
+        )
+        .unwrap();
+        let objects = archival.get_objects().unwrap();
+        let posts = objects.get("post").unwrap();
+        let hello = posts.into_iter().find(|p| p.filename == "hello").unwrap();
+        assert_eq!(
+            hello.values.get("tags"),
+            Some(&FieldValue::List(vec![
+                FieldValue::String("a".to_string()),
+                FieldValue::String("b".to_string())
+            ]))
+        );
+        let Some(FieldValue::File(cover)) = hello.values.get("cover") else {
+            panic!("cover not imported: {:?}", hello.values);
+        };
+        assert_eq!(
+            (cover.sha.as_str(), cover.mime.as_str()),
+            ("abc", "image/png")
+        );
+        assert!(matches!(
+            hello.values.get("date"),
+            Some(FieldValue::Date(_))
+        ));
+        let undated = posts.into_iter().find(|p| p.filename == "undated").unwrap();
+        assert_eq!(undated.values.get("date"), None);
+        assert_eq!(undated.values.get("cover"), None);
+    }
+
+    #[test]
+    fn parse_local_files_to_uploads() {
+        let mut fs = MemoryFileSystem::default();
+        fs.write_str(MANIFEST_FILE_NAME, "upload_prefix = \"\"\n".to_string())
+            .unwrap();
+        fs.write_str(
+            OBJECT_DEFINITION_FILE_NAME,
+            "[product]\ntitle = \"string\"\nimage = \"image\"\n".to_string(),
+        )
+        .unwrap();
+        let archival = crate::Archival::new(fs).unwrap();
+        let site = archival.site();
+        let obj_def = site.object_definitions.get("product").unwrap();
+        let source = tempdir().unwrap();
+        let uploads = tempdir().unwrap();
+        std::fs::write(source.path().join("chair.png"), b"not really a png").unwrap();
+        let target = UploadTarget::Directory {
+            dir: uploads.path().to_owned(),
+            upload_prefix: "".to_string(),
+        };
+        let ndjson = r#"{"name":"chair","title":"Chair","image":"chair.png"}
+{"name":"table","title":"Table","image":""}"#;
+        Command::parse(
+            Some(BufReader::new(ndjson.as_bytes())),
+            "product",
+            HashMap::new(),
+            ImportName::Field("name".to_string()),
+            ValuePath::default(),
+            obj_def,
+            &ImportFormat::Ndjson,
+            ImportOptions::default(),
+            &mut |path| {
+                let (file, data) = upload::read_file(&source.path().join(path))?;
+                target.upload(&file, data)?;
+                Ok(file)
+            },
+            &archival,
+            |_, _, _| {},
+        )
+        .unwrap();
+        let chair = archival.get_object("product", Some("chair")).unwrap();
+        let Some(FieldValue::File(image)) = chair.values.get("image") else {
+            panic!("image not imported: {:?}", chair.values);
+        };
+        let sha = sha_for_data(b"not really a png");
+        assert_eq!(image.sha, sha);
+        assert_eq!(
+            (image.filename.as_str(), image.mime.as_str()),
+            ("chair.png", "image/png")
+        );
+        assert_eq!(
+            std::fs::read(uploads.path().join(&sha).join("chair.png")).unwrap(),
+            b"not really a png"
+        );
+        let table = archival.get_object("product", Some("table")).unwrap();
+        assert_eq!(table.values.get("image"), None);
+    }
+
+    #[test]
+    fn import_modes_match_existing_objects() {
+        let mut fs = MemoryFileSystem::default();
+        fs.write_str(MANIFEST_FILE_NAME, "upload_prefix = \"\"\n".to_string())
+            .unwrap();
+        fs.write_str(
+            OBJECT_DEFINITION_FILE_NAME,
+            "[post]\ntitle = \"string\"\n".to_string(),
+        )
+        .unwrap();
+        fs.write_str("objects/post/a.toml", "title = \"A\"\n".to_string())
+            .unwrap();
+        fs.write_str("objects/post/b.toml", "title = \"B\"\n".to_string())
+            .unwrap();
+        let archival = crate::Archival::new(fs).unwrap();
+        let site = archival.site();
+        let obj_def = site.object_definitions.get("post").unwrap();
+        let ndjson = "{\"name\":\"a\",\"title\":\"A2\"}\n{\"name\":\"c\",\"title\":\"C\"}";
+        let import = |options: ImportOptions| {
+            Command::parse(
+                Some(BufReader::new(ndjson.as_bytes())),
+                "post",
+                HashMap::new(),
+                ImportName::Field("name".to_string()),
+                ValuePath::default(),
+                obj_def,
+                &ImportFormat::Ndjson,
+                options,
+                &mut |_| unreachable!(),
+                &archival,
+                |_, _, _| {},
+            )
+        };
+        let title = |filename: &str| {
+            archival
+                .get_object("post", Some(filename))
+                .ok()
+                .and_then(|o| o.values.get("title").map(|t| t.to_string()))
+        };
+
+        assert!(matches!(
+            import(ImportOptions::default()),
+            Err(ImportError::ObjectExists(..))
+        ));
+        assert_eq!(title("c"), None);
+
+        let result = import(ImportOptions {
+            mode: ImportMode::Upsert,
+            delete_missing: true,
+            dry_run: true,
+        })
+        .unwrap();
+        let previews: Vec<_> = result
+            .previews
+            .iter()
+            .map(|p| (p.filename.as_str(), p.before.is_some(), p.after.is_some()))
+            .collect();
+        assert_eq!(
+            previews,
+            vec![("a", true, true), ("c", false, true), ("b", true, false)]
+        );
+        assert_eq!(title("a"), Some("A".to_string()));
+        assert_eq!(title("c"), None);
+
+        let result = import(ImportOptions {
+            mode: ImportMode::Update,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(result.skipped, vec!["c"]);
+        assert_eq!(title("a"), Some("A2".to_string()));
+        assert_eq!(title("c"), None);
+
+        import(ImportOptions {
+            mode: ImportMode::Upsert,
+            delete_missing: true,
+            dry_run: false,
+        })
+        .unwrap();
+        assert_eq!(title("c"), Some("C".to_string()));
+        assert_eq!(title("b"), None);
     }
 }
 
@@ -514,7 +1199,7 @@ mod csv_tests {
 
     use tracing_test::traced_test;
 
-    use super::{Command, ImportFormat, ImportName};
+    use super::{Command, ImportFormat, ImportName, ImportOptions};
     use crate::fields::DateTime;
     use crate::object::ValuePath;
     use crate::{unpack_zip, FieldValue, MemoryFileSystem};
@@ -528,7 +1213,8 @@ mod csv_tests {
         let zip = include_bytes!("../../../tests/fixtures/archival-website.zip");
         unpack_zip(zip.to_vec(), &mut fs).unwrap();
         let archival = crate::Archival::new_with_upload_prefix(fs, "").unwrap();
-        let obj_def = archival.site.object_definitions.get("post").unwrap();
+        let site = archival.site();
+        let obj_def = site.object_definitions.get("post").unwrap();
         Command::parse(
             Some(BufReader::new(csv_data.as_bytes())),
             "post",
@@ -537,6 +1223,8 @@ mod csv_tests {
             ValuePath::default(),
             obj_def,
             &ImportFormat::Csv,
+            ImportOptions::default(),
+            &mut |_| unreachable!(),
             &archival,
             |m, p, t| println!("{} ({}/{})", m, p, t),
         )
@@ -575,7 +1263,8 @@ mod csv_tests {
         let zip = include_bytes!("../../../tests/fixtures/archival-website.zip");
         unpack_zip(zip.to_vec(), &mut fs).unwrap();
         let archival = crate::Archival::new_with_upload_prefix(fs, "").unwrap();
-        let obj_def = archival.site.object_definitions.get("childlist").unwrap();
+        let site = archival.site();
+        let obj_def = site.object_definitions.get("childlist").unwrap();
         Command::parse(
             Some(BufReader::new(csv_data.as_bytes())),
             "childlist",
@@ -584,6 +1273,8 @@ mod csv_tests {
             ValuePath::from_string("list"),
             obj_def.children.get("list").unwrap(),
             &ImportFormat::Csv,
+            ImportOptions::default(),
+            &mut |_| unreachable!(),
             &archival,
             |m, p, t| println!("{} ({}/{})", m, p, t),
         )
@@ -631,3 +1322,70 @@ mod csv_tests {
         assert!(found);
     }
 }
+
+#[cfg(test)]
+#[cfg(feature = "import-wxr")]
+mod wxr_tests {
+    use super::{Command, ImportFormat, ImportName, ImportOptions};
+    use crate::object::ValuePath;
+    use crate::{unpack_zip, FieldValue, MemoryFileSystem};
+    use std::collections::HashMap;
+    use std::io::BufReader;
+
+    #[test]
+    fn parse_wxr_posts_to_files() {
+        let wxr = r#"<rss xmlns:content="http://purl.org/rss/1.0/modules/content/" xmlns:wp="http://wordpress.org/export/1.2/">
+        <channel>
+          <item>
+            <title>Hello</title>
+            <content:encoded><![CDATA[<p>Some <em>html</em></p>]]></content:encoded>
+            <wp:post_date><![CDATA[2024-01-02 10:00:00]]></wp:post_date>
+            <wp:post_name><![CDATA[from-wordpress]]></wp:post_name>
+            <wp:status><![CDATA[publish]]></wp:status>
+            <wp:post_type><![CDATA[post]]></wp:post_type>
+          </item>
+          <item>
+            <wp:post_type><![CDATA[attachment]]></wp:post_type>
+            <wp:attachment_url><![CDATA[https://example.com/a.png]]></wp:attachment_url>
+          </item>
+        </channel>
+        </rss>"#;
+        let mut fs = MemoryFileSystem::default();
+        let zip = include_bytes!("../../../tests/fixtures/archival-website.zip");
+        unpack_zip(zip.to_vec(), &mut fs).unwrap();
+        let archival = crate::Archival::new_with_upload_prefix(fs, "").unwrap();
+        let site = archival.site();
+        let obj_def = site.object_definitions.get("post").unwrap();
+        let result = Command::parse(
+            Some(BufReader::new(wxr.as_bytes())),
+            "post",
+            HashMap::new(),
+            ImportName::Field("slug".to_string()),
+            ValuePath::default(),
+            obj_def,
+            &ImportFormat::Wxr,
+            ImportOptions::default(),
+            &mut |_| unreachable!(),
+            &archival,
+            |m, p, t| println!("{} ({}/{})", m, p, t),
+        )
+        .unwrap();
+        assert_eq!(result.attachments, vec!["https://example.com/a.png"]);
+        let objects = archival.get_objects().unwrap();
+        let post = objects
+            .get("post")
+            .unwrap()
+            .into_iter()
+            .find(|p| p.filename == "from-wordpress")
+            .unwrap();
+        assert_eq!(
+            post.values.get("title"),
+            Some(&FieldValue::String("Hello".to_string()))
+        );
+        assert_eq!(
+            post.values.get("content"),
+            Some(&FieldValue::Markdown("Some *html*".to_string()))
+        );
+        assert!(matches!(post.values.get("date"), Some(FieldValue::Date(_))));
+    }
+}
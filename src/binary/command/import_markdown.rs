@@ -0,0 +1,560 @@
+//! Imports markdown files with front matter, as written for static site
+//! generators like Jekyll and Hugo, as new objects.
+//!
+//! Front matter may be YAML (between `---` lines) or TOML (between `+++`
+//! lines). Each key is mapped to the field of the same name, or the one given
+//! with `--body`. Upload fields name a local file, relative to the markdown
+//! file. Keys that can't be mapped are reported rather than failing the
+//! import.
+
+use super::import::{existing_objects, ImportError, ImportOptions, ImportResult, RowAction};
+use crate::{
+    events::{AddObjectEvent, ArchivalEvent, EditFieldEvent, RemoveChildEvent},
+    fields::{DateTime, FieldType, File, InvalidFieldError, ObjectValues},
+    journal::{field_values, restore_children},
+    object::{Object, ValuePath},
+    Archival, FieldValue, FileSystemAPI, ObjectDefinition,
+};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug},
+    fs,
+    path::{Path, PathBuf},
+};
+use toml::{Table, Value};
+use walkdir::WalkDir;
+use yaml_rust::{Yaml, YamlLoader};
+
+static RFC3339_OFFSET_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\s*(?<hours>[+-]\d{2}):?(?<minutes>\d{2})$").unwrap());
+
+#[derive(Debug, Clone)]
+pub struct MarkdownFile {
+    pub filename: String,
+    /// The directory the file is in, which upload fields are relative to.
+    pub dir: PathBuf,
+    pub front_matter: Table,
+    pub body: String,
+}
+
+/// A front matter key that wasn't imported, and why.
+#[derive(Debug, Clone)]
+pub struct UnmappedKey {
+    pub filename: String,
+    pub key: String,
+    pub reason: String,
+}
+
+impl fmt::Display for UnmappedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} ({})", self.filename, self.key, self.reason)
+    }
+}
+
+/// The object filename for a markdown file: its name without the extension,
+/// or for Hugo-style bundles (`my-post/index.md`) the name of its directory.
+fn object_filename(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_str()?;
+    let name = if matches!(stem, "index" | "_index") {
+        path.parent()?.file_name()?.to_str()?
+    } else {
+        stem
+    };
+    Some(name.split_whitespace().collect::<Vec<_>>().join("-"))
+}
+
+fn yaml_to_toml(yaml: Yaml) -> Option<Value> {
+    Some(match yaml {
+        Yaml::String(s) => Value::String(s),
+        Yaml::Integer(i) => Value::Integer(i),
+        Yaml::Real(r) => Value::Float(r.parse().ok()?),
+        Yaml::Boolean(b) => Value::Boolean(b),
+        Yaml::Array(items) => Value::Array(items.into_iter().filter_map(yaml_to_toml).collect()),
+        Yaml::Hash(hash) => Value::Table(
+            hash.into_iter()
+                .filter_map(|(k, v)| Some((k.into_string()?, yaml_to_toml(v)?)))
+                .collect(),
+        ),
+        Yaml::Alias(_) | Yaml::Null | Yaml::BadValue => return None,
+    })
+}
+
+/// Splits a markdown file into its front matter and body.
+pub fn parse_file(filename: &str, contents: &str) -> Result<MarkdownFile, ImportError> {
+    let invalid = |e: String| ImportError::InvalidFrontMatter(filename.to_string(), e);
+    let contents = contents.trim_start_matches('\u{feff}');
+    let mut lines = contents.split_inclusive('\n');
+    let fence = lines.next().map(str::trim_end);
+    let (front_matter, body) = match fence {
+        Some(fence @ ("---" | "+++")) => {
+            let start = contents.find('\n').map_or(contents.len(), |i| i + 1);
+            let mut end = start;
+            let mut closed = None;
+            for line in lines {
+                if line.trim_end() == fence {
+                    closed = Some(end + line.len());
+                    break;
+                }
+                end += line.len();
+            }
+            let closed = closed.ok_or_else(|| invalid(format!("missing closing {fence}")))?;
+            let source = &contents[start..end];
+            let front_matter = if fence == "+++" {
+                toml::from_str(source).map_err(|e| invalid(e.to_string()))?
+            } else {
+                match YamlLoader::load_from_str(source)
+                    .map_err(|e| invalid(e.to_string()))?
+                    .into_iter()
+                    .next()
+                {
+                    Some(yaml @ Yaml::Hash(_)) => match yaml_to_toml(yaml) {
+                        Some(Value::Table(table)) => table,
+                        _ => Table::new(),
+                    },
+                    None | Some(Yaml::Null) => Table::new(),
+                    Some(_) => return Err(invalid("front matter is not a map".to_string())),
+                }
+            };
+            (front_matter, &contents[closed..])
+        }
+        _ => (Table::new(), contents),
+    };
+    Ok(MarkdownFile {
+        filename: filename.to_string(),
+        dir: PathBuf::new(),
+        front_matter,
+        body: body.trim_start_matches(['\r', '\n']).to_string(),
+    })
+}
+
+/// Reads `path`, a markdown file or a directory of them.
+pub fn read_files(path: &Path) -> Result<Vec<MarkdownFile>, ImportError> {
+    let mut paths = vec![];
+    if path.is_dir() {
+        for entry in WalkDir::new(path).sort_by_file_name() {
+            let entry = entry.map_err(|e| ImportError::ParseError(e.to_string()))?;
+            if entry.file_type().is_file()
+                && entry
+                    .path()
+                    .extension()
+                    .is_some_and(|ext| ext == "md" || ext == "markdown")
+            {
+                paths.push(entry.into_path());
+            }
+        }
+    } else {
+        paths.push(path.to_path_buf());
+    }
+    paths
+        .into_iter()
+        .map(|path| {
+            let filename = object_filename(&path)
+                .ok_or_else(|| ImportError::InvalidObjectFilename(path.to_owned()))?;
+            let contents = fs::read_to_string(&path)
+                .map_err(|e| ImportError::ParseError(format!("{}: {}", path.display(), e)))?;
+            Ok(MarkdownFile {
+                dir: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+                ..parse_file(&filename, &contents)?
+            })
+        })
+        .collect()
+}
+
+/// Front matter dates are usually RFC 3339 (`2024-01-02T10:00:00Z`) or
+/// Jekyll's `2024-01-02 10:00:00 +0000`, either of which may be just a date.
+/// Returns the format [`DateTime::from`] reads.
+fn date_string(value: &str) -> String {
+    let value = value.trim().replacen('T', " ", 1);
+    if !value.contains(':') {
+        return format!("{value} 00:00:00");
+    }
+    if let Some(value) = value.strip_suffix('Z') {
+        return format!("{value} +0000");
+    }
+    RFC3339_OFFSET_RE
+        .replace(&value, " $hours$minutes")
+        .to_string()
+}
+
+fn front_matter_value(
+    key: &str,
+    field_type: &FieldType,
+    value: &Value,
+) -> anyhow::Result<FieldValue> {
+    let mismatch = || InvalidFieldError::TypeMismatch {
+        field: key.to_string(),
+        field_type: field_type.to_string(),
+        value: value.to_string(),
+    };
+    match field_type.base_type() {
+        FieldType::Date => {
+            let date = match value {
+                Value::String(s) => s.to_string(),
+                Value::Datetime(d) => d.to_string(),
+                _ => return Err(mismatch().into()),
+            };
+            Ok(FieldValue::Date(DateTime::from(&date_string(&date))?))
+        }
+        FieldType::List(item_type) => {
+            let items = match value {
+                Value::Array(items) => items.iter().collect(),
+                // `tags: news` means a list of one.
+                value => vec![value],
+            };
+            Ok(FieldValue::List(
+                items
+                    .into_iter()
+                    .map(|item| front_matter_value(key, item_type, item))
+                    .collect::<anyhow::Result<_>>()?,
+            ))
+        }
+        FieldType::String | FieldType::Secret | FieldType::Markdown | FieldType::Enum(_) => {
+            let value = match value {
+                Value::Integer(_) | Value::Float(_) | Value::Boolean(_) | Value::Datetime(_) => {
+                    Value::String(value.to_string())
+                }
+                value => value.clone(),
+            };
+            FieldValue::from_toml(&key.to_string(), field_type, &value)
+        }
+        _ => FieldValue::from_toml(&key.to_string(), field_type, value),
+    }
+}
+
+/// The values of the object to create for `file`, and the keys that couldn't
+/// be mapped to them. `field_map` maps front matter keys to field names, and
+/// `read_file` uploads the files that upload fields name.
+pub fn object_values<F: FileSystemAPI + Clone + Debug>(
+    file: &MarkdownFile,
+    field_map: &HashMap<String, String>,
+    body_field: Option<&str>,
+    definition: &ObjectDefinition,
+    read_file: &mut dyn FnMut(&Path) -> Result<File, ImportError>,
+    archival: &Archival<F>,
+) -> (ObjectValues, Vec<UnmappedKey>) {
+    let mut values = ObjectValues::new();
+    let mut unmapped = vec![];
+    let mut skip = |key: &str, reason: String| {
+        unmapped.push(UnmappedKey {
+            filename: file.filename.clone(),
+            key: key.to_string(),
+            reason,
+        })
+    };
+    let mut front_matter = file.front_matter.clone();
+    if let Some(body_field) = body_field {
+        front_matter.insert(body_field.to_string(), Value::String(file.body.clone()));
+    } else if !file.body.trim().is_empty() {
+        skip("(body)", "no --body field given".to_string());
+    }
+    for (key, value) in &front_matter {
+        let field = field_map.get(key).unwrap_or(key);
+        if let Some(field_type) = definition.field_type(field) {
+            let value = match value {
+                Value::String(path) if field_type.base_type().is_uploadable() => {
+                    read_file(&file.dir.join(path))
+                        .map(FieldValue::File)
+                        .map_err(anyhow::Error::from)
+                }
+                value => front_matter_value(field, field_type, value),
+            };
+            match value {
+                Ok(value) => {
+                    values.insert(field.to_string(), value);
+                }
+                Err(e) => skip(key, e.to_string()),
+            }
+        } else if let Some(child_def) = definition.children.get(field) {
+            let children = value.as_array().map(|items| {
+                items
+                    .iter()
+                    .map(|item| {
+                        let table = item
+                            .as_table()
+                            .ok_or_else(|| ImportError::InvalidField(format!("{field}: {item}")))?;
+                        Object::values_from_table(
+                            Path::new(&file.filename),
+                            table,
+                            child_def,
+                            &archival.site().manifest.editor_types,
+                            false,
+                        )
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()
+            });
+            match children {
+                Some(Ok(children)) => {
+                    values.insert(field.to_string(), FieldValue::Objects(children));
+                }
+                Some(Err(e)) => skip(key, e.to_string()),
+                None => skip(key, format!("{field} is a list of children")),
+            }
+        } else {
+            skip(key, format!("no field named {field}"));
+        }
+    }
+    (values, unmapped)
+}
+
+/// Creates or updates an object for each of `files`, as `options` says.
+/// Children given in front matter replace an existing object's children.
+/// Returns the keys that weren't imported, as well as the result.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn import<F: FileSystemAPI + Clone + Debug>(
+    files: &[MarkdownFile],
+    object: &str,
+    field_map: &HashMap<String, String>,
+    body_field: Option<&str>,
+    definition: &ObjectDefinition,
+    options: ImportOptions,
+    read_file: &mut dyn FnMut(&Path) -> Result<File, ImportError>,
+    archival: &Archival<F>,
+    progress: impl Fn(&str, u64, u64),
+) -> Result<(ImportResult, Vec<UnmappedKey>), ImportError> {
+    let existing = existing_objects(archival, object)?;
+    let mut result = ImportResult::default();
+    let mut unmapped = vec![];
+    let mut events = vec![];
+    // The values of each imported object, so that files repeating a name
+    // know which children the first one added.
+    let mut imported: Vec<String> = vec![];
+    let mut imported_values: HashMap<String, ObjectValues> = HashMap::new();
+    for (idx, file) in files.iter().enumerate() {
+        progress(
+            &format!("importing {} of {} files...", idx + 1, files.len()),
+            idx as u64 + 1,
+            files.len() as u64,
+        );
+        let exists = existing.contains(&file.filename) || imported.contains(&file.filename);
+        let action = options.row_action(object, &file.filename, exists)?;
+        if action == RowAction::Skip {
+            result.skipped.push(file.filename.clone());
+            continue;
+        }
+        let (values, skipped) =
+            object_values(file, field_map, body_field, definition, read_file, archival);
+        unmapped.extend(skipped);
+        let mut current = if action == RowAction::Create {
+            events.push(ArchivalEvent::AddObject(AddObjectEvent {
+                object: object.to_string(),
+                filename: file.filename.clone(),
+                order: Some(0.),
+                values: field_values(&values),
+            }));
+            ObjectValues::new()
+        } else {
+            let current = match imported_values.remove(&file.filename) {
+                Some(current) => current,
+                None => {
+                    archival
+                        .get_object(object, Some(&file.filename))
+                        .map_err(|e| ImportError::ImportFailed(object.to_string(), e.to_string()))?
+                        .values
+                }
+            };
+            for (field, value) in &values {
+                match value {
+                    FieldValue::Null => {}
+                    FieldValue::Objects(_) => {
+                        let Some(FieldValue::Objects(children)) = current.get(field) else {
+                            continue;
+                        };
+                        for _ in children {
+                            events.push(ArchivalEvent::RemoveChild(RemoveChildEvent {
+                                object: object.to_string(),
+                                filename: file.filename.clone(),
+                                path: ValuePath::empty()
+                                    .append(ValuePath::key(field))
+                                    .append(ValuePath::index(0)),
+                                source: None,
+                            }));
+                        }
+                    }
+                    value => events.push(ArchivalEvent::EditField(EditFieldEvent {
+                        object: object.to_string(),
+                        filename: file.filename.clone(),
+                        path: ValuePath::empty(),
+                        field: field.to_string(),
+                        value: Some(value.clone()),
+                        source: None,
+                    })),
+                }
+            }
+            current
+        };
+        restore_children(
+            object,
+            &file.filename,
+            &ValuePath::empty(),
+            &values,
+            &mut events,
+        );
+        current.extend(values);
+        imported_values.insert(file.filename.clone(), current);
+        if !imported.contains(&file.filename) {
+            imported.push(file.filename.clone());
+        }
+    }
+    result.previews =
+        options.write_events(object, events, &existing, &imported, archival, &progress)?;
+    Ok((result, unmapped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{binary::command::import::ImportMode, unpack_zip, MemoryFileSystem};
+
+    #[test]
+    fn reads_yaml_and_toml_front_matter() -> Result<(), ImportError> {
+        let yaml = parse_file(
+            "hello",
+            "---\ntitle: Hello\ntags: [a, b]\ncount: 3\n---\n\nBody text\n",
+        )?;
+        assert_eq!(yaml.front_matter["title"].as_str(), Some("Hello"));
+        assert_eq!(
+            yaml.front_matter["tags"].as_array().map(|a| a.len()),
+            Some(2)
+        );
+        assert_eq!(yaml.front_matter["count"].as_integer(), Some(3));
+        assert_eq!(yaml.body, "Body text\n");
+
+        let toml = parse_file("hello", "+++\ntitle = \"Hello\"\n+++\nBody\n")?;
+        assert_eq!(toml.front_matter["title"].as_str(), Some("Hello"));
+        assert_eq!(toml.body, "Body\n");
+
+        let plain = parse_file("hello", "Just text\n")?;
+        assert!(plain.front_matter.is_empty());
+        assert!(parse_file("hello", "---\ntitle: x\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn normalizes_front_matter_dates() {
+        assert_eq!(date_string("2024-01-02"), "2024-01-02 00:00:00");
+        assert_eq!(
+            date_string("2024-01-02T10:00:00Z"),
+            "2024-01-02 10:00:00 +0000"
+        );
+        assert_eq!(
+            date_string("2024-01-02T10:00:00-07:00"),
+            "2024-01-02 10:00:00 -0700"
+        );
+        assert_eq!(
+            date_string("2024-01-02 10:00:00 +0000"),
+            "2024-01-02 10:00:00 +0000"
+        );
+        assert_eq!(
+            object_filename(Path::new("posts/my post/index.md")).unwrap(),
+            "my-post"
+        );
+    }
+
+    #[test]
+    fn imports_markdown_as_objects() -> anyhow::Result<()> {
+        let mut fs = MemoryFileSystem::default();
+        let zip = include_bytes!("../../../tests/fixtures/archival-website.zip");
+        unpack_zip(zip.to_vec(), &mut fs)?;
+        let archival = Archival::new_with_upload_prefix(fs, "")?;
+        let site = archival.site();
+        let obj_def = site.object_definitions.get("post").unwrap();
+        let file = parse_file(
+            "2024-01-02-imported",
+            "---\nheadline: Imported\ndate: 2024-01-02T10:00:00Z\nlayout: post\n---\nHi *there*\n",
+        )?;
+        let (_, unmapped) = import(
+            &[file],
+            "post",
+            &HashMap::from([("headline".to_string(), "title".to_string())]),
+            Some("content"),
+            obj_def,
+            ImportOptions::default(),
+            &mut |_| unreachable!(),
+            &archival,
+            |_, _, _| {},
+        )?;
+        let keys: Vec<&str> = unmapped.iter().map(|u| u.key.as_str()).collect();
+        assert_eq!(keys, vec!["layout"]);
+        let post = archival.get_object("post", Some("2024-01-02-imported"))?;
+        assert_eq!(
+            post.values.get("title"),
+            Some(&FieldValue::String("Imported".to_string()))
+        );
+        assert_eq!(
+            post.values.get("content"),
+            Some(&FieldValue::Markdown("Hi *there*\n".to_string()))
+        );
+        assert!(matches!(post.values.get("date"), Some(FieldValue::Date(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn imports_markdown_by_mode() -> anyhow::Result<()> {
+        let mut fs = MemoryFileSystem::default();
+        let zip = include_bytes!("../../../tests/fixtures/archival-website.zip");
+        unpack_zip(zip.to_vec(), &mut fs)?;
+        let archival = Archival::new_with_upload_prefix(fs, "")?;
+        let site = archival.site();
+        let obj_def = site.object_definitions.get("post").unwrap();
+        let files = [
+            parse_file("a-post", "---\ntitle: Updated\n---\n")?,
+            parse_file("new-post", "---\ntitle: New\n---\n")?,
+        ];
+        let posts_before = existing_objects(&archival, "post")?;
+        let import = |options: ImportOptions| {
+            import(
+                &files,
+                "post",
+                &HashMap::new(),
+                None,
+                obj_def,
+                options,
+                &mut |_| unreachable!(),
+                &archival,
+                |_, _, _| {},
+            )
+        };
+        assert!(matches!(
+            import(ImportOptions::default()),
+            Err(ImportError::ObjectExists(_, _))
+        ));
+        let (result, _) = import(ImportOptions {
+            mode: ImportMode::Upsert,
+            dry_run: true,
+            ..Default::default()
+        })?;
+        assert_eq!(result.previews.len(), 2);
+        assert_eq!(existing_objects(&archival, "post")?, posts_before);
+        assert_ne!(
+            archival
+                .get_object("post", Some("a-post"))?
+                .values
+                .get("title"),
+            Some(&FieldValue::String("Updated".to_string()))
+        );
+        let (result, _) = import(ImportOptions {
+            mode: ImportMode::Update,
+            ..Default::default()
+        })?;
+        assert_eq!(result.skipped, vec!["new-post"]);
+        assert_eq!(
+            archival
+                .get_object("post", Some("a-post"))?
+                .values
+                .get("title"),
+            Some(&FieldValue::String("Updated".to_string()))
+        );
+        assert!(archival.get_object("post", Some("new-post")).is_err());
+        import(ImportOptions {
+            mode: ImportMode::Upsert,
+            delete_missing: true,
+            ..Default::default()
+        })?;
+        let mut posts = existing_objects(&archival, "post")?;
+        posts.sort();
+        assert_eq!(posts, vec!["a-post", "new-post"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,604 @@
+//! Reads WordPress export (WXR) files for `archival import --format wxr`.
+//!
+//! Each published, draft or scheduled post and page becomes a row with the
+//! keys `title`, `date`, `slug`, `categories`, `tags`, `author`, `excerpt`,
+//! `content`, `type`, `status`, `link` and `id`, which `--map` can point at
+//! other fields like any other import. Attachments aren't imported; their URLs
+//! are collected so they can be uploaded separately.
+
+use super::import::ImportError;
+use once_cell::sync::Lazy;
+use quick_xml::{escape::unescape, events::Event, Reader, XmlVersion};
+use regex::Regex;
+use std::{collections::HashMap, io::BufRead};
+
+/// Separates the categories and tags of a post in its row. List fields are
+/// split on it again.
+pub const LIST_SEPARATOR: &str = ", ";
+
+/// Post statuses that don't make it into an import.
+const SKIPPED_STATUSES: &[&str] = &["trash", "auto-draft", "inherit"];
+
+#[derive(Debug, Default)]
+pub struct WxrExport {
+    pub rows: Vec<HashMap<String, String>>,
+    pub attachments: Vec<String>,
+}
+
+/// An `<item>` being read: the text of its direct children, keyed by tag name.
+#[derive(Debug, Default)]
+struct Item {
+    values: HashMap<String, String>,
+    categories: Vec<String>,
+    tags: Vec<String>,
+}
+
+fn slugify(title: &str) -> String {
+    title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+impl Item {
+    fn get(&self, key: &str) -> &str {
+        self.values.get(key).map_or("", |v| v.trim())
+    }
+
+    fn into_row(self, authors: &HashMap<String, String>) -> HashMap<String, String> {
+        let login = self.get("dc:creator");
+        let author = authors.get(login).map_or(login, |name| name.as_str());
+        let slug = match self.get("wp:post_name") {
+            "" => match slugify(self.get("title")) {
+                slug if slug.is_empty() => {
+                    format!("{}-{}", self.get("wp:post_type"), self.get("wp:post_id"))
+                }
+                slug => slug,
+            },
+            slug => slug.to_string(),
+        };
+        HashMap::from([
+            ("title", self.get("title").to_string()),
+            ("date", self.get("wp:post_date").to_string()),
+            ("slug", slug),
+            ("categories", self.categories.join(LIST_SEPARATOR)),
+            ("tags", self.tags.join(LIST_SEPARATOR)),
+            ("author", author.to_string()),
+            ("excerpt", self.get("excerpt:encoded").to_string()),
+            ("content", self.get("content:encoded").to_string()),
+            ("type", self.get("wp:post_type").to_string()),
+            ("status", self.get("wp:status").to_string()),
+            ("link", self.get("link").to_string()),
+            ("id", self.get("wp:post_id").to_string()),
+        ])
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect()
+    }
+}
+
+/// Reads the posts, pages and attachment URLs from a WXR file.
+pub fn parse<R: BufRead>(input: R) -> Result<WxrExport, ImportError> {
+    let error = |e: &dyn std::fmt::Display| ImportError::ParseError(format!("wxr: {e}"));
+    let mut reader = Reader::from_reader(input);
+    let mut buf = vec![];
+    let mut export = WxrExport::default();
+    // The open elements, and the text of the innermost one.
+    let mut stack: Vec<String> = vec![];
+    let mut text = String::new();
+    let mut item: Option<Item> = None;
+    let mut category_domain = None;
+    let mut author: HashMap<String, String> = HashMap::new();
+    let mut authors: HashMap<String, String> = HashMap::new();
+    loop {
+        match reader.read_event_into(&mut buf).map_err(|e| error(&e))? {
+            Event::Start(e) => {
+                let name = String::from_utf8_lossy(e.name().as_ref()).to_string();
+                let parent = stack.last().map(|s| s.as_str());
+                match (parent, name.as_str()) {
+                    (Some("channel"), "item") => item = Some(Item::default()),
+                    (Some("item"), "category") => {
+                        category_domain = e
+                            .try_get_attribute("domain")
+                            .map_err(|e| error(&e))?
+                            .map(|a| {
+                                a.normalized_value(XmlVersion::Implicit1_0)
+                                    .map(|v| v.to_string())
+                            })
+                            .transpose()
+                            .map_err(|e| error(&e))?;
+                    }
+                    _ => {}
+                }
+                stack.push(name);
+                text.clear();
+            }
+            Event::Text(e) => text.push_str(&e.decode().map_err(|e| error(&e))?),
+            Event::CData(e) => text.push_str(&e.decode().map_err(|e| error(&e))?),
+            Event::GeneralRef(e) => {
+                let entity = format!("&{};", e.decode().map_err(|e| error(&e))?);
+                text.push_str(&unescape(&entity).map_err(|e| error(&e))?);
+            }
+            Event::End(_) => {
+                let name = stack.pop().unwrap_or_default();
+                let parent = stack.last().map(|s| s.as_str());
+                match (parent, name.as_str()) {
+                    (Some("item"), "category") => {
+                        if let Some(item) = item.as_mut() {
+                            match category_domain.take().as_deref() {
+                                Some("category") => item.categories.push(text.trim().to_string()),
+                                Some("post_tag") => item.tags.push(text.trim().to_string()),
+                                _ => {}
+                            }
+                        }
+                    }
+                    (Some("item"), _) => {
+                        if let Some(item) = item.as_mut() {
+                            item.values.insert(name, std::mem::take(&mut text));
+                        }
+                    }
+                    (Some("channel"), "item") => {
+                        let item = item.take().unwrap_or_default();
+                        match item.get("wp:post_type") {
+                            "attachment" => {
+                                let url = item.get("wp:attachment_url");
+                                if !url.is_empty() && !export.attachments.iter().any(|a| a == url) {
+                                    export.attachments.push(url.to_string());
+                                }
+                            }
+                            "post" | "page"
+                                if !SKIPPED_STATUSES.contains(&item.get("wp:status")) =>
+                            {
+                                export.rows.push(item.into_row(&authors));
+                            }
+                            _ => {}
+                        }
+                    }
+                    (Some("wp:author"), _) => {
+                        author.insert(name, text.trim().to_string());
+                    }
+                    (Some("channel"), "wp:author") => {
+                        if let (Some(login), Some(name)) = (
+                            author.remove("wp:author_login"),
+                            author.remove("wp:author_display_name"),
+                        ) {
+                            authors.insert(login, name);
+                        }
+                        author.clear();
+                    }
+                    _ => {}
+                }
+                text.clear();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(export)
+}
+
+static ATTRIBUTE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"([a-zA-Z_:][-a-zA-Z0-9_:.]*)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap()
+});
+static PARAGRAPH_BREAK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\n[ \t]*\n\s*").unwrap());
+static WHITESPACE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\s+").unwrap());
+static BLANK_LINES_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\n{3,}").unwrap());
+/// Text at the start of a line that markdown would read as a heading, quote,
+/// list item or rule.
+static BLOCK_MARKER_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?:[#>+=-]|\d+[.)])").unwrap());
+
+/// Escapes the characters in `text` that markdown would read as formatting.
+fn escape_markdown(text: &str, line_start: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let is_tag = c == '<'
+            && chars
+                .peek()
+                .is_some_and(|n| n.is_ascii_alphabetic() || matches!(n, '/' | '!'));
+        if is_tag || matches!(c, '\\' | '*' | '_' | '`' | '[' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    match BLOCK_MARKER_RE.find(&escaped) {
+        Some(marker) if line_start => {
+            let at = marker.end() - 1;
+            format!("{}\\{}", &escaped[..at], &escaped[at..])
+        }
+        _ => escaped,
+    }
+}
+
+/// Whether `html` (just after a `<`) starts a tag or comment, rather than
+/// being text like `a < b`.
+fn starts_tag(html: &str) -> bool {
+    let mut chars = html.chars();
+    match chars.next() {
+        Some('/') => chars.next().is_some_and(|c| c.is_ascii_alphabetic()),
+        Some(c) => c.is_ascii_alphabetic() || c == '!',
+        None => false,
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    let named = [
+        ("&nbsp;", " "),
+        ("&hellip;", "…"),
+        ("&mdash;", "—"),
+        ("&ndash;", "–"),
+        ("&lsquo;", "‘"),
+        ("&rsquo;", "’"),
+        ("&ldquo;", "“"),
+        ("&rdquo;", "”"),
+    ];
+    let text = named.iter().fold(text.to_string(), |text, (entity, c)| {
+        text.replace(entity, c)
+    });
+    unescape(&text).map_or(text.clone(), |t| t.into_owned())
+}
+
+/// Converts post HTML to markdown. This handles what WordPress editors
+/// produce: paragraphs (including the blank-line paragraphs of the classic
+/// editor), headings, emphasis, links, images, lists, quotes, code and rules.
+/// Other tags are dropped, keeping their text, and text that markdown would
+/// read as formatting is escaped.
+pub fn html_to_markdown(html: &str) -> String {
+    let mut converter = Converter::default();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            converter.text(rest);
+            break;
+        };
+        converter.text(&rest[..start]);
+        rest = &rest[start..];
+        if !starts_tag(&rest[1..]) {
+            converter.text("<");
+            rest = &rest[1..];
+            continue;
+        }
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let Some(end) = rest.find('>') else {
+            converter.text(rest);
+            break;
+        };
+        converter.tag(&rest[1..end]);
+        rest = &rest[end + 1..];
+    }
+    converter.finish()
+}
+
+/// An element whose contents are rendered on their own, to be wrapped when
+/// it closes.
+enum Container {
+    /// A link, with its href.
+    Link(String),
+    Quote,
+}
+
+#[derive(Default)]
+struct Converter {
+    out: String,
+    /// Open links and quotes, each with the output of its contents so far.
+    open: Vec<(Container, String)>,
+    /// For each open list, the number of its next item if it's ordered.
+    lists: Vec<Option<usize>>,
+    pre: bool,
+    code: bool,
+    skip: bool,
+}
+
+impl Converter {
+    fn out(&mut self) -> &mut String {
+        match self.open.last_mut() {
+            Some((_, buffer)) => buffer,
+            None => &mut self.out,
+        }
+    }
+
+    /// Closes the innermost open container matching `is`, and returns it with
+    /// its contents. Any opened inside it and left unclosed keep their text.
+    /// Closing tags with nothing open to close are ignored.
+    fn close(&mut self, is: impl Fn(&Container) -> bool) -> Option<(Container, String)> {
+        let idx = self.open.iter().rposition(|(container, _)| is(container))?;
+        while self.open.len() > idx + 1 {
+            let (_, inner) = self.open.pop().unwrap();
+            self.out().push_str(&inner);
+        }
+        self.open.pop()
+    }
+
+    fn block(&mut self) {
+        let out = self.out();
+        let trimmed = out.trim_end_matches([' ', '\t']).len();
+        out.truncate(trimmed);
+        if !out.is_empty() && !out.ends_with("\n\n") {
+            out.push_str(if out.ends_with('\n') { "\n" } else { "\n\n" });
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.skip || text.is_empty() {
+            return;
+        }
+        if self.pre {
+            let text = decode_entities(text);
+            self.out().push_str(&text);
+            return;
+        }
+        let text = PARAGRAPH_BREAK_RE.replace_all(text, "\u{0}");
+        let text = WHITESPACE_RE.replace_all(&text, " ");
+        for (idx, paragraph) in text.split('\u{0}').enumerate() {
+            if idx > 0 {
+                self.block();
+            }
+            let paragraph = decode_entities(paragraph);
+            let code = self.code;
+            let out = self.out();
+            let line_start = out.is_empty() || out.ends_with('\n');
+            // Leading whitespace is dropped at the start of a line.
+            let paragraph = if line_start || out.ends_with(' ') {
+                paragraph.trim_start()
+            } else {
+                &paragraph
+            };
+            if code {
+                out.push_str(paragraph);
+            } else {
+                out.push_str(&escape_markdown(paragraph, line_start));
+            }
+        }
+    }
+
+    fn tag(&mut self, tag: &str) {
+        let closing = tag.starts_with('/');
+        let tag = tag.trim_start_matches('/').trim_end_matches('/');
+        let name_end = tag.find(|c: char| c.is_whitespace()).unwrap_or(tag.len());
+        let name = tag[..name_end].to_lowercase();
+        let attr = |key: &str| {
+            ATTRIBUTE_RE
+                .captures_iter(&tag[name_end..])
+                .find(|c| c[1].eq_ignore_ascii_case(key))
+                .and_then(|c| c.get(2).or(c.get(3)).or(c.get(4)))
+                .map(|v| decode_entities(v.as_str()))
+        };
+        match (name.as_str(), closing) {
+            ("script" | "style", closing) => self.skip = !closing,
+            _ if self.skip => {}
+            ("p" | "div" | "figure" | "figcaption" | "table" | "tr", _) => self.block(),
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", false) => {
+                self.block();
+                let level = name[1..].parse().unwrap_or(1);
+                self.out().push_str(&format!("{} ", "#".repeat(level)));
+            }
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", true) => self.block(),
+            ("strong" | "b", _) => self.out().push_str("**"),
+            ("em" | "i", _) => self.out().push('*'),
+            ("code", closing) if !self.pre => {
+                self.code = !closing;
+                self.out().push('`');
+            }
+            ("br", _) => self.out().push_str("  \n"),
+            ("hr", _) => {
+                self.block();
+                self.out().push_str("---");
+                self.block();
+            }
+            ("img", _) => {
+                let (alt, src) = (attr("alt").unwrap_or_default(), attr("src"));
+                if let Some(src) = src {
+                    self.out().push_str(&format!("![{alt}]({src})"));
+                }
+            }
+            ("a", false) => {
+                let href = attr("href").unwrap_or_default();
+                self.open.push((Container::Link(href), String::new()));
+            }
+            ("a", true) => {
+                let Some((Container::Link(href), text)) =
+                    self.close(|c| matches!(c, Container::Link(_)))
+                else {
+                    return;
+                };
+                let text = text.trim();
+                let link = if href.is_empty() {
+                    text.to_string()
+                } else {
+                    format!("[{text}]({href})")
+                };
+                self.out().push_str(&link);
+            }
+            ("pre", false) => {
+                self.block();
+                self.out().push_str("```\n");
+                self.pre = true;
+            }
+            ("pre", true) => {
+                self.pre = false;
+                let out = self.out();
+                if !out.ends_with('\n') {
+                    out.push('\n');
+                }
+                out.push_str("```");
+                self.block();
+            }
+            ("blockquote", false) => {
+                self.block();
+                self.open.push((Container::Quote, String::new()));
+            }
+            ("blockquote", true) => {
+                let Some((_, quote)) = self.close(|c| matches!(c, Container::Quote)) else {
+                    return;
+                };
+                let quote = quote
+                    .trim()
+                    .lines()
+                    .map(|line| format!("> {line}").trim_end().to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                self.out().push_str(&quote);
+                self.block();
+            }
+            ("ul" | "ol", false) => {
+                if self.lists.is_empty() {
+                    self.block();
+                } else {
+                    self.out().push('\n');
+                }
+                self.lists.push((name == "ol").then_some(1));
+            }
+            ("ul" | "ol", true) => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.block();
+                }
+            }
+            ("li", false) => {
+                let indent = "   ".repeat(self.lists.len().saturating_sub(1));
+                let marker = match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}. ", *n - 1)
+                    }
+                    _ => "- ".to_string(),
+                };
+                let out = self.out();
+                if !out.is_empty() && !out.ends_with('\n') {
+                    out.push('\n');
+                }
+                out.push_str(&format!("{indent}{marker}"));
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(mut self) -> String {
+        // Unclosed links and quotes keep their text.
+        while let Some((_, inner)) = self.open.pop() {
+            self.out().push_str(&inner);
+        }
+        BLANK_LINES_RE
+            .replace_all(self.out.trim(), "\n\n")
+            .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = r#"<?xml version="1.0" encoding="UTF-8" ?>
+<rss version="2.0" xmlns:excerpt="http://wordpress.org/export/1.2/excerpt/" xmlns:content="http://purl.org/rss/1.0/modules/content/" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:wp="http://wordpress.org/export/1.2/">
+<channel>
+  <title>A Blog</title>
+  <wp:author><wp:author_login><![CDATA[jo]]></wp:author_login><wp:author_display_name><![CDATA[Jo Writer]]></wp:author_display_name></wp:author>
+  <item>
+    <title>Hello &amp; welcome</title>
+    <dc:creator><![CDATA[jo]]></dc:creator>
+    <content:encoded><![CDATA[<!-- wp:paragraph --><p>Some <strong>bold</strong> text.</p><!-- /wp:paragraph -->]]></content:encoded>
+    <excerpt:encoded><![CDATA[Short]]></excerpt:encoded>
+    <wp:post_id>1</wp:post_id>
+    <wp:post_date><![CDATA[2024-01-02 10:00:00]]></wp:post_date>
+    <wp:post_name><![CDATA[hello-world]]></wp:post_name>
+    <wp:status><![CDATA[publish]]></wp:status>
+    <wp:post_type><![CDATA[post]]></wp:post_type>
+    <category domain="category" nicename="news"><![CDATA[News]]></category>
+    <category domain="post_tag" nicename="a"><![CDATA[A]]></category>
+    <category domain="post_tag" nicename="b"><![CDATA[B]]></category>
+    <wp:postmeta><wp:meta_key><![CDATA[_edit_last]]></wp:meta_key><wp:meta_value><![CDATA[1]]></wp:meta_value></wp:postmeta>
+  </item>
+  <item>
+    <title>photo</title>
+    <wp:post_type><![CDATA[attachment]]></wp:post_type>
+    <wp:status><![CDATA[inherit]]></wp:status>
+    <wp:attachment_url><![CDATA[https://example.com/wp-content/uploads/photo.jpg]]></wp:attachment_url>
+  </item>
+  <item>
+    <title>Old</title>
+    <wp:post_type><![CDATA[page]]></wp:post_type>
+    <wp:status><![CDATA[trash]]></wp:status>
+  </item>
+</channel>
+</rss>"#;
+
+    #[test]
+    fn reads_posts_and_attachments() -> Result<(), ImportError> {
+        let export = parse(EXPORT.as_bytes())?;
+        assert_eq!(
+            export.attachments,
+            vec!["https://example.com/wp-content/uploads/photo.jpg"]
+        );
+        assert_eq!(export.rows.len(), 1);
+        let row = &export.rows[0];
+        assert_eq!(row["title"], "Hello & welcome");
+        assert_eq!(row["slug"], "hello-world");
+        assert_eq!(row["author"], "Jo Writer");
+        assert_eq!(row["categories"], "News");
+        assert_eq!(row["tags"], "A, B");
+        assert_eq!(row["date"], "2024-01-02 10:00:00");
+        assert_eq!(row["excerpt"], "Short");
+        Ok(())
+    }
+
+    #[test]
+    fn converts_html_to_markdown() {
+        assert_eq!(
+            html_to_markdown(
+                "<!-- wp:heading --><h2>Title</h2><p>Some <em>nice</em> &amp; <a href=\"/x\">linked</a> text.<br>Next</p>\
+                 <ul><li>one</li><li>two</li></ul><blockquote><p>Quoted</p></blockquote>\
+                 <img src=\"a.jpg\" alt=\"A\" />"
+            ),
+            "## Title\n\nSome *nice* & [linked](/x) text.  \nNext\n\n- one\n- two\n\n> Quoted\n\n![A](a.jpg)"
+        );
+        // The classic editor separates paragraphs with blank lines.
+        assert_eq!(html_to_markdown("One\n\nTwo\nlines"), "One\n\nTwo lines");
+        assert_eq!(
+            html_to_markdown("<ol><li>a</li><li>b</li></ol><pre><code>x &lt; y\n</code></pre>"),
+            "1. a\n2. b\n\n```\nx < y\n```"
+        );
+    }
+
+    #[test]
+    fn escapes_text_that_looks_like_markdown() {
+        assert_eq!(
+            html_to_markdown("<p># not a heading</p><p>1. not a list, 2 * 3 = 6</p>"),
+            "\\# not a heading\n\n1\\. not a list, 2 \\* 3 = 6"
+        );
+        assert_eq!(
+            html_to_markdown("<p>- dash<br>&gt; quote, [link](x) and <code>a*b</code></p>"),
+            "\\- dash  \n\\> quote, \\[link\\](x) and `a*b`"
+        );
+        // Entities for tags stay text.
+        assert_eq!(html_to_markdown("&lt;div&gt;"), "\\<div>");
+    }
+
+    #[test]
+    fn reads_less_than_as_text() {
+        assert_eq!(
+            html_to_markdown("<p>if a < b and <em>b</em> <= c, then a <3 c</p>"),
+            "if a < b and *b* <= c, then a <3 c"
+        );
+    }
+
+    #[test]
+    fn ignores_stray_closing_tags() {
+        assert_eq!(
+            html_to_markdown(
+                "<p>Before</p></blockquote><p>After</a> <a href=\"/x\">link</blockquote></a></p>"
+            ),
+            "Before\n\nAfter [link](/x)"
+        );
+        // Unclosed containers keep their text.
+        assert_eq!(
+            html_to_markdown("<blockquote><p>Quote <a href=\"/x\">open</p></blockquote>"),
+            "> Quote open"
+        );
+    }
+}
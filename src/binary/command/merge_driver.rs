@@ -0,0 +1,179 @@
+use super::BinaryCommand;
+use crate::{
+    binary::{
+        command::{add_args, command_root, CommandConfig},
+        ExitStatus,
+    },
+    file_system_stdlib::{self, NativeFileSystem},
+    Archival, ArchivalError,
+};
+use anyhow::Result;
+use clap::{arg, value_parser, ArgMatches};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::{atomic::AtomicBool, Arc},
+};
+
+fn read(path: &Path) -> Result<String> {
+    Ok(fs::read_to_string(path)
+        .map_err(|e| ArchivalError::new(&format!("couldn't read {}: {}", path.display(), e)))?)
+}
+
+/// The object file being merged, relative to the site root. Git passes it as
+/// %P; without that, `ours` is used if it is an object file, and otherwise the
+/// object file that holds `ours_contents`, as the working tree still has our
+/// version of it while the driver runs.
+fn merged_path(
+    archival: &Archival<NativeFileSystem>,
+    root_dir: &Path,
+    path: Option<&PathBuf>,
+    ours: &Path,
+    ours_contents: &str,
+) -> Result<PathBuf> {
+    let cwd = env::current_dir()?;
+    let relative = |path: &Path| {
+        let path = cwd.join(path);
+        path.strip_prefix(root_dir).unwrap_or(&path).to_path_buf()
+    };
+    let site = archival.site();
+    if let Some(path) = path {
+        return Ok(relative(path));
+    }
+    if site.object_name_for_path(&relative(ours)).is_some() {
+        return Ok(relative(ours));
+    }
+    let fs = NativeFileSystem::new(root_dir);
+    let mut matches = vec![];
+    for name in site.object_definitions.keys() {
+        for file in site.object_files(name, &fs)? {
+            if fs::read_to_string(root_dir.join(&file)).is_ok_and(|c| c == ours_contents) {
+                matches.push(file);
+            }
+        }
+    }
+    match <[PathBuf; 1]>::try_from(matches) {
+        Ok([path]) => Ok(path),
+        Err(_) => Err(ArchivalError::new(&format!(
+            "couldn't tell which object file {} is; pass its path (%P)",
+            ours.display()
+        ))
+        .into()),
+    }
+}
+
+pub struct Command {}
+impl BinaryCommand for Command {
+    fn name(&self) -> &str {
+        "merge-driver"
+    }
+    fn cli(&self, cmd: clap::Command) -> clap::Command {
+        add_args(
+            cmd.about("merges object files field by field, as a git merge driver")
+                .long_about(
+                    "Merges two versions of an object file with their common ancestor, \
+                     field by field and child by child, writing the result over <ours>. \
+                     Only values changed differently on both sides conflict; they keep \
+                     our version and are listed in a comment at the top of the file, and \
+                     the command exits with an error so git leaves the file conflicted.\n\n\
+                     To use it for a site's objects, add to .gitattributes:\n\n    \
+                     objects/**/*.toml merge=archival\n\n\
+                     and to your git config:\n\n    \
+                     [merge \"archival\"]\n        \
+                     name = archival object merge\n        \
+                     driver = archival merge-driver %O %A %B %P",
+                )
+                .arg(arg!(<base> "the common ancestor (%O).").value_parser(value_parser!(PathBuf)))
+                .arg(
+                    arg!(<ours> "our version, overwritten with the result (%A).")
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(arg!(<theirs> "their version (%B).").value_parser(value_parser!(PathBuf)))
+                .arg(
+                    arg!([path] "the path of the file being merged (%P). If not given, it's found by looking for the object file with our version's contents.")
+                        .value_parser(value_parser!(PathBuf)),
+                ),
+            CommandConfig::no_build(),
+        )
+    }
+    fn handler(&self, args: &ArgMatches, _quit: Arc<AtomicBool>) -> Result<ExitStatus> {
+        let root_dir = command_root(args);
+        let fs = file_system_stdlib::NativeFileSystem::new(&root_dir);
+        let archival = Archival::new(fs)?;
+        let ours = args.get_one::<PathBuf>("ours").unwrap();
+        let ours_contents = read(ours)?;
+        let relative = &merged_path(
+            &archival,
+            &root_dir,
+            args.get_one::<PathBuf>("path"),
+            ours,
+            &ours_contents,
+        )?;
+        let obj_type = archival
+            .site()
+            .object_name_for_path(relative)
+            .ok_or_else(|| {
+                ArchivalError::new(&format!("{} is not an object file", relative.display()))
+            })?
+            .to_string();
+        let filename = relative
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| ArchivalError::new(&format!("invalid path {}", relative.display())))?;
+        let merged = archival.merge_object_files(
+            &obj_type,
+            filename,
+            &read(args.get_one::<PathBuf>("base").unwrap())?,
+            &ours_contents,
+            &read(args.get_one::<PathBuf>("theirs").unwrap())?,
+        )?;
+        fs::write(ours, merged.contents)?;
+        if merged.conflicts.is_empty() {
+            return Ok(ExitStatus::Ok);
+        }
+        eprintln!("Conflicts in {}:", relative.display());
+        for conflict in &merged.conflicts {
+            eprintln!("  {conflict}");
+        }
+        Ok(ExitStatus::Error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{MANIFEST_FILE_NAME, OBJECT_DEFINITION_FILE_NAME};
+    use tempfile::tempdir;
+
+    #[test]
+    fn finds_the_merged_file_without_its_path() -> Result<()> {
+        let site = tempdir()?;
+        let root = site.path().canonicalize()?;
+        fs::write(root.join(MANIFEST_FILE_NAME), "upload_prefix = \"\"\n")?;
+        fs::write(
+            root.join(OBJECT_DEFINITION_FILE_NAME),
+            "[post]\ntitle = \"string\"\n",
+        )?;
+        fs::create_dir_all(root.join("objects/post"))?;
+        fs::write(root.join("objects/post/a.toml"), "title = \"A\"\n")?;
+        fs::write(root.join("objects/post/b.toml"), "title = \"B\"\n")?;
+        let archival = Archival::new(NativeFileSystem::new(&root))?;
+        // Git's copy of our version is a temp file named nothing like it.
+        let ours = root.join(".merge_file_a1b2c3");
+        fs::write(&ours, "title = \"B\"\n")?;
+
+        let path = |path: Option<&PathBuf>, contents: &str| {
+            merged_path(&archival, &root, path, &ours, contents)
+        };
+        assert_eq!(
+            path(None, "title = \"B\"\n")?,
+            PathBuf::from("objects/post/b.toml")
+        );
+        assert_eq!(
+            path(Some(&root.join("objects/post/a.toml")), "title = \"B\"\n")?,
+            PathBuf::from("objects/post/a.toml")
+        );
+        assert!(path(None, "title = \"C\"\n").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+use super::BinaryCommand;
+use crate::{
+    binary::{
+        command::{add_args, command_root, CommandConfig},
+        ExitStatus,
+    },
+    file_system_stdlib,
+    migrate::Migration,
+    Archival, ObjectDefinition,
+};
+use anyhow::Result;
+use clap::{arg, value_parser, ArgMatches};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{atomic::AtomicBool, Arc},
+};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone)]
+pub enum MigrateError {
+    #[error("couldn't run git: {0}")]
+    NoGit(String),
+    #[error("couldn't read {path} at {rev} from git: {error}")]
+    GitShowFailed {
+        path: PathBuf,
+        rev: String,
+        error: String,
+    },
+    #[error("couldn't read {0}: {1}")]
+    NotReadable(PathBuf, String),
+}
+
+fn definitions_from_git(root: &Path, rev: &str, path: &Path) -> Result<String> {
+    let output = std::process::Command::new("git")
+        .current_dir(root)
+        .arg("show")
+        // `./` makes the path relative to the site rather than the repo root.
+        .arg(format!("{rev}:./{}", path.display()))
+        .output()
+        .map_err(|e| MigrateError::NoGit(e.to_string()))?;
+    if !output.status.success() {
+        return Err(MigrateError::GitShowFailed {
+            path: path.to_path_buf(),
+            rev: rev.to_string(),
+            error: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        }
+        .into());
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+fn read(path: &Path) -> Result<String> {
+    Ok(fs::read_to_string(path)
+        .map_err(|e| MigrateError::NotReadable(path.to_path_buf(), e.to_string()))?)
+}
+
+pub struct Command {}
+impl BinaryCommand for Command {
+    fn name(&self) -> &str {
+        "migrate"
+    }
+    fn cli(&self, cmd: clap::Command) -> clap::Command {
+        add_args(
+            cmd.about("rewrites object files after their definitions change")
+                .long_about(
+                    "Rewrites object files after their definitions change.\n\n\
+                     Compares the object definitions your objects were written against \
+                     (by default, archival_objects.toml as of git HEAD) with the current \
+                     ones, proposes operations - renaming, converting, moving and deleting \
+                     fields - and shows how they would change every object file. Nothing \
+                     is written until you run it again with --yes.\n\n\
+                     Fields are only proposed as renamed when there's no doubt about what \
+                     they became; otherwise they're deleted. To adjust the proposal, write \
+                     it out with --print-ops, edit it, and pass it back with --ops.",
+                )
+                .arg(
+                    arg!(--from <path> "a saved copy of the old object definitions.")
+                        .required(false)
+                        .conflicts_with("git")
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    arg!(--git <rev> "the git revision to read the old object definitions from.")
+                        .required(false)
+                        .default_value("HEAD")
+                        .value_parser(value_parser!(String)),
+                )
+                .arg(
+                    arg!(--ops <path> "a JSON file of operations to apply, instead of proposing them.")
+                        .required(false)
+                        .conflicts_with_all(["from", "git"])
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    arg!(--"print-ops" "print the proposed operations as JSON and exit.")
+                        .required(false),
+                )
+                .arg(
+                    arg!(-y --yes "write the migrated files, rather than only showing the changes.")
+                        .required(false),
+                ),
+            CommandConfig::no_build(),
+        )
+    }
+    fn handler(&self, args: &ArgMatches, _quit: Arc<AtomicBool>) -> Result<ExitStatus> {
+        let root_dir = command_root(args);
+        let fs = file_system_stdlib::NativeFileSystem::new(&root_dir);
+        let archival = Archival::new(fs)?;
+        let migration = if let Some(ops) = args.get_one::<PathBuf>("ops") {
+            serde_json::from_str(&read(ops)?)?
+        } else {
+            let old_source = match args.get_one::<PathBuf>("from") {
+                Some(from) => read(from)?,
+                None => definitions_from_git(
+                    &root_dir,
+                    args.get_one::<String>("git").unwrap(),
+                    &archival.site().manifest.object_definition_file,
+                )?,
+            };
+            let old =
+                ObjectDefinition::from_source(&old_source, &archival.site().manifest.editor_types)?;
+            Migration::propose(&old, &archival.site().object_definitions)
+        };
+        if args.get_flag("print-ops") {
+            for ambiguity in &migration.ambiguities {
+                eprintln!("{ambiguity}");
+            }
+            println!("{}", serde_json::to_string_pretty(&migration)?);
+            return Ok(ExitStatus::Ok);
+        }
+        for op in &migration.ops {
+            println!("{op}");
+        }
+        if !migration.ambiguities.is_empty() {
+            println!();
+            for ambiguity in &migration.ambiguities {
+                println!("{ambiguity}");
+            }
+            println!(
+                "These were proposed as deletes. To rename them instead, edit the output \
+                 of --print-ops and pass it back with --ops."
+            );
+        }
+        let plan = archival.plan_migration(&migration)?;
+        if plan.is_empty() {
+            println!("No object files need migrating.");
+            return Ok(ExitStatus::Ok);
+        }
+        for file in &plan.files {
+            println!("\n{}", file.path.display());
+            if file.changes.is_empty() {
+                println!("  reformatted");
+            }
+            for change in &file.changes {
+                println!("  {change}");
+            }
+        }
+        if args.get_flag("yes") {
+            archival.apply_migration(&plan)?;
+            println!("\nMigrated {} files.", plan.files.len());
+        } else {
+            println!(
+                "\n{} files would be migrated. Run again with --yes to write them.",
+                plan.files.len()
+            );
+        }
+        Ok(ExitStatus::Ok)
+    }
+}
@@ -9,17 +9,27 @@ mod build;
 #[cfg(feature = "carriers")]
 mod carriers;
 mod compat;
+mod export;
 mod format;
+mod history;
 mod import;
+#[cfg(feature = "import-markdown")]
+mod import_markdown;
+#[cfg(feature = "import-wxr")]
+mod import_wxr;
 mod login;
 #[cfg(feature = "lsp")]
 mod lsp;
 mod manifest;
+mod merge_driver;
+mod migrate;
 mod objects;
 mod prebuild;
 #[cfg(feature = "proto")]
 mod proto;
+mod restore;
 mod run;
+mod schema_diff;
 #[cfg(feature = "json-schema")]
 mod schemas;
 mod types;
@@ -114,7 +124,13 @@ pub const COMMANDS: &[&'static dyn BinaryCommand] = &[
     &compat::Command {},
     &upload::Command {},
     &import::Command {},
+    &export::Command {},
     &objects::Command {},
+    &migrate::Command {},
+    &merge_driver::Command {},
+    &history::Command {},
+    &restore::Command {},
+    &schema_diff::Command {},
     &types::Command {},
     #[cfg(feature = "carriers")]
     &carriers::Command {},
@@ -0,0 +1,47 @@
+use super::{history::object_arg, BinaryCommand};
+use crate::{
+    binary::{
+        command::{add_args, command_root, CommandConfig},
+        ExitStatus,
+    },
+    file_system_stdlib, Archival,
+};
+use anyhow::Result;
+use clap::{arg, ArgMatches};
+use std::sync::{atomic::AtomicBool, Arc};
+
+pub struct Command {}
+impl BinaryCommand for Command {
+    fn name(&self) -> &str {
+        "restore"
+    }
+    fn cli(&self, cmd: clap::Command) -> clap::Command {
+        add_args(
+            cmd.about("restores an object to a previous version")
+                .long_about(
+                    "Restores an object to how it was at a revision in this site's git \
+                     repository. See `archival history` for an object's revisions.",
+                )
+                .arg(arg!(<object> "the object, e.g. post/hello, or the name of a root object."))
+                .arg(
+                    arg!(--at <rev> "the revision to restore, e.g. a commit hash or HEAD~2.")
+                        .required(true),
+                ),
+            CommandConfig::no_build(),
+        )
+    }
+    fn handler(&self, args: &ArgMatches, _quit: Arc<AtomicBool>) -> Result<ExitStatus> {
+        let root_dir = command_root(args);
+        let fs = file_system_stdlib::NativeFileSystem::new(&root_dir);
+        let archival = Archival::new(fs)?;
+        let (obj_type, filename) = object_arg(args.get_one::<String>("object").unwrap());
+        let revision = args.get_one::<String>("at").unwrap();
+        let responses = archival.restore_object(obj_type, filename, revision, None)?;
+        if responses.is_empty() {
+            println!("{obj_type}/{filename} is already as it was at {revision}.");
+        } else {
+            println!("Restored {obj_type}/{filename} to {revision}.");
+        }
+        Ok(ExitStatus::Ok)
+    }
+}
@@ -0,0 +1,84 @@
+use super::BinaryCommand;
+use crate::{
+    binary::{
+        command::{add_args, command_root, CommandConfig},
+        ExitStatus,
+    },
+    file_system_stdlib, Archival, ArchivalError, ObjectDefinition, ObjectDefinitions,
+};
+use anyhow::Result;
+use clap::{arg, value_parser, ArgMatches};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{atomic::AtomicBool, Arc},
+};
+
+fn read_definitions(
+    path: &Path,
+    archival: &Archival<file_system_stdlib::NativeFileSystem>,
+) -> Result<ObjectDefinitions> {
+    let source = fs::read_to_string(path)
+        .map_err(|e| ArchivalError::new(&format!("couldn't read {}: {}", path.display(), e)))?;
+    ObjectDefinition::from_source(&source, &archival.site().manifest.editor_types)
+}
+
+pub struct Command {}
+impl BinaryCommand for Command {
+    fn name(&self) -> &str {
+        "schema-diff"
+    }
+    fn cli(&self, cmd: clap::Command) -> clap::Command {
+        add_args(
+            cmd.about("compares two object definition files")
+                .long_about(
+                    "Compares two object definition files, classifying each change by \
+                     whether it breaks existing content, editors, or consumers of \
+                     `archival types` output, and counting the objects in this site it \
+                     affects.",
+                )
+                .arg(arg!(<old> "the old object definitions.").value_parser(value_parser!(PathBuf)))
+                .arg(arg!(<new> "the new object definitions.").value_parser(value_parser!(PathBuf)))
+                .arg(arg!(--json "print the changes as JSON.").required(false))
+                .arg(arg!(--check "exit with an error if any change is breaking.").required(false)),
+            CommandConfig::no_build(),
+        )
+    }
+    fn handler(&self, args: &ArgMatches, _quit: Arc<AtomicBool>) -> Result<ExitStatus> {
+        let root_dir = command_root(args);
+        let fs = file_system_stdlib::NativeFileSystem::new(&root_dir);
+        let archival = Archival::new(fs)?;
+        let old = read_definitions(args.get_one::<PathBuf>("old").unwrap(), &archival)?;
+        let new = read_definitions(args.get_one::<PathBuf>("new").unwrap(), &archival)?;
+        let diff = archival.schema_diff(&old, &new)?;
+        if args.get_flag("json") {
+            println!("{}", serde_json::to_string_pretty(&diff)?);
+        } else if diff.changes.is_empty() {
+            println!("No changes.");
+        } else {
+            for change in &diff.changes {
+                let breaks = if change.breaks.is_empty() {
+                    "non-breaking".to_string()
+                } else {
+                    format!(
+                        "breaks {}",
+                        change
+                            .breaks
+                            .iter()
+                            .map(|b| format!("{b:?}").to_lowercase())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                };
+                println!(
+                    "{} ({breaks}; {} objects affected)",
+                    change.change, change.affected_objects
+                );
+            }
+        }
+        if args.get_flag("check") && diff.is_breaking() {
+            return Ok(ExitStatus::Error);
+        }
+        Ok(ExitStatus::Ok)
+    }
+}
@@ -20,7 +20,7 @@ use reqwest::header::{HeaderMap, AUTHORIZATION};
 use serde_json::json;
 use std::{
     fs, io,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{atomic::AtomicBool, Arc},
 };
 use thiserror::Error;
@@ -38,6 +38,8 @@ pub enum UploadError {
     FileIsDirectory(PathBuf),
     #[error("could not read file '{0}': {1}")]
     FileNotReadable(PathBuf, String),
+    #[error("could not write file '{0}': {1}")]
+    FileNotWritable(PathBuf, String),
     #[error("invalid object path '{0}'\n{USAGE_HINT}")]
     InvalidObjectPath(PathBuf),
     #[error("no object {0} found in this site.\n{1}\n{USAGE_HINT}")]
@@ -96,6 +98,176 @@ fn uploadable_fields_hint(def: &ObjectDefinition) -> String {
     }
 }
 
+/// Reads a local file to upload, returning the `File` that will refer to it
+/// once it's uploaded, and its contents.
+pub(super) fn read_file(path: &Path) -> Result<(File, Vec<u8>), UploadError> {
+    match fs::metadata(path) {
+        Ok(meta) if meta.is_dir() => return Err(UploadError::FileIsDirectory(path.to_owned())),
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(UploadError::FileNotExists(path.to_owned()))
+        }
+        Err(e) => return Err(UploadError::FileNotReadable(path.to_owned(), e.to_string())),
+    }
+    // Uploads usually live outside of the site root, so read them from the
+    // OS, not the site's fs.
+    let data =
+        fs::read(path).map_err(|e| UploadError::FileNotReadable(path.to_owned(), e.to_string()))?;
+    let mut file = File::from_mime_guess(mime_guess::from_path(path));
+    file.sha = sha_for_data(&data);
+    file.filename = path
+        .file_name()
+        .map_or("".to_string(), |f| f.to_string_lossy().to_string());
+    Ok((file, data))
+}
+
+/// Where uploaded files are sent.
+pub(super) enum UploadTarget {
+    /// The archival upload api, for a repo (e.g. github/jesseditson/blog).
+    Archival {
+        access_token: String,
+        repo_id: String,
+    },
+    /// A local directory, laid out like the uploads url:
+    /// `<dir>/<upload prefix><sha>/<filename>`.
+    Directory { dir: PathBuf, upload_prefix: String },
+}
+
+impl UploadTarget {
+    /// The archival upload api, for `repo` or, if it isn't given, the repo of
+    /// the first git remote of the site at `root_dir`.
+    pub(super) fn archival(root_dir: &Path, repo: Option<&str>) -> Result<Self, UploadError> {
+        let access_token = ArchivalConfig::get()
+            .access_token
+            .ok_or(UploadError::NotLoggedIn)?;
+        let repo_id = if let Some(repo) = repo {
+            repo.to_string()
+        } else {
+            let github_remote_match = Regex::new(r"github.com.+?\b(.+)\/(.+)\.git").unwrap();
+            let git_command = std::process::Command::new("git")
+                .current_dir(root_dir)
+                .arg("remote")
+                .arg("-v")
+                .output()
+                .map_err(|e| UploadError::NoGit(e.to_string()))?;
+            let output = String::from_utf8(git_command.stdout.as_slice().to_vec())
+                .map_err(|err| UploadError::InferringRepoFailed(err.to_string()))?;
+            let first_origin = output.split("\n").next().ok_or_else(|| {
+                UploadError::InferringRepoFailed(format!("No origins found in {}", output))
+            })?;
+            let first_match = github_remote_match
+                .captures_iter(first_origin)
+                .next()
+                .ok_or_else(|| {
+                    UploadError::InferringRepoFailed(format!(
+                        "No github origin found in {}",
+                        first_origin
+                    ))
+                })?;
+            format!(
+                "github/{}/{}",
+                first_match.get(1).unwrap().as_str(),
+                first_match.get(2).unwrap().as_str()
+            )
+        };
+        Ok(Self::Archival {
+            access_token,
+            repo_id,
+        })
+    }
+
+    /// Uploads `data` as `file`. Returns false if it had already been
+    /// uploaded.
+    pub(super) fn upload(&self, file: &File, data: Vec<u8>) -> Result<bool, UploadError> {
+        match self {
+            Self::Archival {
+                access_token,
+                repo_id,
+            } => upload_to_archival(access_token, repo_id, file, data),
+            Self::Directory { dir, upload_prefix } => {
+                let path = dir
+                    .join(format!("{}{}", upload_prefix, file.sha))
+                    .join(&file.filename);
+                if path.exists() {
+                    return Ok(false);
+                }
+                let write_error =
+                    |e: io::Error| UploadError::FileNotWritable(path.to_owned(), e.to_string());
+                fs::create_dir_all(path.parent().unwrap_or(dir)).map_err(write_error)?;
+                fs::write(&path, data).map_err(write_error)?;
+                Ok(true)
+            }
+        }
+    }
+}
+
+fn upload_to_archival(
+    access_token: &str,
+    repo_id: &str,
+    file: &File,
+    data: Vec<u8>,
+) -> Result<bool, UploadError> {
+    let mut headers = HeaderMap::new();
+    headers.append(
+        AUTHORIZATION,
+        format!("Bearer {}", access_token).parse().unwrap(),
+    );
+    let client = reqwest::blocking::Client::builder()
+        .default_headers(headers)
+        .build()
+        .map_err(|e| UploadError::UploadFailed(API_URL.to_string(), e.to_string()))?;
+    // The upload endpoint is keyed by the repo's durable object id, which
+    // only the api can resolve, so ask it where to send this file.
+    let create_url = format!("{}/create-upload/{}", API_URL, repo_id);
+    let failed = |url: &str| {
+        let url = url.to_string();
+        move |e: reqwest::Error| UploadError::UploadFailed(url, e.to_string())
+    };
+    let r = client
+        .post(&create_url)
+        .json(file)
+        .send()
+        .map_err(failed(&create_url))?;
+    if !r.status().is_success() {
+        return Err(UploadError::UploadFailed(create_url, response_error(r)));
+    }
+    match r
+        .json::<api_response::CreateUploadResponse>()
+        .map_err(failed(&create_url))?
+    {
+        api_response::CreateUploadResponse::Existed => Ok(false),
+        api_response::CreateUploadResponse::Created(upload) => {
+            let upload_url = format!("{}/{}", API_URL, encode_path(&upload.endpoint));
+            // TODO: set a max chunk size and parallelize chunked uploads
+            let put_r = client
+                .put(&upload_url)
+                .query(&[
+                    ("uploadId", &upload.upload_id),
+                    ("partNumber", &"1".to_string()),
+                ])
+                .body(data)
+                .send()
+                .map_err(failed(&upload_url))?;
+            if !put_r.status().is_success() {
+                return Err(UploadError::UploadFailed(upload_url, response_error(put_r)));
+            }
+            let part = put_r
+                .json::<api_response::UploadedPart>()
+                .map_err(failed(&upload_url))?;
+            let r = client
+                .post(&upload_url)
+                .query(&[("action", "mpu-complete"), ("uploadId", &upload.upload_id)])
+                .body(json!({ "parts": vec![part] }).to_string())
+                .send()
+                .map_err(failed(&upload_url))?;
+            if !r.status().is_success() {
+                return Err(UploadError::UploadFailed(upload_url, response_error(r)));
+            }
+            Ok(true)
+        }
+    }
+}
+
 pub struct Command {}
 impl BinaryCommand for Command {
     fn name(&self) -> &str {
@@ -130,24 +302,12 @@ impl BinaryCommand for Command {
     ) -> Result<crate::binary::ExitStatus> {
         let root_dir = command_root(args);
         // Fail fast if we aren't logged in
-        let config = ArchivalConfig::get();
-        let access_token = config.access_token.ok_or(UploadError::NotLoggedIn)?;
+        ArchivalConfig::get()
+            .access_token
+            .ok_or(UploadError::NotLoggedIn)?;
         // Fail fast if file doesn't exist
         let file_path = args.get_one::<PathBuf>("file").unwrap();
-        match fs::metadata(file_path) {
-            Ok(meta) if meta.is_dir() => {
-                return Err(UploadError::FileIsDirectory(file_path.to_owned()).into())
-            }
-            Ok(_) => {}
-            Err(e) if e.kind() == io::ErrorKind::NotFound => {
-                return Err(UploadError::FileNotExists(file_path.to_owned()).into())
-            }
-            Err(e) => {
-                return Err(
-                    UploadError::FileNotReadable(file_path.to_owned(), e.to_string()).into(),
-                )
-            }
-        }
+        let (file, file_data) = read_file(file_path)?;
         let object = args.get_one::<PathBuf>("object").unwrap();
         let object_name = object
             .with_extension("")
@@ -182,7 +342,7 @@ impl BinaryCommand for Command {
         // surfaces here as an Err rather than Ok(false).
         if !matches!(archival.object_exists(&object_type, &object_name), Ok(true)) {
             let known_types = archival
-                .site
+                .site()
                 .object_definitions
                 .keys()
                 .cloned()
@@ -200,8 +360,8 @@ impl BinaryCommand for Command {
             .into());
         }
         // Find the specified object definition
-        let obj_def = archival
-            .site
+        let site = archival.site();
+        let obj_def = site
             .object_definitions
             .get(&object_type)
             .ok_or_else(|| UploadError::InvalidObjectType(object_type.to_owned()))?;
@@ -223,95 +383,18 @@ impl BinaryCommand for Command {
             .into());
         }
         // Validate repo
-        let repo_id = if let Some(repo) = args.get_one::<String>("repo") {
-            repo.to_string()
-        } else {
-            let github_remote_match = Regex::new(r"github.com.+?\b(.+)\/(.+)\.git").unwrap();
-            let git_command = std::process::Command::new("git")
-                .current_dir(root_dir)
-                .arg("remote")
-                .arg("-v")
-                .output()
-                .map_err(|e| UploadError::NoGit(e.to_string()))?;
-            let output = String::from_utf8(git_command.stdout.as_slice().to_vec())
-                .map_err(|err| UploadError::InferringRepoFailed(err.to_string()))?;
-            let first_origin = output.split("\n").next().ok_or_else(|| {
-                UploadError::InferringRepoFailed(format!("No origins found in {}", output))
-            })?;
-            let first_match = github_remote_match
-                .captures_iter(first_origin)
-                .next()
-                .ok_or_else(|| {
-                    UploadError::InferringRepoFailed(format!(
-                        "No github origin found in {}",
-                        first_origin
-                    ))
-                })?;
-            format!(
-                "github/{}/{}",
-                first_match.get(1).unwrap().as_str(),
-                first_match.get(2).unwrap().as_str()
-            )
-        };
-        // Ok, this looks legit. Upload the file. Uploads usually live outside
-        // of the site root, so read them from the OS, not the site's fs.
-        let file_data = fs::read(file_path)
-            .map_err(|e| UploadError::FileNotReadable(file_path.to_owned(), e.to_string()))?;
-        let sha = sha_for_data(&file_data);
-        let mime = mime_guess::from_path(file_path);
-        let mut file = File::from_mime_guess(mime);
-        file.sha = sha;
-        file.filename = file_path
-            .file_name()
-            .map_or("".to_string(), |f| f.to_string_lossy().to_string());
+        let target = UploadTarget::archival(
+            &root_dir,
+            args.get_one::<String>("repo").map(|r| r.as_str()),
+        )?;
+        // Ok, this looks legit. Upload the file.
         let bar = ProgressBar::new_spinner();
         bar.set_style(ProgressStyle::with_template("{msg} {spinner}").unwrap());
         bar.set_message(format!("uploading {}", file_path.to_string_lossy()));
-        let mut headers = HeaderMap::new();
-        headers.append(
-            AUTHORIZATION,
-            format!("Bearer {}", access_token).parse().unwrap(),
-        );
-        let client = reqwest::blocking::Client::builder()
-            .default_headers(headers)
-            .build()?;
-        // The upload endpoint is keyed by the repo's durable object id, which
-        // only the api can resolve, so ask it where to send this file.
-        let create_url = format!("{}/create-upload/{}", API_URL, repo_id);
-        let r = client.post(&create_url).json(&file).send()?;
-        let create_status = r.status();
-        if !create_status.is_success() {
-            return Err(UploadError::UploadFailed(create_url, response_error(r)).into());
-        }
-        match r.json::<api_response::CreateUploadResponse>()? {
-            api_response::CreateUploadResponse::Existed => {
-                bar.finish_with_message(format!("{} was already uploaded", file.filename));
-            }
-            api_response::CreateUploadResponse::Created(upload) => {
-                let upload_url = format!("{}/{}", API_URL, encode_path(&upload.endpoint));
-                // TODO: set a max chunk size and parallelize chunked uploads
-                let put_r = client
-                    .put(&upload_url)
-                    .query(&[
-                        ("uploadId", &upload.upload_id),
-                        ("partNumber", &"1".to_string()),
-                    ])
-                    .body(file_data)
-                    .send()?;
-                if !put_r.status().is_success() {
-                    return Err(UploadError::UploadFailed(upload_url, response_error(put_r)).into());
-                }
-                let part = put_r.json::<api_response::UploadedPart>()?;
-                let r = client
-                    .post(&upload_url)
-                    .query(&[("action", "mpu-complete"), ("uploadId", &upload.upload_id)])
-                    .body(json!({ "parts": vec![part] }).to_string())
-                    .send()?;
-                if !r.status().is_success() {
-                    return Err(UploadError::UploadFailed(upload_url, response_error(r)).into());
-                }
-                bar.finish();
-            }
+        if target.upload(&file, file_data)? {
+            bar.finish();
+        } else {
+            bar.finish_with_message(format!("{} was already uploaded", file.filename));
         }
         let field_data = FieldValue::File(file);
         // Now write our file
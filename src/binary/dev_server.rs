@@ -1,7 +1,7 @@
 #[cfg(feature = "carriers")]
 use crate::binary::carriers::{objects::build_payload, CarrierOptions, CarrierSupervisor};
-use crate::BuildOptions;
-use crate::{file_system::WatchableFileSystemAPI, file_system_stdlib, server, site::Site};
+use crate::{file_system::WatchableFileSystemAPI, file_system_stdlib, server};
+use crate::{Archival, BuildOptions};
 use anyhow::Result;
 use console::{style, Term};
 use indicatif::ProgressBar;
@@ -98,24 +98,26 @@ pub fn watch_with(options: DevServerOptions) -> Result<crate::binary::ExitStatus
     let mut term = Term::stdout();
     let is_interactive =
         term.features().is_attended() && !matches!(mode, DevServerMode::NoServeStreamLogs);
-    let mut fs = file_system_stdlib::NativeFileSystem::new(&root_dir);
-    let mut site = Site::load(&fs, uploads_config.prefix)?;
+    let fs = file_system_stdlib::NativeFileSystem::new(&root_dir);
+    let archival = match uploads_config.prefix {
+        Some(prefix) => Archival::new_with_upload_prefix(fs.clone(), prefix)?,
+        None => Archival::new(fs.clone())?,
+    };
     if let Some(uploads_url) = uploads_config.url {
-        site.modify_manifest(&mut fs, |manifest| {
+        archival.modify_manifest(|manifest| {
             manifest.uploads_url = Some(uploads_url.to_string());
         })?;
     }
-    site.sync_static_files(&mut fs)?;
     let (tx, rx) = mpsc::channel();
-    let initial_build = site.build(&mut fs, BuildOptions::default());
-    let mut init_message = format!("Watching site: {}", site);
+    let initial_build = archival.build(BuildOptions::default());
+    let mut init_message = format!("Watching site: {}", archival.site());
     let change_queue = Arc::new(RwLock::new(vec![]));
     let queue_changes = change_sender.is_some();
     // This won't leak because the process is ended when we
     // abort anyway
     let watcher_queue = change_queue.clone();
     let mut merged_watch_paths = watch_paths.unwrap_or_default();
-    merged_watch_paths.append(&mut site.manifest.watched_paths());
+    merged_watch_paths.append(&mut archival.site().manifest.watched_paths());
     // Carriers need a running server to be reachable, so with --noserve there
     // is nothing to attach them to.
     #[cfg(feature = "carriers")]
@@ -139,7 +141,7 @@ pub fn watch_with(options: DevServerOptions) -> Result<crate::binary::ExitStatus
             }
         }
     })?;
-    let path = root_dir.join(&site.manifest.build_dir);
+    let path = root_dir.join(&archival.site().manifest.build_dir);
     #[cfg(feature = "carriers")]
     let mut carrier_site_url = site_url;
     if let DevServerMode::Serve(port) = mode {
@@ -182,10 +184,11 @@ pub fn watch_with(options: DevServerOptions) -> Result<crate::binary::ExitStatus
     })?;
     let mut last_build = Instant::now();
     let mut changed = false;
-    // The object definition file (the "objects file" the manifest points to) is
-    // parsed into memory once at load time; invalidate_file only clears caches
-    // and never refreshes it. Track edits to it so we can reload the site.
-    let mut object_definitions_changed = false;
+    // Changes go through archival so that its subscribers hear about them, and
+    // a changed manifest or object definition file reloads the site. When that
+    // reload fails we keep the previously loaded site so the dev server stays
+    // up, and report the failure in place of the next rebuild.
+    let mut reload_error = None;
     // Static files are synced at startup, so rebuilds only need to re-sync
     // them when a file inside the static dir actually changed.
     let mut static_files_changed = false;
@@ -206,7 +209,7 @@ pub fn watch_with(options: DevServerOptions) -> Result<crate::binary::ExitStatus
             Ok(path) => {
                 let changed_file = path.strip_prefix(&root_dir).unwrap();
                 // Carrier files are none of the site's business. Handing them
-                // to invalidate_file would make every file an `npm install`
+                // to files_changed would make every file an `npm install`
                 // writes trigger a full site rebuild.
                 //
                 // Handled without `continue`, so that a burst of carrier events
@@ -222,13 +225,18 @@ pub fn watch_with(options: DevServerOptions) -> Result<crate::binary::ExitStatus
                     }
                 }
                 if !claimed {
-                    if changed_file == site.manifest.object_definition_file {
-                        object_definitions_changed = true;
-                    }
+                    let site = archival.site();
                     if changed_file.starts_with(&site.manifest.static_dir) {
                         static_files_changed = true;
                     }
-                    site.invalidate_file(changed_file);
+                    if let Err(e) = archival.files_changed([changed_file.to_path_buf()]) {
+                        reload_error = Some(e);
+                    }
+                    // Reloading the site clears its static file cache (and may
+                    // change the static dir), so sync in that case too.
+                    if !Arc::ptr_eq(&site, &archival.site()) {
+                        static_files_changed = true;
+                    }
                     changed = true;
                 }
             }
@@ -251,8 +259,11 @@ pub fn watch_with(options: DevServerOptions) -> Result<crate::binary::ExitStatus
         #[cfg(feature = "carriers")]
         if carrier_objects_stale {
             if let Some(carriers) = &carriers {
-                let fs = file_system_stdlib::NativeFileSystem::new(&root_dir);
-                match build_payload(&site, &fs, carrier_site_url.as_deref().unwrap_or_default()) {
+                match build_payload(
+                    &archival.site(),
+                    &fs,
+                    carrier_site_url.as_deref().unwrap_or_default(),
+                ) {
                     Ok(payload) => {
                         carriers.set_objects(payload);
                         carrier_objects_stale = false;
@@ -283,35 +294,14 @@ pub fn watch_with(options: DevServerOptions) -> Result<crate::binary::ExitStatus
                 println!("Rebuilding...");
                 None
             };
-            let mut fs = file_system_stdlib::NativeFileSystem::new(&root_dir);
-            // When the object definition file changed, reload the site so the
-            // rebuild picks up the latest definitions. On failure we keep the
-            // previously loaded site so the dev server stays up.
-            let mut reload_error = None;
-            let mut site_reloaded = false;
-            if object_definitions_changed {
-                object_definitions_changed = false;
-                match Site::load(&fs, uploads_config.prefix) {
-                    Ok(mut reloaded) => {
-                        if let Some(uploads_url) = uploads_config.url {
-                            reloaded.manifest.uploads_url = Some(uploads_url.to_string());
-                        }
-                        site = reloaded;
-                        site_reloaded = true;
-                    }
-                    Err(e) => reload_error = Some(e),
-                }
-            }
-            let output = if let Some(e) = reload_error {
+            let output = if let Some(e) = reload_error.take() {
                 format!("{} {}", style("Reload failed:").red(), style(e).red())
             } else {
-                // Reloading the site clears its static file cache (and may
-                // change the static dir), so sync in that case too.
-                if static_files_changed || site_reloaded {
-                    static_files_changed = false;
-                    site.sync_static_files(&mut fs).unwrap();
-                }
-                if let Err(e) = site.build(&mut fs, BuildOptions::default()) {
+                let options = BuildOptions {
+                    skip_static: !std::mem::take(&mut static_files_changed),
+                    ..Default::default()
+                };
+                if let Err(e) = archival.build(options) {
                     format!("{} {}", style("Build failed:").red(), style(e).red())
                 } else {
                     #[cfg(feature = "carriers")]
@@ -194,7 +194,7 @@ mod tests {
         // Get the initial rendered output
         let initial_html = archival
             .fs_mutex
-            .with_fs(|fs| fs.read_to_string(archival.site.manifest.build_dir.join("index.html")))?
+            .with_fs(|fs| fs.read_to_string(archival.site().manifest.build_dir.join("index.html")))?
             .unwrap();
         println!("initial html: {}", initial_html);
 
@@ -217,7 +217,7 @@ mod tests {
         // Get the updated rendered output
         let updated_html = archival
             .fs_mutex
-            .with_fs(|fs| fs.read_to_string(archival.site.manifest.build_dir.join("index.html")))?
+            .with_fs(|fs| fs.read_to_string(archival.site().manifest.build_dir.join("index.html")))?
             .unwrap();
         println!("updated html: {}", updated_html);
 
@@ -232,4 +232,40 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    #[traced_test]
+    fn data_files_are_rendered_and_tracked() -> Result<()> {
+        let mut fs = MemoryFileSystem::default();
+        let zip = include_bytes!("../tests/fixtures/archival-website.zip");
+        unpack_zip(zip.to_vec(), &mut fs)?;
+        let archival = Archival::new(fs)?;
+        let manifest = &archival.site().manifest;
+        let data_file = manifest.data_dir.join("site.json");
+        archival.fs_mutex.with_fs(|fs| {
+            fs.write_str(&data_file, r#"{"tagline": "from data"}"#.to_string())?;
+            fs.write_str(
+                manifest.pages_dir.join("data-test.liquid"),
+                "<p>{{ data.site.tagline }}</p>".to_string(),
+            )
+        })?;
+        assert!(archival.list_build_files()?.any(|p| p == data_file));
+        archival.build(BuildOptions::default())?;
+        let html = archival
+            .fs_mutex
+            .with_fs(|fs| fs.read_to_string(manifest.build_dir.join("data-test.html")))?
+            .unwrap();
+        assert!(html.contains("<p>from data</p>"), "{}", html);
+
+        let fs_id = archival.fs_id()?;
+        archival
+            .fs_mutex
+            .with_fs(|fs| fs.write_str(&data_file, r#"{"tagline": "changed"}"#.to_string()))?;
+        assert_ne!(
+            archival.fs_id()?,
+            fs_id,
+            "fs id did not change after a data file changed"
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,249 @@
+//! Notifications of what changed in a site, for embedders that need to keep
+//! something (an editor, or the clients of a server) up to date without
+//! polling [`crate::Archival::objects_generation`].
+//!
+//! [`crate::Archival::subscribe`] returns a channel that receives a
+//! [`Change`] for each object an event touches, for files reported by a
+//! watcher through [`crate::Archival::files_changed`], and for each build.
+//! Dropping the receiver unsubscribes.
+
+use crate::{
+    events::ArchivalEvent, object::Object, reserved_fields, value_path::ValuePath, DistFile,
+};
+use serde::Serialize;
+use std::{
+    collections::BTreeSet,
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Mutex,
+    },
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum Change {
+    ObjectAdded {
+        object: String,
+        filename: String,
+    },
+    ObjectRemoved {
+        object: String,
+        filename: String,
+    },
+    ObjectRenamed {
+        object: String,
+        from: String,
+        to: String,
+    },
+    /// The value at `path` changed. Changes to an object's order have the
+    /// path `order`, and an empty path means the whole object may have
+    /// changed.
+    FieldChanged {
+        object: String,
+        filename: String,
+        path: ValuePath,
+    },
+    /// The manifest and object definitions were read again.
+    DefinitionsReloaded,
+    /// A build finished. `changed` holds the files in the build dir that it
+    /// wrote, and `removed` the paths it deleted, both relative to the build
+    /// dir.
+    BuildCompleted {
+        build_id: u64,
+        changed: Vec<DistFile>,
+        removed: Vec<PathBuf>,
+    },
+}
+
+impl Change {
+    fn field(object: &str, filename: &str, path: ValuePath) -> Self {
+        Self::FieldChanged {
+            object: object.to_string(),
+            filename: filename.to_string(),
+            path,
+        }
+    }
+}
+
+impl From<&ArchivalEvent> for Change {
+    fn from(event: &ArchivalEvent) -> Self {
+        let (object, filename) = (event.object_name(), event.filename());
+        match event {
+            ArchivalEvent::AddObject(_) | ArchivalEvent::AddRootObject(_) => Self::ObjectAdded {
+                object: object.to_string(),
+                filename: filename.to_string(),
+            },
+            ArchivalEvent::DeleteObject(_) => Self::ObjectRemoved {
+                object: object.to_string(),
+                filename: filename.to_string(),
+            },
+            ArchivalEvent::RenameObject(event) => Self::ObjectRenamed {
+                object: object.to_string(),
+                from: event.from.clone(),
+                to: event.to.clone(),
+            },
+            ArchivalEvent::EditField(event) => Self::field(
+                object,
+                filename,
+                event
+                    .path
+                    .clone()
+                    .concat(ValuePath::from_string(&event.field)),
+            ),
+            ArchivalEvent::EditOrder(_) => Self::field(
+                object,
+                filename,
+                ValuePath::empty().append(ValuePath::key(reserved_fields::ORDER)),
+            ),
+            // Adding or removing an item shifts those after it, so the whole
+            // list is reported.
+            ArchivalEvent::AddChild(event) => Self::field(object, filename, event.path.clone()),
+            ArchivalEvent::AddListValue(event) => Self::field(object, filename, event.path.clone()),
+            ArchivalEvent::MoveListValue(event) => {
+                Self::field(object, filename, event.path.clone())
+            }
+            ArchivalEvent::RemoveChild(event) => {
+                let mut path = event.path.clone();
+                path.pop();
+                Self::field(object, filename, path)
+            }
+            ArchivalEvent::RemoveListValue(event) => {
+                let mut path = event.path.clone();
+                path.pop();
+                Self::field(object, filename, path)
+            }
+        }
+    }
+}
+
+/// The changes to an object file seen by a watcher, given its object before
+/// (if it had been read) and after (if it could be read). Edits are reported
+/// per top-level field.
+pub(crate) fn object_changes(
+    object: &str,
+    filename: &str,
+    old: Option<&Object>,
+    new: Option<&Object>,
+    exists: bool,
+) -> Vec<Change> {
+    if !exists {
+        return vec![Change::ObjectRemoved {
+            object: object.to_string(),
+            filename: filename.to_string(),
+        }];
+    }
+    let (Some(old), Some(new)) = (old, new) else {
+        return vec![Change::field(object, filename, ValuePath::empty())];
+    };
+    let mut changes = vec![];
+    if old.order != new.order {
+        changes.push(Change::field(
+            object,
+            filename,
+            ValuePath::empty().append(ValuePath::key(reserved_fields::ORDER)),
+        ));
+    }
+    let keys: BTreeSet<&String> = old.values.keys().chain(new.values.keys()).collect();
+    for key in keys {
+        if old.values.get(key) != new.values.get(key) {
+            changes.push(Change::field(
+                object,
+                filename,
+                ValuePath::empty().append(ValuePath::key(key)),
+            ));
+        }
+    }
+    changes
+}
+
+/// The channels changes are sent to.
+#[derive(Debug, Default)]
+pub(crate) struct Subscribers(Mutex<Vec<Sender<Change>>>);
+
+impl Subscribers {
+    pub(crate) fn subscribe(&self) -> Receiver<Change> {
+        let (tx, rx) = mpsc::channel();
+        self.0.lock().unwrap().push(tx);
+        rx
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.lock().unwrap().is_empty()
+    }
+
+    /// Sends `changes` to every subscriber, dropping those that have gone
+    /// away.
+    pub(crate) fn notify(&self, changes: Vec<Change>) {
+        if changes.is_empty() {
+            return;
+        }
+        self.0
+            .lock()
+            .unwrap()
+            .retain(|tx| changes.iter().all(|change| tx.send(change.clone()).is_ok()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{events::EditFieldEvent, fields::FieldValue};
+
+    fn object(order: Option<f64>, values: &[(&str, &str)]) -> Object {
+        Object {
+            filename: "a".to_string(),
+            object_name: "post".to_string(),
+            order,
+            values: values
+                .iter()
+                .map(|(k, v)| (k.to_string(), FieldValue::String(v.to_string())))
+                .collect(),
+        }
+    }
+
+    fn paths(changes: Vec<Change>) -> Vec<String> {
+        changes
+            .into_iter()
+            .map(|change| match change {
+                Change::FieldChanged { path, .. } => path.to_string(),
+                change => format!("{:?}", change),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn diffs_watched_objects_by_field() {
+        let old = object(Some(1.), &[("title", "A"), ("body", "x")]);
+        let new = object(Some(2.), &[("title", "A"), ("summary", "y")]);
+        assert_eq!(
+            paths(object_changes("post", "a", Some(&old), Some(&new), true)),
+            vec!["order", "body", "summary"]
+        );
+        assert_eq!(
+            paths(object_changes("post", "a", None, Some(&new), true)),
+            vec![""]
+        );
+        assert!(matches!(
+            object_changes("post", "a", Some(&old), None, false)[..],
+            [Change::ObjectRemoved { .. }]
+        ));
+    }
+
+    #[test]
+    fn dropped_subscribers_are_forgotten() {
+        let subscribers = Subscribers::default();
+        let kept = subscribers.subscribe();
+        drop(subscribers.subscribe());
+        let change = Change::from(&ArchivalEvent::EditField(EditFieldEvent {
+            object: "post".to_string(),
+            filename: "a".to_string(),
+            path: ValuePath::from_string("links.0"),
+            field: "url".to_string(),
+            value: None,
+            source: None,
+        }));
+        subscribers.notify(vec![change.clone()]);
+        assert_eq!(kept.try_recv().ok(), Some(change));
+        assert_eq!(subscribers.0.lock().unwrap().len(), 1);
+    }
+}
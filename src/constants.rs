@@ -12,6 +12,7 @@ pub const BUILD_DIR_NAME: &str = "dist";
 pub const SCHEMAS_DIR_NAME: &str = "schemas";
 pub const STATIC_DIR_NAME: &str = "public";
 pub const LAYOUT_DIR_NAME: &str = "layout";
+pub const DATA_DIR_NAME: &str = "data";
 pub const NESTED_TYPES: [&str; 5] = ["meta", "upload", "video", "audio", "image"];
 #[cfg(debug_assertions)]
 pub const UPLOADS_URL: &str = "http://localhost:7777";
@@ -0,0 +1,217 @@
+//! Global data files. Every `.json`, `.toml` and `.csv` file in the manifest's
+//! `data_dir` is parsed and exposed to templates as `data.<filename>`, with
+//! files in subdirectories nested under the directory name
+//! (`data/team/members.csv` is `data.team.members`).
+//!
+//! JSON and TOML files become whatever value they contain. CSV files become an
+//! array with an object per row, keyed by the header row; their values are
+//! always strings.
+
+use crate::{ArchivalError, FileSystemAPI};
+use anyhow::Result;
+use liquid::model::Value;
+use std::path::{Component, Path};
+
+/// Reads all data files in `data_dir`. A missing directory is not an error,
+/// since most sites don't have one.
+pub(crate) fn load(data_dir: &Path, fs: &impl FileSystemAPI) -> Result<liquid::Object> {
+    let mut data = liquid::Object::new();
+    if !fs.exists(data_dir)? {
+        return Ok(data);
+    }
+    let mut files: Vec<_> = fs.walk_dir(data_dir, false)?.collect();
+    files.sort();
+    for file in files {
+        let Some(extension) = file.extension().and_then(|e| e.to_str()) else {
+            continue;
+        };
+        let Some(contents) = fs.read_to_string(data_dir.join(&file))? else {
+            continue;
+        };
+        let value = parse(extension, &contents)
+            .map_err(|e| ArchivalError::new(&format!("data file {}: {}", file.display(), e)))?;
+        let Some(value) = value else {
+            continue;
+        };
+        insert(&mut data, &file, value)?;
+    }
+    Ok(data)
+}
+
+/// Parses the contents of a data file, returning `None` for files that aren't
+/// data files.
+fn parse(extension: &str, contents: &str) -> Result<Option<Value>> {
+    Ok(Some(match extension {
+        "json" => {
+            let value: serde_json::Value = serde_json::from_str(contents)?;
+            liquid_core::model::to_value(&value)?
+        }
+        "toml" => toml_value(toml::from_str(contents)?),
+        "csv" => csv_value(contents)?,
+        _ => return Ok(None),
+    }))
+}
+
+/// Converted by hand rather than through serde, because toml datetimes
+/// serialize as a private wrapper struct rather than a string.
+fn toml_value(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::scalar(s),
+        toml::Value::Integer(i) => Value::scalar(i),
+        toml::Value::Float(f) => Value::scalar(f),
+        toml::Value::Boolean(b) => Value::scalar(b),
+        toml::Value::Datetime(d) => Value::scalar(d.to_string()),
+        toml::Value::Array(a) => Value::array(a.into_iter().map(toml_value)),
+        toml::Value::Table(t) => Value::Object(
+            t.into_iter()
+                .map(|(k, v)| (k.into(), toml_value(v)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(feature = "import-csv")]
+fn csv_value(contents: &str) -> Result<Value> {
+    let mut reader = csv::ReaderBuilder::new().from_reader(contents.as_bytes());
+    let headers = reader.headers()?.clone();
+    let mut rows = vec![];
+    for record in reader.records() {
+        let record = record?;
+        let row: liquid::Object = headers
+            .iter()
+            .zip(record.iter())
+            .map(|(header, value)| (header.to_string().into(), Value::scalar(value.to_string())))
+            .collect();
+        rows.push(Value::Object(row));
+    }
+    Ok(Value::Array(rows))
+}
+
+#[cfg(not(feature = "import-csv"))]
+fn csv_value(_contents: &str) -> Result<Value> {
+    Err(ArchivalError::new(
+        "csv data files require archival to be built with the `import-csv` feature",
+    )
+    .into())
+}
+
+/// Inserts `value` at the key path made of `file`'s parent directories and
+/// stem.
+fn insert(data: &mut liquid::Object, file: &Path, value: Value) -> Result<()> {
+    let conflict = || {
+        ArchivalError::new(&format!(
+            "data file {} conflicts with another data file or directory of the same name",
+            file.display()
+        ))
+    };
+    let mut target = data;
+    if let Some(parent) = file.parent() {
+        for component in parent.components() {
+            let Component::Normal(dir) = component else {
+                continue;
+            };
+            let entry = target
+                .entry(dir.to_string_lossy().into_owned())
+                .or_insert_with(|| Value::Object(liquid::Object::new()));
+            target = match entry {
+                Value::Object(o) => o,
+                _ => return Err(conflict().into()),
+            };
+        }
+    }
+    let Some(stem) = file.file_stem() else {
+        return Ok(());
+    };
+    let key = stem.to_string_lossy().into_owned();
+    if target.contains_key(key.as_str()) {
+        return Err(conflict().into());
+    }
+    target.insert(key.into(), value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_system_memory::MemoryFileSystem;
+    use liquid::ValueView;
+
+    fn data_fs(files: &[(&str, &str)]) -> Result<MemoryFileSystem> {
+        let mut fs = MemoryFileSystem::default();
+        for (path, contents) in files {
+            fs.write_str(Path::new("data").join(path), contents.to_string())?;
+        }
+        Ok(fs)
+    }
+
+    fn render(data: liquid::Object, template: &str) -> Result<String> {
+        let parser = liquid::ParserBuilder::with_stdlib().build()?;
+        Ok(parser
+            .parse(template)?
+            .render(&liquid::object!({ "data": data }))?)
+    }
+
+    #[test]
+    fn missing_dir_is_empty() -> Result<()> {
+        let data = load(Path::new("data"), &MemoryFileSystem::default())?;
+        assert!(data.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn parses_json_and_toml() -> Result<()> {
+        let fs = data_fs(&[
+            (
+                "site.json",
+                r#"{"nav": [{"title": "Home", "url": "/"}], "count": 2}"#,
+            ),
+            (
+                "settings.toml",
+                "title = \"My Site\"\nlaunched = 2024-01-02T03:04:05Z\n[social]\nhandle = \"@me\"",
+            ),
+            ("notes.txt", "ignored"),
+        ])?;
+        let data = load(Path::new("data"), &fs)?;
+        assert_eq!(data.len(), 2);
+        assert_eq!(
+            render(
+                data,
+                "{{ data.site.nav[0].title }} {{ data.site.count | plus: 1 }} {{ data.settings.title }} {{ data.settings.launched }} {{ data.settings.social.handle }}"
+            )?,
+            "Home 3 My Site 2024-01-02T03:04:05Z @me"
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "import-csv")]
+    #[test]
+    fn parses_csv_rows() -> Result<()> {
+        let fs = data_fs(&[("team/members.csv", "name,role\nJesse,Owner\nAlex,Editor\n")])?;
+        let data = load(Path::new("data"), &fs)?;
+        assert_eq!(
+            render(
+                data,
+                "{% for m in data.team.members %}{{ m.name }}:{{ m.role }};{% endfor %}"
+            )?,
+            "Jesse:Owner;Alex:Editor;"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reports_bad_files() -> Result<()> {
+        let fs = data_fs(&[("broken.json", "{")])?;
+        let err = load(Path::new("data"), &fs).unwrap_err();
+        assert!(err.to_string().contains("broken.json"), "{}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_conflicting_names() -> Result<()> {
+        let fs = data_fs(&[("team.json", "{}"), ("team.toml", "a = 1")])?;
+        assert!(load(Path::new("data"), &fs).is_err());
+        let data = load(Path::new("data"), &data_fs(&[("a.json", "[1, 2]")])?)?;
+        assert_eq!(data.get("a").unwrap().as_array().unwrap().size(), 2);
+        Ok(())
+    }
+}
@@ -5,6 +5,7 @@ use std::{
     fmt::{Debug, Display},
     hash::Hash,
 };
+use thiserror::Error;
 #[cfg(feature = "typescript")]
 use typescript_type_def::TypeDef;
 
@@ -24,6 +25,9 @@ pub enum ArchivalEvent {
     EditOrder(EditOrderEvent),
     AddChild(AddChildEvent),
     RemoveChild(RemoveChildEvent),
+    AddListValue(AddListValueEvent),
+    RemoveListValue(RemoveListValueEvent),
+    MoveListValue(MoveListValueEvent),
 }
 
 impl ArchivalEvent {
@@ -36,9 +40,34 @@ impl ArchivalEvent {
             ArchivalEvent::EditOrder(evt) => &evt.object,
             ArchivalEvent::AddChild(evt) => &evt.object,
             ArchivalEvent::RemoveChild(evt) => &evt.object,
+            ArchivalEvent::AddListValue(evt) => &evt.object,
+            ArchivalEvent::RemoveListValue(evt) => &evt.object,
+            ArchivalEvent::MoveListValue(evt) => &evt.object,
             ArchivalEvent::RenameObject(evt) => &evt.object,
         }
     }
+    /// The path to the value this event changes, which a [`CheckedEvent`] can
+    /// expect: the field for an edit, the list for list events, and the list
+    /// of children for child events. `None` for events that change a whole
+    /// object.
+    pub fn value_path(&self) -> Option<ValuePath> {
+        match self {
+            ArchivalEvent::EditField(evt) => {
+                Some(evt.path.clone().concat(ValuePath::from_string(&evt.field)))
+            }
+            ArchivalEvent::AddChild(evt) => Some(evt.path.clone()),
+            ArchivalEvent::AddListValue(evt) => Some(evt.path.clone()),
+            ArchivalEvent::MoveListValue(evt) => Some(evt.path.clone()),
+            // These paths end with the index being removed.
+            ArchivalEvent::RemoveChild(evt) => Some(evt.path.without_last()),
+            ArchivalEvent::RemoveListValue(evt) => Some(evt.path.without_last()),
+            ArchivalEvent::AddObject(_)
+            | ArchivalEvent::AddRootObject(_)
+            | ArchivalEvent::DeleteObject(_)
+            | ArchivalEvent::EditOrder(_)
+            | ArchivalEvent::RenameObject(_) => None,
+        }
+    }
     /// Get the current filename of a given event. Note that for renames, this
     /// will return the current filename, not the name it was renamed from, as
     /// this method is usually used to find an (existing) file.
@@ -51,9 +80,28 @@ impl ArchivalEvent {
             ArchivalEvent::EditOrder(evt) => &evt.filename,
             ArchivalEvent::AddChild(evt) => &evt.filename,
             ArchivalEvent::RemoveChild(evt) => &evt.filename,
+            ArchivalEvent::AddListValue(evt) => &evt.filename,
+            ArchivalEvent::RemoveListValue(evt) => &evt.filename,
+            ArchivalEvent::MoveListValue(evt) => &evt.filename,
             ArchivalEvent::RenameObject(evt) => &evt.to,
         }
     }
+    /// Where the event came from, for events that say.
+    pub fn source(&self) -> Option<&str> {
+        match self {
+            ArchivalEvent::DeleteObject(evt) => evt.source.as_deref(),
+            ArchivalEvent::EditField(evt) => evt.source.as_deref(),
+            ArchivalEvent::EditOrder(evt) => evt.source.as_deref(),
+            ArchivalEvent::RemoveChild(evt) => evt.source.as_deref(),
+            ArchivalEvent::RemoveListValue(evt) => evt.source.as_deref(),
+            ArchivalEvent::MoveListValue(evt) => evt.source.as_deref(),
+            ArchivalEvent::AddObject(_)
+            | ArchivalEvent::AddRootObject(_)
+            | ArchivalEvent::AddChild(_)
+            | ArchivalEvent::AddListValue(_)
+            | ArchivalEvent::RenameObject(_) => None,
+        }
+    }
 }
 
 impl ArchivalEvent {
@@ -67,13 +115,13 @@ impl ArchivalEvent {
             }
             ArchivalEvent::RenameObject(evt) => archival.object_file(&evt.object, &evt.from),
             ArchivalEvent::AddObject(evt) => {
-                let obj_def = archival.get_object_definition(&evt.object)?;
+                let obj_def = &archival.get_object_definition(&evt.object)?;
                 let object =
                     Object::from_def(obj_def, &evt.filename, evt.order, evt.values.clone())?;
                 Ok(object.to_toml(obj_def)?)
             }
             ArchivalEvent::AddRootObject(evt) => {
-                let obj_def = archival.get_object_definition(&evt.object)?;
+                let obj_def = &archival.get_object_definition(&evt.object)?;
                 let object = Object::from_def(obj_def, &evt.object, None, evt.values.clone())?;
                 Ok(object.to_toml(obj_def)?)
             }
@@ -129,6 +177,16 @@ impl Display for ArchivalEvent {
                         evt.filename
                     )
                 }
+                ArchivalEvent::AddListValue(evt) => format!(
+                    "Add a value to {} in {} '{}'",
+                    evt.path, evt.object, evt.filename
+                ),
+                ArchivalEvent::RemoveListValue(evt) =>
+                    format!("Remove {} from {} '{}'", evt.path, evt.object, evt.filename),
+                ArchivalEvent::MoveListValue(evt) => format!(
+                    "Move {}.{} to {}.{} in {} '{}'",
+                    evt.path, evt.from, evt.path, evt.to, evt.object, evt.filename
+                ),
                 ArchivalEvent::RenameObject(evt) => {
                     format!(
                         "Rename {} '{}' to '{}'",
@@ -159,6 +217,45 @@ pub struct EditFieldEvent {
     pub value: Option<FieldValue>,
     pub source: Option<String>,
 }
+
+/// An event that is rejected with a [`ConflictError`] unless its object is
+/// still as the sender last saw it, so that changes made from a stale copy of
+/// an object don't silently overwrite newer ones. See
+/// [`Archival::send_checked_event`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Hash)]
+#[cfg_attr(feature = "typescript", derive(TypeDef))]
+pub struct CheckedEvent {
+    pub event: ArchivalEvent,
+    pub expected: ExpectedVersion,
+}
+
+/// What a [`CheckedEvent`] expects its object to hold.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Hash)]
+#[cfg_attr(feature = "typescript", derive(TypeDef))]
+pub enum ExpectedVersion {
+    /// The version of the whole object, as returned by [`Object::version`].
+    /// Any change to the object conflicts.
+    Version(String),
+    /// The previous value of what the event changes, or `None` if it was
+    /// unset: the field for an edit, the list for list events, and the list of
+    /// children for child events. Changes elsewhere in the object don't
+    /// conflict. Other events can only expect a version.
+    Value(Option<FieldValue>),
+}
+
+/// A [`CheckedEvent`] was made from a stale copy of its object. Carries the
+/// object's current version, and the current value of what the event changes
+/// (at `path`), so the sender can show what changed and retry.
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("{object} '{filename}' has changed since it was read")]
+pub struct ConflictError {
+    pub object: String,
+    pub filename: String,
+    pub path: ValuePath,
+    pub current_version: String,
+    pub current_value: Option<FieldValue>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[cfg_attr(feature = "typescript", derive(TypeDef))]
 pub struct EditOrderEvent {
@@ -237,6 +334,42 @@ pub struct RemoveChildEvent {
     pub source: Option<String>,
 }
 
+/// Adds a value to a list field. `path` points at the list, e.g. `tags` or
+/// `links.0.tags`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Hash)]
+#[cfg_attr(feature = "typescript", derive(TypeDef))]
+pub struct AddListValueEvent {
+    pub object: String,
+    pub filename: String,
+    pub path: ValuePath,
+    pub value: FieldValue,
+    /// If not provided, this will just append to the end of the list.
+    pub index: Option<usize>,
+}
+
+/// Removes a value from a list field. `path` points at the value, e.g.
+/// `tags.2`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Hash)]
+#[cfg_attr(feature = "typescript", derive(TypeDef))]
+pub struct RemoveListValueEvent {
+    pub object: String,
+    pub filename: String,
+    pub path: ValuePath,
+    pub source: Option<String>,
+}
+
+/// Moves the value at index `from` of the list at `path` to index `to`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Hash)]
+#[cfg_attr(feature = "typescript", derive(TypeDef))]
+pub struct MoveListValueEvent {
+    pub object: String,
+    pub filename: String,
+    pub path: ValuePath,
+    pub from: usize,
+    pub to: usize,
+    pub source: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Hash)]
 #[cfg_attr(feature = "typescript", derive(TypeDef))]
 pub struct RenameObjectEvent {
@@ -260,7 +393,7 @@ mod export_types {
             header: Some("// AUTO-GENERATED by typescript-type-def\n"),
             root_namespace: None,
         };
-        write_definition_file::<_, ArchivalEvent>(&mut buf, options).unwrap();
+        write_definition_file::<_, (ArchivalEvent, CheckedEvent)>(&mut buf, options).unwrap();
         fs::write("./events.d.ts", buf).expect("Failed to write file");
     }
 }
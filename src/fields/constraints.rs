@@ -0,0 +1,326 @@
+//! Constraints declared on a field in `archival_objects.toml`, alongside its
+//! type:
+//!
+//! ```toml
+//! [post]
+//! title = { type = "string", required = true, max_length = 80 }
+//! rating = { type = "number", min = 0, max = 5 }
+//! published = { type = "date", min = 2020-01-01 }
+//! tags = { type = ["string"], max_length = 5 }
+//! ```
+//!
+//! Events that would break a constraint are rejected (see
+//! [`FieldValue::validate`] and [`crate::Archival::send_event`]). Files edited
+//! by hand are still read, and the language server reports what they break.
+
+use super::{FieldType, FieldValue, InvalidFieldError};
+use serde::{Deserialize, Serialize};
+use std::hash::Hash;
+use thiserror::Error;
+
+pub(crate) const REQUIRED: &str = "required";
+pub(crate) const MIN: &str = "min";
+pub(crate) const MAX: &str = "max";
+pub(crate) const MIN_LENGTH: &str = "min_length";
+pub(crate) const MAX_LENGTH: &str = "max_length";
+/// The keys a field table may use to declare constraints.
+pub(crate) const CONSTRAINT_OPTIONS: [&str; 5] = [REQUIRED, MIN, MAX, MIN_LENGTH, MAX_LENGTH];
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
+pub struct FieldConstraints {
+    /// The field must have a value.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub required: bool,
+    /// The smallest number, or earliest date, the field may hold.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<FieldValue>,
+    /// The largest number, or latest date, the field may hold.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<FieldValue>,
+    /// The fewest characters a string or markdown field may hold, or the
+    /// fewest items a list may.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<usize>,
+    /// The most characters a string or markdown field may hold, or the most
+    /// items a list may.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
+}
+
+impl Hash for FieldConstraints {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.required.hash(state);
+        // FieldValue's own hash only special-cases numbers; bounds are numbers
+        // or dates, so their string form is a stable stand-in.
+        self.min.as_ref().map(|v| v.to_string()).hash(state);
+        self.max.as_ref().map(|v| v.to_string()).hash(state);
+        self.min_length.hash(state);
+        self.max_length.hash(state);
+    }
+}
+
+/// How a value breaks its field's constraints.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ConstraintViolation {
+    #[error("is required")]
+    Required,
+    #[error("must be at least {0}")]
+    BelowMinimum(FieldValue),
+    #[error("must be at most {0}")]
+    AboveMaximum(FieldValue),
+    #[error("must be at least {min} characters long (found {length})")]
+    TooShort { min: usize, length: usize },
+    #[error("must be at most {max} characters long (found {length})")]
+    TooLong { max: usize, length: usize },
+    #[error("must have at least {min} items (found {count})")]
+    TooFewItems { min: usize, count: usize },
+    #[error("must have at most {max} items (found {count})")]
+    TooManyItems { max: usize, count: usize },
+}
+
+impl ConstraintViolation {
+    /// Whether this only means the value hasn't been filled in yet: a missing
+    /// required value, or a list that needs more items.
+    pub fn is_incomplete(&self) -> bool {
+        matches!(self, Self::Required | Self::TooFewItems { .. })
+    }
+}
+
+impl FieldConstraints {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Reads the constraints declared in a field's option table. `key` names
+    /// the field in errors.
+    pub(crate) fn from_table(
+        key: &str,
+        field_type: &FieldType,
+        table: &toml::Table,
+    ) -> Result<Self, InvalidFieldError> {
+        let invalid = |error: String| InvalidFieldError::InvalidConstraint {
+            field: key.to_string(),
+            error,
+        };
+        let base_type = field_type.base_type();
+        let mut constraints = Self::default();
+        if let Some(required) = table.get(REQUIRED) {
+            constraints.required = required
+                .as_bool()
+                .ok_or_else(|| invalid(format!("{REQUIRED} must be true or false")))?;
+        }
+        for (option, bound) in [(MIN, &mut constraints.min), (MAX, &mut constraints.max)] {
+            let Some(value) = table.get(option) else {
+                continue;
+            };
+            if !matches!(base_type, FieldType::Number | FieldType::Date) {
+                return Err(invalid(format!(
+                    "{option} only applies to number and date fields, not {field_type}"
+                )));
+            }
+            *bound = Some(
+                FieldValue::from_toml(&key.to_string(), base_type, value)
+                    .map_err(|e| invalid(e.to_string()))?,
+            );
+        }
+        for (option, length) in [
+            (MIN_LENGTH, &mut constraints.min_length),
+            (MAX_LENGTH, &mut constraints.max_length),
+        ] {
+            let Some(value) = table.get(option) else {
+                continue;
+            };
+            if !has_length(base_type) {
+                return Err(invalid(format!(
+                    "{option} only applies to string, markdown and list fields, not {field_type}"
+                )));
+            }
+            *length = Some(
+                value
+                    .as_integer()
+                    .and_then(|l| usize::try_from(l).ok())
+                    .ok_or_else(|| invalid(format!("{option} must be a positive integer")))?,
+            );
+        }
+        if let (Some(min), Some(max)) = (&constraints.min, &constraints.max) {
+            if compare(min, max) == Some(std::cmp::Ordering::Greater) {
+                return Err(invalid(format!("{MIN} is greater than {MAX}")));
+            }
+        }
+        if let (Some(min), Some(max)) = (constraints.min_length, constraints.max_length) {
+            if min > max {
+                return Err(invalid(format!(
+                    "{MIN_LENGTH} is greater than {MAX_LENGTH}"
+                )));
+            }
+        }
+        Ok(constraints)
+    }
+
+    /// Checks `value` against these constraints. A missing value and an
+    /// explicit null are treated the same.
+    #[allow(clippy::result_large_err)]
+    pub fn check(&self, value: Option<&FieldValue>) -> Result<(), ConstraintViolation> {
+        let value = match value {
+            None | Some(FieldValue::Null) => {
+                return if self.required {
+                    Err(ConstraintViolation::Required)
+                } else {
+                    Ok(())
+                };
+            }
+            Some(value) => value,
+        };
+        if let Some(min) = &self.min {
+            if compare(value, min) == Some(std::cmp::Ordering::Less) {
+                return Err(ConstraintViolation::BelowMinimum(min.clone()));
+            }
+        }
+        if let Some(max) = &self.max {
+            if compare(value, max) == Some(std::cmp::Ordering::Greater) {
+                return Err(ConstraintViolation::AboveMaximum(max.clone()));
+            }
+        }
+        if let FieldValue::List(items) = value {
+            let count = items.len();
+            if let Some(min) = self.min_length {
+                if count < min {
+                    return Err(ConstraintViolation::TooFewItems { min, count });
+                }
+            }
+            if let Some(max) = self.max_length {
+                if count > max {
+                    return Err(ConstraintViolation::TooManyItems { max, count });
+                }
+            }
+        } else if let Some(length) = length(value) {
+            if let Some(min) = self.min_length {
+                if length < min {
+                    return Err(ConstraintViolation::TooShort { min, length });
+                }
+            }
+            if let Some(max) = self.max_length {
+                if length > max {
+                    return Err(ConstraintViolation::TooLong { max, length });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn has_length(field_type: &FieldType) -> bool {
+    matches!(
+        field_type,
+        FieldType::String | FieldType::Markdown | FieldType::Secret | FieldType::List(_)
+    )
+}
+
+fn length(value: &FieldValue) -> Option<usize> {
+    match value {
+        FieldValue::String(s) | FieldValue::Markdown(s) | FieldValue::Secret(s) => {
+            Some(s.chars().count())
+        }
+        _ => None,
+    }
+}
+
+/// Orders two bounds of the same type. Values of different types don't
+/// compare, and so never break a bound.
+fn compare(a: &FieldValue, b: &FieldValue) -> Option<std::cmp::Ordering> {
+    match (a, b) {
+        (FieldValue::Number(a), FieldValue::Number(b)) => a.partial_cmp(b),
+        (FieldValue::Date(a), FieldValue::Date(b)) => {
+            a.as_liquid_datetime().partial_cmp(&b.as_liquid_datetime())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fields::DateTime;
+
+    fn constraints(
+        field_type: FieldType,
+        source: &str,
+    ) -> Result<FieldConstraints, InvalidFieldError> {
+        FieldConstraints::from_table("field", &field_type, &toml::from_str(source).unwrap())
+    }
+
+    #[test]
+    fn number_ranges() {
+        let c = constraints(FieldType::Number, "min = 0\nmax = 5.5").unwrap();
+        assert_eq!(c.check(Some(&FieldValue::Number(3.))), Ok(()));
+        assert_eq!(c.check(None), Ok(()));
+        assert_eq!(
+            c.check(Some(&FieldValue::Number(-1.))),
+            Err(ConstraintViolation::BelowMinimum(FieldValue::Number(0.)))
+        );
+        assert_eq!(
+            c.check(Some(&FieldValue::Number(6.))),
+            Err(ConstraintViolation::AboveMaximum(FieldValue::Number(5.5)))
+        );
+    }
+
+    #[test]
+    fn date_ranges() {
+        let c = constraints(FieldType::Date, "min = 2020-01-01\nmax = \"2020-12-31\"").unwrap();
+        let date = |y, m, d| FieldValue::Date(DateTime::from_ymd(y, m, d));
+        assert_eq!(c.check(Some(&date(2020, 6, 1))), Ok(()));
+        assert!(matches!(
+            c.check(Some(&date(2019, 12, 31))),
+            Err(ConstraintViolation::BelowMinimum(_))
+        ));
+        assert!(matches!(
+            c.check(Some(&date(2021, 1, 1))),
+            Err(ConstraintViolation::AboveMaximum(_))
+        ));
+    }
+
+    #[test]
+    fn string_lengths_count_characters() {
+        let c = constraints(
+            FieldType::String,
+            "required = true\nmin_length = 2\nmax_length = 3",
+        )
+        .unwrap();
+        assert_eq!(c.check(None), Err(ConstraintViolation::Required));
+        assert_eq!(
+            c.check(Some(&FieldValue::Null)),
+            Err(ConstraintViolation::Required)
+        );
+        assert_eq!(c.check(Some(&FieldValue::String("héé".into()))), Ok(()));
+        assert_eq!(
+            c.check(Some(&FieldValue::String("a".into()))),
+            Err(ConstraintViolation::TooShort { min: 2, length: 1 })
+        );
+        assert_eq!(
+            c.check(Some(&FieldValue::String("abcd".into()))),
+            Err(ConstraintViolation::TooLong { max: 3, length: 4 })
+        );
+    }
+
+    #[test]
+    fn invalid_constraints_are_rejected() {
+        for (field_type, source) in [
+            (FieldType::Boolean, "min = 1"),
+            (FieldType::Number, "min_length = 1"),
+            (FieldType::String, "min_length = -1"),
+            (FieldType::String, "required = \"yes\""),
+            (FieldType::Number, "min = \"one\""),
+            (FieldType::Number, "min = 2\nmax = 1"),
+            (FieldType::String, "min_length = 2\nmax_length = 1"),
+        ] {
+            assert!(
+                matches!(
+                    constraints(field_type, source),
+                    Err(InvalidFieldError::InvalidConstraint { .. })
+                ),
+                "{source}"
+            );
+        }
+    }
+}
@@ -56,6 +56,18 @@ pub enum InvalidFieldError {
     UnsupportedStringValue(String),
     #[error("type {0} was not provided a value and has no default")]
     NoDefaultForType(String),
+    #[error("invalid computed field {field:?}: {error}")]
+    InvalidComputed { field: String, error: String },
+    #[error("invalid default for field {field:?}: {error}")]
+    InvalidDefault { field: String, error: String },
+    #[error("invalid constraint for field {field:?}: {error}")]
+    InvalidConstraint { field: String, error: String },
+    #[error("lists can't hold {0} values")]
+    InvalidList(String),
+    #[error("invalid sort for object {object:?}: {error}")]
+    InvalidSort { object: String, error: String },
+    #[error("{field:?} is where the properties of markdown field {markdown:?} go, so it can't be a field")]
+    MarkdownMetaCollision { field: String, markdown: String },
 }
 
 #[cfg(feature = "typescript")]
@@ -104,6 +116,13 @@ pub enum FieldType {
     Audio,
     Meta,
     Oneof(Vec<OneofOption>),
+    /// A list of primitive values, stored as a toml array and declared as the
+    /// item type in brackets: `tags = ["string"]`, or `kinds = [["a", "b"]]`
+    /// for a list of enum values.
+    List(
+        #[cfg_attr(feature = "typescript", type_def(type_of = "typedefs::FieldTypeDef"))]
+        Box<FieldType>,
+    ),
     Alias(
         #[cfg_attr(feature = "typescript", type_def(type_of = "typedefs::AliasTypeDef"))]
         Box<(FieldType, String)>,
@@ -132,6 +151,7 @@ impl FieldType {
                 .join("|")
                 .to_string()
                 .into(),
+            Self::List(item) => format!("[{}]", item.as_str()).into(),
             Self::Alias(a) => a.0.as_str(),
         }
     }
@@ -145,6 +165,81 @@ impl FieldType {
             _ => false,
         }
     }
+    /// Whether values of this type are a single toml value rather than a
+    /// table, which is what a field's `default` may be.
+    pub fn is_scalar(&self) -> bool {
+        matches!(
+            self,
+            Self::String
+                | Self::Secret
+                | Self::Number
+                | Self::Date
+                | Self::Enum(_)
+                | Self::Markdown
+                | Self::Boolean
+        )
+    }
+    /// Whether a computed field may have this type. Computed fields are the
+    /// output of a liquid expression, so they are limited to the types that
+    /// can be parsed back out of a string.
+    pub fn is_computable(&self) -> bool {
+        matches!(
+            self,
+            Self::String | Self::Number | Self::Date | Self::Markdown | Self::Boolean
+        )
+    }
+    /// Whether a list field may hold values of this type: the scalar types
+    /// other than those with lengthy or hidden values, plus files.
+    pub fn is_list_item(&self) -> bool {
+        matches!(
+            self.base_type(),
+            Self::String
+                | Self::Number
+                | Self::Date
+                | Self::Enum(_)
+                | Self::Image
+                | Self::Video
+                | Self::Audio
+                | Self::Upload
+        )
+    }
+    /// The type of a list's items, or `None` when this is not a list.
+    pub fn list_item(&self) -> Option<&FieldType> {
+        match self.base_type() {
+            Self::List(item) => Some(item),
+            _ => None,
+        }
+    }
+    /// Reads a list type from the array it is declared as - a single type
+    /// name (`["string"]`), or a single enum (`[["a", "b"]]`). Returns `None`
+    /// for any other array, which is an enum or oneof instead.
+    pub fn list_from_toml(
+        values: &[toml::Value],
+        editor_types: &EditorTypes,
+    ) -> Result<Option<FieldType>, InvalidFieldError> {
+        let [item] = values else {
+            return Ok(None);
+        };
+        let item = match item {
+            toml::Value::Array(enum_values) => FieldType::Enum(
+                enum_values
+                    .iter()
+                    .map(|v| v.as_str().map(|s| s.to_string()))
+                    .collect::<Option<_>>()
+                    .ok_or_else(|| InvalidFieldError::InvalidEnum(format!("{values:?}")))?,
+            ),
+            toml::Value::String(type_name) => match FieldType::from_str(type_name, editor_types) {
+                Ok(item) => item,
+                // Not a type, so a single-value enum.
+                Err(_) => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        if !item.is_list_item() {
+            return Err(InvalidFieldError::InvalidList(item.to_string()));
+        }
+        Ok(Some(FieldType::List(Box::new(item))))
+    }
     /// The underlying built-in type of this field, following aliases (which may
     /// themselves alias other editor types).
     pub fn base_type(&self) -> &FieldType {
@@ -247,6 +342,18 @@ impl FieldType {
                     schema.insert("enum".into(), json!(valid_values));
                     options.decorate(field_path, field_type, &mut schema);
                     schema
+                } else if let Self::List(item_type) = self {
+                    let mut schema = serde_json::Map::new();
+                    schema.insert("description".into(), description.into());
+                    schema.insert("type".into(), "array".into());
+                    schema.insert(
+                        "items".into(),
+                        item_type
+                            .to_json_schema_property(description, field_path, options)
+                            .into(),
+                    );
+                    options.decorate(field_path, field_type, &mut schema);
+                    schema
                 } else if let Self::Oneof(field_types) = self {
                     let mut schema = serde_json::Map::new();
                     schema.insert("description".into(), description.into());
@@ -1,5 +1,7 @@
 use super::file::File;
+use super::markdown::rendered_markdown_cached;
 use super::meta::Meta;
+use super::ConstraintViolation;
 use super::DateTime;
 use super::{FieldType, InvalidFieldError};
 use crate::fields::file::RenderedFile;
@@ -8,12 +10,10 @@ use crate::manifest::{EditorTypes, ManifestEditorTypeValidator};
 use crate::object::to_liquid::{object_to_liquid_with, ToLiquidOptions};
 use crate::object::Renderable;
 use crate::util::integer_decode;
-use crate::value_path::ValuePathError;
+use crate::value_path::{ValuePathComponent, ValuePathError};
 use crate::{FieldConfig, ObjectDefinition, ValuePath};
 use anyhow::Result;
-use comrak::{markdown_to_html, ComrakOptions};
 use liquid::{model, ValueView};
-use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::BTreeMap;
@@ -42,6 +42,10 @@ pub enum FieldValueValidationError {
     CannotValidateType(FieldValue, ValuePath),
     #[error("field '{0}' at {1} failed validator '{2}'")]
     FailedValidation(String, ValuePath, String),
+    #[error("{0} is a computed field and cannot be set")]
+    ComputedField(ValuePath),
+    #[error("{0} {1}")]
+    ConstraintViolation(ValuePath, ConstraintViolation),
 }
 
 // These are BTrees rather than OrderMaps because we only serialize them when we
@@ -92,6 +96,20 @@ mod typedefs {
             r#ref: TypeExpr::ident(Ident("[string, FieldValue | null]")),
         });
     }
+    pub struct RenderedListTypeDef;
+    impl TypeDef for RenderedListTypeDef {
+        const INFO: TypeInfo = TypeInfo::Native(NativeTypeInfo {
+            // Workaround for circular type: https://github.com/dbeckwith/rust-typescript-type-def/issues/18#issuecomment-2078469020
+            r#ref: TypeExpr::ident(Ident("RenderedFieldValue[]")),
+        });
+    }
+    pub struct ListTypeDef;
+    impl TypeDef for ListTypeDef {
+        const INFO: TypeInfo = TypeInfo::Native(NativeTypeInfo {
+            // Workaround for circular type: https://github.com/dbeckwith/rust-typescript-type-def/issues/18#issuecomment-2078469020
+            r#ref: TypeExpr::ident(Ident("FieldValue[]")),
+        });
+    }
 }
 
 macro_rules! compare_values {
@@ -132,6 +150,13 @@ pub enum RenderedFieldValue {
         )]
         (String, Box<Option<RenderedFieldValue>>),
     ),
+    List(
+        #[cfg_attr(
+            feature = "typescript",
+            type_def(type_of = "typedefs::RenderedListTypeDef")
+        )]
+        Vec<RenderedFieldValue>,
+    ),
     Boolean(bool),
     File(RenderedFile),
     Meta(Meta),
@@ -168,6 +193,11 @@ pub enum FieldValue {
         #[cfg_attr(feature = "typescript", type_def(type_of = "typedefs::OneofTypeDef"))]
         (String, Box<Option<FieldValue>>),
     ),
+    /// The values of a list field, see [`FieldType::List`].
+    List(
+        #[cfg_attr(feature = "typescript", type_def(type_of = "typedefs::ListTypeDef"))]
+        Vec<FieldValue>,
+    ),
     Boolean(bool),
     File(File),
     Meta(Meta),
@@ -206,6 +236,12 @@ impl Renderable for FieldValue {
             FieldValue::Oneof((t, v)) => {
                 RenderedFieldValue::Oneof((t, Box::new(v.map(|fv| fv.rendered(field_config)))))
             }
+            FieldValue::List(items) => RenderedFieldValue::List(
+                items
+                    .into_iter()
+                    .map(|item| item.rendered(field_config))
+                    .collect(),
+            ),
             FieldValue::Boolean(b) => RenderedFieldValue::Boolean(b),
             FieldValue::File(file) => RenderedFieldValue::File(file.rendered(field_config)),
             FieldValue::Meta(m) => RenderedFieldValue::Meta(m),
@@ -214,47 +250,6 @@ impl Renderable for FieldValue {
     }
 }
 
-pub static MARKDOWN_OPTIONS: Lazy<ComrakOptions> = Lazy::new(|| {
-    let mut options = ComrakOptions::default();
-    options.extension.autolink = true;
-    options.extension.strikethrough = true;
-    options.extension.table = true;
-    options.extension.superscript = true;
-    options.extension.description_lists = true;
-    // NOTE: it's unclear how much nannying we need to do here, as users are
-    // only able to update their own markdown and by definition they have access
-    // to the html if they have access to the repo... however if someone is
-    // tricked into pasting things into markdown they could potentially open
-    // some issues?
-    options.extension.tagfilter = false;
-    options.extension.header_ids = Some("".to_string());
-    options.extension.footnotes = true;
-    options.render.unsafe_ = true;
-    options
-});
-
-// Markdown fields are converted to html every time they are turned into a
-// liquid value, which happens at least once per build and, in long-lived
-// processes like the dev server, once per rebuild. Conversion output only
-// depends on the source, so memoize it. The cap just bounds memory in
-// processes that see many unique documents (e.g. editing sessions).
-const MARKDOWN_CACHE_MAX_ENTRIES: usize = 1024;
-static MARKDOWN_CACHE: Lazy<std::sync::RwLock<std::collections::HashMap<String, String>>> =
-    Lazy::new(|| std::sync::RwLock::new(std::collections::HashMap::new()));
-
-fn markdown_to_html_cached(source: &str) -> String {
-    if let Some(html) = MARKDOWN_CACHE.read().unwrap().get(source) {
-        return html.clone();
-    }
-    let html = markdown_to_html(source, &MARKDOWN_OPTIONS);
-    let mut cache = MARKDOWN_CACHE.write().unwrap();
-    if cache.len() >= MARKDOWN_CACHE_MAX_ENTRIES {
-        cache.clear();
-    }
-    cache.insert(source.to_owned(), html.clone());
-    html
-}
-
 impl FieldValue {
     // Note that this comparison just skips fields that cannot be compared and
     // returns None.
@@ -286,9 +281,11 @@ impl FieldValue {
         options: ToLiquidOptions,
     ) -> model::Value {
         if let FieldValue::Objects(children) = self {
+            let mut children: Vec<&ObjectValues> = children.iter().collect();
+            children.sort_by(|a, b| definition.compare_children(a, b));
             model::Value::Array(
                 children
-                    .iter()
+                    .into_iter()
                     .map(|child| {
                         model::Value::Object(object_to_liquid_with(
                             child,
@@ -324,6 +321,7 @@ impl FieldValue {
             FieldValue::Date(_) => false,
             FieldValue::Objects(_) => false,
             FieldValue::Oneof(_) => false,
+            FieldValue::List(items) => items.is_empty(),
             FieldValue::Boolean(_) => false,
             FieldValue::File(f) => !f.is_valid(),
             FieldValue::Meta(meta) => meta.is_empty(),
@@ -522,9 +520,41 @@ impl FieldValue {
         definition: &ObjectDefinition,
         custom_types: &EditorTypes,
     ) -> Result<(), FieldValueValidationError> {
-        let field_type = path.get_field_definition(definition).map_err(|e| {
+        let field = path.get_field(definition).map_err(|e| {
             FieldValueValidationError::FieldDefinitionNotFound(path.clone(), definition.clone(), e)
         })?;
+        if field.is_computed() {
+            return Err(FieldValueValidationError::ComputedField(path.clone()));
+        }
+        let field_type = &field.r#type;
+        // A path ending in an index points at one item of a list, which is
+        // validated against the item type. Constraints apply to whole lists.
+        if let (Some(item_type), Some(ValuePathComponent::Index(_))) =
+            (field_type.list_item(), path.last())
+        {
+            self.run_custom_validation(path, item_type, custom_types)?;
+            return self.validate_type(path, item_type.base_type());
+        }
+        self.run_custom_validation(path, field_type, custom_types)?;
+        // After we've run custom validation, aliases should just be dereferenced.
+        let field_type = match field_type {
+            FieldType::Alias(val) => &val.0,
+            _ => field_type,
+        };
+        self.validate_value(path, definition, field_type, custom_types)?;
+        field.constraints.check(Some(self)).map_err(|violation| {
+            FieldValueValidationError::ConstraintViolation(path.clone(), violation)
+        })
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn validate_value(
+        &self,
+        path: &ValuePath,
+        definition: &ObjectDefinition,
+        field_type: &FieldType,
+        custom_types: &EditorTypes,
+    ) -> Result<(), FieldValueValidationError> {
         let field_mismatch = || {
             Err(FieldValueValidationError::TypeMismatch(
                 self.clone(),
@@ -532,12 +562,6 @@ impl FieldValue {
                 field_type.clone(),
             ))
         };
-        self.run_custom_validation(path, field_type, custom_types)?;
-        // After we've run custom validation, aliases should just be dereferenced.
-        let field_type = match field_type {
-            FieldType::Alias(val) => &val.0,
-            _ => field_type,
-        };
         match self {
             // Oneof needs special checking since we need to validate the inner
             // type and the type name against the valid options
@@ -567,6 +591,18 @@ impl FieldValue {
                     field_mismatch()
                 }
             }
+            Self::List(items) => {
+                if let FieldType::List(item_type) = field_type {
+                    for (idx, item) in items.iter().enumerate() {
+                        let item_path = path.clone().append(ValuePath::index(idx));
+                        item.run_custom_validation(&item_path, item_type, custom_types)?;
+                        item.validate_type(&item_path, item_type.base_type())?;
+                    }
+                    Ok(())
+                } else {
+                    field_mismatch()
+                }
+            }
             // Objects should be recursively validated
             Self::Objects(children) => {
                 for (idx, child) in children.iter().enumerate() {
@@ -652,6 +688,12 @@ impl From<&FieldValue> for Option<toml::Value> {
                 }
                 Some(toml::Value::Table(table))
             }
+            FieldValue::List(items) => Some(toml::Value::Array(
+                items
+                    .iter()
+                    .filter_map(Option::<toml::Value>::from)
+                    .collect(),
+            )),
             FieldValue::File(f) => Some(toml::Value::Table(f.to_toml())),
             FieldValue::Meta(m) => Some(toml::Value::Table(m.to_toml())),
             FieldValue::Null => None,
@@ -687,6 +729,7 @@ impl ValueView for FieldValue {
             FieldValue::File(_) => "file",
             FieldValue::Meta(_) => "meta",
             FieldValue::Oneof(_) => "oneof",
+            FieldValue::List(_) => "list",
             FieldValue::Null => "null",
         }
     }
@@ -713,10 +756,13 @@ impl ValueView for FieldValue {
             FieldValue::Number(n) => Some(model::ScalarCow::new(*n)),
             // TODO: should be able to return a datetime value here
             FieldValue::Date(d) => Some(model::ScalarCow::new((*d).as_liquid_datetime())),
-            FieldValue::Markdown(s) => Some(model::ScalarCow::new(markdown_to_html_cached(s))),
+            FieldValue::Markdown(s) => Some(model::ScalarCow::new(
+                rendered_markdown_cached(s).html.clone(),
+            )),
             FieldValue::Boolean(b) => Some(model::ScalarCow::new(*b)),
             FieldValue::Objects(_) => None,
             FieldValue::Oneof((_, v)) => v.as_scalar(),
+            FieldValue::List(_) => None,
             FieldValue::File(_f) => None,
             FieldValue::Meta(_m) => None,
             FieldValue::Null => None,
@@ -725,6 +771,7 @@ impl ValueView for FieldValue {
     fn as_array(&self) -> Option<&dyn model::ArrayView> {
         match self {
             FieldValue::Objects(a) => Some(a),
+            FieldValue::List(a) => Some(a),
             _ => None,
         }
     }
@@ -745,6 +792,7 @@ impl ValueView for FieldValue {
             FieldValue::Date(_) => self.as_scalar().to_value(),
             FieldValue::Boolean(_) => self.as_scalar().to_value(),
             FieldValue::Objects(_) => self.as_array().to_value(),
+            FieldValue::List(_) => self.as_array().to_value(),
             FieldValue::File(_) => {
                 panic!("files cannot be rendered via value parsing. Use file.to_liquid instead.")
             }
@@ -897,33 +945,48 @@ impl FieldValue {
                 FieldType::Meta => Ok(Some(FieldValue::Meta(Meta::from(o)))),
                 _ => Err(InvalidFieldError::UnrecognizedType(field_type.to_string()).into()),
             },
-            serde_json::Value::Array(v) => Ok(Some(FieldValue::Objects(
-                v.iter()
-                    .enumerate()
-                    .map(|(index, val)| {
-                        let mut map = BTreeMap::new();
-                        if let Some(obj) = val.as_object() {
-                            for (k, v) in obj.iter() {
-                                if let Some(value) = FieldValue::from_json(
-                                    v,
-                                    &parent_path.clone(),
-                                    object_definition,
-                                )? {
-                                    map.insert(k.to_string(), value);
-                                } else {
-                                    warn!(
-                                        "{} {} had invalid key {}, skipping.",
-                                        parent_path, index, k
-                                    );
+            serde_json::Value::Array(v) => match field_type {
+                FieldType::List(item_type) => Ok(Some(FieldValue::List(
+                    v.iter()
+                        .filter_map(|item| {
+                            FieldValue::field_from_json(
+                                item,
+                                item_type,
+                                parent_path,
+                                object_definition,
+                            )
+                            .transpose()
+                        })
+                        .collect::<Result<_>>()?,
+                ))),
+                _ => Ok(Some(FieldValue::Objects(
+                    v.iter()
+                        .enumerate()
+                        .map(|(index, val)| {
+                            let mut map = BTreeMap::new();
+                            if let Some(obj) = val.as_object() {
+                                for (k, v) in obj.iter() {
+                                    if let Some(value) = FieldValue::from_json(
+                                        v,
+                                        &parent_path.clone(),
+                                        object_definition,
+                                    )? {
+                                        map.insert(k.to_string(), value);
+                                    } else {
+                                        warn!(
+                                            "{} {} had invalid key {}, skipping.",
+                                            parent_path, index, k
+                                        );
+                                    }
                                 }
+                            } else {
+                                panic!("Invalid value {} for child", val);
                             }
-                        } else {
-                            panic!("Invalid value {} for child", val);
-                        }
-                        Ok::<_, anyhow::Error>(map)
-                    })
-                    .collect::<Result<Vec<_>, _>>()?,
-            ))),
+                            Ok::<_, anyhow::Error>(map)
+                        })
+                        .collect::<Result<Vec<_>, _>>()?,
+                ))),
+            },
         }
     }
 
@@ -1124,6 +1187,18 @@ impl FieldValue {
                     value: value.to_string(),
                 },
             )?))),
+            FieldType::List(item_type) => Ok(FieldValue::List(
+                value
+                    .as_array()
+                    .ok_or_else(|| InvalidFieldError::TypeMismatch {
+                        field: key.to_owned(),
+                        field_type: field_type.to_string(),
+                        value: value.to_string(),
+                    })?
+                    .iter()
+                    .map(|item| Self::from_toml(key, item_type, item))
+                    .collect::<Result<_>>()?,
+            )),
             FieldType::Alias(a) => Self::from_toml(key, &a.0, value),
         }
     }
@@ -1153,6 +1228,11 @@ impl FieldValue {
                     None => "null".to_string(),
                 }
             ),
+            FieldValue::List(items) => items
+                .iter()
+                .map(|item| item.as_string(config))
+                .collect::<Vec<_>>()
+                .join(", "),
             FieldValue::Meta(m) => format!("{:?}", serde_json::Value::from(m)),
             FieldValue::Null => "null".to_string(),
         }
@@ -0,0 +1,298 @@
+//! Markdown fields render to html, but templates usually want more than the
+//! html: a table of contents, an excerpt for index pages, a reading time. These
+//! are derived here from the same parse that produces the html, so a markdown
+//! document is only ever parsed once per unique source.
+//!
+//! In liquid a markdown field is still just its html string, so templates
+//! that compare, filter or test it behave as they always have. The derived
+//! properties sit beside it as `<field>_meta` (see [`meta_field_name`] and
+//! [`RenderedMarkdown::meta_liquid`]), e.g. `post.body_meta.toc`. Object
+//! definitions can't have a field or child with that name.
+
+use comrak::{
+    html::collect_text,
+    nodes::{AstNode, NodeValue},
+    parse_document, Anchorizer, Arena, ComrakOptions,
+};
+use liquid::model::Value;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+pub static MARKDOWN_OPTIONS: Lazy<ComrakOptions> = Lazy::new(|| {
+    let mut options = ComrakOptions::default();
+    options.extension.autolink = true;
+    options.extension.strikethrough = true;
+    options.extension.table = true;
+    options.extension.superscript = true;
+    options.extension.description_lists = true;
+    // NOTE: it's unclear how much nannying we need to do here, as users are
+    // only able to update their own markdown and by definition they have access
+    // to the html if they have access to the repo... however if someone is
+    // tricked into pasting things into markdown they could potentially open
+    // some issues?
+    options.extension.tagfilter = false;
+    options.extension.header_ids = Some("".to_string());
+    options.extension.footnotes = true;
+    options.render.unsafe_ = true;
+    options
+});
+
+/// Everything before this marker is the excerpt. Matches the jekyll/hugo
+/// convention so imported content keeps its hand-picked excerpts.
+pub const EXCERPT_SEPARATOR: &str = "<!-- more -->";
+/// Excerpt length for documents without an [`EXCERPT_SEPARATOR`].
+pub const EXCERPT_WORDS: usize = 55;
+/// Used to derive `reading_time` (in whole minutes) from `word_count`.
+pub const WORDS_PER_MINUTE: usize = 200;
+
+/// Appended to a markdown field's name to name its derived properties.
+pub const META_SUFFIX: &str = "_meta";
+
+// Keys of the liquid object a markdown value's derived properties become.
+const TOC_KEY: &str = "toc";
+const EXCERPT_KEY: &str = "excerpt";
+const WORD_COUNT_KEY: &str = "word_count";
+const READING_TIME_KEY: &str = "reading_time";
+
+/// A heading in a document's table of contents. `children` holds the headings
+/// nested under this one, so the toc is a tree rather than a flat list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TocEntry {
+    pub level: u8,
+    pub title: String,
+    /// The anchor id the heading is rendered with, so `#{{ id }}` links to it.
+    pub id: String,
+    pub children: Vec<TocEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedMarkdown {
+    pub html: String,
+    pub toc: Vec<TocEntry>,
+    /// Plain text, either everything before an [`EXCERPT_SEPARATOR`] or the
+    /// first [`EXCERPT_WORDS`] words.
+    pub excerpt: String,
+    pub word_count: usize,
+}
+
+impl RenderedMarkdown {
+    pub fn new(source: &str) -> Self {
+        let arena = Arena::new();
+        let root = parse_document(&arena, source, &MARKDOWN_OPTIONS);
+        let mut html = vec![];
+        comrak::format_html(root, &MARKDOWN_OPTIONS, &mut html)
+            .expect("writing to a Vec cannot fail");
+        let words = plain_text(root);
+        let word_count = words.split_whitespace().count();
+        let excerpt = match source.split_once(EXCERPT_SEPARATOR) {
+            Some((before, _)) => {
+                let arena = Arena::new();
+                let excerpt = plain_text(parse_document(&arena, before, &MARKDOWN_OPTIONS));
+                excerpt.split_whitespace().collect::<Vec<_>>().join(" ")
+            }
+            None => {
+                let mut excerpt = words
+                    .split_whitespace()
+                    .take(EXCERPT_WORDS)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                if word_count > EXCERPT_WORDS {
+                    excerpt.push('…');
+                }
+                excerpt
+            }
+        };
+        Self {
+            html: String::from_utf8(html).expect("comrak only writes utf-8"),
+            toc: toc(root),
+            excerpt,
+            word_count,
+        }
+    }
+
+    /// Whole minutes, rounded up, so any non-empty document takes at least a
+    /// minute to read.
+    pub fn reading_time(&self) -> usize {
+        self.word_count.div_ceil(WORDS_PER_MINUTE)
+    }
+
+    /// The derived properties, without the html.
+    pub fn meta_liquid(&self) -> Value {
+        let mut object = liquid::Object::new();
+        object.insert(TOC_KEY.into(), toc_to_liquid(&self.toc));
+        object.insert(EXCERPT_KEY.into(), Value::scalar(self.excerpt.clone()));
+        object.insert(WORD_COUNT_KEY.into(), Value::scalar(self.word_count as i64));
+        object.insert(
+            READING_TIME_KEY.into(),
+            Value::scalar(self.reading_time() as i64),
+        );
+        Value::Object(object)
+    }
+}
+
+fn toc_to_liquid(entries: &[TocEntry]) -> Value {
+    Value::array(entries.iter().map(|entry| {
+        liquid::object!({
+            "level": entry.level as i64,
+            "title": entry.title,
+            "id": entry.id,
+            "children": toc_to_liquid(&entry.children),
+        })
+        .into()
+    }))
+}
+
+/// The name a markdown field's derived properties are exposed under.
+pub fn meta_field_name(field: &str) -> String {
+    format!("{field}{META_SUFFIX}")
+}
+
+fn toc<'a>(root: &'a AstNode<'a>) -> Vec<TocEntry> {
+    // Headings are anchorized in document order by the html renderer too, so
+    // a fresh anchorizer produces the same (deduplicated) ids.
+    let mut anchorizer = Anchorizer::new();
+    let mut entries: Vec<TocEntry> = vec![];
+    for node in root.descendants() {
+        let NodeValue::Heading(heading) = node.data.borrow().value else {
+            continue;
+        };
+        let mut text = vec![];
+        collect_text(node, &mut text);
+        let title = String::from_utf8_lossy(&text).to_string();
+        let entry = TocEntry {
+            level: heading.level,
+            id: anchorizer.anchorize(title.clone()),
+            title,
+            children: vec![],
+        };
+        insert_toc_entry(&mut entries, entry);
+    }
+    entries
+}
+
+/// Nests `entry` under the last entry at each depth whose level is lower.
+fn insert_toc_entry(entries: &mut Vec<TocEntry>, entry: TocEntry) {
+    match entries.last_mut() {
+        Some(parent) if parent.level < entry.level => insert_toc_entry(&mut parent.children, entry),
+        _ => entries.push(entry),
+    }
+}
+
+/// The readable text of a document, with blocks separated by whitespace. Raw
+/// html (including the excerpt separator) is not text.
+fn plain_text<'a>(root: &'a AstNode<'a>) -> String {
+    fn walk<'a>(node: &'a AstNode<'a>, out: &mut String) {
+        match &node.data.borrow().value {
+            NodeValue::Text(t) => out.push_str(t),
+            NodeValue::Code(c) => out.push_str(&c.literal),
+            NodeValue::CodeBlock(c) => out.push_str(&c.literal),
+            NodeValue::Math(m) => out.push_str(&m.literal),
+            NodeValue::LineBreak | NodeValue::SoftBreak => out.push(' '),
+            NodeValue::HtmlBlock(_) | NodeValue::HtmlInline(_) => {}
+            value => {
+                for child in node.children() {
+                    walk(child, out);
+                }
+                if value.block() {
+                    out.push(' ');
+                }
+            }
+        }
+    }
+    let mut out = String::new();
+    walk(root, &mut out);
+    out
+}
+
+// Markdown fields are converted every time they are turned into a liquid
+// value, which happens at least once per build and, in long-lived processes
+// like the dev server, once per rebuild. Conversion output only depends on the
+// source, so memoize it. The cap just bounds memory in processes that see many
+// unique documents (e.g. editing sessions).
+const MARKDOWN_CACHE_MAX_ENTRIES: usize = 1024;
+static MARKDOWN_CACHE: Lazy<RwLock<HashMap<String, Arc<RenderedMarkdown>>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+pub fn rendered_markdown_cached(source: &str) -> Arc<RenderedMarkdown> {
+    if let Some(rendered) = MARKDOWN_CACHE.read().unwrap().get(source) {
+        return rendered.clone();
+    }
+    let rendered = Arc::new(RenderedMarkdown::new(source));
+    let mut cache = MARKDOWN_CACHE.write().unwrap();
+    if cache.len() >= MARKDOWN_CACHE_MAX_ENTRIES {
+        cache.clear();
+    }
+    cache.insert(source.to_owned(), rendered.clone());
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use comrak::markdown_to_html;
+    use liquid::ValueView;
+
+    const DOC: &str = "# Intro\n\nSome *intro* text.\n\n## Details\n\nMore `code` here.\n\n### Deeper\n\n## Details\n\nLast words.\n";
+
+    #[test]
+    fn html_matches_plain_conversion() {
+        let rendered = RenderedMarkdown::new(DOC);
+        assert_eq!(rendered.html, markdown_to_html(DOC, &MARKDOWN_OPTIONS));
+    }
+
+    #[test]
+    fn toc_is_a_tree_with_rendered_ids() {
+        let rendered = RenderedMarkdown::new(DOC);
+        assert_eq!(rendered.toc.len(), 1);
+        let intro = &rendered.toc[0];
+        assert_eq!(intro.title, "Intro");
+        assert_eq!(intro.id, "intro");
+        let details: Vec<_> = intro.children.iter().map(|c| c.id.as_str()).collect();
+        // Duplicate headings get the same suffixed ids as the html.
+        assert_eq!(details, vec!["details", "details-1"]);
+        assert_eq!(intro.children[0].children[0].title, "Deeper");
+        for entry in [intro, &intro.children[0], &intro.children[1]] {
+            assert!(
+                rendered.html.contains(&format!("id=\"{}\"", entry.id)),
+                "{} not in html",
+                entry.id
+            );
+        }
+    }
+
+    #[test]
+    fn words_are_counted_from_text_only() {
+        let rendered =
+            RenderedMarkdown::new("# Title\n\none two <span>three</span>\n\n<div>skipped</div>\n");
+        assert_eq!(rendered.word_count, 4);
+        assert_eq!(rendered.reading_time(), 1);
+        assert_eq!(RenderedMarkdown::new("").reading_time(), 0);
+        let long = "word ".repeat(WORDS_PER_MINUTE * 2 + 1);
+        assert_eq!(RenderedMarkdown::new(&long).reading_time(), 3);
+    }
+
+    #[test]
+    fn excerpt_ends_at_the_separator() {
+        let rendered = RenderedMarkdown::new("First **bold** para.\n\n<!-- more -->\n\nHidden.");
+        assert_eq!(rendered.excerpt, "First bold para.");
+    }
+
+    #[test]
+    fn excerpt_defaults_to_leading_words() {
+        let long = "word ".repeat(EXCERPT_WORDS + 10);
+        let rendered = RenderedMarkdown::new(&long);
+        assert_eq!(rendered.excerpt.split_whitespace().count(), EXCERPT_WORDS);
+        assert!(rendered.excerpt.ends_with('…'));
+        assert_eq!(RenderedMarkdown::new("short one").excerpt, "short one");
+    }
+
+    #[test]
+    fn meta_leaves_out_the_html() {
+        let meta = RenderedMarkdown::new("hi there").meta_liquid();
+        let meta = meta.as_object().unwrap();
+        assert!(!meta.contains_key("html"));
+        assert_eq!(meta.get(WORD_COUNT_KEY).unwrap().to_kstr(), "2");
+        assert_eq!(meta_field_name("body"), "body_meta");
+    }
+}
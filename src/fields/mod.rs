@@ -1,11 +1,16 @@
+pub(crate) mod constraints;
 mod date_time;
 pub(crate) mod field_type;
 pub(crate) mod field_value;
 pub(crate) mod file;
+pub(crate) mod markdown;
 pub(crate) mod meta;
+pub use constraints::{ConstraintViolation, FieldConstraints};
 pub use date_time::DateTime;
 pub use field_type::{FieldType, InvalidFieldError, OneofOption};
-pub use field_value::{FieldValue, ObjectValues, RenderedFieldValue, RenderedObjectValues};
+pub use field_value::{
+    FieldValue, FieldValueValidationError, ObjectValues, RenderedFieldValue, RenderedObjectValues,
+};
 pub use file::{DisplayType, File, RenderedFile};
 pub use meta::{Meta, MetaValue};
 use serde::{Deserialize, Serialize};
@@ -0,0 +1,238 @@
+use crate::FileSystemAPI;
+use anyhow::Result;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+/// A filesystem that reads through to `base`, but keeps its writes and
+/// deletes in memory until they're committed. Used to apply a batch of events
+/// all or nothing.
+#[derive(Debug, Default)]
+pub(crate) struct StagedFileSystem<F: FileSystemAPI> {
+    base: F,
+    /// Files written (`Some`) or deleted (`None`) since staging began.
+    changes: BTreeMap<PathBuf, Option<Vec<u8>>>,
+    dirs: BTreeSet<PathBuf>,
+}
+
+impl<F: FileSystemAPI> StagedFileSystem<F> {
+    /// Stages changes over `base`, which should be a copy of the filesystem
+    /// they'll be committed to.
+    pub(crate) fn new(base: F) -> Self {
+        Self {
+            base,
+            changes: BTreeMap::new(),
+            dirs: BTreeSet::new(),
+        }
+    }
+
+    /// The files written or deleted so far.
+    pub(crate) fn changed_paths(&self) -> impl Iterator<Item = &PathBuf> {
+        self.changes.keys()
+    }
+
+    /// Writes the staged changes to `fs`, returning the paths changed. If a
+    /// write fails, the files already written are put back as they were.
+    pub(crate) fn commit(self, fs: &mut impl FileSystemAPI) -> Result<Vec<PathBuf>> {
+        let mut originals = vec![];
+        for path in self.changes.keys() {
+            // Some filesystems error reading a file that doesn't exist, and
+            // most batches create files.
+            let original = match fs.exists(path)? {
+                true => fs.read(path)?,
+                false => None,
+            };
+            originals.push((path, original));
+        }
+        for dir in &self.dirs {
+            fs.create_dir_all(dir)?;
+        }
+        let mut written = 0;
+        let result = self.changes.iter().try_for_each(|(path, contents)| {
+            match contents {
+                Some(contents) => fs.write(path, contents.clone())?,
+                None if fs.exists(path)? => fs.delete(path)?,
+                None => {}
+            }
+            written += 1;
+            Ok::<_, anyhow::Error>(())
+        });
+        if let Err(error) = result {
+            for (path, original) in originals.into_iter().take(written + 1) {
+                // Best effort: the original error is the one worth reporting.
+                let _ = match original {
+                    Some(contents) => fs.write(path, contents),
+                    None if fs.exists(path).unwrap_or(false) => fs.delete(path),
+                    None => Ok(()),
+                };
+            }
+            return Err(error);
+        }
+        Ok(self.changes.into_keys().collect())
+    }
+}
+
+impl<F: FileSystemAPI> FileSystemAPI for StagedFileSystem<F> {
+    fn root_dir(&self) -> &Path {
+        self.base.root_dir()
+    }
+    fn exists(&self, path: impl AsRef<Path>) -> Result<bool> {
+        let path = path.as_ref();
+        match self.changes.get(path) {
+            Some(contents) => Ok(contents.is_some()),
+            None => Ok(self.is_dir(path)? || self.base.exists(path)?),
+        }
+    }
+    fn is_dir(&self, path: impl AsRef<Path>) -> Result<bool> {
+        let path = path.as_ref();
+        Ok(self.dirs.contains(path)
+            || self.changes.iter().any(|(file, contents)| {
+                contents.is_some() && file.starts_with(path) && file != path
+            })
+            || self.base.is_dir(path)?)
+    }
+    fn remove_dir_all(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let files: Vec<PathBuf> = self.walk_dir(path, false)?.collect();
+        for file in files {
+            self.changes.insert(path.join(file), None);
+        }
+        self.dirs.retain(|dir| !dir.starts_with(path));
+        Ok(())
+    }
+    fn create_dir_all(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        self.dirs.insert(path.as_ref().to_path_buf());
+        Ok(())
+    }
+    fn read(&self, path: impl AsRef<Path>) -> Result<Option<Vec<u8>>> {
+        match self.changes.get(path.as_ref()) {
+            Some(contents) => Ok(contents.clone()),
+            None => self.base.read(path),
+        }
+    }
+    fn read_to_string(&self, path: impl AsRef<Path>) -> Result<Option<String>> {
+        match self.read(path)? {
+            Some(contents) => Ok(Some(String::from_utf8(contents)?)),
+            None => Ok(None),
+        }
+    }
+    fn delete(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        self.changes.insert(path.as_ref().to_path_buf(), None);
+        Ok(())
+    }
+    fn write(&mut self, path: impl AsRef<Path>, contents: Vec<u8>) -> Result<()> {
+        self.changes
+            .insert(path.as_ref().to_path_buf(), Some(contents));
+        Ok(())
+    }
+    fn rename(&mut self, from: impl AsRef<Path>, to: impl AsRef<Path>) -> Result<()> {
+        let contents = self.read(&from)?.ok_or_else(|| {
+            crate::ArchivalError::new(&format!("file not found: {}", from.as_ref().display()))
+        })?;
+        self.write(to, contents)?;
+        self.delete(from)
+    }
+    fn write_str(&mut self, path: impl AsRef<Path>, contents: String) -> Result<()> {
+        self.write(path, contents.into_bytes())
+    }
+    /// Non-recursive listings come from the base filesystem, without staged
+    /// changes; nothing that stages changes lists directories that way.
+    fn list_dir(
+        &self,
+        path: impl AsRef<Path>,
+        recursive: bool,
+    ) -> Result<Box<dyn Iterator<Item = PathBuf>>> {
+        if recursive {
+            self.walk_dir(path, true)
+        } else {
+            self.base.list_dir(path, false)
+        }
+    }
+    fn walk_dir(
+        &self,
+        path: impl AsRef<Path>,
+        include_dirs: bool,
+    ) -> Result<Box<dyn Iterator<Item = PathBuf>>> {
+        let path = path.as_ref();
+        let mut files: BTreeSet<PathBuf> = if self.base.is_dir(path)? {
+            self.base
+                .walk_dir(path, include_dirs)?
+                .filter(|file| !matches!(self.changes.get(&path.join(file)), Some(None)))
+                .collect()
+        } else {
+            BTreeSet::new()
+        };
+        for (file, contents) in &self.changes {
+            if let (Some(_), Ok(relative)) = (contents, file.strip_prefix(path)) {
+                files.insert(relative.to_path_buf());
+            }
+        }
+        Ok(Box::new(files.into_iter()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryFileSystem;
+
+    #[test]
+    fn stages_changes_until_committed() -> Result<()> {
+        let mut fs = MemoryFileSystem::default();
+        fs.write_str("objects/post/a.toml", "a".to_string())?;
+        fs.write_str("objects/post/b.toml", "b".to_string())?;
+        let mut staged = StagedFileSystem::new(fs.clone());
+        staged.write_str("objects/post/c.toml", "c".to_string())?;
+        staged.delete("objects/post/a.toml")?;
+        staged.write_str("objects/post/b.toml", "b2".to_string())?;
+
+        assert!(!staged.exists("objects/post/a.toml")?);
+        assert_eq!(
+            staged.read_to_string("objects/post/b.toml")?,
+            Some("b2".to_string())
+        );
+        assert_eq!(
+            staged.walk_dir("objects/post", false)?.collect::<Vec<_>>(),
+            vec![PathBuf::from("b.toml"), PathBuf::from("c.toml")]
+        );
+        // Nothing has been written yet.
+        assert!(fs.exists("objects/post/a.toml")?);
+        assert!(!fs.exists("objects/post/c.toml")?);
+
+        let changed = staged.commit(&mut fs)?;
+        assert_eq!(changed.len(), 3);
+        assert!(!fs.exists("objects/post/a.toml")?);
+        assert_eq!(
+            fs.read_to_string("objects/post/b.toml")?,
+            Some("b2".to_string())
+        );
+        assert_eq!(
+            fs.read_to_string("objects/post/c.toml")?,
+            Some("c".to_string())
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "stdlib-fs")]
+    #[test]
+    fn commits_new_files_to_disk() -> Result<()> {
+        use crate::file_system_stdlib::NativeFileSystem;
+        let dir = tempfile::tempdir()?;
+        let mut fs = NativeFileSystem::new(dir.path());
+        fs.write_str("objects/post/a.toml", "a".to_string())?;
+        let mut staged = StagedFileSystem::new(NativeFileSystem::new(dir.path()));
+        staged.create_dir_all("objects/page")?;
+        staged.write_str("objects/page/b.toml", "b".to_string())?;
+        staged.delete("objects/post/a.toml")?;
+
+        let changed = staged.commit(&mut fs)?;
+        assert_eq!(changed.len(), 2);
+        assert!(!dir.path().join("objects/post/a.toml").exists());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("objects/page/b.toml"))?,
+            "b"
+        );
+        Ok(())
+    }
+}
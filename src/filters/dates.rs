@@ -0,0 +1,92 @@
+use liquid_core::model::DateTime;
+use liquid_core::Result;
+use liquid_core::Runtime;
+use liquid_core::{Display_filter, Filter, FilterReflection, ParseFilter};
+use liquid_core::{Error, Value, ValueView};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+/// Date fields are date-times in liquid, but a date-only value (or a string
+/// that parses as one) is accepted as midnight UTC.
+fn date_time(input: &dyn ValueView) -> Result<DateTime> {
+    input
+        .as_scalar()
+        .and_then(|scalar| {
+            scalar.to_date_time().or_else(|| {
+                scalar
+                    .to_date()
+                    .map(|date| DateTime::from_ymd(date.year(), date.month(), date.day()))
+            })
+        })
+        .ok_or_else(|| Error::with_msg("Invalid date").context("input", input.to_kstr()))
+}
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "date_to_xmlschema",
+    description = "Formats a date as ISO 8601, as used by Atom feeds and sitemaps.",
+    parsed(DateToXmlschemaFilter)
+)]
+pub struct DateToXmlschema;
+
+#[derive(Debug, Default, Display_filter)]
+#[name = "date_to_xmlschema"]
+struct DateToXmlschemaFilter;
+
+impl Filter for DateToXmlschemaFilter {
+    fn evaluate(&self, input: &dyn ValueView, _runtime: &dyn Runtime) -> Result<Value> {
+        let formatted = OffsetDateTime::format(*date_time(input)?, &Rfc3339)
+            .map_err(|e| Error::with_msg(e.to_string()))?;
+        Ok(Value::scalar(formatted))
+    }
+}
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "date_to_rfc822",
+    description = "Formats a date as RFC 822, as used by RSS feeds.",
+    parsed(DateToRfc822Filter)
+)]
+pub struct DateToRfc822;
+
+#[derive(Debug, Default, Display_filter)]
+#[name = "date_to_rfc822"]
+struct DateToRfc822Filter;
+
+impl Filter for DateToRfc822Filter {
+    fn evaluate(&self, input: &dyn ValueView, _runtime: &dyn Runtime) -> Result<Value> {
+        Ok(Value::scalar(date_time(input)?.to_rfc2822()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fields::DateTime as ArchivalDateTime;
+
+    #[test]
+    fn formats_xmlschema() {
+        let date = ArchivalDateTime::from("2024-03-05 13:07:54").unwrap();
+        assert_eq!(
+            liquid_core::call_filter!(DateToXmlschema, date.as_liquid_datetime()).unwrap(),
+            liquid_core::value!("2024-03-05T13:07:54Z")
+        );
+        assert_eq!(
+            liquid_core::call_filter!(DateToXmlschema, "2024-03-05").unwrap(),
+            liquid_core::value!("2024-03-05T00:00:00Z")
+        );
+    }
+
+    #[test]
+    fn formats_rfc822() {
+        let date = ArchivalDateTime::from("2024-03-05 13:07:54").unwrap();
+        assert_eq!(
+            liquid_core::call_filter!(DateToRfc822, date.as_liquid_datetime()).unwrap(),
+            liquid_core::value!("Tue, 05 Mar 2024 13:07:54 +0000")
+        );
+    }
+
+    #[test]
+    fn rejects_values_that_are_not_dates() {
+        assert!(liquid_core::call_filter!(DateToRfc822, "soon").is_err());
+    }
+}
@@ -0,0 +1,110 @@
+use super::property;
+use liquid_core::Expression;
+use liquid_core::Result;
+use liquid_core::Runtime;
+use liquid_core::{
+    Display_filter, Filter, FilterParameters, FilterReflection, FromFilterParameters, ParseFilter,
+};
+use liquid_core::{Error, Value, ValueView};
+
+#[derive(Debug, FilterParameters)]
+struct GroupByArgs {
+    #[parameter(
+        description = "The property to group by. May be a path, e.g. \"author.name\".",
+        arg_type = "str"
+    )]
+    property: Expression,
+}
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "group_by",
+    description = "Groups an array of objects by a property. Returns groups with a `name`, `items` and `size`, in the order each name first appears.",
+    parameters(GroupByArgs),
+    parsed(GroupByFilter)
+)]
+pub struct GroupBy;
+
+#[derive(Debug, FromFilterParameters, Display_filter)]
+#[name = "group_by"]
+struct GroupByFilter {
+    #[parameters]
+    args: GroupByArgs,
+}
+
+impl Filter for GroupByFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let args = self.args.evaluate(runtime)?;
+        let array = input
+            .as_array()
+            .ok_or_else(|| Error::with_msg("Array expected").context("input", input.to_kstr()))?;
+        // Groups are keyed by their rendered name, so `1` and `"1"` share a
+        // group, matching how they'd render in a heading.
+        let mut groups: Vec<(String, Value, Vec<Value>)> = vec![];
+        for item in array.values() {
+            let name = property(item, &args.property);
+            let key = name.map(|n| n.to_kstr().into_string()).unwrap_or_default();
+            match groups.iter_mut().find(|(k, _, _)| *k == key) {
+                Some((_, _, items)) => items.push(item.to_value()),
+                None => groups.push((
+                    key,
+                    name.map(|n| n.to_value()).unwrap_or(Value::Nil),
+                    vec![item.to_value()],
+                )),
+            }
+        }
+        Ok(Value::array(groups.into_iter().map(|(_, name, items)| {
+            let mut group = liquid_core::Object::new();
+            group.insert("name".into(), name);
+            group.insert("size".into(), Value::scalar(items.len() as i64));
+            group.insert("items".into(), Value::Array(items));
+            Value::Object(group)
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_in_order_of_appearance() {
+        let posts = liquid_core::value!([
+            { "title": "a", "author": { "name": "Lu" } },
+            { "title": "b", "author": { "name": "Ana" } },
+            { "title": "c", "author": { "name": "Lu" } },
+            { "title": "d" },
+        ]);
+        let groups = liquid_core::call_filter!(GroupBy, posts, "author.name").unwrap();
+        let summary: Vec<String> = groups
+            .as_array()
+            .unwrap()
+            .values()
+            .map(|group| {
+                let group = group.as_object().unwrap();
+                let titles: Vec<String> = group
+                    .get("items")
+                    .unwrap()
+                    .as_array()
+                    .unwrap()
+                    .values()
+                    .map(|item| {
+                        item.as_object()
+                            .unwrap()
+                            .get("title")
+                            .unwrap()
+                            .to_kstr()
+                            .into_string()
+                    })
+                    .collect();
+                format!(
+                    "{}:{}:{}",
+                    group.get("name").unwrap().to_kstr(),
+                    group.get("size").unwrap().to_kstr(),
+                    titles.join(",")
+                )
+            })
+            .collect();
+        assert_eq!(summary, vec!["Lu:2:a,c", "Ana:1:b", ":1:d"]);
+    }
+}
@@ -0,0 +1,224 @@
+//! Html-aware text filters. Markdown fields reach templates as html, so these
+//! are what templates use to make summaries of them.
+
+use liquid_core::Expression;
+use liquid_core::Result;
+use liquid_core::Runtime;
+use liquid_core::{
+    Display_filter, Filter, FilterParameters, FilterReflection, FromFilterParameters, ParseFilter,
+};
+use liquid_core::{Value, ValueView};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Elements that never have content or a closing tag.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+static INVISIBLE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<script.*?</script>|<style.*?</style>|<!--.*?-->").unwrap());
+static BLOCK_TAG_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)</?(address|article|aside|blockquote|br|dd|div|dl|dt|figcaption|figure|footer|h[1-6]|header|hr|li|main|nav|ol|p|pre|section|table|td|th|tr|ul)\b[^>]*>").unwrap()
+});
+static TAG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+static ENTITY_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[a-zA-Z]+);").unwrap());
+
+fn decode_entity(entity: &str) -> Option<char> {
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => {
+            let number = entity.strip_prefix('#')?;
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+/// The text of an html fragment: tags removed, scripts, styles and comments
+/// dropped entirely, common entities decoded and whitespace collapsed. Block
+/// elements are separated by a space, so `<p>a</p><p>b</p>` is `a b` rather
+/// than `ab`.
+pub(crate) fn strip_html(html: &str) -> String {
+    let text = INVISIBLE_RE.replace_all(html, "");
+    let text = BLOCK_TAG_RE.replace_all(&text, " ");
+    let text = TAG_RE.replace_all(&text, "");
+    let text = ENTITY_RE.replace_all(&text, |captures: &regex::Captures| {
+        match decode_entity(&captures[1]) {
+            Some(c) => c.to_string(),
+            None => captures[0].to_string(),
+        }
+    });
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "html_to_text",
+    description = "Removes html tags, scripts, styles and comments from a string, leaving its text with whitespace collapsed. Unlike `strip_html`, block elements are separated by a space.",
+    parsed(HtmlToTextFilter)
+)]
+pub struct HtmlToText;
+
+#[derive(Debug, Default, Display_filter)]
+#[name = "html_to_text"]
+struct HtmlToTextFilter;
+
+impl Filter for HtmlToTextFilter {
+    fn evaluate(&self, input: &dyn ValueView, _runtime: &dyn Runtime) -> Result<Value> {
+        Ok(Value::scalar(strip_html(&input.to_kstr())))
+    }
+}
+
+/// Truncates html to `length` characters of text, keeping it well formed:
+/// tags don't count towards the length, entities count as one character, and
+/// tags left open at the cut are closed.
+pub(crate) fn truncate_html(html: &str, length: usize, ellipsis: &str) -> String {
+    let mut out = String::with_capacity(html.len().min(length * 2));
+    let mut open: Vec<String> = vec![];
+    let mut count = 0;
+    let mut rest = html;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            let end = if rest.starts_with("<!--") {
+                rest.find("-->").map(|at| at + 3)
+            } else {
+                rest.find('>').map(|at| at + 1)
+            };
+            let Some(end) = end else {
+                break;
+            };
+            let tag = &rest[..end];
+            rest = &rest[end..];
+            if !tag.starts_with("<!") && !tag.starts_with("<?") {
+                let name: String = tag
+                    .trim_start_matches(['<', '/'])
+                    .chars()
+                    .take_while(|c| c.is_ascii_alphanumeric())
+                    .collect::<String>()
+                    .to_ascii_lowercase();
+                if tag.starts_with("</") {
+                    if let Some(at) = open.iter().rposition(|n| *n == name) {
+                        open.truncate(at);
+                    }
+                } else if !tag.ends_with("/>") && !VOID_ELEMENTS.contains(&name.as_str()) {
+                    open.push(name);
+                }
+            }
+            out.push_str(tag);
+            continue;
+        }
+        if count == length {
+            out.push_str(ellipsis);
+            break;
+        }
+        let len = match ENTITY_RE.find(rest) {
+            Some(entity) if entity.start() == 0 => entity.end(),
+            _ => c.len_utf8(),
+        };
+        out.push_str(&rest[..len]);
+        rest = &rest[len..];
+        count += 1;
+    }
+    for name in open.iter().rev() {
+        out.push_str(&format!("</{name}>"));
+    }
+    out
+}
+
+#[derive(Debug, FilterParameters)]
+struct TruncateHtmlArgs {
+    #[parameter(
+        description = "The number of text characters to keep. Defaults to 50.",
+        arg_type = "integer"
+    )]
+    length: Option<Expression>,
+    #[parameter(
+        description = "Appended to the text when it is truncated. Defaults to \"...\".",
+        arg_type = "str"
+    )]
+    ellipsis: Option<Expression>,
+}
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "truncate_html",
+    description = "Shortens html to a number of text characters, closing any tags left open.",
+    parameters(TruncateHtmlArgs),
+    parsed(TruncateHtmlFilter)
+)]
+pub struct TruncateHtml;
+
+#[derive(Debug, FromFilterParameters, Display_filter)]
+#[name = "truncate_html"]
+struct TruncateHtmlFilter {
+    #[parameters]
+    args: TruncateHtmlArgs,
+}
+
+impl Filter for TruncateHtmlFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let args = self.args.evaluate(runtime)?;
+        let length = args.length.unwrap_or(50).max(0) as usize;
+        let ellipsis = args.ellipsis.as_deref().unwrap_or("...");
+        Ok(Value::scalar(truncate_html(
+            &input.to_kstr(),
+            length,
+            ellipsis,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_html_leaves_readable_text() {
+        assert_eq!(
+            strip_html("<h1>Title</h1><p>One &amp; <em>two</em>&nbsp;&#x21;</p><script>x()</script><!-- c --><p>Three</p>"),
+            "Title One & two ! Three"
+        );
+        assert_eq!(
+            liquid_core::call_filter!(HtmlToText, "<style>p{}</style>a<br/>b").unwrap(),
+            liquid_core::value!("a b")
+        );
+    }
+
+    #[test]
+    fn truncate_html_counts_text_only() {
+        let html = "<p>Hello <strong>big</strong> world</p>";
+        assert_eq!(truncate_html(html, 100, "..."), html);
+        assert_eq!(
+            truncate_html(html, 8, "..."),
+            "<p>Hello <strong>bi...</strong></p>"
+        );
+        assert_eq!(
+            truncate_html("<p>a &amp; b<br>c</p>", 3, "…"),
+            "<p>a &amp;…</p>"
+        );
+    }
+
+    #[test]
+    fn truncate_html_filter_uses_defaults() {
+        let long = format!("<p>{}</p>", "x".repeat(60));
+        assert_eq!(
+            liquid_core::call_filter!(TruncateHtml, long).unwrap(),
+            liquid_core::value!(format!("<p>{}...</p>", "x".repeat(50)))
+        );
+        assert_eq!(
+            liquid_core::call_filter!(TruncateHtml, "<i>abc</i>", 2, "").unwrap(),
+            liquid_core::value!("<i>ab</i>")
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use liquid_core::model::Scalar;
+use liquid_core::Result;
+use liquid_core::Runtime;
+use liquid_core::{Display_filter, Filter, FilterReflection, ParseFilter};
+use liquid_core::{Value, ValueView};
+use serde_json::Number;
+use time::{format_description::well_known::Rfc3339, macros::format_description, UtcOffset};
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "jsonify",
+    description = "Converts a value to JSON, with dates as ISO 8601 strings.",
+    parsed(JsonifyFilter)
+)]
+pub struct Jsonify;
+
+#[derive(Debug, Default, Display_filter)]
+#[name = "jsonify"]
+struct JsonifyFilter;
+
+impl Filter for JsonifyFilter {
+    fn evaluate(&self, input: &dyn ValueView, _runtime: &dyn Runtime) -> Result<Value> {
+        Ok(Value::scalar(liquid_to_json(&input.to_value()).to_string()))
+    }
+}
+
+/// Liquid values derive `Serialize`, but a `DateTime` serializes through
+/// liquid's own display format ("2022-12-22 00:00:00 +0000"). Carriers and
+/// `jsonify` output are consumed as ISO 8601 dates, so the conversion is
+/// written out by hand.
+pub(crate) fn liquid_to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Nil | Value::State(_) => serde_json::Value::Null,
+        Value::Array(values) => {
+            serde_json::Value::Array(values.iter().map(liquid_to_json).collect())
+        }
+        Value::Object(object) => serde_json::Value::Object(
+            object
+                .iter()
+                .map(|(k, v)| (k.to_string(), liquid_to_json(v)))
+                .collect(),
+        ),
+        Value::Scalar(scalar) => scalar_to_json(scalar),
+    }
+}
+
+fn scalar_to_json(scalar: &Scalar) -> serde_json::Value {
+    // Discriminating by type_name is deliberate: to_integer and to_date_time
+    // parse string values, so a "42" field would silently become a number.
+    match scalar.type_name() {
+        "whole number" => scalar
+            .to_integer()
+            .map(|i| serde_json::Value::Number(i.into()))
+            .unwrap_or(serde_json::Value::Null),
+        "fractional number" => scalar
+            .to_float()
+            .and_then(Number::from_f64)
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null),
+        "boolean" => scalar
+            .to_bool()
+            .map(serde_json::Value::Bool)
+            .unwrap_or(serde_json::Value::Null),
+        "date time" => scalar
+            .to_date_time()
+            .and_then(|dt| dt.to_offset(UtcOffset::UTC).format(&Rfc3339).ok())
+            .map(serde_json::Value::String)
+            .unwrap_or(serde_json::Value::Null),
+        "date" => scalar
+            .to_date()
+            .and_then(|d| d.format(format_description!("[year]-[month]-[day]")).ok())
+            .map(serde_json::Value::String)
+            .unwrap_or(serde_json::Value::Null),
+        _ => serde_json::Value::String(scalar.to_kstr().into_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_values() {
+        assert_eq!(
+            liquid_core::call_filter!(Jsonify, liquid_core::value!({ "a": [1, "two"] })).unwrap(),
+            liquid_core::value!(r#"{"a":[1,"two"]}"#)
+        );
+        assert_eq!(
+            liquid_core::call_filter!(Jsonify, "say \"hi\"").unwrap(),
+            liquid_core::value!(r#""say \"hi\"""#)
+        );
+    }
+}
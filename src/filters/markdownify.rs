@@ -0,0 +1,41 @@
+use crate::fields::markdown::rendered_markdown_cached;
+use liquid_core::Result;
+use liquid_core::Runtime;
+use liquid_core::{Display_filter, Filter, FilterReflection, ParseFilter};
+use liquid_core::{Value, ValueView};
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "markdownify",
+    description = "Converts a markdown string to html, the same way markdown fields are.",
+    parsed(MarkdownifyFilter)
+)]
+pub struct Markdownify;
+
+#[derive(Debug, Default, Display_filter)]
+#[name = "markdownify"]
+struct MarkdownifyFilter;
+
+impl Filter for MarkdownifyFilter {
+    fn evaluate(&self, input: &dyn ValueView, _runtime: &dyn Runtime) -> Result<Value> {
+        if input.is_nil() {
+            return Ok(Value::scalar(""));
+        }
+        Ok(Value::scalar(
+            rendered_markdown_cached(&input.to_kstr()).html.clone(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_markdown() {
+        assert_eq!(
+            liquid_core::call_filter!(Markdownify, "**hi**").unwrap(),
+            liquid_core::value!("<p><strong>hi</strong></p>\n")
+        );
+    }
+}
@@ -1,2 +1,116 @@
 // mod size;
 // pub use size::SizeFilterParser;
+mod dates;
+mod group_by;
+mod html;
+mod jsonify;
+mod markdownify;
+mod number_with_delimiter;
+mod query;
+mod slugify;
+mod sort_by;
+mod urls;
+mod where_exp;
+pub use dates::{DateToRfc822, DateToXmlschema};
+pub use group_by::GroupBy;
+pub use html::{HtmlToText, TruncateHtml};
+#[cfg(any(feature = "carriers", feature = "plugins"))]
+pub(crate) use jsonify::liquid_to_json;
+pub use jsonify::Jsonify;
+pub use markdownify::Markdownify;
+pub use number_with_delimiter::NumberWithDelimiter;
+pub use query::Query;
+pub use slugify::Slugify;
+pub use sort_by::SortBy;
+pub use urls::{AbsoluteUrl, RelativeUrl};
+
+use crate::tags::output::OutputContext;
+use liquid_core::{ParseFilter, ValueView};
+#[cfg(feature = "lsp")]
+use liquid_lib::stdlib;
+use std::sync::Arc;
+use where_exp::WhereExp;
+
+/// Looks up a dotted property path (`author.name`) on an object.
+fn property<'v>(value: &'v dyn ValueView, path: &str) -> Option<&'v dyn ValueView> {
+    path.split('.')
+        .try_fold(value, |value, key| value.as_object()?.get(key))
+}
+
+/// Archival's own filters, registered after (and so taking precedence over)
+/// [`stdlib_filters`]. `ctx` is the parser's output context, which filters
+/// that evaluate liquid expressions parse them with.
+pub(crate) fn archival_filters(ctx: &Arc<OutputContext>) -> Vec<Box<dyn ParseFilter>> {
+    vec![
+        Box::new(AbsoluteUrl),
+        Box::new(DateToRfc822),
+        Box::new(DateToXmlschema),
+        Box::new(GroupBy),
+        Box::new(HtmlToText),
+        Box::new(Jsonify),
+        Box::new(Markdownify),
+        Box::new(NumberWithDelimiter),
+        Box::new(Query),
+        Box::new(RelativeUrl),
+        Box::new(Slugify),
+        Box::new(SortBy),
+        Box::new(TruncateHtml),
+        Box::new(WhereExp::new(Arc::clone(ctx))),
+    ]
+}
+
+/// The filters `liquid::ParserBuilder::with_stdlib` registers, for tools that
+/// list them alongside [`archival_filters`].
+#[cfg(feature = "lsp")]
+pub fn stdlib_filters() -> Vec<Box<dyn ParseFilter>> {
+    vec![
+        Box::new(stdlib::Abs),
+        Box::new(stdlib::Append),
+        Box::new(stdlib::AtLeast),
+        Box::new(stdlib::AtMost),
+        Box::new(stdlib::Capitalize),
+        Box::new(stdlib::Ceil),
+        Box::new(stdlib::Compact),
+        Box::new(stdlib::Concat),
+        Box::new(stdlib::Date),
+        Box::new(stdlib::Default),
+        Box::new(stdlib::DividedBy),
+        Box::new(stdlib::Downcase),
+        Box::new(stdlib::Escape),
+        Box::new(stdlib::EscapeOnce),
+        Box::new(stdlib::First),
+        Box::new(stdlib::Floor),
+        Box::new(stdlib::Join),
+        Box::new(stdlib::Last),
+        Box::new(stdlib::Lstrip),
+        Box::new(stdlib::Map),
+        Box::new(stdlib::Minus),
+        Box::new(stdlib::Modulo),
+        Box::new(stdlib::NewlineToBr),
+        Box::new(stdlib::Plus),
+        Box::new(stdlib::Prepend),
+        Box::new(stdlib::Remove),
+        Box::new(stdlib::RemoveFirst),
+        Box::new(stdlib::Replace),
+        Box::new(stdlib::ReplaceFirst),
+        Box::new(stdlib::Reverse),
+        Box::new(stdlib::Round),
+        Box::new(stdlib::Rstrip),
+        Box::new(stdlib::Size),
+        Box::new(stdlib::Slice),
+        Box::new(stdlib::Sort),
+        Box::new(stdlib::SortNatural),
+        Box::new(stdlib::Split),
+        Box::new(stdlib::Strip),
+        Box::new(stdlib::StripHtml),
+        Box::new(stdlib::StripNewlines),
+        Box::new(stdlib::Times),
+        Box::new(stdlib::Truncate),
+        Box::new(stdlib::TruncateWords),
+        Box::new(stdlib::Uniq),
+        Box::new(stdlib::Upcase),
+        Box::new(stdlib::UrlDecode),
+        Box::new(stdlib::UrlEncode),
+        Box::new(stdlib::Where),
+    ]
+}
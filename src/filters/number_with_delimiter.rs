@@ -0,0 +1,110 @@
+use liquid_core::Expression;
+use liquid_core::Result;
+use liquid_core::Runtime;
+use liquid_core::{
+    Display_filter, Filter, FilterParameters, FilterReflection, FromFilterParameters, ParseFilter,
+};
+use liquid_core::{Error, Value, ValueView};
+
+#[derive(Debug, FilterParameters)]
+struct NumberWithDelimiterArgs {
+    #[parameter(
+        description = "Placed between each group of three digits. Defaults to \",\".",
+        arg_type = "str"
+    )]
+    delimiter: Option<Expression>,
+    #[parameter(
+        description = "Placed between the whole and fractional parts. Defaults to \".\".",
+        arg_type = "str"
+    )]
+    separator: Option<Expression>,
+}
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "number_with_delimiter",
+    description = "Formats a number with its thousands delimited, e.g. 1234567 -> 1,234,567.",
+    parameters(NumberWithDelimiterArgs),
+    parsed(NumberWithDelimiterFilter)
+)]
+pub struct NumberWithDelimiter;
+
+#[derive(Debug, FromFilterParameters, Display_filter)]
+#[name = "number_with_delimiter"]
+struct NumberWithDelimiterFilter {
+    #[parameters]
+    args: NumberWithDelimiterArgs,
+}
+
+impl Filter for NumberWithDelimiterFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let args = self.args.evaluate(runtime)?;
+        let delimiter = args.delimiter.as_deref().unwrap_or(",");
+        let separator = args.separator.as_deref().unwrap_or(".");
+        let number = input
+            .as_scalar()
+            .and_then(|s| s.to_float())
+            .ok_or_else(|| Error::with_msg("Number expected").context("input", input.to_kstr()))?;
+        // Formatting the scalar (rather than the float) keeps integers
+        // integral and floats as precise as they were written.
+        let text = input.to_kstr();
+        let text = if text.parse::<f64>().is_ok() {
+            text.into_string()
+        } else {
+            number.to_string()
+        };
+        let (sign, digits) = match text.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", &text[..]),
+        };
+        let (whole, fraction) = match digits.split_once('.') {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (digits, None),
+        };
+        let mut delimited = String::with_capacity(text.len() * 2);
+        for (i, digit) in whole.chars().enumerate() {
+            if i > 0 && (whole.len() - i) % 3 == 0 {
+                delimited.push_str(delimiter);
+            }
+            delimited.push(digit);
+        }
+        Ok(Value::scalar(match fraction {
+            Some(fraction) => format!("{sign}{delimited}{separator}{fraction}"),
+            None => format!("{sign}{delimited}"),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delimits_thousands() {
+        assert_eq!(
+            liquid_core::call_filter!(NumberWithDelimiter, 1234567).unwrap(),
+            liquid_core::value!("1,234,567")
+        );
+        assert_eq!(
+            liquid_core::call_filter!(NumberWithDelimiter, -1234.5).unwrap(),
+            liquid_core::value!("-1,234.5")
+        );
+        assert_eq!(
+            liquid_core::call_filter!(NumberWithDelimiter, 999).unwrap(),
+            liquid_core::value!("999")
+        );
+    }
+
+    #[test]
+    fn accepts_a_custom_delimiter_and_separator() {
+        assert_eq!(
+            liquid_core::call_filter!(NumberWithDelimiter, "1234567.89", ".", ",").unwrap(),
+            liquid_core::value!("1.234.567,89")
+        );
+    }
+
+    #[test]
+    fn rejects_non_numbers() {
+        assert!(liquid_core::call_filter!(NumberWithDelimiter, "many").is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use crate::query::FilterOp;
+use liquid_core::model::ValueViewCmp;
+use liquid_core::{
+    Display_filter, Error, Expression, Filter, FilterParameters, FilterReflection,
+    FromFilterParameters, ParseFilter, Result, Runtime, Value, ValueView,
+};
+
+#[derive(Debug, FilterParameters)]
+struct QueryArgs {
+    #[parameter(
+        description = "A path to the values to compare, e.g. \"tags\" or \"links.0.url\". A child list without an index matches if any of its entries does.",
+        arg_type = "str"
+    )]
+    path: Expression,
+    #[parameter(
+        description = "One of ==, !=, <, <=, >, >=, contains or starts_with.",
+        arg_type = "str"
+    )]
+    op: Expression,
+    #[parameter(description = "The value to compare with.")]
+    value: Expression,
+}
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "query",
+    description = "Filters an array of objects the same way `Archival::query` does.",
+    parameters(QueryArgs),
+    parsed(QueryFilter)
+)]
+pub struct Query;
+
+#[derive(Debug, FromFilterParameters, Display_filter)]
+#[name = "query"]
+struct QueryFilter {
+    #[parameters]
+    args: QueryArgs,
+}
+
+/// Every value at `path`, leading into each item of an array unless the next
+/// component is an index.
+fn values_at<'v>(value: &'v dyn ValueView, path: &[&str]) -> Vec<&'v dyn ValueView> {
+    let Some((key, rest)) = path.split_first() else {
+        return vec![value];
+    };
+    if let Some(array) = value.as_array() {
+        return match key.parse::<i64>() {
+            Ok(idx) => array
+                .get(idx)
+                .map(|item| values_at(item, rest))
+                .unwrap_or_default(),
+            Err(_) => array
+                .values()
+                .flat_map(|item| values_at(item, path))
+                .collect(),
+        };
+    }
+    value
+        .as_object()
+        .and_then(|object| object.get(key))
+        .map(|value| values_at(value, rest))
+        .unwrap_or_default()
+}
+
+fn test(value: &dyn ValueView, op: FilterOp, needle: &dyn ValueView) -> bool {
+    let compare = |v: &dyn ValueView| ValueViewCmp::new(v).partial_cmp(&ValueViewCmp::new(needle));
+    match op {
+        FilterOp::Contains => match value.as_array() {
+            Some(array) => array.values().any(|item| FilterOp::Eq.holds(compare(item))),
+            None => {
+                value.is_scalar()
+                    && needle.is_scalar()
+                    && value.to_kstr().contains(needle.to_kstr().as_str())
+            }
+        },
+        FilterOp::StartsWith => {
+            value.is_scalar()
+                && needle.is_scalar()
+                && value.to_kstr().starts_with(needle.to_kstr().as_str())
+        }
+        op => op.holds(compare(value)),
+    }
+}
+
+impl Filter for QueryFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let args = self.args.evaluate(runtime)?;
+        let op: FilterOp = args
+            .op
+            .parse()
+            .map_err(|e: crate::query::UnknownFilterOp| Error::with_msg(e.to_string()))?;
+        let path: Vec<&str> = args.path.split('.').collect();
+        let items: Vec<&dyn ValueView> = match input.as_array() {
+            Some(array) => array.values().collect(),
+            None if input.is_nil() => vec![],
+            None => return Err(Error::with_msg("Array expected").context("input", input.to_kstr())),
+        };
+        let matches = items.into_iter().filter(|item| {
+            let values = values_at(*item, &path);
+            // As in `QueryFilter::matches`, != holds when no value is equal.
+            if op == FilterOp::Ne {
+                !values
+                    .iter()
+                    .any(|v| test(*v, FilterOp::Eq, args.value.as_view()))
+            } else {
+                values.iter().any(|v| test(*v, op, args.value.as_view()))
+            }
+        });
+        Ok(Value::Array(matches.map(|item| item.to_value()).collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::liquid_parser;
+
+    fn render(template: &str) -> String {
+        let parser = liquid_parser::build_with_partials(Default::default()).unwrap();
+        liquid_parser::parse(&parser, template)
+            .unwrap()
+            .render(&liquid::object!({
+                "posts": [
+                    { "title": "a", "n": 1, "tags": ["rust"], "links": [{ "url": "https://a.com" }] },
+                    { "title": "b", "n": 2, "tags": ["web"], "links": [] },
+                    { "title": "c", "n": 3, "tags": ["rust", "web"], "links": [{ "url": "https://x.com" }, { "url": "https://a.com/c" }] },
+                ]
+            }))
+            .unwrap()
+    }
+
+    fn titles(filter: &str) -> String {
+        render(&format!(
+            "{{% assign r = posts | query: {filter} %}}{{% for p in r %}}{{{{ p.title }}}}{{% endfor %}}"
+        ))
+    }
+
+    #[test]
+    fn compares_values() {
+        assert_eq!(titles("'n', '>=', 2"), "bc");
+        assert_eq!(titles("'n', '!=', 2"), "ac");
+        assert_eq!(titles("'title', '==', 'b'"), "b");
+    }
+
+    #[test]
+    fn matches_lists_and_children() {
+        assert_eq!(titles("'tags', 'contains', 'rust'"), "ac");
+        assert_eq!(titles("'links.url', 'starts_with', 'https://a.com'"), "ac");
+        assert_eq!(titles("'links.0.url', 'starts_with', 'https://a.com'"), "a");
+        assert_eq!(titles("'links.url', '!=', 'https://x.com'"), "ab");
+    }
+
+    #[test]
+    fn rejects_unknown_operators() {
+        let parser = liquid_parser::build_with_partials(Default::default()).unwrap();
+        let template = liquid_parser::parse(&parser, "{{ posts | query: 'n', '~', 1 }}").unwrap();
+        assert!(template.render(&liquid::object!({ "posts": [] })).is_err());
+    }
+}
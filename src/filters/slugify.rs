@@ -0,0 +1,36 @@
+use crate::util::slugify;
+use liquid_core::Result;
+use liquid_core::Runtime;
+use liquid_core::{Display_filter, Filter, FilterReflection, ParseFilter};
+use liquid_core::{Value, ValueView};
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "slugify",
+    description = "Lowercases a string and joins its words with `-`, for use in urls.",
+    parsed(SlugifyFilter)
+)]
+pub struct Slugify;
+
+#[derive(Debug, Default, Display_filter)]
+#[name = "slugify"]
+struct SlugifyFilter;
+
+impl Filter for SlugifyFilter {
+    fn evaluate(&self, input: &dyn ValueView, _runtime: &dyn Runtime) -> Result<Value> {
+        Ok(Value::scalar(slugify(&input.to_kstr())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugifies_strings() {
+        assert_eq!(
+            liquid_core::call_filter!(Slugify, "My First Post: 2024!").unwrap(),
+            liquid_core::value!("my-first-post-2024")
+        );
+    }
+}
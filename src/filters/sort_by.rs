@@ -0,0 +1,173 @@
+use super::property;
+use liquid_core::model::ValueViewCmp;
+use liquid_core::parser::{FilterArguments, FilterReflection, ParameterReflection};
+use liquid_core::{Error, Expression, Filter, ParseFilter, Result, Runtime, Value, ValueView};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// Takes any number of keys, which liquid's derived parameters can't express,
+/// so parsing and reflection are implemented by hand.
+#[derive(Clone)]
+pub struct SortBy;
+
+static SORT_BY_PARAMETERS: [ParameterReflection; 1] = [ParameterReflection {
+    name: "keys",
+    description: "One or more properties to sort by, most significant first. Prefix a property with `-` to sort it in descending order, e.g. \"-date\", \"title\".",
+    is_optional: false,
+}];
+
+impl FilterReflection for SortBy {
+    fn name(&self) -> &str {
+        "sort_by"
+    }
+
+    fn description(&self) -> &str {
+        "Sorts an array of objects by one or more properties. Items missing a property sort last, and items that compare equal keep their order."
+    }
+
+    fn positional_parameters(&self) -> &'static [ParameterReflection] {
+        &SORT_BY_PARAMETERS
+    }
+
+    fn keyword_parameters(&self) -> &'static [ParameterReflection] {
+        &[]
+    }
+}
+
+impl ParseFilter for SortBy {
+    fn parse(&self, mut arguments: FilterArguments) -> Result<Box<dyn Filter>> {
+        if let Some((name, _)) = arguments.keyword.next() {
+            return Err(
+                Error::with_msg("Unexpected named argument").context("argument", name.to_string())
+            );
+        }
+        let keys: Vec<Expression> = arguments.positional.collect();
+        if keys.is_empty() {
+            return Err(Error::with_msg("sort_by requires at least one key"));
+        }
+        Ok(Box::new(SortByFilter { keys }))
+    }
+
+    fn reflection(&self) -> &dyn FilterReflection {
+        self
+    }
+}
+
+#[derive(Debug)]
+struct SortByFilter {
+    keys: Vec<Expression>,
+}
+
+impl fmt::Display for SortByFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keys: Vec<String> = self.keys.iter().map(|k| k.to_string()).collect();
+        write!(f, "sort_by: {}", keys.join(", "))
+    }
+}
+
+fn compare(a: Option<&dyn ValueView>, b: Option<&dyn ValueView>) -> Ordering {
+    let a = a.filter(|v| !v.is_nil());
+    let b = b.filter(|v| !v.is_nil());
+    match (a, b) {
+        (Some(a), Some(b)) => ValueViewCmp::new(a)
+            .partial_cmp(&ValueViewCmp::new(b))
+            .unwrap_or(Ordering::Equal),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+impl Filter for SortByFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let keys = self
+            .keys
+            .iter()
+            .map(|key| {
+                let key = key.evaluate(runtime)?.to_kstr().into_string();
+                Ok(match key.strip_prefix('-') {
+                    Some(key) => (key.to_string(), true),
+                    None => (key, false),
+                })
+            })
+            .collect::<Result<Vec<(String, bool)>>>()?;
+        let array = input
+            .as_array()
+            .ok_or_else(|| Error::with_msg("Array expected").context("input", input.to_kstr()))?;
+        let mut items: Vec<&dyn ValueView> = array.values().collect();
+        items.sort_by(|a, b| {
+            for (key, descending) in &keys {
+                let (a, b) = (property(*a, key), property(*b, key));
+                // Missing values sort last in either direction.
+                let ordering = match (
+                    a.is_some_and(|v| !v.is_nil()),
+                    b.is_some_and(|v| !v.is_nil()),
+                ) {
+                    (true, true) if *descending => compare(b, a),
+                    _ => compare(a, b),
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            Ordering::Equal
+        });
+        Ok(Value::array(items.into_iter().map(|v| v.to_value())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn titles(value: Value) -> String {
+        value
+            .as_array()
+            .unwrap()
+            .values()
+            .map(|v| {
+                v.as_object()
+                    .unwrap()
+                    .get("t")
+                    .unwrap()
+                    .to_kstr()
+                    .into_string()
+            })
+            .collect()
+    }
+
+    fn posts() -> Value {
+        liquid_core::value!([
+            { "t": "a", "n": 2, "g": "x" },
+            { "t": "b", "n": 1, "g": "y" },
+            { "t": "c", "g": "x" },
+            { "t": "d", "n": 2, "g": "y" },
+            { "t": "e", "n": 1, "g": "x" },
+        ])
+    }
+
+    #[test]
+    fn sorts_by_one_key_stably_with_missing_values_last() {
+        assert_eq!(
+            titles(liquid_core::call_filter!(SortBy, posts(), "n").unwrap()),
+            "beadc"
+        );
+        assert_eq!(
+            titles(liquid_core::call_filter!(SortBy, posts(), "-n").unwrap()),
+            "adbec"
+        );
+    }
+
+    #[test]
+    fn sorts_by_several_keys() {
+        assert_eq!(
+            titles(liquid_core::call_filter!(SortBy, posts(), "g", "-n").unwrap()),
+            "aecdb"
+        );
+    }
+
+    #[test]
+    fn requires_a_key() {
+        assert!(liquid_core::call_filter!(SortBy, posts()).is_err());
+    }
+}
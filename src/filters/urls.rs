@@ -0,0 +1,148 @@
+//! Url filters resolve paths against `site_url`, which every render context
+//! carries (see `page::RenderGlobals`).
+
+use liquid_core::model::ScalarCow;
+use liquid_core::Result;
+use liquid_core::Runtime;
+use liquid_core::{Display_filter, Filter, FilterReflection, ParseFilter};
+use liquid_core::{Value, ValueView};
+
+fn site_url(runtime: &dyn Runtime) -> String {
+    runtime
+        .try_get(&[ScalarCow::new("site_url")])
+        .map(|url| url.to_kstr().trim_end_matches('/').to_string())
+        .unwrap_or_default()
+}
+
+/// Urls with a scheme (or protocol-relative ones) already point somewhere.
+fn is_absolute(url: &str) -> bool {
+    url.starts_with("//")
+        || url.split_once(':').is_some_and(|(scheme, _)| {
+            !scheme.is_empty()
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        })
+}
+
+/// The path part of a url. `site_url` may be written without a scheme
+/// (`example.com/blog`), or as just a path (`/blog`).
+fn url_path(url: &str) -> &str {
+    let rest = match url.split_once("://") {
+        Some((_, rest)) => rest,
+        None => match url.strip_prefix("//") {
+            Some(rest) => rest,
+            None if url.starts_with('/') => return url,
+            None => url,
+        },
+    };
+    rest.find('/').map_or("", |at| &rest[at..])
+}
+
+fn join(base: &str, path: &str) -> String {
+    format!("{}/{}", base, path.trim_start_matches('/'))
+}
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "absolute_url",
+    description = "Prefixes a path with `site_url`. Urls that are already absolute are left alone.",
+    parsed(AbsoluteUrlFilter)
+)]
+pub struct AbsoluteUrl;
+
+#[derive(Debug, Default, Display_filter)]
+#[name = "absolute_url"]
+struct AbsoluteUrlFilter;
+
+impl Filter for AbsoluteUrlFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let input = input.to_kstr();
+        if is_absolute(&input) {
+            return Ok(Value::scalar(input.into_string()));
+        }
+        Ok(Value::scalar(join(&site_url(runtime), &input)))
+    }
+}
+
+#[derive(Clone, ParseFilter, FilterReflection)]
+#[filter(
+    name = "relative_url",
+    description = "Prefixes a path with the path `site_url` is served from, so that links work when a site is not at the root of its domain.",
+    parsed(RelativeUrlFilter)
+)]
+pub struct RelativeUrl;
+
+#[derive(Debug, Default, Display_filter)]
+#[name = "relative_url"]
+struct RelativeUrlFilter;
+
+impl Filter for RelativeUrlFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let input = input.to_kstr();
+        if is_absolute(&input) {
+            return Ok(Value::scalar(input.into_string()));
+        }
+        let site_url = site_url(runtime);
+        Ok(Value::scalar(join(url_path(&site_url), &input)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::liquid_parser;
+
+    fn render(template: &str, site_url: &str) -> String {
+        let parser = liquid_parser::build_with_partials(Default::default()).unwrap();
+        liquid_parser::parse(&parser, template)
+            .unwrap()
+            .render(&liquid::object!({ "site_url": site_url }))
+            .unwrap()
+    }
+
+    #[test]
+    fn absolute_urls_include_the_site_url() {
+        assert_eq!(
+            render("{{ '/post/a.html' | absolute_url }}", "https://a.com/"),
+            "https://a.com/post/a.html"
+        );
+        assert_eq!(
+            render("{{ 'img.png' | absolute_url }}", "https://a.com/blog"),
+            "https://a.com/blog/img.png"
+        );
+        assert_eq!(
+            render("{{ 'https://b.com/x' | absolute_url }}", "https://a.com"),
+            "https://b.com/x"
+        );
+    }
+
+    #[test]
+    fn relative_urls_include_the_site_path() {
+        assert_eq!(
+            render("{{ 'post/a.html' | relative_url }}", "https://a.com/blog/"),
+            "/blog/post/a.html"
+        );
+        assert_eq!(
+            render("{{ '/post/a.html' | relative_url }}", "https://a.com"),
+            "/post/a.html"
+        );
+        assert_eq!(
+            render("{{ 'mailto:a@b.com' | relative_url }}", "https://a.com"),
+            "mailto:a@b.com"
+        );
+        // Without a scheme, the host is still not part of the path.
+        assert_eq!(
+            render("{{ 'a.html' | relative_url }}", "a.com/blog"),
+            "/blog/a.html"
+        );
+        assert_eq!(render("{{ 'a.html' | relative_url }}", "a.com"), "/a.html");
+        assert_eq!(
+            render("{{ 'a.html' | relative_url }}", "/blog"),
+            "/blog/a.html"
+        );
+        assert_eq!(
+            render("{{ 'a.html' | relative_url }}", "//a.com/blog"),
+            "/blog/a.html"
+        );
+    }
+}
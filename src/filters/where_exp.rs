@@ -0,0 +1,153 @@
+use crate::tags::output::OutputContext;
+use liquid_core::error::ResultLiquidExt;
+use liquid_core::parser::FilterArguments;
+use liquid_core::runtime::{self, StackFrame};
+use liquid_core::{
+    Display_filter, Expression, Filter, FilterParameters, FilterReflection, ParseFilter, Renderable,
+};
+use liquid_core::{Error, Object, Result, Runtime, Value, ValueView};
+use std::sync::Arc;
+
+#[derive(Debug, FilterParameters)]
+struct WhereExpArgs {
+    #[parameter(
+        description = "The name each item is bound to while the expression is evaluated.",
+        arg_type = "str"
+    )]
+    variable: Expression,
+    #[parameter(
+        description = "A condition, as accepted by `{% if %}`, e.g. \"post.tags contains 'rust'\".",
+        arg_type = "str"
+    )]
+    expression: Expression,
+}
+
+/// Expressions are parsed with the same `Language` as the page, so they are
+/// parsed at render time via the parser's `OutputContext`. Parsed conditions
+/// are cached there alongside nested values.
+#[derive(Clone, FilterReflection)]
+#[filter(
+    name = "where_exp",
+    description = "Filters an array to the items for which a liquid condition is true.",
+    parameters(WhereExpArgs)
+)]
+pub(crate) struct WhereExp {
+    ctx: Arc<OutputContext>,
+}
+
+impl WhereExp {
+    pub(crate) fn new(ctx: Arc<OutputContext>) -> Self {
+        Self { ctx }
+    }
+}
+
+impl ParseFilter for WhereExp {
+    fn parse(&self, arguments: FilterArguments) -> Result<Box<dyn Filter>> {
+        let args = WhereExpArgs::from_args(arguments)?;
+        Ok(Box::new(WhereExpFilter {
+            args,
+            ctx: self.ctx.clone(),
+        }))
+    }
+
+    fn reflection(&self) -> &dyn FilterReflection {
+        self
+    }
+}
+
+#[derive(Debug, Display_filter)]
+#[name = "where_exp"]
+struct WhereExpFilter {
+    #[parameters]
+    args: WhereExpArgs,
+    ctx: Arc<OutputContext>,
+}
+
+fn holds(condition: &runtime::Template, runtime: &dyn Runtime) -> Result<bool> {
+    let mut out = Vec::new();
+    condition.render_to(&mut out, runtime)?;
+    Ok(out == b"true")
+}
+
+impl Filter for WhereExpFilter {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> Result<Value> {
+        let args = self.args.evaluate(runtime)?;
+        let condition = self
+            .ctx
+            .condition(&args.expression)
+            .context_key("where_exp")
+            .value_with(|| args.expression.to_string().into())?;
+        let items: Vec<&dyn ValueView> = match input.as_array() {
+            Some(array) => array.values().collect(),
+            None if input.is_nil() => vec![],
+            None => return Err(Error::with_msg("Array expected").context("input", input.to_kstr())),
+        };
+        let mut matches = vec![];
+        for item in items {
+            let mut scope = Object::new();
+            scope.insert(args.variable.to_string().into(), item.to_value());
+            if holds(&condition, &StackFrame::new(runtime, &scope))? {
+                matches.push(item.to_value());
+            }
+        }
+        Ok(Value::Array(matches))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::liquid_parser;
+
+    fn render(template: &str) -> String {
+        let parser = liquid_parser::build_with_partials(Default::default()).unwrap();
+        liquid_parser::parse(&parser, template)
+            .unwrap()
+            .render(&liquid::object!({
+                "min": 2,
+                "posts": [
+                    { "title": "a", "n": 1, "tags": ["rust"] },
+                    { "title": "b", "n": 2, "tags": ["web"] },
+                    { "title": "c", "n": 3, "tags": ["rust", "web"] },
+                ]
+            }))
+            .unwrap()
+    }
+
+    #[test]
+    fn keeps_items_matching_the_expression() {
+        assert_eq!(
+            render("{% assign r = posts | where_exp: 'p', \"p.tags contains 'rust'\" %}{% for p in r %}{{ p.title }}{% endfor %}"),
+            "ac"
+        );
+        assert_eq!(
+            render("{% assign r = posts | where_exp: 'p', 'p.n >= min and p.title != \"c\"' %}{% for p in r %}{{ p.title }}{% endfor %}"),
+            "b"
+        );
+    }
+
+    #[test]
+    fn invalid_expressions_are_errors() {
+        let parser = liquid_parser::build_with_partials(Default::default()).unwrap();
+        let err = liquid_parser::parse(&parser, "{{ posts | where_exp: 'p', 'p.n >' }}")
+            .unwrap()
+            .render(&liquid::object!({ "posts": [{ "n": 1 }] }))
+            .unwrap_err();
+        assert!(err.to_string().contains("where_exp"), "{}", err);
+    }
+
+    #[test]
+    fn expressions_cannot_add_markup() {
+        let parser = liquid_parser::build_with_partials(Default::default()).unwrap();
+        let injected = "{{ posts | where_exp: 'p', \"true %}leaked{% endif %}{% if true\" }}";
+        let err = liquid_parser::parse(&parser, injected)
+            .unwrap()
+            .render(&liquid::object!({ "posts": [{ "n": 1 }] }))
+            .unwrap_err();
+        assert!(err.to_string().contains("markup"), "{}", err);
+        // Delimiters inside strings are just text.
+        assert_eq!(
+            render("{% assign r = posts | where_exp: 'p', \"p.title != '%}'\" %}{{ r.size }}"),
+            "3"
+        );
+    }
+}
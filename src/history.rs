@@ -0,0 +1,531 @@
+//! Previous versions of objects, read from the git repository a site is
+//! checked into.
+//!
+//! [`crate::Archival::object_history`] lists the commits that changed an
+//! object file, each with the changes it made diffed field by field using the
+//! object's definition. [`crate::Archival::restore_object`] puts an object
+//! back as it was at a revision by sending the events that turn its current
+//! values into the old ones, so a restore is journaled, validated and built
+//! like any other edit.
+
+use crate::{
+    events::{
+        AddObjectEvent, AddRootObjectEvent, ArchivalEvent, DeleteObjectEvent, EditFieldEvent,
+        EditOrderEvent, RemoveChildEvent,
+    },
+    fields::{FieldValue, ObjectValues},
+    journal::{field_values, restore_children},
+    object::Object,
+    reserved_fields,
+    value_path::ValuePath,
+};
+use serde::Serialize;
+use std::{
+    collections::BTreeSet,
+    fmt,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone)]
+pub enum HistoryError {
+    #[error("failed running git: {0}")]
+    Git(String),
+    #[error("unknown revision {0}")]
+    UnknownRevision(String),
+}
+
+/// A commit that changed an object file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Revision {
+    pub id: String,
+    pub author: String,
+    /// The author date, in ISO 8601 format.
+    pub date: String,
+    pub summary: String,
+}
+
+/// An object as a revision left it, and what that revision changed. `object`
+/// is `None` if the revision deleted it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ObjectVersion {
+    pub revision: Revision,
+    pub object: Option<Object>,
+    pub changes: Vec<FieldChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum FieldChange {
+    Added {
+        path: ValuePath,
+        value: FieldValue,
+    },
+    Removed {
+        path: ValuePath,
+        value: FieldValue,
+    },
+    Changed {
+        path: ValuePath,
+        from: FieldValue,
+        to: FieldValue,
+    },
+}
+
+impl fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Added { path, value } => write!(f, "+ {path}: {value}"),
+            Self::Removed { path, value } => write!(f, "- {path}: {value}"),
+            Self::Changed { path, from, to } => write!(f, "~ {path}: {from} -> {to}"),
+        }
+    }
+}
+
+fn git(root: &Path, args: &[&str]) -> Result<Output, HistoryError> {
+    Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()
+        .map_err(|e| HistoryError::Git(e.to_string()))
+}
+
+/// The commits that changed `path` (relative to `root`), newest first, each
+/// with the path the file had then. Renames are followed, so this goes back
+/// past the file being moved or renamed.
+pub(crate) fn file_log(root: &Path, path: &Path) -> Result<Vec<(Revision, PathBuf)>, HistoryError> {
+    let output = git(
+        root,
+        &[
+            "log",
+            "--follow",
+            "--name-status",
+            "--format=%x1e%H%x1f%an%x1f%aI%x1f%s",
+            "--",
+            &path.to_string_lossy(),
+        ],
+    )?;
+    if !output.status.success() {
+        return Err(HistoryError::Git(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    let mut path = path.to_path_buf();
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\x1e')
+        .filter_map(|entry| {
+            let mut lines = entry.lines();
+            let mut parts = lines.next()?.split('\x1f');
+            let revision = Revision {
+                id: parts.next()?.to_string(),
+                author: parts.next()?.to_string(),
+                date: parts.next()?.to_string(),
+                summary: parts.next().unwrap_or_default().to_string(),
+            };
+            // The status line is `M\tpath`, or `R100\told\tnew` for a rename.
+            // Commits without one (like merges) leave the file where the newer
+            // commit found it.
+            let status = lines.rfind(|line| line.contains('\t'));
+            let mut paths = status.into_iter().flat_map(|s| s.split('\t').skip(1));
+            let (from, to) = (paths.next(), paths.next());
+            if let Some(at) = to.or(from) {
+                path = PathBuf::from(at);
+            }
+            let entry = (revision, path.clone());
+            // Older commits find the file where it was before a rename.
+            if let (Some(from), Some(_)) = (from, to) {
+                path = PathBuf::from(from);
+            }
+            Some(entry)
+        })
+        .collect())
+}
+
+/// Where the file now at `path` (relative to `root`) was at `revision`,
+/// following renames like [`file_log`].
+pub(crate) fn path_at(root: &Path, revision: &str, path: &Path) -> Result<PathBuf, HistoryError> {
+    for (logged, logged_path) in file_log(root, path)? {
+        let is_ancestor = git(root, &["merge-base", "--is-ancestor", &logged.id, revision])?;
+        if is_ancestor.status.success() {
+            return Ok(logged_path);
+        }
+    }
+    Ok(path.to_path_buf())
+}
+
+/// The contents of `path` (relative to `root`) at `revision`, or `None` if it
+/// didn't exist then.
+pub(crate) fn file_at(
+    root: &Path,
+    revision: &str,
+    path: &Path,
+) -> Result<Option<String>, HistoryError> {
+    let verify = git(
+        root,
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{revision}^{{commit}}"),
+        ],
+    )?;
+    if !verify.status.success() {
+        return Err(HistoryError::UnknownRevision(revision.to_string()));
+    }
+    let output = git(
+        root,
+        &["show", &format!("{revision}:./{}", path.to_string_lossy())],
+    )?;
+    Ok(output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned()))
+}
+
+/// Unset values and explicit nulls are the same thing to a diff.
+fn present(value: Option<&FieldValue>) -> Option<&FieldValue> {
+    value.filter(|value| !matches!(value, FieldValue::Null))
+}
+
+/// The field-level changes that turn `old` into `new`. Children are compared
+/// by index.
+pub fn diff_objects(old: Option<&Object>, new: Option<&Object>) -> Vec<FieldChange> {
+    let mut changes = vec![];
+    let order = |object: Option<&Object>| object.and_then(|o| o.order).map(FieldValue::Number);
+    diff_value(
+        order(old).as_ref(),
+        order(new).as_ref(),
+        ValuePath::empty().append(ValuePath::key(reserved_fields::ORDER)),
+        &mut changes,
+    );
+    let empty = ObjectValues::new();
+    diff_values(
+        old.map_or(&empty, |o| &o.values),
+        new.map_or(&empty, |o| &o.values),
+        &ValuePath::empty(),
+        &mut changes,
+    );
+    changes
+}
+
+fn diff_values(
+    old: &ObjectValues,
+    new: &ObjectValues,
+    path: &ValuePath,
+    changes: &mut Vec<FieldChange>,
+) {
+    let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    for key in keys {
+        let path = path.clone().append(ValuePath::key(key));
+        match (present(old.get(key)), present(new.get(key))) {
+            (Some(FieldValue::Objects(old)), Some(FieldValue::Objects(new))) => {
+                for idx in 0..old.len().max(new.len()) {
+                    let child_path = path.clone().append(ValuePath::index(idx));
+                    let child = |values: &ObjectValues| FieldValue::Objects(vec![values.clone()]);
+                    match (old.get(idx), new.get(idx)) {
+                        (Some(old), Some(new)) => diff_values(old, new, &child_path, changes),
+                        (old, new) => diff_value(
+                            old.map(child).as_ref(),
+                            new.map(child).as_ref(),
+                            child_path,
+                            changes,
+                        ),
+                    }
+                }
+            }
+            (old, new) => diff_value(old, new, path, changes),
+        }
+    }
+}
+
+fn diff_value(
+    old: Option<&FieldValue>,
+    new: Option<&FieldValue>,
+    path: ValuePath,
+    changes: &mut Vec<FieldChange>,
+) {
+    match (old, new) {
+        (Some(old), Some(new)) if old != new => changes.push(FieldChange::Changed {
+            path,
+            from: old.clone(),
+            to: new.clone(),
+        }),
+        (None, Some(new)) => changes.push(FieldChange::Added {
+            path,
+            value: new.clone(),
+        }),
+        (Some(old), None) => changes.push(FieldChange::Removed {
+            path,
+            value: old.clone(),
+        }),
+        _ => {}
+    }
+}
+
+/// The events that turn `current` into `target`, where `None` means the
+/// object doesn't exist.
+pub(crate) fn restore_events(
+    object: &str,
+    filename: &str,
+    is_root: bool,
+    current: Option<&Object>,
+    target: Option<&Object>,
+) -> Vec<ArchivalEvent> {
+    let mut events = vec![];
+    match (current, target) {
+        (Some(_), None) => events.push(ArchivalEvent::DeleteObject(DeleteObjectEvent {
+            object: object.to_string(),
+            filename: filename.to_string(),
+            source: None,
+        })),
+        (None, Some(target)) => {
+            events.push(if is_root {
+                ArchivalEvent::AddRootObject(AddRootObjectEvent {
+                    object: object.to_string(),
+                    values: field_values(&target.values),
+                })
+            } else {
+                ArchivalEvent::AddObject(AddObjectEvent {
+                    object: object.to_string(),
+                    filename: filename.to_string(),
+                    order: target.order,
+                    values: field_values(&target.values),
+                })
+            });
+            restore_children(
+                object,
+                filename,
+                &ValuePath::empty(),
+                &target.values,
+                &mut events,
+            );
+        }
+        (Some(current), Some(target)) => {
+            if current.order != target.order {
+                events.push(ArchivalEvent::EditOrder(EditOrderEvent {
+                    object: object.to_string(),
+                    filename: filename.to_string(),
+                    order: target.order,
+                    source: None,
+                }));
+            }
+            edit_events(
+                object,
+                filename,
+                &ValuePath::empty(),
+                &current.values,
+                &target.values,
+                &mut events,
+            );
+        }
+        (None, None) => {}
+    }
+    events
+}
+
+/// Edits the values at `path` from `current` to `target`. Children are edited
+/// in place if there are as many of them as there were, and otherwise
+/// replaced.
+fn edit_events(
+    object: &str,
+    filename: &str,
+    path: &ValuePath,
+    current: &ObjectValues,
+    target: &ObjectValues,
+    events: &mut Vec<ArchivalEvent>,
+) {
+    let keys: BTreeSet<&String> = current.keys().chain(target.keys()).collect();
+    for key in keys {
+        let (current, target) = (present(current.get(key)), present(target.get(key)));
+        if current == target {
+            continue;
+        }
+        let children_path = path.clone().append(ValuePath::key(key));
+        match (current, target) {
+            (Some(FieldValue::Objects(current)), Some(FieldValue::Objects(target)))
+                if current.len() == target.len() =>
+            {
+                for (idx, (current, target)) in current.iter().zip(target).enumerate() {
+                    let child_path = children_path.clone().append(ValuePath::index(idx));
+                    edit_events(object, filename, &child_path, current, target, events);
+                }
+            }
+            (current, Some(FieldValue::Objects(_)))
+            | (current @ Some(FieldValue::Objects(_)), _) => {
+                if let Some(FieldValue::Objects(current)) = current {
+                    for idx in (0..current.len()).rev() {
+                        events.push(ArchivalEvent::RemoveChild(RemoveChildEvent {
+                            object: object.to_string(),
+                            filename: filename.to_string(),
+                            path: children_path.clone().append(ValuePath::index(idx)),
+                            source: None,
+                        }));
+                    }
+                }
+                if let Some(target) = target {
+                    let values = ObjectValues::from([(key.clone(), target.clone())]);
+                    restore_children(object, filename, path, &values, events);
+                }
+            }
+            (_, target) => events.push(ArchivalEvent::EditField(EditFieldEvent {
+                object: object.to_string(),
+                filename: filename.to_string(),
+                path: path.clone(),
+                field: key.clone(),
+                value: target.cloned(),
+                source: None,
+            })),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::{MANIFEST_FILE_NAME, OBJECT_DEFINITION_FILE_NAME},
+        file_system_stdlib::NativeFileSystem,
+        Archival,
+    };
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn commit(root: &Path, message: &str) {
+        let run = |args: &[&str]| {
+            let output = Command::new("git")
+                .arg("-C")
+                .arg(root)
+                .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "{output:?}");
+        };
+        run(&["add", "-A"]);
+        run(&["commit", "-q", "-m", message]);
+    }
+
+    fn object(title: &str, links: &[&str]) -> Object {
+        let link = |url: &&str| {
+            ObjectValues::from([("url".to_string(), FieldValue::String(url.to_string()))])
+        };
+        Object {
+            filename: "a".to_string(),
+            object_name: "post".to_string(),
+            order: None,
+            values: ObjectValues::from([
+                ("title".to_string(), FieldValue::String(title.to_string())),
+                (
+                    "links".to_string(),
+                    FieldValue::Objects(links.iter().map(link).collect()),
+                ),
+            ]),
+        }
+    }
+
+    #[test]
+    fn diffs_by_field() {
+        let changes = diff_objects(
+            Some(&object("A", &["one"])),
+            Some(&object("B", &["uno", "two"])),
+        );
+        let changes: Vec<String> = changes.iter().map(|c| c.to_string()).collect();
+        assert_eq!(changes[0], "~ links.0.url: one -> uno");
+        assert!(changes[1].starts_with("+ links.1: "));
+        assert_eq!(changes[2], "~ title: A -> B");
+    }
+
+    #[test]
+    fn edits_children_in_place_when_they_line_up() {
+        let current = object("A", &["one"]);
+        let events = restore_events(
+            "post",
+            "a",
+            false,
+            Some(&current),
+            Some(&object("A", &["uno"])),
+        );
+        assert!(matches!(
+            &events[..],
+            [ArchivalEvent::EditField(EditFieldEvent { field, path, .. })]
+                if field == "url" && path.to_string() == "links.0"
+        ));
+        let events = restore_events("post", "a", false, Some(&current), Some(&object("A", &[])));
+        assert!(matches!(&events[..], [ArchivalEvent::RemoveChild(_)]));
+    }
+
+    #[test]
+    fn reads_and_restores_history() -> anyhow::Result<()> {
+        let root = tempdir()?.keep();
+        let post = root.join("objects/post/a.toml");
+        fs::create_dir_all(post.parent().unwrap())?;
+        fs::write(root.join(MANIFEST_FILE_NAME), "upload_prefix = \"\"\n")?;
+        fs::write(
+            root.join(OBJECT_DEFINITION_FILE_NAME),
+            "[post]\ntitle = \"string\"\nbody = \"string\"\n",
+        )?;
+        fs::write(&post, "title = \"A\"\n")?;
+        git(&root, &["init", "-q"])?;
+        commit(&root, "Add a");
+        fs::write(&post, "title = \"B\"\nbody = \"x\"\n")?;
+        commit(&root, "Edit a");
+
+        let archival = Archival::new(NativeFileSystem::new(&root))?;
+        let history = archival.object_history("post", "a")?;
+        let summaries: Vec<&str> = history
+            .iter()
+            .map(|v| v.revision.summary.as_str())
+            .collect();
+        assert_eq!(summaries, vec!["Edit a", "Add a"]);
+        let changes: Vec<String> = history[0].changes.iter().map(|c| c.to_string()).collect();
+        assert_eq!(changes, vec!["+ body: x", "~ title: A -> B"]);
+
+        archival.restore_object("post", "a", "HEAD~1", None)?;
+        let object = archival.get_object("post", Some("a"))?;
+        assert_eq!(object.values["title"], FieldValue::String("A".to_string()));
+        assert!(!object.values.contains_key("body"));
+        assert!(archival.restore_object("post", "a", "nope", None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn follows_renamed_objects() -> anyhow::Result<()> {
+        let root = tempdir()?.keep();
+        let posts = root.join("objects/post");
+        fs::create_dir_all(&posts)?;
+        fs::write(root.join(MANIFEST_FILE_NAME), "upload_prefix = \"\"\n")?;
+        fs::write(
+            root.join(OBJECT_DEFINITION_FILE_NAME),
+            "[post]\ntitle = \"string\"\nbody = \"string\"\n",
+        )?;
+        fs::write(
+            posts.join("draft.toml"),
+            "title = \"A\"\nbody = \"some text\"\n",
+        )?;
+        git(&root, &["init", "-q"])?;
+        commit(&root, "Add draft");
+        fs::rename(posts.join("draft.toml"), posts.join("a.toml"))?;
+        commit(&root, "Rename draft");
+        fs::write(
+            posts.join("a.toml"),
+            "title = \"B\"\nbody = \"some text\"\n",
+        )?;
+        commit(&root, "Edit a");
+
+        let archival = Archival::new(NativeFileSystem::new(&root))?;
+        let history = archival.object_history("post", "a")?;
+        let summaries: Vec<&str> = history
+            .iter()
+            .map(|v| v.revision.summary.as_str())
+            .collect();
+        assert_eq!(summaries, vec!["Edit a", "Rename draft", "Add draft"]);
+        assert!(history.iter().all(|v| v.object.is_some()));
+        assert!(history[1].changes.is_empty());
+
+        archival.restore_object("post", "a", "HEAD~2", None)?;
+        let object = archival.get_object("post", Some("a"))?;
+        assert_eq!(object.values["title"], FieldValue::String("A".to_string()));
+        Ok(())
+    }
+}
@@ -0,0 +1,455 @@
+//! An undo/redo journal of the events sent to an [`crate::Archival`].
+//!
+//! When enabled with [`crate::Archival::enable_journal`], each event sent is
+//! recorded alongside the events that would reverse it, computed from the
+//! object as it was just before. Consecutive events with the same group ID, or
+//! failing that the same `source`, are undone and redone together.
+//!
+//! A journal serializes with serde, so an editor can save it and pass it back
+//! to `enable_journal` after a reload.
+
+use crate::{
+    events::{
+        AddChildEvent, AddListValueEvent, AddObjectEvent, AddObjectValue, AddRootObjectEvent,
+        ArchivalEvent, DeleteObjectEvent, EditFieldEvent, EditOrderEvent, MoveListValueEvent,
+        RemoveChildEvent, RemoveListValueEvent, RenameObjectEvent,
+    },
+    fields::{FieldValue, ObjectValues},
+    object::Object,
+    query::value_at,
+    value_path::{ValuePath, ValuePathComponent, ValuePathError},
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// The most groups a journal keeps to undo. Older groups are forgotten.
+const MAX_UNDO_GROUPS: usize = 500;
+
+#[derive(Error, Debug, Clone)]
+pub enum JournalError {
+    #[error("the journal is not enabled")]
+    Disabled,
+    #[error("{0} needs the object it changes, which wasn't found")]
+    MissingObject(String),
+}
+
+/// An event that was applied, and the events that reverse it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub event: ArchivalEvent,
+    pub inverse: Vec<ArchivalEvent>,
+}
+
+/// Entries that are undone and redone together.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalGroup {
+    /// The explicit group ID or event source the entries share, if any.
+    pub id: Option<String>,
+    pub entries: Vec<JournalEntry>,
+}
+
+impl JournalGroup {
+    /// A description of the group for undo and redo menus, e.g. "Change
+    /// field title in post 'a'".
+    pub fn description(&self) -> String {
+        match &self.entries[..] {
+            [entry] => entry.event.to_string(),
+            [first, ..] => format!("{} and {} more", first.event, self.entries.len() - 1),
+            [] => String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Journal {
+    undo: Vec<JournalGroup>,
+    redo: Vec<JournalGroup>,
+    /// The group set by [`Self::start_group`].
+    #[serde(skip)]
+    group: Option<String>,
+    /// Whether the last undo group can still be added to. A reloaded journal
+    /// starts a new group.
+    #[serde(skip)]
+    open: bool,
+}
+
+impl Journal {
+    /// The groups that can be undone, most recent last.
+    pub fn undo_groups(&self) -> &[JournalGroup] {
+        &self.undo
+    }
+
+    /// The groups that can be redone, next last.
+    pub fn redo_groups(&self) -> &[JournalGroup] {
+        &self.redo
+    }
+
+    /// Groups the events that follow under `id`, until [`Self::end_group`],
+    /// whatever their source.
+    pub fn start_group(&mut self, id: impl Into<String>) {
+        self.group = Some(id.into());
+        self.open = false;
+    }
+
+    pub fn end_group(&mut self) {
+        self.group = None;
+        self.open = false;
+    }
+
+    pub(crate) fn record(&mut self, entry: JournalEntry) {
+        let id = self
+            .group
+            .clone()
+            .or_else(|| entry.event.source().map(|s| s.to_string()));
+        self.record_group(id, vec![entry]);
+    }
+
+    /// Records the events of a batch, which are undone together. A batch
+    /// joins the group set by [`Self::start_group`], if there is one, and is
+    /// otherwise a group of its own.
+    pub(crate) fn record_batch(&mut self, entries: Vec<JournalEntry>) {
+        if entries.is_empty() {
+            return;
+        }
+        self.record_group(self.group.clone(), entries);
+        if self.group.is_none() {
+            self.open = false;
+        }
+    }
+
+    fn record_group(&mut self, id: Option<String>, entries: Vec<JournalEntry>) {
+        match self.undo.last_mut() {
+            Some(last) if self.open && id.is_some() && last.id == id => {
+                last.entries.extend(entries)
+            }
+            _ => {
+                self.undo.push(JournalGroup { id, entries });
+                if self.undo.len() > MAX_UNDO_GROUPS {
+                    self.undo.remove(0);
+                }
+            }
+        }
+        self.open = true;
+        self.redo.clear();
+    }
+
+    pub(crate) fn pop_undo(&mut self) -> Option<JournalGroup> {
+        self.open = false;
+        self.undo.pop()
+    }
+
+    pub(crate) fn push_undo(&mut self, group: JournalGroup) {
+        self.undo.push(group);
+    }
+
+    pub(crate) fn pop_redo(&mut self) -> Option<JournalGroup> {
+        self.open = false;
+        self.redo.pop()
+    }
+
+    pub(crate) fn push_redo(&mut self, group: JournalGroup) {
+        self.redo.push(group);
+    }
+}
+
+/// The fields of `values` that add events set directly: everything but
+/// children, which are added afterwards by [`restore_children`].
+pub(crate) fn field_values(values: &ObjectValues) -> Vec<AddObjectValue> {
+    values
+        .iter()
+        .filter(|(_, value)| !matches!(value, FieldValue::Objects(_) | FieldValue::Null))
+        .map(|(key, value)| AddObjectValue {
+            path: ValuePath::empty().append(ValuePath::key(key)),
+            value: value.clone(),
+        })
+        .collect()
+}
+
+/// Events that add back the children in `values`, which lives at `path`, in
+/// order and at their indices.
+pub(crate) fn restore_children(
+    object: &str,
+    filename: &str,
+    path: &ValuePath,
+    values: &ObjectValues,
+    events: &mut Vec<ArchivalEvent>,
+) {
+    for (key, value) in values {
+        let FieldValue::Objects(children) = value else {
+            continue;
+        };
+        let children_path = path.clone().append(ValuePath::key(key));
+        for (idx, child) in children.iter().enumerate() {
+            events.push(ArchivalEvent::AddChild(AddChildEvent {
+                object: object.to_string(),
+                filename: filename.to_string(),
+                path: children_path.clone(),
+                values: field_values(child),
+                index: Some(idx),
+            }));
+            let child_path = children_path.clone().append(ValuePath::index(idx));
+            restore_children(object, filename, &child_path, child, events);
+        }
+    }
+}
+
+/// The child at `path`, which ends with its index.
+fn child_at<'a>(
+    object: &'a Object,
+    path: &ValuePath,
+) -> Result<(ValuePath, usize, &'a ObjectValues), ValuePathError> {
+    let not_found = || ValuePathError::NotFound(path.clone(), object.filename.clone());
+    let mut children_path = path.clone();
+    let Some(ValuePathComponent::Index(idx)) = children_path.pop() else {
+        return Err(not_found());
+    };
+    match value_at(object, &children_path) {
+        Some(FieldValue::Objects(children)) => children
+            .get(idx)
+            .map(|child| (children_path, idx, child))
+            .ok_or_else(not_found),
+        _ => Err(not_found()),
+    }
+}
+
+fn list_len(object: &Object, path: &ValuePath) -> usize {
+    match value_at(object, path) {
+        Some(FieldValue::List(items)) => items.len(),
+        _ => 0,
+    }
+}
+
+fn child_count(object: &Object, path: &ValuePath) -> usize {
+    match value_at(object, path) {
+        Some(FieldValue::Objects(children)) => children.len(),
+        _ => 0,
+    }
+}
+
+/// Works out how to reverse `event`, given the object it changes as it is
+/// before the event is applied, and whether that object is a root object.
+/// Events that append are recorded with the index they append at, so that
+/// redoing them lands in the same place.
+pub(crate) fn entry_for(
+    event: ArchivalEvent,
+    current: Option<&Object>,
+    is_root: bool,
+) -> anyhow::Result<JournalEntry> {
+    let current = || current.ok_or_else(|| JournalError::MissingObject(event.to_string()));
+    let (event, inverse) = match &event {
+        ArchivalEvent::AddObject(evt) => (
+            event.clone(),
+            vec![ArchivalEvent::DeleteObject(DeleteObjectEvent {
+                object: evt.object.clone(),
+                filename: evt.filename.clone(),
+                source: None,
+            })],
+        ),
+        ArchivalEvent::AddRootObject(evt) => (
+            event.clone(),
+            vec![ArchivalEvent::DeleteObject(DeleteObjectEvent {
+                object: evt.object.clone(),
+                filename: evt.object.clone(),
+                source: None,
+            })],
+        ),
+        ArchivalEvent::DeleteObject(evt) => {
+            let object = current()?;
+            let mut inverse = vec![if is_root {
+                ArchivalEvent::AddRootObject(AddRootObjectEvent {
+                    object: evt.object.clone(),
+                    values: field_values(&object.values),
+                })
+            } else {
+                ArchivalEvent::AddObject(AddObjectEvent {
+                    object: evt.object.clone(),
+                    filename: evt.filename.clone(),
+                    order: object.order,
+                    values: field_values(&object.values),
+                })
+            }];
+            restore_children(
+                &evt.object,
+                &evt.filename,
+                &ValuePath::empty(),
+                &object.values,
+                &mut inverse,
+            );
+            (event.clone(), inverse)
+        }
+        ArchivalEvent::RenameObject(evt) => (
+            event.clone(),
+            vec![ArchivalEvent::RenameObject(RenameObjectEvent {
+                object: evt.object.clone(),
+                from: evt.to.clone(),
+                to: evt.from.clone(),
+            })],
+        ),
+        ArchivalEvent::EditField(evt) => {
+            // `field` may itself be a path, like `tags.1`.
+            let field_path = evt.path.clone().concat(ValuePath::from_string(&evt.field));
+            let previous = value_at(current()?, &field_path)
+                .filter(|v| !matches!(v, FieldValue::Null))
+                .cloned();
+            (
+                event.clone(),
+                vec![ArchivalEvent::EditField(EditFieldEvent {
+                    value: previous,
+                    ..evt.clone()
+                })],
+            )
+        }
+        ArchivalEvent::EditOrder(evt) => (
+            event.clone(),
+            vec![ArchivalEvent::EditOrder(EditOrderEvent {
+                order: current()?.order,
+                ..evt.clone()
+            })],
+        ),
+        ArchivalEvent::AddChild(evt) => {
+            let index = evt
+                .index
+                .unwrap_or_else(|| current().map_or(0, |o| child_count(o, &evt.path)));
+            (
+                ArchivalEvent::AddChild(AddChildEvent {
+                    index: Some(index),
+                    ..evt.clone()
+                }),
+                vec![ArchivalEvent::RemoveChild(RemoveChildEvent {
+                    object: evt.object.clone(),
+                    filename: evt.filename.clone(),
+                    path: evt.path.clone().append(ValuePath::index(index)),
+                    source: None,
+                })],
+            )
+        }
+        ArchivalEvent::RemoveChild(evt) => {
+            let (children_path, idx, child) = child_at(current()?, &evt.path)?;
+            let mut inverse = vec![ArchivalEvent::AddChild(AddChildEvent {
+                object: evt.object.clone(),
+                filename: evt.filename.clone(),
+                path: children_path,
+                values: field_values(child),
+                index: Some(idx),
+            })];
+            restore_children(&evt.object, &evt.filename, &evt.path, child, &mut inverse);
+            (event.clone(), inverse)
+        }
+        ArchivalEvent::AddListValue(evt) => {
+            let len = current().map_or(0, |o| list_len(o, &evt.path));
+            let index = evt.index.filter(|idx| *idx <= len).unwrap_or(len);
+            (
+                ArchivalEvent::AddListValue(AddListValueEvent {
+                    index: Some(index),
+                    ..evt.clone()
+                }),
+                vec![ArchivalEvent::RemoveListValue(RemoveListValueEvent {
+                    object: evt.object.clone(),
+                    filename: evt.filename.clone(),
+                    path: evt.path.clone().append(ValuePath::index(index)),
+                    source: None,
+                })],
+            )
+        }
+        ArchivalEvent::RemoveListValue(evt) => {
+            let mut list_path = evt.path.clone();
+            let (Some(ValuePathComponent::Index(index)), Some(value)) =
+                (list_path.pop(), value_at(current()?, &evt.path))
+            else {
+                return Err(
+                    ValuePathError::NotFound(evt.path.clone(), evt.filename.clone()).into(),
+                );
+            };
+            (
+                event.clone(),
+                vec![ArchivalEvent::AddListValue(AddListValueEvent {
+                    object: evt.object.clone(),
+                    filename: evt.filename.clone(),
+                    path: list_path,
+                    value: value.clone(),
+                    index: Some(index),
+                })],
+            )
+        }
+        ArchivalEvent::MoveListValue(evt) => (
+            event.clone(),
+            vec![ArchivalEvent::MoveListValue(MoveListValueEvent {
+                from: evt.to,
+                to: evt.from,
+                ..evt.clone()
+            })],
+        ),
+    };
+    Ok(JournalEntry { event, inverse })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(source: Option<&str>, value: &str) -> JournalEntry {
+        let event = |value: &str| {
+            ArchivalEvent::EditField(EditFieldEvent {
+                object: "post".to_string(),
+                filename: "a".to_string(),
+                path: ValuePath::empty(),
+                field: "title".to_string(),
+                value: Some(FieldValue::String(value.to_string())),
+                source: source.map(|s| s.to_string()),
+            })
+        };
+        JournalEntry {
+            event: event(value),
+            inverse: vec![event("")],
+        }
+    }
+
+    fn group_sizes(journal: &Journal) -> Vec<usize> {
+        journal
+            .undo_groups()
+            .iter()
+            .map(|g| g.entries.len())
+            .collect()
+    }
+
+    #[test]
+    fn groups_by_source_and_explicit_id() {
+        let mut journal = Journal::default();
+        journal.record(edit(Some("typing"), "a"));
+        journal.record(edit(Some("typing"), "ab"));
+        journal.record(edit(None, "x"));
+        journal.record(edit(None, "y"));
+        journal.start_group("paste");
+        journal.record(edit(Some("typing"), "1"));
+        journal.record(edit(None, "2"));
+        journal.end_group();
+        journal.record(edit(Some("typing"), "z"));
+        assert_eq!(group_sizes(&journal), vec![2, 1, 1, 2, 1]);
+        assert_eq!(
+            journal.undo_groups()[0].description(),
+            "Change field title in post 'a' and 1 more"
+        );
+    }
+
+    #[test]
+    fn undoing_closes_the_group_and_new_events_clear_redo() {
+        let mut journal = Journal::default();
+        journal.record(edit(Some("typing"), "a"));
+        let group = journal.pop_undo().unwrap();
+        journal.push_redo(group);
+        journal.record(edit(Some("typing"), "b"));
+        assert_eq!(group_sizes(&journal), vec![1]);
+        assert!(journal.redo_groups().is_empty());
+    }
+
+    #[test]
+    fn reloaded_journals_start_a_new_group() {
+        let mut journal = Journal::default();
+        journal.record(edit(Some("typing"), "a"));
+        let mut reloaded: Journal =
+            serde_json::from_str(&serde_json::to_string(&journal).unwrap()).unwrap();
+        assert_eq!(reloaded.undo_groups(), journal.undo_groups());
+        reloaded.record(edit(Some("typing"), "b"));
+        assert_eq!(group_sizes(&reloaded), vec![1, 1]);
+    }
+}
@@ -77,6 +77,35 @@ impl std::fmt::Debug for ObjectSchemaOptions {
     }
 }
 
+/// The keys of `properties` that a complete object must have. Read-only
+/// properties (computed fields) are derived rather than stored, so they are
+/// never required.
+pub(crate) fn required_keys(properties: &ObjectSchema) -> Vec<String> {
+    properties
+        .iter()
+        .filter(|(_, property)| property.get("readOnly") != Some(&serde_json::Value::Bool(true)))
+        .map(|(k, _)| k.to_string())
+        .collect()
+}
+
+/// The keys of `properties` an object is required to have: all of them when
+/// `all_fields_required` is set, otherwise the fields declared `required`.
+pub(crate) fn required_for(
+    definition: &ObjectDefinition,
+    properties: &ObjectSchema,
+    options: &ObjectSchemaOptions,
+) -> Vec<String> {
+    if options.all_fields_required {
+        return required_keys(properties);
+    }
+    definition
+        .fields
+        .iter()
+        .filter(|(name, field)| field.constraints.required && properties.contains_key(*name))
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
 pub fn generate_root_json_schema(
     id: &str,
     title: Option<&str>,
@@ -105,11 +134,7 @@ pub fn generate_root_json_schema(
             continue;
         }
         let obj_properties = def.to_json_schema_properties(false, &mut options, object_path);
-        let required: Vec<String> = if options.all_fields_required {
-            obj_properties.keys().map(|k| k.to_string()).collect()
-        } else {
-            vec![]
-        };
+        let required = required_for(def, &obj_properties, &options);
         if root_objects.contains(name) {
             properties.insert(
                 name.into(),
@@ -139,7 +164,7 @@ pub fn generate_root_json_schema(
         }
     }
     if options.all_fields_required {
-        let keys: Vec<String> = properties.keys().map(|k| k.to_string()).collect();
+        let keys = required_keys(&properties);
         schema.insert("required".into(), keys.into());
     }
     schema.insert("properties".into(), properties.into());
@@ -160,9 +185,9 @@ pub fn generate_json_schema(
     schema.insert("type".into(), "object".into());
     let object_path = ValuePath::empty();
     let properties = definition.to_json_schema_properties(false, &mut options, object_path);
-    if options.all_fields_required {
-        let keys: Vec<String> = properties.keys().map(|k| k.to_string()).collect();
-        schema.insert("required".into(), keys.into());
+    let required = required_for(definition, &properties, &options);
+    if options.all_fields_required || !required.is_empty() {
+        schema.insert("required".into(), required.into());
     }
     schema.insert("properties".into(), properties.into());
     schema.insert("additionalProperties".into(), false.into());
@@ -342,4 +367,113 @@ pub mod tests {
         assert_eq!(properties["videos"]["description"], json!("videos"));
         Ok(())
     }
+
+    #[test]
+    fn computed_fields_are_read_only() -> Result<()> {
+        let defs = ObjectDefinition::from_source(
+            r#"
+            [post]
+            title = "string"
+            slug = { computed = "title | slugify" }
+            "#,
+            &OrderMap::new(),
+        )?;
+        let schema = generate_json_schema(
+            "post",
+            defs.get("post").unwrap(),
+            ObjectSchemaOptions::default().with_all_fields_required(),
+        );
+        assert_eq!(schema["properties"]["slug"]["readOnly"], json!(true));
+        assert_eq!(schema["properties"]["slug"]["type"], json!("string"));
+        assert!(schema["properties"]["title"].get("readOnly").is_none());
+        assert_eq!(schema["required"], json!(["order", "title"]));
+        Ok(())
+    }
+
+    #[test]
+    fn field_defaults_are_emitted() -> Result<()> {
+        let defs = ObjectDefinition::from_source(
+            r#"
+            [post]
+            status = { values = ["draft", "published"], default = "draft" }
+            views = { type = "number", default = 0 }
+            featured = { type = "boolean", default = false }
+            title = "string"
+            "#,
+            &OrderMap::new(),
+        )?;
+        let schema = generate_json_schema(
+            "post",
+            defs.get("post").unwrap(),
+            ObjectSchemaOptions::default(),
+        );
+        assert_eq!(schema["properties"]["status"]["default"], json!("draft"));
+        assert_eq!(schema["properties"]["views"]["default"], json!(0.0));
+        assert_eq!(schema["properties"]["featured"]["default"], json!(false));
+        assert!(schema["properties"]["title"].get("default").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn field_constraints_are_emitted() -> Result<()> {
+        let defs = ObjectDefinition::from_source(
+            r#"
+            [post]
+            title = { required = true, min_length = 1, max_length = 80 }
+            rating = { type = "number", min = 0, max = 5 }
+            published = { type = "date", min = 2020-01-01 }
+            notes = "string"
+            [post.links]
+            url = { required = true }
+            label = "string"
+            "#,
+            &OrderMap::new(),
+        )?;
+        let schema = generate_json_schema(
+            "post",
+            defs.get("post").unwrap(),
+            ObjectSchemaOptions::default(),
+        );
+        let properties = &schema["properties"];
+        assert_eq!(properties["title"]["minLength"], json!(1));
+        assert_eq!(properties["title"]["maxLength"], json!(80));
+        assert_eq!(properties["rating"]["minimum"], json!(0.0));
+        assert_eq!(properties["rating"]["maximum"], json!(5.0));
+        assert_eq!(
+            properties["published"]["formatMinimum"],
+            json!("2020-01-01")
+        );
+        assert!(properties["notes"].get("maxLength").is_none());
+        assert_eq!(schema["required"], json!(["title"]));
+        assert_eq!(properties["links"]["items"]["required"], json!(["url"]));
+        Ok(())
+    }
+
+    #[test]
+    fn list_fields_are_arrays() -> Result<()> {
+        let defs = ObjectDefinition::from_source(
+            r#"
+            [post]
+            tags = { type = ["string"], min_length = 1, max_length = 3 }
+            moods = [["happy", "sad"]]
+            "#,
+            &OrderMap::new(),
+        )?;
+        let schema = generate_json_schema(
+            "post",
+            defs.get("post").unwrap(),
+            ObjectSchemaOptions::default(),
+        );
+        let properties = &schema["properties"];
+        assert_eq!(properties["tags"]["type"], json!("array"));
+        assert_eq!(properties["tags"]["items"]["type"], json!("string"));
+        assert_eq!(properties["tags"]["minItems"], json!(1));
+        assert_eq!(properties["tags"]["maxItems"], json!(3));
+        assert!(properties["tags"].get("maxLength").is_none());
+        assert_eq!(
+            properties["moods"]["items"]["enum"],
+            json!(["happy", "sad"])
+        );
+        Ok(())
+    }
 }
@@ -1,10 +1,12 @@
 mod archival_error;
 #[cfg(test)]
 mod build_id_tests;
+mod data_files;
 mod definition_comments;
 mod file_system;
 mod file_system_memory;
 mod file_system_mutex;
+mod file_system_staged;
 #[cfg(test)]
 mod file_system_tests;
 mod filters;
@@ -29,10 +31,15 @@ mod util;
 mod value_path;
 use anyhow::Result;
 use events::{
-    AddChildEvent, AddObjectEvent, ArchivalEvent, DeleteObjectEvent, EditFieldEvent,
-    EditOrderEvent, RemoveChildEvent, RenameObjectEvent,
+    AddChildEvent, AddListValueEvent, AddObjectEvent, ArchivalEvent, DeleteObjectEvent,
+    EditFieldEvent, EditOrderEvent, MoveListValueEvent, RemoveChildEvent, RemoveListValueEvent,
+    RenameObjectEvent,
 };
-use events::{AddRootObjectEvent, ArchivalEventResponse};
+use events::{
+    AddObjectValue, AddRootObjectEvent, ArchivalEventResponse, CheckedEvent, ConflictError,
+    ExpectedVersion,
+};
+use fields::FieldValueValidationError;
 use manifest::Manifest;
 use mime_guess::MimeGuess;
 use seahash::SeaHasher;
@@ -40,10 +47,12 @@ use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use site::Site;
 use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::hash::Hasher;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex, RwLock};
 use tracing::{debug, error};
 #[cfg(feature = "binary")]
 pub mod binary;
@@ -55,16 +64,28 @@ mod json_schema;
 #[cfg(feature = "binary")]
 mod server;
 use file_system_mutex::FileSystemMutex;
-use object::{Object, ObjectEntry};
+use file_system_staged::StagedFileSystem;
+use object::{Object, ObjectEntry, ValuePathComponent, ValuePathError};
 use semver::{Version, VersionReq};
 
 // Re-exports
+pub mod changes;
 pub mod events;
 pub mod fields;
+#[cfg(feature = "stdlib-fs")]
+pub mod history;
+pub mod journal;
 pub mod manifest;
+pub mod merge;
+pub mod migrate;
 pub mod object;
+#[cfg(feature = "plugins")]
+pub mod plugins;
 #[cfg(feature = "proto")]
 pub mod proto;
+pub mod query;
+pub mod schema_diff;
+pub mod search;
 pub use archival_error::ArchivalError;
 pub use constants::{
     LEGACY_MANIFEST_FILE_NAME, LEGACY_OBJECT_DEFINITION_FILE_NAME, MANIFEST_FILE_NAME,
@@ -114,7 +135,7 @@ impl BuildOptions {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DistFile {
     pub path: PathBuf,
     pub mime: String,
@@ -133,6 +154,18 @@ impl DistFile {
     }
 }
 
+/// An object touched by a batch of events, as it was before them and would be
+/// after them. See [`Archival::preview_events`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectPreview {
+    pub object: String,
+    pub filename: String,
+    /// `None` if the object doesn't exist yet.
+    pub before: Option<Object>,
+    /// `None` if the events delete the object.
+    pub after: Option<Object>,
+}
+
 pub static ARCHIVAL_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 pub(crate) fn check_compatibility(version_string: &str) -> (bool, String) {
@@ -158,8 +191,14 @@ pub fn sha_for_data(data: &[u8]) -> String {
 #[derive(Debug)]
 pub struct Archival<F: FileSystemAPI + Clone + Debug> {
     fs_mutex: FileSystemMutex<F>,
-    pub site: site::Site,
+    /// Swapped out whole when the manifest or object definitions change, see
+    /// [`Self::site`].
+    site: RwLock<Arc<site::Site>>,
     last_build_id: AtomicU64,
+    journal: Mutex<Option<journal::Journal>>,
+    /// Set while a batch of events is staged, see [`Self::send_events`].
+    batching: AtomicBool,
+    subscribers: changes::Subscribers,
 }
 
 impl<F: FileSystemAPI + Clone + Debug> Archival<F> {
@@ -180,8 +219,11 @@ impl<F: FileSystemAPI + Clone + Debug> Archival<F> {
         let fs_mutex = FileSystemMutex::init(fs);
         Ok(Self {
             fs_mutex,
-            site,
+            site: RwLock::new(Arc::new(site)),
             last_build_id: AtomicU64::new(0),
+            journal: Mutex::new(None),
+            batching: AtomicBool::new(false),
+            subscribers: changes::Subscribers::default(),
         })
     }
     pub fn new_with_upload_prefix(fs: F, upload_prefix: &str) -> Result<Self> {
@@ -189,55 +231,97 @@ impl<F: FileSystemAPI + Clone + Debug> Archival<F> {
         let fs_mutex = FileSystemMutex::init(fs);
         Ok(Self {
             fs_mutex,
-            site,
+            site: RwLock::new(Arc::new(site)),
             last_build_id: AtomicU64::new(0),
+            journal: Mutex::new(None),
+            batching: AtomicBool::new(false),
+            subscribers: changes::Subscribers::default(),
         })
     }
     pub fn build(&self, options: BuildOptions) -> Result<ArchivalBuildId> {
-        let (build_id, built) = self.fs_mutex.with_fs(|fs| {
+        let site = self.site();
+        let (build_id, built, change) = self.fs_mutex.with_fs(|fs| {
             if !options.skip_static {
-                self.site.sync_static_files(fs)?;
+                site.sync_static_files(fs)?;
             }
-            let build_id = self.site.build_id();
+            let build_id = site.build_id();
             let should_build =
                 build_id == 0 || self.last_build_id.load(AtomicOrdering::Relaxed) != build_id;
+            let mut change = None;
             if should_build {
-                debug!("build {} {:#?}", self.site, options);
-                self.site.build(fs, options)?;
+                debug!("build {} {:#?}", site, options);
+                let previous = (!self.subscribers.is_empty()).then(|| site.build_hashes());
+                site.build(fs, options)?;
+                if let Some(previous) = previous {
+                    change = Some(self.built_files(&site, previous, fs)?);
+                }
             } else {
                 #[cfg(feature = "verbose-logging")]
                 debug!("skipping duplicate build");
             }
             // Recompute build_id after site.build() populates the cache
-            let final_build_id = self.site.build_id();
-            Ok((final_build_id, should_build))
+            let final_build_id = site.build_id();
+            Ok((final_build_id, should_build, change))
         })?;
         // Only update last_build_id if we actually built
         if built {
-            self.last_build_id
-                .fetch_update(AtomicOrdering::Relaxed, AtomicOrdering::Relaxed, |_| {
-                    Some(build_id)
-                })
-                .unwrap();
+            self.last_build_id.store(build_id, AtomicOrdering::Relaxed);
+        }
+        if let Some((changed, removed)) = change {
+            self.subscribers
+                .notify(vec![changes::Change::BuildCompleted {
+                    build_id,
+                    changed,
+                    removed,
+                }]);
         }
         Ok(self.last_build_id.load(AtomicOrdering::Relaxed))
     }
+    /// The files a build wrote and removed, compared with the hashes of the
+    /// build before it.
+    fn built_files(
+        &self,
+        site: &Site,
+        previous: HashMap<PathBuf, u64>,
+        fs: &F,
+    ) -> Result<(Vec<DistFile>, Vec<PathBuf>)> {
+        let build_dir = &site.manifest.build_dir;
+        let relative = |path: &Path| path.strip_prefix(build_dir).unwrap_or(path).to_path_buf();
+        let current = site.build_hashes();
+        let mut changed = vec![];
+        for (path, hash) in &current {
+            if previous.get(path) != Some(hash) {
+                if let Some(data) = fs.read(path)? {
+                    changed.push(DistFile::new(relative(path), data));
+                }
+            }
+        }
+        changed.sort_by(|a, b| a.path.cmp(&b.path));
+        let mut removed: Vec<PathBuf> = previous
+            .keys()
+            .filter(|path| !current.contains_key(*path))
+            .map(|path| relative(path))
+            .collect();
+        removed.sort();
+        Ok((changed, removed))
+    }
     #[cfg(feature = "json-schema")]
     pub fn dump_schemas(&self) -> Result<()> {
-        debug!("dump schemas {}", self.site);
-        self.fs_mutex.with_fs(|fs| self.site.dump_schemas(fs))
+        debug!("dump schemas {}", self.site());
+        self.fs_mutex.with_fs(|fs| self.site().dump_schemas(fs))
     }
     #[cfg(feature = "json-schema")]
     pub fn generate_root_json_schema(&self, options: ObjectSchemaOptions) -> ObjectSchema {
+        let site = self.site();
         json_schema::generate_root_json_schema(
-            &format!("{}/root.schema.json", self.site.schema_prefix()),
-            self.site.manifest.site_name.as_deref(),
+            &format!("{}/root.schema.json", site.schema_prefix()),
+            site.manifest.site_name.as_deref(),
             &format!(
                 "Object definitions{}",
                 if let Some(name) = options
                     .name
                     .as_ref()
-                    .and(self.site.manifest.site_name.as_ref())
+                    .and(site.manifest.site_name.as_ref())
                     .to_owned()
                 {
                     format!(" for {}", name)
@@ -245,23 +329,23 @@ impl<F: FileSystemAPI + Clone + Debug> Archival<F> {
                     "".to_string()
                 }
             ),
-            &self.site.object_definitions,
+            &site.object_definitions,
             &self
                 .fs_mutex
-                .with_fs(|fs| Ok(self.site.root_objects(fs)))
+                .with_fs(|fs| Ok(site.root_objects(fs)))
                 .unwrap(),
             options,
         )
     }
     pub fn dist_file(&self, path: &Path) -> Option<Vec<u8>> {
-        let path = self.site.manifest.build_dir.join(path);
+        let path = self.site().manifest.build_dir.join(path);
         self.fs_mutex.with_fs(|fs| fs.read(&path)).unwrap_or(None)
     }
     pub fn dist_files(&self) -> Vec<DistFile> {
         let mut files = vec![];
         self.fs_mutex
             .with_fs(|fs| {
-                let build_dir = &self.site.manifest.build_dir;
+                let build_dir = &self.site().manifest.build_dir;
                 for file in fs.walk_dir(build_dir, true)? {
                     if let Some(data) = fs.read(build_dir.join(&file)).unwrap_or(None) {
                         files.push(DistFile::new(file, data));
@@ -282,11 +366,81 @@ impl<F: FileSystemAPI + Clone + Debug> Archival<F> {
             .unwrap()
     }
     pub fn build_id(&self) -> u64 {
-        self.site.build_id()
+        self.site().build_id()
     }
     /// See [Site::objects_generation].
     pub fn objects_generation(&self) -> u64 {
-        self.site.objects_generation()
+        self.site().objects_generation()
+    }
+    /// Returns a channel that receives each change to the site from now on,
+    /// see [`changes`]. Dropping the receiver unsubscribes.
+    pub fn subscribe(&self) -> std::sync::mpsc::Receiver<changes::Change> {
+        self.subscribers.subscribe()
+    }
+    /// The site as currently loaded. A changed manifest or object definition
+    /// file swaps in a freshly loaded one (see [`Self::files_changed`]), so
+    /// hold on to this only as long as you need a consistent view of it.
+    pub fn site(&self) -> Arc<Site> {
+        self.site.read().unwrap().clone()
+    }
+    /// Tells archival about files changed outside of it, e.g. those reported
+    /// by a `WatchableFileSystemAPI` watcher, with paths absolute or relative
+    /// to the site root. Their caches are invalidated and subscribers told
+    /// what changed; a changed manifest or object definition file reloads the
+    /// site.
+    pub fn files_changed(&self, paths: impl IntoIterator<Item = PathBuf>) -> Result<()> {
+        let site = self.site();
+        let mut changes = vec![];
+        let mut reload = false;
+        self.fs_mutex.with_fs(|fs| {
+            let manifest_path = Manifest::path_in(Path::new(""), fs)?;
+            for path in paths {
+                let path = match path.strip_prefix(fs.root_dir()) {
+                    Ok(relative) => relative.to_path_buf(),
+                    Err(_) => path,
+                };
+                if path == manifest_path || path == site.manifest.object_definition_file {
+                    reload = true;
+                    continue;
+                }
+                let old = site.loaded_object(&path);
+                site.invalidate_file(&path);
+                let (Some(object), Some(filename)) = (
+                    site.object_name_for_path(&path),
+                    path.file_stem().and_then(|stem| stem.to_str()),
+                ) else {
+                    continue;
+                };
+                let exists = fs.exists(&path)?;
+                let new = if exists {
+                    site.get_object(object, Some(filename), fs).ok()
+                } else {
+                    None
+                };
+                changes.extend(changes::object_changes(
+                    object,
+                    filename,
+                    old.as_ref(),
+                    new.as_ref(),
+                    exists,
+                ));
+            }
+            Ok(())
+        })?;
+        if reload {
+            self.reload_site(&site.manifest.upload_prefix)?;
+            changes.push(changes::Change::DefinitionsReloaded);
+        }
+        self.subscribers.notify(changes);
+        Ok(())
+    }
+    /// Loads the site from disk again, keeping the current one if that fails.
+    fn reload_site(&self, upload_prefix: &str) -> Result<()> {
+        let site = self
+            .fs_mutex
+            .with_fs(|fs| Site::load(fs, Some(upload_prefix)))?;
+        *self.site.write().unwrap() = Arc::new(site);
+        Ok(())
     }
     pub fn fs_id(&self) -> Result<u64> {
         self.fs_mutex.with_fs(|fs| self.fs_id_for_fs(fs))
@@ -304,20 +458,34 @@ impl<F: FileSystemAPI + Clone + Debug> Archival<F> {
             layout_dir,
             objects_dir,
             static_dir,
+            data_dir,
             ..
-        } = &self.site.manifest;
+        } = self.site().manifest.clone();
         let root_files = [
             Manifest::path_in(Path::new(""), fs)?,
             object_definition_file.to_owned(),
         ];
         Ok(root_files
             .into_iter()
-            .chain(fs.walk_dir(static_dir, false)?.map(|p| static_dir.join(p)))
-            .chain(fs.walk_dir(pages_dir, false)?.map(|p| pages_dir.join(p)))
-            .chain(fs.walk_dir(layout_dir, false)?.map(|p| layout_dir.join(p)))
             .chain(
-                fs.walk_dir(objects_dir, false)?
-                    .map(|p| objects_dir.join(p)),
+                fs.walk_dir(&static_dir, false)?
+                    .map(move |p| static_dir.join(p)),
+            )
+            .chain(
+                fs.walk_dir(&pages_dir, false)?
+                    .map(move |p| pages_dir.join(p)),
+            )
+            .chain(
+                fs.walk_dir(&layout_dir, false)?
+                    .map(move |p| layout_dir.join(p)),
+            )
+            .chain(
+                fs.walk_dir(&objects_dir, false)?
+                    .map(move |p| objects_dir.join(p)),
+            )
+            .chain(
+                fs.walk_dir(&data_dir, false)?
+                    .map(move |p| data_dir.join(p)),
             ))
     }
     fn fs_id_for_fs(&self, fs: &F) -> Result<u64> {
@@ -331,20 +499,24 @@ impl<F: FileSystemAPI + Clone + Debug> Archival<F> {
         }
         Ok(hasher.finish())
     }
-    fn object_path_impl(&self, obj_type: &str, filename: &str, fs: &F) -> Result<PathBuf> {
-        let objects = self.site.get_objects(fs)?;
+    fn object_path_impl(
+        &self,
+        obj_type: &str,
+        filename: &str,
+        fs: &impl FileSystemAPI,
+    ) -> Result<PathBuf> {
+        let site = self.site();
+        let objects = site.get_objects(fs)?;
         let entry = objects.get(obj_type).ok_or(ArchivalError::new(&format!(
             "object type not found: {}",
             obj_type
         )))?;
         Ok(if matches!(entry, ObjectEntry::Object(_)) {
-            self.site
-                .manifest
+            site.manifest
                 .objects_dir
                 .join(Path::new(&format!("{}.toml", obj_type)))
         } else {
-            self.site
-                .manifest
+            site.manifest
                 .objects_dir
                 .join(Path::new(&obj_type))
                 .join(Path::new(&format!("{}.toml", filename)))
@@ -364,29 +536,47 @@ impl<F: FileSystemAPI + Clone + Debug> Archival<F> {
 
     pub fn write_file(&self, obj_type: &str, filename: &str, contents: String) -> Result<()> {
         // Validate toml
-        let obj_def = self.get_object_definition(obj_type)?;
+        let obj_def = &self.get_object_definition(obj_type)?;
         let table: toml::Table = toml::from_str(&contents)?;
         // Note that this also fails when custom validation fails.
-        let _ = Object::from_table(
+        let object = Object::from_table(
             obj_def,
             Path::new(filename),
             &table,
-            &self.site.manifest.editor_types,
+            &self.site().manifest.editor_types,
             false,
         )?;
+        obj_def.check_constraints(&object.values, &ValuePath::empty())?;
         // Object is valid, write it
-        self.fs_mutex
-            .with_fs(|fs| fs.write_str(&self.object_path_impl(obj_type, filename, fs)?, contents))
+        let site = self.site();
+        let changes = self.fs_mutex.with_fs(|fs| {
+            let path = self.object_path_impl(obj_type, filename, fs)?;
+            let existed = fs.exists(&path)?;
+            let old = site.get_object(obj_type, Some(filename), fs).ok();
+            fs.write_str(&path, contents)?;
+            site.invalidate_file(&path);
+            Ok(if existed {
+                changes::object_changes(obj_type, filename, old.as_ref(), Some(&object), true)
+            } else {
+                vec![changes::Change::ObjectAdded {
+                    object: obj_type.to_string(),
+                    filename: filename.to_string(),
+                }]
+            })
+        })?;
+        self.subscribers.notify(changes);
+        Ok(())
     }
     fn modify_object_file(
         &self,
         obj_type: &str,
         filename: &str,
         obj_cb: impl FnOnce(&mut Object) -> Result<&mut Object>,
-        fs: &F,
+        fs: &impl FileSystemAPI,
     ) -> Result<String> {
-        let mut all_objects = self.site.get_objects(fs)?;
-        let definitions = &self.site.object_definitions;
+        let site = self.site();
+        let mut all_objects = site.get_objects(fs)?;
+        let definitions = &site.object_definitions;
         if let Some(objects) = all_objects.get_mut(obj_type) {
             if let Some(object) = objects.iter_mut().find(|o| o.filename == filename) {
                 let object = obj_cb(object)?;
@@ -418,157 +608,553 @@ impl<F: FileSystemAPI + Clone + Debug> Archival<F> {
         }
     }
 
+    /// Applies `event`, whatever its object holds now. To reject it if the
+    /// object has changed since its sender read it, send it as a
+    /// [`CheckedEvent`] with [`Self::send_checked_event`].
     pub fn send_event(
         &self,
         event: ArchivalEvent,
         build_options: Option<BuildOptions>,
     ) -> Result<ArchivalEventResponse> {
-        let r = match event {
-            ArchivalEvent::AddObject(event) => self.add_object(event)?,
-            ArchivalEvent::RenameObject(event) => self.rename_object(event)?,
-            ArchivalEvent::AddRootObject(event) => self.add_root_object(event)?,
-            ArchivalEvent::DeleteObject(event) => self.delete_object(event)?,
-            ArchivalEvent::EditField(event) => self.edit_field(event)?,
-            ArchivalEvent::EditOrder(event) => self.edit_order(event)?,
-            ArchivalEvent::AddChild(event) => self.add_child(event)?,
-            ArchivalEvent::RemoveChild(event) => self.remove_child(event)?,
-        };
+        self.validate_event(&event)?;
+        let change = changes::Change::from(&event);
+        let mut journal = self.journal.lock().unwrap();
+        let r = self.fs_mutex.with_fs(|fs| match journal.as_mut() {
+            Some(journal) => {
+                let entry = self.journal_entry(event, fs)?;
+                let r = self.apply_event(entry.event.clone(), fs)?;
+                journal.record(entry);
+                Ok(r)
+            }
+            None => self.apply_event(event, fs),
+        })?;
+        drop(journal);
+        self.subscribers.notify(vec![change]);
         if let Some(build_options) = build_options {
             self.build(build_options)?;
         }
         Ok(r)
     }
 
-    // Internal
-    fn add_root_object(&self, event: AddRootObjectEvent) -> Result<ArchivalEventResponse> {
-        let obj_def = self.get_object_definition(&event.object)?;
+    /// Sends several events as one: either all of them are applied, or none
+    /// are and the first error is returned. Events are validated against the
+    /// definitions before any are applied, then applied in order to a staged
+    /// copy of the files they touch, which is only written once every event
+    /// has succeeded. Caches are invalidated and the site built (if
+    /// `build_options` are given) once, after the batch is written. Journaled
+    /// batches are undone together.
+    pub fn send_events(
+        &self,
+        events: Vec<ArchivalEvent>,
+        build_options: Option<BuildOptions>,
+    ) -> Result<Vec<ArchivalEventResponse>> {
+        self.send_batch(
+            events.into_iter().map(|event| (event, None)).collect(),
+            build_options,
+        )
+    }
+
+    /// Sends several events as one, like [`Self::send_events`], failing with
+    /// an [`events::ConflictError`] if any event's object doesn't hold what
+    /// the event expects when it is applied.
+    pub fn send_checked_events(
+        &self,
+        events: Vec<CheckedEvent>,
+        build_options: Option<BuildOptions>,
+    ) -> Result<Vec<ArchivalEventResponse>> {
+        self.send_batch(
+            events
+                .into_iter()
+                .map(|checked| (checked.event, Some(checked.expected)))
+                .collect(),
+            build_options,
+        )
+    }
+
+    /// Sends `event` like [`Self::send_event`], failing with an
+    /// [`events::ConflictError`] if its object doesn't hold what it expects.
+    pub fn send_checked_event(
+        &self,
+        event: CheckedEvent,
+        build_options: Option<BuildOptions>,
+    ) -> Result<ArchivalEventResponse> {
+        let mut responses = self.send_checked_events(vec![event], build_options)?;
+        Ok(responses.remove(0))
+    }
+
+    fn send_batch(
+        &self,
+        events: Vec<(ArchivalEvent, Option<ExpectedVersion>)>,
+        build_options: Option<BuildOptions>,
+    ) -> Result<Vec<ArchivalEventResponse>> {
+        for (event, _) in &events {
+            self.validate_event(event)?;
+        }
+        let mut journal = self.journal.lock().unwrap();
+        let (responses, entries) = self.apply_checked_batch(events, journal.is_some())?;
+        if let Some(journal) = journal.as_mut() {
+            journal.record_batch(entries);
+        }
+        drop(journal);
+        if let Some(build_options) = build_options {
+            self.build(build_options)?;
+        }
+        Ok(responses)
+    }
+
+    /// Validates and applies `events` like [`Self::send_events`], but only to a
+    /// staged copy of the site, which is thrown away. Returns each object the
+    /// events touch, in the order they first touch it, as it is now and as it
+    /// would be after the events, or the first error sending them would
+    /// return.
+    pub fn preview_events(&self, events: Vec<ArchivalEvent>) -> Result<Vec<ObjectPreview>> {
+        for event in &events {
+            self.validate_event(event)?;
+        }
+        let mut touched: Vec<(String, String)> = vec![];
+        for event in &events {
+            let key = (
+                event.object_name().to_string(),
+                event.filename().to_string(),
+            );
+            if !touched.contains(&key) {
+                touched.push(key);
+            }
+        }
+        let site = self.site();
         self.fs_mutex.with_fs(|fs| {
-            let dir_path = self
-                .site
-                .manifest
-                .objects_dir
-                .join(Path::new(&event.object));
-            if fs.is_dir(&dir_path)? && fs.walk_dir(&dir_path, false)?.next().is_some() {
-                return Err(ArchivalError::new(&format!(
-                    "cannod add root {} object, found existing non-roots.",
-                    event.object
-                ))
-                .into());
+            let before: Vec<_> = touched
+                .iter()
+                .map(|(object, filename)| site.get_object(object, Some(filename), fs).ok())
+                .collect();
+            let mut staged = StagedFileSystem::new(fs.clone());
+            self.batching.store(true, AtomicOrdering::Relaxed);
+            let applied = events
+                .into_iter()
+                .try_for_each(|event| self.apply_event(event, &mut staged).map(|_| ()));
+            self.batching.store(false, AtomicOrdering::Relaxed);
+            let after: Vec<_> = touched
+                .iter()
+                .map(|(object, filename)| site.get_object(object, Some(filename), &staged).ok())
+                .collect();
+            // Staged objects may have been cached while applying or reading.
+            for path in staged.changed_paths() {
+                site.invalidate_file(path);
             }
-            let path = self
-                .site
-                .manifest
-                .objects_dir
-                .join(Path::new(&format!("{}.toml", event.object)));
-            if fs.exists(&path)? {
-                return Err(ArchivalError::new(&format!(
-                    "cannod add root {}, file already exists.",
-                    event.object
-                ))
-                .into());
+            applied?;
+            Ok(touched
+                .iter()
+                .zip(before.into_iter().zip(after))
+                .map(|((object, filename), (before, after))| ObjectPreview {
+                    object: object.to_string(),
+                    filename: filename.to_string(),
+                    before,
+                    after,
+                })
+                .collect())
+        })
+    }
+
+    /// Stages `events`, then writes them if they all succeeded, returning
+    /// their responses and, if `journaled`, their journal entries. Undo and
+    /// redo replay events through here without validating them, since they
+    /// return objects to states they've already been in: undoing the first
+    /// edit to a required field clears it, which validation would reject.
+    fn apply_batch(
+        &self,
+        events: Vec<ArchivalEvent>,
+        journaled: bool,
+    ) -> Result<(Vec<ArchivalEventResponse>, Vec<journal::JournalEntry>)> {
+        self.apply_checked_batch(
+            events.into_iter().map(|event| (event, None)).collect(),
+            journaled,
+        )
+    }
+
+    /// Like [`Self::apply_batch`], checking each event that expects something
+    /// of its object against the staged copy, so after the events before it.
+    fn apply_checked_batch(
+        &self,
+        events: Vec<(ArchivalEvent, Option<ExpectedVersion>)>,
+        journaled: bool,
+    ) -> Result<(Vec<ArchivalEventResponse>, Vec<journal::JournalEntry>)> {
+        let changes = events
+            .iter()
+            .map(|(event, _)| changes::Change::from(event))
+            .collect();
+        let applied = self.fs_mutex.with_fs(|fs| {
+            let mut staged = StagedFileSystem::new(fs.clone());
+            self.batching.store(true, AtomicOrdering::Relaxed);
+            let applied = events
+                .into_iter()
+                .map(|(event, expected)| {
+                    if let Some(expected) = &expected {
+                        self.check_expected(&event, expected, &staged)?;
+                    }
+                    let entry = if journaled {
+                        Some(self.journal_entry(event.clone(), &staged)?)
+                    } else {
+                        None
+                    };
+                    let response = self.apply_event(event, &mut staged)?;
+                    Ok((response, entry))
+                })
+                .collect::<Result<Vec<_>>>();
+            self.batching.store(false, AtomicOrdering::Relaxed);
+            let changed: Vec<PathBuf> = staged.changed_paths().cloned().collect();
+            let result = applied.and_then(|applied| {
+                staged.commit(fs)?;
+                Ok(applied)
+            });
+            // Objects read while staging may have been cached, so these are
+            // invalidated whether or not the batch was written.
+            for path in &changed {
+                self.site().invalidate_file(path);
             }
-            let object = Object::from_def(obj_def, &event.object, None, event.values)?;
-            fs.write_str(&path, object.to_toml(obj_def)?)?;
-            self.site.invalidate_file(&path);
-            Ok(())
+            let (responses, entries) = result?.into_iter().unzip::<_, _, Vec<_>, Vec<_>>();
+            Ok((responses, entries.into_iter().flatten().collect()))
         })?;
-        Ok(ArchivalEventResponse::None)
+        self.subscribers.notify(changes);
+        Ok(applied)
     }
 
-    fn add_object(&self, event: AddObjectEvent) -> Result<ArchivalEventResponse> {
-        let obj_def = self.get_object_definition(&event.object)?;
-        self.fs_mutex.with_fs(|fs| {
-            let obj_dir = self
-                .site
-                .manifest
-                .objects_dir
-                .join(Path::new(&event.object));
-            fs.create_dir_all(&obj_dir)?;
-            let path = obj_dir.join(Path::new(&format!("{}.toml", event.filename)));
-            if fs.exists(&path)? {
-                return Err(ArchivalError::new(&format!(
-                    "cannod add {} named {}, file already exists.",
-                    event.object, event.filename
-                ))
-                .into());
+    /// Checks an event against the object definitions, before reading or
+    /// writing anything. Checks that need the object, like list constraints,
+    /// happen as the event is applied.
+    fn validate_event(&self, event: &ArchivalEvent) -> Result<()> {
+        let def = &self.get_object_definition(event.object_name())?;
+        let editor_types = &self.site().manifest.editor_types;
+        match event {
+            ArchivalEvent::AddObject(AddObjectEvent { values, .. })
+            | ArchivalEvent::AddRootObject(AddRootObjectEvent { values, .. }) => {
+                for value in values {
+                    allow_incomplete(value.value.validate(&value.path, def, editor_types))?;
+                }
             }
-            let root_path = self
-                .site
-                .manifest
-                .objects_dir
-                .join(Path::new(&format!("{}.toml", event.object)));
-            if fs.exists(&root_path)? {
-                return Err(ArchivalError::new(&format!(
-                    "cannod add {} named {}, there's already a root {}.",
-                    event.object, event.filename, event.object
-                ))
-                .into());
+            ArchivalEvent::EditField(event) => {
+                let field_path = event
+                    .path
+                    .clone()
+                    .concat(ValuePath::from_string(&event.field));
+                // Validation catches this too, but clearing a field isn't validated.
+                if field_path.get_field(def).is_ok_and(|f| f.is_computed()) {
+                    return Err(FieldValueValidationError::ComputedField(field_path).into());
+                }
+                match &event.value {
+                    Some(value) => value.validate(&field_path, def, editor_types)?,
+                    // Clearing a list item removes it, which doesn't clear the list.
+                    None if matches!(field_path.last(), Some(ValuePathComponent::Index(_))) => {}
+                    None => {
+                        if let Ok(field) = field_path.get_field(def) {
+                            field.constraints.check(None).map_err(|violation| {
+                                FieldValueValidationError::ConstraintViolation(
+                                    field_path.clone(),
+                                    violation,
+                                )
+                            })?;
+                        }
+                    }
+                }
             }
-            let object = Object::from_def(obj_def, &event.filename, event.order, event.values)?;
-            fs.write_str(&path, object.to_toml(obj_def)?)
-                .map_err(|error| {
-                    ArchivalError::new(&format!("failed writing to {}: {}", path.display(), error))
-                })?;
-            self.site.invalidate_file(&path);
-            Ok(())
-        })?;
+            ArchivalEvent::AddChild(event) => {
+                for value in &event.values {
+                    allow_incomplete(value.value.validate(
+                        &event.path.clone().concat(value.path.clone()),
+                        def,
+                        editor_types,
+                    ))?;
+                }
+            }
+            ArchivalEvent::AddListValue(event) => {
+                event.value.validate(
+                    &event
+                        .path
+                        .clone()
+                        .append(ValuePath::index(event.index.unwrap_or(0))),
+                    def,
+                    editor_types,
+                )?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Applies an event that has passed [`Self::validate_event`] to `fs`.
+    fn apply_event(
+        &self,
+        event: ArchivalEvent,
+        fs: &mut impl FileSystemAPI,
+    ) -> Result<ArchivalEventResponse> {
+        match event {
+            ArchivalEvent::AddObject(event) => self.add_object(event, fs),
+            ArchivalEvent::RenameObject(event) => self.rename_object(event, fs),
+            ArchivalEvent::AddRootObject(event) => self.add_root_object(event, fs),
+            ArchivalEvent::DeleteObject(event) => self.delete_object(event, fs),
+            ArchivalEvent::EditField(event) => self.edit_field(event, fs),
+            ArchivalEvent::EditOrder(event) => self.edit_order(event, fs),
+            ArchivalEvent::AddChild(event) => self.add_child(event, fs),
+            ArchivalEvent::RemoveChild(event) => self.remove_child(event, fs),
+            ArchivalEvent::AddListValue(event) => self.add_list_value(event, fs),
+            ArchivalEvent::RemoveListValue(event) => self.remove_list_value(event, fs),
+            ArchivalEvent::MoveListValue(event) => self.move_list_value(event, fs),
+        }
+    }
+
+    /// Drops a file written by an event from the caches. Within a batch the
+    /// write is only staged, so only the object cache (which staging reads
+    /// through) is touched until the batch is written.
+    fn invalidate(&self, path: &Path) {
+        if self.batching.load(AtomicOrdering::Relaxed) {
+            self.site().evict_object(path);
+        } else {
+            self.site().invalidate_file(path);
+        }
+    }
+
+    /// Starts journaling the events sent from now on, so that they can be
+    /// undone. Pass [`journal::Journal::default`] to start afresh, or a journal
+    /// saved from [`Self::journal`] to pick up where it left off.
+    pub fn enable_journal(&self, journal: journal::Journal) {
+        *self.journal.lock().unwrap() = Some(journal);
+    }
+
+    /// Stops journaling, returning the journal.
+    pub fn disable_journal(&self) -> Option<journal::Journal> {
+        self.journal.lock().unwrap().take()
+    }
+
+    /// A copy of the journal, e.g. to save it.
+    pub fn journal(&self) -> Option<journal::Journal> {
+        self.journal.lock().unwrap().clone()
+    }
+
+    /// Runs `f` on the journal, if it's enabled, e.g. to group events with
+    /// [`journal::Journal::start_group`].
+    pub fn with_journal<R>(&self, f: impl FnOnce(&mut journal::Journal) -> R) -> Result<R> {
+        let mut journal = self.journal.lock().unwrap();
+        let journal = journal.as_mut().ok_or(journal::JournalError::Disabled)?;
+        Ok(f(journal))
+    }
+
+    fn journal_entry(
+        &self,
+        event: ArchivalEvent,
+        fs: &impl FileSystemAPI,
+    ) -> Result<journal::JournalEntry> {
+        let current = match &event {
+            ArchivalEvent::AddObject(_)
+            | ArchivalEvent::AddRootObject(_)
+            | ArchivalEvent::RenameObject(_) => None,
+            evt => self
+                .site()
+                .get_object(evt.object_name(), Some(evt.filename()), fs)
+                .ok(),
+        };
+        let is_root = event.object_name() == event.filename()
+            && self.site().root_objects(fs).contains(event.object_name());
+        journal::entry_for(event, current.as_ref(), is_root)
+    }
+
+    /// Reverses the most recent group of journaled events, returning false if
+    /// there was nothing to undo. The group is reversed as a batch (see
+    /// [`Self::send_events`]), so if reversing any event fails, none are and
+    /// the group is left to undo.
+    pub fn undo(&self, build_options: Option<BuildOptions>) -> Result<bool> {
+        let mut journal = self.journal.lock().unwrap();
+        let journal = journal.as_mut().ok_or(journal::JournalError::Disabled)?;
+        let Some(group) = journal.pop_undo() else {
+            return Ok(false);
+        };
+        let events = group
+            .entries
+            .iter()
+            .rev()
+            .flat_map(|entry| entry.inverse.iter().cloned())
+            .collect();
+        if let Err(error) = self.apply_batch(events, false) {
+            journal.push_undo(group);
+            return Err(error);
+        }
+        journal.push_redo(group);
+        if let Some(build_options) = build_options {
+            self.build(build_options)?;
+        }
+        Ok(true)
+    }
+
+    /// Reapplies the most recently undone group of events, returning false if
+    /// there was nothing to redo.
+    pub fn redo(&self, build_options: Option<BuildOptions>) -> Result<bool> {
+        let mut journal = self.journal.lock().unwrap();
+        let journal = journal.as_mut().ok_or(journal::JournalError::Disabled)?;
+        let Some(group) = journal.pop_redo() else {
+            return Ok(false);
+        };
+        let events = group
+            .entries
+            .iter()
+            .map(|entry| entry.event.clone())
+            .collect();
+        if let Err(error) = self.apply_batch(events, false) {
+            journal.push_redo(group);
+            return Err(error);
+        }
+        journal.push_undo(group);
+        if let Some(build_options) = build_options {
+            self.build(build_options)?;
+        }
+        Ok(true)
+    }
+
+    // Internal
+    fn add_root_object(
+        &self,
+        event: AddRootObjectEvent,
+        fs: &mut impl FileSystemAPI,
+    ) -> Result<ArchivalEventResponse> {
+        let obj_def = &self.get_object_definition(&event.object)?;
+        let dir_path = self
+            .site()
+            .manifest
+            .objects_dir
+            .join(Path::new(&event.object));
+        if fs.is_dir(&dir_path)? && fs.walk_dir(&dir_path, false)?.next().is_some() {
+            return Err(ArchivalError::new(&format!(
+                "cannod add root {} object, found existing non-roots.",
+                event.object
+            ))
+            .into());
+        }
+        let path = self
+            .site()
+            .manifest
+            .objects_dir
+            .join(Path::new(&format!("{}.toml", event.object)));
+        if fs.exists(&path)? {
+            return Err(ArchivalError::new(&format!(
+                "cannod add root {}, file already exists.",
+                event.object
+            ))
+            .into());
+        }
+        let object = self.new_object(obj_def, &event.object, None, event.values)?;
+        fs.write_str(&path, object.to_toml(obj_def)?)?;
+        self.invalidate(&path);
         Ok(ArchivalEventResponse::None)
     }
 
-    fn rename_object(&self, event: RenameObjectEvent) -> Result<ArchivalEventResponse> {
-        let obj_def = self.get_object_definition(&event.object)?;
-        self.fs_mutex.with_fs(|fs| {
-            let root_objects = self.site.root_objects(fs);
-            if root_objects.contains(&event.from) {
-                return Err(ArchivalError::new(&format!(
-                    "cannot rename root object {}",
-                    event.from
-                ))
-                .into());
-            }
-            let from_path = self.object_path_impl(&obj_def.name, &event.from, fs)?;
-            let to_path = self.object_path_impl(&obj_def.name, &event.to, fs)?;
-            let content = fs.read(&from_path)?.ok_or(ArchivalError::new(&format!(
-                "file not found: {}",
-                event.from
-            )))?;
-            fs.write(&to_path, content)?;
-            fs.delete(&from_path)?;
-            self.site.invalidate_file(&from_path);
-            Ok(())
-        })?;
+    fn add_object(
+        &self,
+        event: AddObjectEvent,
+        fs: &mut impl FileSystemAPI,
+    ) -> Result<ArchivalEventResponse> {
+        let obj_def = &self.get_object_definition(&event.object)?;
+        let obj_dir = self
+            .site()
+            .manifest
+            .objects_dir
+            .join(Path::new(&event.object));
+        fs.create_dir_all(&obj_dir)?;
+        let path = obj_dir.join(Path::new(&format!("{}.toml", event.filename)));
+        if fs.exists(&path)? {
+            return Err(ArchivalError::new(&format!(
+                "cannod add {} named {}, file already exists.",
+                event.object, event.filename
+            ))
+            .into());
+        }
+        let root_path = self
+            .site()
+            .manifest
+            .objects_dir
+            .join(Path::new(&format!("{}.toml", event.object)));
+        if fs.exists(&root_path)? {
+            return Err(ArchivalError::new(&format!(
+                "cannod add {} named {}, there's already a root {}.",
+                event.object, event.filename, event.object
+            ))
+            .into());
+        }
+        let object = self.new_object(obj_def, &event.filename, event.order, event.values)?;
+        fs.write_str(&path, object.to_toml(obj_def)?)
+            .map_err(|error| {
+                ArchivalError::new(&format!("failed writing to {}: {}", path.display(), error))
+            })?;
+        self.invalidate(&path);
         Ok(ArchivalEventResponse::None)
     }
 
-    fn delete_object(&self, event: DeleteObjectEvent) -> Result<ArchivalEventResponse> {
-        let obj_def = self.get_object_definition(&event.object)?;
-        self.fs_mutex.with_fs(|fs| {
-            let path = self.object_path_impl(&obj_def.name, &event.filename, fs)?;
-            fs.delete(&path)?;
-            self.site.invalidate_file(&path);
-            Ok(())
-        })?;
+    /// Builds an object for an add event, rejecting values that would break
+    /// the definition's constraints. Required values may still be missing.
+    fn new_object(
+        &self,
+        definition: &ObjectDefinition,
+        filename: &str,
+        order: Option<f64>,
+        values: Vec<AddObjectValue>,
+    ) -> Result<Object> {
+        let object = Object::from_def(definition, filename, order, values)?;
+        definition.check_new_constraints(&object.values, &ValuePath::empty())?;
+        Ok(object)
+    }
+
+    fn rename_object(
+        &self,
+        event: RenameObjectEvent,
+        fs: &mut impl FileSystemAPI,
+    ) -> Result<ArchivalEventResponse> {
+        let obj_def = &self.get_object_definition(&event.object)?;
+        let root_objects = self.site().root_objects(fs);
+        if root_objects.contains(&event.from) {
+            return Err(
+                ArchivalError::new(&format!("cannot rename root object {}", event.from)).into(),
+            );
+        }
+        let from_path = self.object_path_impl(&obj_def.name, &event.from, fs)?;
+        let to_path = self.object_path_impl(&obj_def.name, &event.to, fs)?;
+        let content = fs.read(&from_path)?.ok_or(ArchivalError::new(&format!(
+            "file not found: {}",
+            event.from
+        )))?;
+        fs.write(&to_path, content)?;
+        fs.delete(&from_path)?;
+        self.invalidate(&from_path);
+        self.invalidate(&to_path);
+        Ok(ArchivalEventResponse::None)
+    }
+
+    fn delete_object(
+        &self,
+        event: DeleteObjectEvent,
+        fs: &mut impl FileSystemAPI,
+    ) -> Result<ArchivalEventResponse> {
+        let obj_def = &self.get_object_definition(&event.object)?;
+        let path = self.object_path_impl(&obj_def.name, &event.filename, fs)?;
+        fs.delete(&path)?;
+        self.invalidate(&path);
         Ok(ArchivalEventResponse::None)
     }
 
     pub fn manifest_content(&self) -> Result<String> {
-        self.fs_mutex.with_fs(|fs| self.site.manifest_content(fs))
+        self.fs_mutex.with_fs(|fs| self.site().manifest_content(fs))
     }
 
-    pub fn get_object_definition(&self, name: &str) -> Result<&ObjectDefinition, ArchivalError> {
-        self.site
+    pub fn get_object_definition(&self, name: &str) -> Result<ObjectDefinition, ArchivalError> {
+        self.site()
             .object_definitions
             .get(name)
+            .cloned()
             .ok_or(ArchivalError::new(&format!("object not found: {}", name)))
     }
 
     pub fn get_objects(&self) -> Result<ObjectMap> {
-        self.fs_mutex.with_fs(|fs| self.site.get_objects(fs))
+        self.fs_mutex.with_fs(|fs| self.site().get_objects(fs))
     }
 
     pub fn get_object(&self, name: &str, filename: Option<&str>) -> Result<Object> {
         self.fs_mutex
-            .with_fs(|fs| self.site.get_object(name, filename, fs))
+            .with_fs(|fs| self.site().get_object(name, filename, fs))
     }
 
     pub fn get_objects_sorted(
@@ -576,12 +1162,156 @@ impl<F: FileSystemAPI + Clone + Debug> Archival<F> {
         sort: impl Fn(&Object, &Object) -> Ordering,
     ) -> Result<ObjectMap> {
         self.fs_mutex
-            .with_fs(|fs| self.site.get_objects_sorted(fs, Some(sort)))
+            .with_fs(|fs| self.site().get_objects_sorted(fs, Some(sort)))
+    }
+
+    /// Works out how `migration` would rewrite this site's object files,
+    /// without writing anything.
+    pub fn plan_migration(&self, migration: &migrate::Migration) -> Result<migrate::MigrationPlan> {
+        self.fs_mutex
+            .with_fs(|fs| migrate::plan(&self.site(), fs, migration))
+    }
+
+    /// Writes the object files of a plan made by [`Self::plan_migration`].
+    pub fn apply_migration(&self, plan: &migrate::MigrationPlan) -> Result<()> {
+        self.fs_mutex
+            .with_fs(|fs| migrate::apply(&self.site(), fs, plan))
+    }
+
+    /// Compares two sets of definitions, counting the objects in this site
+    /// each change affects.
+    pub fn schema_diff(
+        &self,
+        old: &ObjectDefinitions,
+        new: &ObjectDefinitions,
+    ) -> Result<schema_diff::SchemaDiff> {
+        let mut diff = schema_diff::SchemaDiff::new(old, new);
+        self.fs_mutex
+            .with_fs(|fs| diff.count_affected(&self.site(), fs))?;
+        Ok(diff)
+    }
+
+    /// Merges two versions of an object file with the version they both came
+    /// from, see [`merge`]. An empty `base` is treated as an object with no
+    /// values, for files both sides added.
+    pub fn merge_object_files(
+        &self,
+        obj_type: &str,
+        filename: &str,
+        base: &str,
+        ours: &str,
+        theirs: &str,
+    ) -> Result<merge::MergedFile> {
+        let obj_def = &self.get_object_definition(obj_type)?;
+        let parse = |contents| self.parse_object_file(obj_def, filename, contents);
+        let merged = merge::merge_objects(&parse(base)?, &parse(ours)?, &parse(theirs)?);
+        Ok(merge::MergedFile {
+            contents: merge::conflict_comment(&merged.conflicts)
+                + &merged.object.to_toml(obj_def)?,
+            conflicts: merged.conflicts,
+        })
+    }
+
+    /// Reads the contents of an object file that isn't (or might not be) the
+    /// one on disk, without validating its values.
+    fn parse_object_file(
+        &self,
+        obj_def: &ObjectDefinition,
+        filename: &str,
+        contents: &str,
+    ) -> Result<Object> {
+        let table: toml::Table = toml::from_str(contents)?;
+        Object::from_table(
+            obj_def,
+            Path::new(filename),
+            &table,
+            &self.site().manifest.editor_types,
+            true,
+        )
+    }
+
+    /// The versions of an object in the git repository the site is checked
+    /// into, newest first, each with the changes its commit made. See
+    /// [`history`].
+    #[cfg(feature = "stdlib-fs")]
+    pub fn object_history(
+        &self,
+        obj_type: &str,
+        filename: &str,
+    ) -> Result<Vec<history::ObjectVersion>> {
+        let obj_def = &self.get_object_definition(obj_type)?;
+        let (root, path) = self.fs_mutex.with_fs(|fs| {
+            Ok::<_, anyhow::Error>((
+                fs.root_dir().to_path_buf(),
+                self.object_path_impl(obj_type, filename, fs)?,
+            ))
+        })?;
+        let mut versions = vec![];
+        for (revision, path) in history::file_log(&root, &path)? {
+            let object = history::file_at(&root, &revision.id, &path)?
+                .map(|contents| self.parse_object_file(obj_def, filename, &contents))
+                .transpose()?;
+            versions.push((revision, object));
+        }
+        let mut history = vec![];
+        for (idx, (revision, object)) in versions.iter().enumerate() {
+            let previous = versions.get(idx + 1).and_then(|(_, o)| o.as_ref());
+            history.push(history::ObjectVersion {
+                changes: history::diff_objects(previous, object.as_ref()),
+                revision: revision.clone(),
+                object: object.clone(),
+            });
+        }
+        Ok(history)
+    }
+
+    /// Puts an object back as it was at `revision` (anything git can resolve
+    /// to a commit), by sending the events that make it so as a batch.
+    /// Restoring to a revision before the object existed deletes it.
+    #[cfg(feature = "stdlib-fs")]
+    pub fn restore_object(
+        &self,
+        obj_type: &str,
+        filename: &str,
+        revision: &str,
+        build_options: Option<BuildOptions>,
+    ) -> Result<Vec<ArchivalEventResponse>> {
+        let obj_def = &self.get_object_definition(obj_type)?;
+        let (root, path, current, is_root) = self.fs_mutex.with_fs(|fs| {
+            Ok::<_, anyhow::Error>((
+                fs.root_dir().to_path_buf(),
+                self.object_path_impl(obj_type, filename, fs)?,
+                self.site().get_object(obj_type, Some(filename), fs).ok(),
+                obj_type == filename && self.site().root_objects(fs).contains(obj_type),
+            ))
+        })?;
+        let path = history::path_at(&root, revision, &path)?;
+        let target = history::file_at(&root, revision, &path)?
+            .map(|contents| self.parse_object_file(obj_def, filename, &contents))
+            .transpose()?;
+        let events = history::restore_events(
+            obj_type,
+            filename,
+            is_root,
+            current.as_ref(),
+            target.as_ref(),
+        );
+        self.send_events(events, build_options)
+    }
+
+    /// Starts a query over the objects named `object`, see [`query`].
+    pub fn query(&self, object: &str) -> query::Query<'_, F> {
+        query::Query::new(self, object)
+    }
+
+    /// Searches the text of every object, best match first. See [`search`].
+    pub fn search(&self, query: &str) -> Result<Vec<search::SearchResult>> {
+        self.fs_mutex.with_fs(|fs| self.site().search(query, fs))
     }
 
     pub fn get_rendered_objects(&self) -> Result<RenderedObjectMap> {
         self.fs_mutex
-            .with_fs(|fs| self.site.get_rendered_objects(fs))
+            .with_fs(|fs| self.site().get_rendered_objects(fs))
     }
 
     pub fn get_rendered_object(
@@ -590,7 +1320,7 @@ impl<F: FileSystemAPI + Clone + Debug> Archival<F> {
         filename: Option<&str>,
     ) -> Result<RenderedObject> {
         self.fs_mutex
-            .with_fs(|fs| self.site.get_rendered_object(name, filename, fs))
+            .with_fs(|fs| self.site().get_rendered_object(name, filename, fs))
     }
 
     pub fn get_rendered_objects_sorted(
@@ -598,80 +1328,110 @@ impl<F: FileSystemAPI + Clone + Debug> Archival<F> {
         sort: impl Fn(&Object, &Object) -> Ordering,
     ) -> Result<RenderedObjectMap> {
         self.fs_mutex
-            .with_fs(|fs| self.site.get_rendered_objects_sorted(fs, Some(sort)))
+            .with_fs(|fs| self.site().get_rendered_objects_sorted(fs, Some(sort)))
     }
 
-    fn edit_field(&self, event: EditFieldEvent) -> Result<ArchivalEventResponse> {
-        let def = self
-            .site
-            .object_definitions
-            .get(&event.object)
-            .ok_or_else(|| {
-                ArchivalError::new(&format!("object type not found: {}", event.object))
-            })?;
-        if let Some(value) = &event.value {
-            value.validate(
-                &event
-                    .path
-                    .clone()
-                    .concat(ValuePath::from_string(&event.field)),
-                def,
-                &self.site.manifest.editor_types,
-            )?;
-        }
-        self.write_object(&event.object, &event.filename, |existing| {
-            event
-                .path
-                .append((&(*event.field)).into())
-                .set_in_object(existing, event.value)?;
+    fn edit_field(
+        &self,
+        event: EditFieldEvent,
+        fs: &mut impl FileSystemAPI,
+    ) -> Result<ArchivalEventResponse> {
+        let field_path = event
+            .path
+            .clone()
+            .concat(ValuePath::from_string(&event.field));
+        self.write_object(&event.object, &event.filename, fs, |existing| {
+            // Fields may be list items (`tags.1`), so use the parsed path.
+            field_path.set_in_object(existing, event.value)?;
             Ok(existing)
         })?;
         Ok(ArchivalEventResponse::None)
     }
-    fn edit_order(&self, event: EditOrderEvent) -> Result<ArchivalEventResponse> {
-        self.write_object(&event.object, &event.filename, |existing| {
+    /// Rejects an event made from a stale copy of its object.
+    fn check_expected(
+        &self,
+        event: &ArchivalEvent,
+        expected: &ExpectedVersion,
+        fs: &impl FileSystemAPI,
+    ) -> Result<()> {
+        let value_path = event.value_path();
+        if matches!(expected, ExpectedVersion::Value(_)) && value_path.is_none() {
+            return Err(ArchivalError::new(&format!(
+                "{event} doesn't change a value, so can only expect a version"
+            ))
+            .into());
+        }
+        let object = self
+            .site()
+            .get_object(event.object_name(), Some(event.filename()), fs)?;
+        let path = value_path.unwrap_or_default();
+        let current_version = object.version();
+        let current_value = query::value_at(&object, &path)
+            .filter(|value| !matches!(value, FieldValue::Null))
+            .cloned();
+        let stale = match expected {
+            ExpectedVersion::Version(version) => *version != current_version,
+            ExpectedVersion::Value(value) => *value != current_value,
+        };
+        if stale {
+            return Err(ConflictError {
+                object: event.object_name().to_string(),
+                filename: event.filename().to_string(),
+                path,
+                current_version,
+                current_value,
+            }
+            .into());
+        }
+        Ok(())
+    }
+    fn edit_order(
+        &self,
+        event: EditOrderEvent,
+        fs: &mut impl FileSystemAPI,
+    ) -> Result<ArchivalEventResponse> {
+        self.write_object(&event.object, &event.filename, fs, |existing| {
             existing.order = event.order;
             Ok(existing)
         })?;
         Ok(ArchivalEventResponse::None)
     }
 
-    fn add_child(&self, event: AddChildEvent) -> Result<ArchivalEventResponse> {
+    fn add_child(
+        &self,
+        event: AddChildEvent,
+        fs: &mut impl FileSystemAPI,
+    ) -> Result<ArchivalEventResponse> {
         let mut added_idx = usize::MAX;
-        let def = self
-            .site
-            .object_definitions
-            .get(&event.object)
-            .ok_or_else(|| {
-                ArchivalError::new(&format!("object type not found: {}", event.object))
-            })?;
-        // Validate any initial values
-        for value in &event.values {
-            value.value.validate(
-                &event.path.clone().concat(value.path.clone()),
-                def,
-                &self.site.manifest.editor_types,
-            )?;
-        }
+        let site = self.site();
+        let def = site.object_definitions.get(&event.object).ok_or_else(|| {
+            ArchivalError::new(&format!("object type not found: {}", event.object))
+        })?;
         // Seed the new child from its definition so it carries the same field
-        // scaffolding (notably empty child collections) as objects created any
-        // other way - `from_def` does this via `empty_object`. Without it a new
-        // child is a bare map with every value undefined.
+        // scaffolding (empty child collections and field defaults) as objects
+        // created any other way - `from_def` does this via `default_object`.
+        // Without it a new child is a bare map with every value undefined.
         let child_def = event.path.get_definition(def)?;
-        self.write_object(&event.object, &event.filename, |existing| {
+        let mut new_child = child_def.default_object();
+        for value in event.values {
+            value.path.set_in_tree(&mut new_child, Some(value.value))?;
+        }
+        child_def.check_new_constraints(&new_child, &event.path)?;
+        self.write_object(&event.object, &event.filename, fs, |existing| {
             added_idx = event.path.add_child(existing, event.index, |child| {
-                *child = child_def.empty_object();
-                for value in event.values {
-                    value.path.set_in_tree(child, Some(value.value))?;
-                }
+                *child = new_child;
                 Ok(())
             })?;
             Ok(existing)
         })?;
         Ok(ArchivalEventResponse::Index(added_idx))
     }
-    fn remove_child(&self, event: RemoveChildEvent) -> Result<ArchivalEventResponse> {
-        self.write_object(&event.object, &event.filename, move |existing| {
+    fn remove_child(
+        &self,
+        event: RemoveChildEvent,
+        fs: &mut impl FileSystemAPI,
+    ) -> Result<ArchivalEventResponse> {
+        self.write_object(&event.object, &event.filename, fs, move |existing| {
             let mut path = event.path;
             path.remove_child(existing)?;
             Ok(existing)
@@ -679,40 +1439,136 @@ impl<F: FileSystemAPI + Clone + Debug> Archival<F> {
         Ok(ArchivalEventResponse::None)
     }
 
+    fn add_list_value(
+        &self,
+        event: AddListValueEvent,
+        fs: &mut impl FileSystemAPI,
+    ) -> Result<ArchivalEventResponse> {
+        let def = &self.get_object_definition(&event.object)?;
+        let mut added_idx = usize::MAX;
+        self.write_object(&event.object, &event.filename, fs, |existing| {
+            event.path.modify_list(existing, def, |items| {
+                added_idx = match event.index {
+                    Some(index) if index <= items.len() => index,
+                    _ => items.len(),
+                };
+                items.insert(added_idx, event.value);
+            })?;
+            self.check_list_constraints(existing, def, &event.path)?;
+            Ok(existing)
+        })?;
+        Ok(ArchivalEventResponse::Index(added_idx))
+    }
+    fn remove_list_value(
+        &self,
+        event: RemoveListValueEvent,
+        fs: &mut impl FileSystemAPI,
+    ) -> Result<ArchivalEventResponse> {
+        let def = &self.get_object_definition(&event.object)?;
+        let mut list_path = event.path.clone();
+        let Some(ValuePathComponent::Index(index)) = list_path.pop() else {
+            return Err(ValuePathError::NotFound(event.path, event.filename).into());
+        };
+        self.write_object(&event.object, &event.filename, fs, |existing| {
+            list_path
+                .modify_list(existing, def, |items| {
+                    (index < items.len()).then(|| items.remove(index))
+                })?
+                .ok_or_else(|| {
+                    ValuePathError::NotFound(event.path.clone(), event.filename.clone())
+                })?;
+            self.check_list_constraints(existing, def, &list_path)?;
+            Ok(existing)
+        })?;
+        Ok(ArchivalEventResponse::None)
+    }
+    fn move_list_value(
+        &self,
+        event: MoveListValueEvent,
+        fs: &mut impl FileSystemAPI,
+    ) -> Result<ArchivalEventResponse> {
+        let def = &self.get_object_definition(&event.object)?;
+        self.write_object(&event.object, &event.filename, fs, |existing| {
+            event
+                .path
+                .modify_list(existing, def, |items| {
+                    if event.from < items.len() && event.to < items.len() {
+                        let value = items.remove(event.from);
+                        items.insert(event.to, value);
+                        Some(())
+                    } else {
+                        None
+                    }
+                })?
+                .ok_or_else(|| {
+                    ArchivalError::new(&format!(
+                        "cannot move {}.{} to {}.{}, out of range",
+                        event.path, event.from, event.path, event.to
+                    ))
+                })?;
+            Ok(existing)
+        })?;
+        Ok(ArchivalEventResponse::None)
+    }
+    /// Lists are validated item by item as they're edited, but their
+    /// constraints (how many items they hold) only apply to the whole list.
+    fn check_list_constraints(
+        &self,
+        object: &Object,
+        definition: &ObjectDefinition,
+        path: &ValuePath,
+    ) -> Result<()> {
+        let field = path.get_field(definition)?;
+        field
+            .constraints
+            .check(path.get_in_object(object))
+            .map_err(|violation| {
+                FieldValueValidationError::ConstraintViolation(path.clone(), violation)
+            })?;
+        Ok(())
+    }
+
     fn write_object(
         &self,
         obj_type: &str,
         filename: &str,
+        fs: &mut impl FileSystemAPI,
         obj_cb: impl FnOnce(&mut Object) -> Result<&mut Object>,
     ) -> Result<()> {
         debug!("write object {}: {}", obj_type, filename);
-        self.fs_mutex.with_fs(|fs| {
-            let path = self.object_path_impl(obj_type, filename, fs)?;
-            let contents = self.modify_object_file(obj_type, filename, obj_cb, fs)?;
-            fs.write_str(&path, contents)?;
-            self.site.invalidate_file(&path);
-            Ok(())
-        })
+        let path = self.object_path_impl(obj_type, filename, fs)?;
+        let contents = self.modify_object_file(obj_type, filename, obj_cb, fs)?;
+        fs.write_str(&path, contents)?;
+        self.invalidate(&path);
+        Ok(())
     }
 
-    pub fn modify_manifest(&mut self, modify: impl FnOnce(&mut Manifest)) -> Result<()> {
+    pub fn modify_manifest(&self, modify: impl FnOnce(&mut Manifest)) -> Result<()> {
+        let site = self.site();
+        let mut manifest = site.manifest.clone();
+        modify(&mut manifest);
         self.fs_mutex.with_fs(|fs| {
-            self.site.modify_manifest(fs, modify)?;
-            Ok(())
-        })
+            let manifest_path = Manifest::path_in(Path::new(""), fs)?;
+            fs.write_str(manifest_path, manifest.to_toml()?)
+        })?;
+        self.reload_site(&site.manifest.upload_prefix)?;
+        self.subscribers
+            .notify(vec![changes::Change::DefinitionsReloaded]);
+        Ok(())
     }
 
     /// Deletes all the object files for the given object types, except the ones
     /// (optionally) specified in the list of keep_objects.
     /// This only deletes the files, and does not generate events or rebuild the
-    /// archival site.
+    /// archival site, though subscribers are told of each removed object.
     pub fn delete_objects(
         &self,
         object_names: impl IntoIterator<Item = impl AsRef<str>>,
         keep_objects: Option<Vec<ValuePath>>,
     ) -> Result<()> {
         let objects = self.get_objects()?;
-        self.fs_mutex.with_fs(|fs| {
+        let mut changes = vec![];
+        let deleted = self.fs_mutex.with_fs(|fs| {
             for on in object_names {
                 let object_name = on.as_ref();
                 let current_path: ValuePath =
@@ -742,11 +1598,18 @@ impl<F: FileSystemAPI + Clone + Debug> Archival<F> {
                     }
                     let path = self.object_path_impl(object_name, filename, fs)?;
                     fs.delete(&path)?;
-                    self.site.invalidate_file(&path);
+                    self.site().invalidate_file(&path);
+                    changes.push(changes::Change::ObjectRemoved {
+                        object: object_name.to_string(),
+                        filename: filename.to_string(),
+                    });
                 }
             }
             Ok(())
-        })
+        });
+        // Files deleted before a failure are gone, so report them either way.
+        self.subscribers.notify(changes);
+        deleted
     }
 
     pub fn take_fs(self) -> F {
@@ -757,12 +1620,30 @@ impl<F: FileSystemAPI + Clone + Debug> Archival<F> {
     }
 }
 
+/// Values added along with a new object or child may leave it incomplete, as
+/// with [`ObjectDefinition::check_new_constraints`]: a list can be added before
+/// it has all of its items.
+#[allow(clippy::result_large_err)]
+fn allow_incomplete(
+    result: Result<(), FieldValueValidationError>,
+) -> Result<(), FieldValueValidationError> {
+    match result {
+        Err(FieldValueValidationError::ConstraintViolation(_, violation))
+            if violation.is_incomplete() =>
+        {
+            Ok(())
+        }
+        result => result,
+    }
+}
+
 #[cfg(test)]
 mod lib {
     use anyhow::Result;
 
     use crate::{file_system::unpack_zip, test_utils::as_path_str, value_path::ValuePath};
     use events::AddObjectValue;
+    use fields::ConstraintViolation;
     use tracing_test::traced_test;
 
     use super::*;
@@ -774,10 +1655,10 @@ mod lib {
         let zip = include_bytes!("../tests/fixtures/archival-website.zip");
         unpack_zip(zip.to_vec(), &mut fs)?;
         let archival = Archival::new(fs)?;
-        assert_eq!(archival.site.object_definitions.len(), 4);
-        assert!(archival.site.object_definitions.contains_key("section"));
-        assert!(archival.site.object_definitions.contains_key("post"));
-        assert!(archival.site.object_definitions.contains_key("site"));
+        assert_eq!(archival.site().object_definitions.len(), 4);
+        assert!(archival.site().object_definitions.contains_key("section"));
+        assert!(archival.site().object_definitions.contains_key("post"));
+        assert!(archival.site().object_definitions.contains_key("site"));
         let objects = archival.get_objects()?;
         let section_objs = objects.get("section").unwrap();
         assert!(matches!(section_objs, ObjectEntry::List(_)));
@@ -788,7 +1669,7 @@ mod lib {
         let fp = post_obj.into_iter().next().unwrap();
         let m = ValuePath::from_string("media").get_in_object(fp).unwrap();
         assert!(matches!(m, FieldValue::Oneof(..)));
-        let fc = &archival.site.field_config;
+        let fc = &archival.site().field_config;
         if let FieldValue::Oneof((t, val)) = m {
             assert_eq!(t, "image");
             assert!(matches!(**val, Some(FieldValue::File(_))));
@@ -818,7 +1699,7 @@ mod lib {
         let post_html = archival
             .fs_mutex
             .with_fs(|fs| {
-                fs.read_to_string(archival.site.manifest.build_dir.join("post/a-post.html"))
+                fs.read_to_string(archival.site().manifest.build_dir.join("post/a-post.html"))
             })?
             .unwrap();
         println!("{}", post_html);
@@ -887,7 +1768,7 @@ mod lib {
             Some(BuildOptions::default()),
         )?;
         // Sending an event should result in an updated fs
-        let sections_dir = archival.site.manifest.objects_dir.join("section");
+        let sections_dir = archival.site().manifest.objects_dir.join("section");
         let sections = archival.fs_mutex.with_fs(|fs| {
             fs.walk_dir(&sections_dir, false)
                 .map(|d| d.collect::<Vec<PathBuf>>())
@@ -900,7 +1781,7 @@ mod lib {
         assert!(section_toml.is_ok());
         let index_html = archival
             .fs_mutex
-            .with_fs(|fs| fs.read_to_string(archival.site.manifest.build_dir.join("index.html")))?
+            .with_fs(|fs| fs.read_to_string(archival.site().manifest.build_dir.join("index.html")))?
             .unwrap();
         let rendered_sections: Vec<_> = index_html.match_indices("<h2>").collect();
         println!("MATCHED: {:?}", rendered_sections);
@@ -928,7 +1809,7 @@ mod lib {
         // Sending an event should result in an updated fs
         let index_html = archival
             .fs_mutex
-            .with_fs(|fs| fs.read_to_string(archival.site.manifest.build_dir.join("index.html")))?
+            .with_fs(|fs| fs.read_to_string(archival.site().manifest.build_dir.join("index.html")))?
             .unwrap();
         println!("index: {}", index_html);
         assert!(index_html.contains("This is the new name"));
@@ -950,7 +1831,7 @@ mod lib {
             Some(BuildOptions::default()),
         )?;
         // Sending an event should result in an updated fs
-        let sections_dir = archival.site.manifest.objects_dir.join("section");
+        let sections_dir = archival.site().manifest.objects_dir.join("section");
         let sections = archival.fs_mutex.with_fs(|fs| {
             fs.walk_dir(&sections_dir, false)
                 .map(|d| d.collect::<Vec<PathBuf>>())
@@ -959,7 +1840,7 @@ mod lib {
         assert_eq!(sections.len(), 1);
         let index_html = archival
             .fs_mutex
-            .with_fs(|fs| fs.read_to_string(archival.site.manifest.build_dir.join("index.html")))?
+            .with_fs(|fs| fs.read_to_string(archival.site().manifest.build_dir.join("index.html")))?
             .unwrap();
         println!("index: {}", index_html);
         assert!(!index_html.contains("This is the new title"));
@@ -972,7 +1853,7 @@ mod lib {
         let zip = include_bytes!("../tests/fixtures/archival-website.zip");
         unpack_zip(zip.to_vec(), &mut fs)?;
         let archival = Archival::new(fs)?;
-        let sections_dir = archival.site.manifest.objects_dir.join("section");
+        let sections_dir = archival.site().manifest.objects_dir.join("section");
         let sections_before_rename = archival.fs_mutex.with_fs(|fs| {
             fs.walk_dir(&sections_dir, false)
                 .map(|d| d.collect::<Vec<PathBuf>>())
@@ -986,7 +1867,7 @@ mod lib {
             Some(BuildOptions::default()),
         )?;
         // Sending an event should result in an updated fs
-        let sections_dir = archival.site.manifest.objects_dir.join("section");
+        let sections_dir = archival.site().manifest.objects_dir.join("section");
         let sections = archival.fs_mutex.with_fs(|fs| {
             fs.walk_dir(&sections_dir, false)
                 .map(|d| d.collect::<Vec<PathBuf>>())
@@ -1003,7 +1884,7 @@ mod lib {
         let zip = include_bytes!("../tests/fixtures/archival-website.zip");
         unpack_zip(zip.to_vec(), &mut fs)?;
         let archival = Archival::new(fs)?;
-        let sections_dir = archival.site.manifest.objects_dir.join("section");
+        let sections_dir = archival.site().manifest.objects_dir.join("section");
         let sections_before_rename = archival.fs_mutex.with_fs(|fs| {
             fs.walk_dir(&sections_dir, false)
                 .map(|d| d.collect::<Vec<PathBuf>>())
@@ -1028,7 +1909,7 @@ mod lib {
             Some(BuildOptions::default()),
         )?;
         // Sending an event should result in an updated fs
-        let sections_dir = archival.site.manifest.objects_dir.join("section");
+        let sections_dir = archival.site().manifest.objects_dir.join("section");
         let sections = archival.fs_mutex.with_fs(|fs| {
             fs.walk_dir(&sections_dir, false)
                 .map(|d| d.collect::<Vec<PathBuf>>())
@@ -1082,7 +1963,7 @@ mod lib {
         archival.build(BuildOptions::default())?;
         let index_html = archival
             .fs_mutex
-            .with_fs(|fs| fs.read_to_string(archival.site.manifest.build_dir.join("index.html")))?
+            .with_fs(|fs| fs.read_to_string(archival.site().manifest.build_dir.join("index.html")))?
             .unwrap();
         println!("index: {}", index_html);
         let c1 = index_html.find("1 Some Content").unwrap();
@@ -1100,7 +1981,7 @@ mod lib {
         // Sending an event should result in an updated fs
         let index_html = archival
             .fs_mutex
-            .with_fs(|fs| fs.read_to_string(archival.site.manifest.build_dir.join("index.html")))?
+            .with_fs(|fs| fs.read_to_string(archival.site().manifest.build_dir.join("index.html")))?
             .unwrap();
         println!("index: {}", index_html);
         let c1 = index_html.find("12 Some Content").unwrap();
@@ -1122,7 +2003,7 @@ mod lib {
             .with_fs(|fs| {
                 fs.read_to_string(
                     archival
-                        .site
+                        .site()
                         .manifest
                         .build_dir
                         .join(Path::new("post/a-post.html")),
@@ -1175,7 +2056,7 @@ mod lib {
             .with_fs(|fs| {
                 fs.read_to_string(
                     archival
-                        .site
+                        .site()
                         .manifest
                         .build_dir
                         .join(Path::new("post/a-post.html")),
@@ -1257,144 +2138,1236 @@ mod lib {
     }
 
     #[test]
-    fn remove_child() -> Result<()> {
+    fn added_objects_and_children_get_field_defaults() -> Result<()> {
         let mut fs = MemoryFileSystem::default();
-        let zip = include_bytes!("../tests/fixtures/archival-website.zip");
-        unpack_zip(zip.to_vec(), &mut fs)?;
+        fs.write_str(MANIFEST_FILE_NAME, "upload_prefix = \"\"\n".to_string())?;
+        fs.write_str(
+            OBJECT_DEFINITION_FILE_NAME,
+            r#"
+            [post]
+            title = "string"
+            status = { values = ["draft", "published"], default = "draft" }
+            [post.links]
+            url = "string"
+            label = { default = "Read more" }
+            "#
+            .to_string(),
+        )?;
         let archival = Archival::new(fs)?;
-        archival
-            .send_event(
-                ArchivalEvent::RemoveChild(RemoveChildEvent {
-                    object: "post".to_string(),
-                    filename: "a-post".to_string(),
-                    path: ValuePath::default()
-                        .append(ValuePath::key("links"))
-                        .append(ValuePath::index(0)),
-                    source: None,
-                }),
-                Some(BuildOptions::default()),
-            )
-            .unwrap();
-        // Sending an event should result in an updated fs
-        let post_html = archival
-            .fs_mutex
-            .with_fs(|fs| {
-                fs.read_to_string(
-                    archival
-                        .site
-                        .manifest
-                        .build_dir
-                        .join(Path::new("post/a-post.html")),
-                )
-            })?
-            .unwrap();
-        println!("post: {}", post_html);
-        let rendered_links: Vec<_> = post_html.match_indices("<a href=").collect();
-        println!("LINKS: {:?}", rendered_links);
-        assert_eq!(rendered_links.len(), 1);
+        archival.send_event(
+            ArchivalEvent::AddObject(AddObjectEvent {
+                object: "post".to_string(),
+                filename: "hello".to_string(),
+                order: None,
+                values: vec![AddObjectValue {
+                    path: ValuePath::from_string("title"),
+                    value: FieldValue::String("Hello".to_string()),
+                }],
+            }),
+            None,
+        )?;
+        archival.send_event(
+            ArchivalEvent::AddChild(AddChildEvent {
+                object: "post".to_string(),
+                filename: "hello".to_string(),
+                path: ValuePath::from_string("links"),
+                values: vec![],
+                index: None,
+            }),
+            None,
+        )?;
+        let post = archival.get_object("post", Some("hello"))?;
+        assert_eq!(
+            post.values.get("status"),
+            Some(&FieldValue::Enum("draft".to_string()))
+        );
+        assert_eq!(
+            ValuePath::from_string("links.0.label").get_in_object(&post),
+            Some(&FieldValue::String("Read more".to_string()))
+        );
+
+        // Values sent with the event win over defaults.
+        archival.send_event(
+            ArchivalEvent::AddObject(AddObjectEvent {
+                object: "post".to_string(),
+                filename: "live".to_string(),
+                order: None,
+                values: vec![AddObjectValue {
+                    path: ValuePath::from_string("status"),
+                    value: FieldValue::Enum("published".to_string()),
+                }],
+            }),
+            None,
+        )?;
+        let post = archival.get_object("post", Some("live"))?;
+        assert_eq!(
+            post.values.get("status"),
+            Some(&FieldValue::Enum("published".to_string()))
+        );
         Ok(())
     }
 
-    #[test]
-    fn modify_manifest() -> Result<()> {
+    fn constrained_site() -> Result<Archival<MemoryFileSystem>> {
         let mut fs = MemoryFileSystem::default();
-        let zip = include_bytes!("../tests/fixtures/archival-website.zip");
-        unpack_zip(zip.to_vec(), &mut fs)?;
-        let mut archival = Archival::new(fs)?;
-        archival.modify_manifest(|m| {
-            m.site_url = Some("test.com".to_string());
-            m.prebuild = vec!["test".to_string()];
-        })?;
-        let output = archival.site.manifest.to_toml()?;
-        println!("{}", output);
-        assert!(output.contains("site_url = \"test.com\""));
-        // Doesn't fill defaults
-        assert!(!output.contains("objects_dir"));
-        assert!(!output.contains("objects"));
-        // Does show non-defaults
-        assert!(output.contains("prebuild = [\"test\"]"));
-        Ok(())
+        fs.write_str(MANIFEST_FILE_NAME, "upload_prefix = \"\"\n".to_string())?;
+        fs.write_str(
+            OBJECT_DEFINITION_FILE_NAME,
+            r#"
+            [post]
+            title = { required = true, max_length = 10 }
+            rating = { type = "number", min = 0, max = 5 }
+            [post.links]
+            url = { required = true }
+            "#
+            .to_string(),
+        )?;
+        fs.write_str("objects/post/hello.toml", "title = \"Hello\"".to_string())?;
+        Archival::new(fs)
+    }
+
+    fn violation(err: anyhow::Error) -> (String, ConstraintViolation) {
+        match err.downcast::<FieldValueValidationError>() {
+            Ok(FieldValueValidationError::ConstraintViolation(path, violation)) => {
+                (path.to_string(), violation)
+            }
+            other => panic!("expected a constraint violation, got {other:?}"),
+        }
     }
 
     #[test]
-    fn bulk_delete_objects() -> Result<()> {
-        let mut fs = MemoryFileSystem::default();
-        let zip = include_bytes!("../tests/fixtures/archival-website.zip");
-        unpack_zip(zip.to_vec(), &mut fs)?;
-        let archival = Archival::new(fs)?;
-        archival.delete_objects(
-            vec!["section", "site"],
-            Some(vec![ValuePath::from_string("section.second")]),
-        )?;
-        // This should result in the relevant files being missing
-        let sections_dir = archival.site.manifest.objects_dir.join("section");
-        let sections = archival.fs_mutex.with_fs(|fs| {
-            fs.walk_dir(&sections_dir, false)
-                .map(|d| d.collect::<Vec<PathBuf>>())
-        })?;
-        println!("SECTIONS: {:?}", sections);
-        assert_eq!(sections.len(), 1);
-        let site_file_exists = archival
-            .fs_mutex
-            .with_fs(|fs| fs.exists(archival.site.manifest.objects_dir.join("site.toml")))?;
-        assert!(!site_file_exists);
+    fn events_that_break_constraints_are_rejected() -> Result<()> {
+        let archival = constrained_site()?;
+        let edit = |field: &str, value: Option<FieldValue>| {
+            archival.send_event(
+                ArchivalEvent::EditField(EditFieldEvent {
+                    object: "post".to_string(),
+                    filename: "hello".to_string(),
+                    path: ValuePath::empty(),
+                    field: field.to_string(),
+                    value,
+                    source: None,
+                }),
+                None,
+            )
+        };
+        assert_eq!(
+            violation(edit("title", None).unwrap_err()),
+            ("title".to_string(), ConstraintViolation::Required)
+        );
+        assert_eq!(
+            violation(edit("title", Some(FieldValue::String("Far too long".into()))).unwrap_err()),
+            (
+                "title".to_string(),
+                ConstraintViolation::TooLong {
+                    max: 10,
+                    length: 12
+                }
+            )
+        );
+        assert_eq!(
+            violation(edit("rating", Some(FieldValue::Number(6.))).unwrap_err()),
+            (
+                "rating".to_string(),
+                ConstraintViolation::AboveMaximum(FieldValue::Number(5.))
+            )
+        );
+        edit("rating", Some(FieldValue::Number(4.)))?;
+
+        let add_object = |filename: &str, values: Vec<AddObjectValue>| {
+            archival.send_event(
+                ArchivalEvent::AddObject(AddObjectEvent {
+                    object: "post".to_string(),
+                    filename: filename.to_string(),
+                    order: None,
+                    values,
+                }),
+                None,
+            )
+        };
+        let err = add_object(
+            "too-long",
+            vec![AddObjectValue {
+                path: ValuePath::from_string("title"),
+                value: FieldValue::String("Far too long".into()),
+            }],
+        )
+        .unwrap_err();
+        assert_eq!(
+            violation(err),
+            (
+                "title".to_string(),
+                ConstraintViolation::TooLong {
+                    max: 10,
+                    length: 12
+                }
+            )
+        );
+        // Nothing rejected was written.
+        let post = archival.get_object("post", Some("hello"))?;
+        assert_eq!(post.values.get("rating"), Some(&FieldValue::Number(4.)));
+        assert!(archival.get_object("post", Some("too-long")).is_err());
         Ok(())
     }
 
     #[test]
-    fn edit_enum() -> Result<()> {
-        let mut fs = MemoryFileSystem::default();
-        let zip = include_bytes!("../tests/fixtures/archival-website.zip");
-        unpack_zip(zip.to_vec(), &mut fs)?;
-        let archival = Archival::new(fs)?;
+    fn new_objects_and_children_may_be_incomplete() -> Result<()> {
+        let archival = constrained_site()?;
+        // Importers create objects first and fill them in with edits, so a
+        // missing required value isn't an error until something clears it.
         archival.send_event(
-            ArchivalEvent::EditField(EditFieldEvent {
+            ArchivalEvent::AddObject(AddObjectEvent {
                 object: "post".to_string(),
-                filename: "a-post".to_string(),
-                path: ValuePath::empty(),
-                field: "state".to_string(),
-                value: Some(FieldValue::Enum("draft".to_string())),
-                source: None,
+                filename: "untitled".to_string(),
+                order: None,
+                values: vec![],
             }),
-            Some(BuildOptions::default()),
+            None,
         )?;
-        let post_html = archival
-            .fs_mutex
-            .with_fs(|fs| {
-                fs.read_to_string(
-                    archival
-                        .site
-                        .manifest
-                        .build_dir
-                        .join(Path::new("post/a-post.html")),
-                )
-            })?
-            .unwrap();
-        println!("post: {}", post_html);
-        assert!(post_html.contains("State: draft"));
+        archival.send_event(
+            ArchivalEvent::AddChild(AddChildEvent {
+                object: "post".to_string(),
+                filename: "untitled".to_string(),
+                path: ValuePath::from_string("links"),
+                values: vec![],
+                index: None,
+            }),
+            None,
+        )?;
+        let post = archival.get_object("post", Some("untitled"))?;
+        let definition = archival.get_object_definition("post")?;
+        assert_eq!(
+            definition
+                .constraint_violations(&post.values)
+                .into_iter()
+                .map(|(path, violation)| (path.to_string(), violation))
+                .collect::<Vec<_>>(),
+            vec![
+                ("title".to_string(), ConstraintViolation::Required),
+                ("links.0.url".to_string(), ConstraintViolation::Required),
+            ]
+        );
         Ok(())
     }
+
     #[test]
-    fn edit_enum_fails_when_invalid() -> Result<()> {
+    fn list_values_are_added_edited_moved_and_removed() -> Result<()> {
         let mut fs = MemoryFileSystem::default();
-        let zip = include_bytes!("../tests/fixtures/archival-website.zip");
-        unpack_zip(zip.to_vec(), &mut fs)?;
+        fs.write_str(MANIFEST_FILE_NAME, "upload_prefix = \"\"\n".to_string())?;
+        fs.write_str(
+            OBJECT_DEFINITION_FILE_NAME,
+            r#"
+            [post]
+            tags = { type = ["string"], max_length = 3 }
+            [post.links]
+            scores = ["number"]
+            "#
+            .to_string(),
+        )?;
+        fs.write_str(
+            "objects/post/hello.toml",
+            "tags = [\"a\"]\n[[links]]\n".to_string(),
+        )?;
         let archival = Archival::new(fs)?;
-        assert!(archival
-            .send_event(
-                ArchivalEvent::EditField(EditFieldEvent {
+        let tags = |archival: &Archival<MemoryFileSystem>| -> Result<Option<FieldValue>> {
+            Ok(archival
+                .get_object("post", Some("hello"))?
+                .values
+                .get("tags")
+                .cloned())
+        };
+        let string_list = |items: &[&str]| {
+            FieldValue::List(
+                items
+                    .iter()
+                    .map(|s| FieldValue::String(s.to_string()))
+                    .collect(),
+            )
+        };
+        let add = |path: &str, value: FieldValue, index: Option<usize>| {
+            archival.send_event(
+                ArchivalEvent::AddListValue(AddListValueEvent {
                     object: "post".to_string(),
-                    filename: "a-post".to_string(),
-                    path: ValuePath::empty(),
-                    field: "state".to_string(),
-                    value: Some(FieldValue::Enum("poopoo".to_string())),
-                    source: None,
+                    filename: "hello".to_string(),
+                    path: ValuePath::from_string(path),
+                    value,
+                    index,
                 }),
-                Some(BuildOptions::default()),
+                None,
             )
-            .is_err());
-        Ok(())
-    }
+        };
+
+        let added = add("tags", FieldValue::String("c".into()), None)?;
+        assert!(matches!(added, ArchivalEventResponse::Index(1)));
+        add("tags", FieldValue::String("b".into()), Some(1))?;
+        assert_eq!(tags(&archival)?, Some(string_list(&["a", "b", "c"])));
+        // Items are validated against the list's item type...
+        assert!(add("tags", FieldValue::Number(1.), None).is_err());
+        // ...and the list against its constraints.
+        assert_eq!(
+            violation(add("tags", FieldValue::String("d".into()), None).unwrap_err()),
+            (
+                "tags".to_string(),
+                ConstraintViolation::TooManyItems { max: 3, count: 4 }
+            )
+        );
+
+        archival.send_event(
+            ArchivalEvent::EditField(EditFieldEvent {
+                object: "post".to_string(),
+                filename: "hello".to_string(),
+                path: ValuePath::empty(),
+                field: "tags.0".to_string(),
+                value: Some(FieldValue::String("z".into())),
+                source: None,
+            }),
+            None,
+        )?;
+        archival.send_event(
+            ArchivalEvent::MoveListValue(MoveListValueEvent {
+                object: "post".to_string(),
+                filename: "hello".to_string(),
+                path: ValuePath::from_string("tags"),
+                from: 0,
+                to: 2,
+                source: None,
+            }),
+            None,
+        )?;
+        assert_eq!(tags(&archival)?, Some(string_list(&["b", "c", "z"])));
+        archival.send_event(
+            ArchivalEvent::RemoveListValue(RemoveListValueEvent {
+                object: "post".to_string(),
+                filename: "hello".to_string(),
+                path: ValuePath::from_string("tags.1"),
+                source: None,
+            }),
+            None,
+        )?;
+        assert_eq!(tags(&archival)?, Some(string_list(&["b", "z"])));
+
+        // Lists inside children are created as they're added to.
+        add("links.0.scores", FieldValue::Number(2.), None)?;
+        let post = archival.get_object("post", Some("hello"))?;
+        assert_eq!(
+            ValuePath::from_string("links.0.scores.0").get_in_object(&post),
+            Some(&FieldValue::Number(2.))
+        );
+        assert!(add("links.0.nope", FieldValue::Number(2.), None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn journaled_events_undo_and_redo() -> Result<()> {
+        let mut fs = MemoryFileSystem::default();
+        fs.write_str(MANIFEST_FILE_NAME, "upload_prefix = \"\"\n".to_string())?;
+        fs.write_str(
+            OBJECT_DEFINITION_FILE_NAME,
+            r#"
+            [post]
+            title = "string"
+            tags = ["string"]
+            [post.links]
+            url = "string"
+            [post.links.notes]
+            text = "string"
+            "#
+            .to_string(),
+        )?;
+        fs.write_str(
+            "objects/post/a.toml",
+            r#"
+            order = 2
+            title = "A"
+            tags = ["x", "y", "z"]
+            [[links]]
+            url = "one"
+            [[links.notes]]
+            text = "first"
+            [[links.notes]]
+            text = "second"
+            [[links]]
+            url = "two"
+            "#
+            .to_string(),
+        )?;
+        let archival = Archival::new(fs)?;
+        let original = archival.object_file("post", "a")?;
+        archival.enable_journal(journal::Journal::default());
+        let edit_title = |title: &str| {
+            ArchivalEvent::EditField(EditFieldEvent {
+                object: "post".to_string(),
+                filename: "a".to_string(),
+                path: ValuePath::empty(),
+                field: "title".to_string(),
+                value: Some(FieldValue::String(title.to_string())),
+                source: Some("title-input".to_string()),
+            })
+        };
+        let events = vec![
+            edit_title("B"),
+            edit_title("Bo"),
+            ArchivalEvent::EditOrder(EditOrderEvent {
+                object: "post".to_string(),
+                filename: "a".to_string(),
+                order: Some(7.),
+                source: None,
+            }),
+            ArchivalEvent::AddListValue(AddListValueEvent {
+                object: "post".to_string(),
+                filename: "a".to_string(),
+                path: ValuePath::from_string("tags"),
+                value: FieldValue::String("w".to_string()),
+                index: None,
+            }),
+            ArchivalEvent::RemoveListValue(RemoveListValueEvent {
+                object: "post".to_string(),
+                filename: "a".to_string(),
+                path: ValuePath::from_string("tags.1"),
+                source: None,
+            }),
+            ArchivalEvent::MoveListValue(MoveListValueEvent {
+                object: "post".to_string(),
+                filename: "a".to_string(),
+                path: ValuePath::from_string("tags"),
+                from: 0,
+                to: 2,
+                source: None,
+            }),
+            ArchivalEvent::AddChild(AddChildEvent {
+                object: "post".to_string(),
+                filename: "a".to_string(),
+                path: ValuePath::from_string("links"),
+                values: vec![],
+                index: None,
+            }),
+            ArchivalEvent::RemoveChild(RemoveChildEvent {
+                object: "post".to_string(),
+                filename: "a".to_string(),
+                path: ValuePath::from_string("links.0"),
+                source: None,
+            }),
+            ArchivalEvent::RenameObject(RenameObjectEvent {
+                object: "post".to_string(),
+                from: "a".to_string(),
+                to: "b".to_string(),
+            }),
+            ArchivalEvent::DeleteObject(DeleteObjectEvent {
+                object: "post".to_string(),
+                filename: "b".to_string(),
+                source: None,
+            }),
+            ArchivalEvent::AddObject(AddObjectEvent {
+                object: "post".to_string(),
+                filename: "c".to_string(),
+                order: None,
+                values: vec![],
+            }),
+        ];
+        for event in events {
+            archival.send_event(event, None)?;
+        }
+        let journal = archival.journal().unwrap();
+        // The two title edits share a source, so they undo together.
+        assert_eq!(journal.undo_groups().len(), 10);
+        assert_eq!(
+            journal.undo_groups()[0].description(),
+            "Change field title in post 'a' and 1 more"
+        );
+
+        while archival.undo(None)? {}
+        assert_eq!(archival.object_file("post", "a")?, original);
+        assert!(archival.get_object("post", Some("c")).is_err());
+
+        // A saved journal picks up where it left off.
+        let saved = serde_json::to_string(&archival.disable_journal().unwrap())?;
+        archival.enable_journal(serde_json::from_str(&saved)?);
+        assert!(archival.redo(None)?);
+        assert_eq!(
+            archival.get_object("post", Some("a"))?.values.get("title"),
+            Some(&FieldValue::String("Bo".to_string()))
+        );
+        while archival.redo(None)? {}
+        assert!(archival.get_object("post", Some("b")).is_err());
+        assert!(archival.get_object("post", Some("c")).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn undoes_list_item_and_required_field_edits() -> Result<()> {
+        let mut fs = MemoryFileSystem::default();
+        fs.write_str(MANIFEST_FILE_NAME, "upload_prefix = \"\"\n".to_string())?;
+        fs.write_str(
+            OBJECT_DEFINITION_FILE_NAME,
+            r#"
+            [post]
+            title = { required = true }
+            tags = ["string"]
+            "#
+            .to_string(),
+        )?;
+        fs.write_str("objects/post/a.toml", "tags = [\"x\", \"y\"]".to_string())?;
+        let archival = Archival::new(fs)?;
+        archival.enable_journal(journal::Journal::default());
+        let edit = |field: &str, value: &str| {
+            ArchivalEvent::EditField(EditFieldEvent {
+                object: "post".to_string(),
+                filename: "a".to_string(),
+                path: ValuePath::empty(),
+                field: field.to_string(),
+                value: Some(FieldValue::String(value.to_string())),
+                source: None,
+            })
+        };
+        archival.send_event(edit("tags.1", "z"), None)?;
+        archival.send_event(edit("title", "A"), None)?;
+        let values = |archival: &Archival<MemoryFileSystem>| {
+            archival.get_object("post", Some("a")).map(|o| o.values)
+        };
+        assert_eq!(
+            values(&archival)?.get("tags"),
+            Some(&FieldValue::List(vec![
+                FieldValue::String("x".to_string()),
+                FieldValue::String("z".to_string())
+            ]))
+        );
+
+        // The title had no value before, so undoing clears the required field.
+        assert!(archival.undo(None)?);
+        assert!(!values(&archival)?.contains_key("title"));
+        assert!(archival.undo(None)?);
+        assert_eq!(
+            values(&archival)?.get("tags"),
+            Some(&FieldValue::List(vec![
+                FieldValue::String("x".to_string()),
+                FieldValue::String("y".to_string())
+            ]))
+        );
+        while archival.redo(None)? {}
+        assert_eq!(
+            values(&archival)?.get("title"),
+            Some(&FieldValue::String("A".to_string()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn send_events_is_all_or_nothing() -> Result<()> {
+        let mut fs = MemoryFileSystem::default();
+        fs.write_str(MANIFEST_FILE_NAME, "upload_prefix = \"\"\n".to_string())?;
+        fs.write_str(
+            OBJECT_DEFINITION_FILE_NAME,
+            "[post]\ntitle = \"string\"\n[post.links]\nurl = \"string\"\n".to_string(),
+        )?;
+        fs.write_str("objects/post/a.toml", "title = \"A\"\n".to_string())?;
+        let archival = Archival::new(fs)?;
+        archival.enable_journal(journal::Journal::default());
+        let add = |filename: &str| {
+            ArchivalEvent::AddObject(AddObjectEvent {
+                object: "post".to_string(),
+                filename: filename.to_string(),
+                order: None,
+                values: vec![],
+            })
+        };
+        let edit_title = |filename: &str, title: &str| {
+            ArchivalEvent::EditField(EditFieldEvent {
+                object: "post".to_string(),
+                filename: filename.to_string(),
+                path: ValuePath::empty(),
+                field: "title".to_string(),
+                value: Some(FieldValue::String(title.to_string())),
+                source: None,
+            })
+        };
+        let title = |filename: &str| -> Result<Option<FieldValue>> {
+            Ok(archival
+                .get_object("post", Some(filename))?
+                .values
+                .get("title")
+                .cloned())
+        };
+        let original = archival.object_file("post", "a")?;
+
+        // Invalid events are caught before anything is applied.
+        let invalid = ArchivalEvent::EditField(EditFieldEvent {
+            object: "post".to_string(),
+            filename: "a".to_string(),
+            path: ValuePath::empty(),
+            field: "title".to_string(),
+            value: Some(FieldValue::Boolean(true)),
+            source: None,
+        });
+        assert!(archival.send_events(vec![add("b"), invalid], None).is_err());
+        assert!(archival.get_object("post", Some("b")).is_err());
+
+        // An event that fails as it's applied rolls back those before it.
+        assert!(archival
+            .send_events(vec![add("b"), edit_title("a", "Changed"), add("a")], None)
+            .is_err());
+        assert!(archival.get_object("post", Some("b")).is_err());
+        assert_eq!(archival.object_file("post", "a")?, original);
+        assert_eq!(title("a")?, Some(FieldValue::String("A".to_string())));
+        assert!(archival.journal().unwrap().undo_groups().is_empty());
+
+        // Later events see the changes of earlier ones.
+        let responses = archival.send_events(
+            vec![
+                add("b"),
+                edit_title("b", "B"),
+                ArchivalEvent::AddChild(AddChildEvent {
+                    object: "post".to_string(),
+                    filename: "b".to_string(),
+                    path: ValuePath::from_string("links"),
+                    values: vec![],
+                    index: None,
+                }),
+            ],
+            None,
+        )?;
+        assert!(matches!(
+            responses.last(),
+            Some(ArchivalEventResponse::Index(0))
+        ));
+        assert_eq!(title("b")?, Some(FieldValue::String("B".to_string())));
+        assert_eq!(archival.journal().unwrap().undo_groups().len(), 1);
+
+        assert!(archival.undo(None)?);
+        assert!(archival.get_object("post", Some("b")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn preview_events_leaves_files_unchanged() -> Result<()> {
+        let mut fs = MemoryFileSystem::default();
+        fs.write_str(MANIFEST_FILE_NAME, "upload_prefix = \"\"\n".to_string())?;
+        fs.write_str(
+            OBJECT_DEFINITION_FILE_NAME,
+            "[post]\ntitle = \"string\"\n".to_string(),
+        )?;
+        fs.write_str("objects/post/a.toml", "title = \"A\"\n".to_string())?;
+        let archival = Archival::new(fs)?;
+        let title = |filename: &str, title: &str| {
+            ArchivalEvent::EditField(EditFieldEvent {
+                object: "post".to_string(),
+                filename: filename.to_string(),
+                path: ValuePath::empty(),
+                field: "title".to_string(),
+                value: Some(FieldValue::String(title.to_string())),
+                source: None,
+            })
+        };
+        let original = archival.object_file("post", "a")?;
+        let previews = archival.preview_events(vec![
+            title("a", "Changed"),
+            ArchivalEvent::AddObject(AddObjectEvent {
+                object: "post".to_string(),
+                filename: "b".to_string(),
+                order: None,
+                values: vec![],
+            }),
+            title("b", "B"),
+        ])?;
+        assert_eq!(previews.len(), 2);
+        let title_of = |object: &Option<Object>| object.as_ref().map(|o| o.values["title"].clone());
+        assert_eq!(
+            title_of(&previews[0].before),
+            Some(FieldValue::String("A".to_string()))
+        );
+        assert_eq!(
+            title_of(&previews[0].after),
+            Some(FieldValue::String("Changed".to_string()))
+        );
+        assert_eq!(
+            (previews[1].filename.as_str(), &previews[1].before),
+            ("b", &None)
+        );
+        assert_eq!(
+            title_of(&previews[1].after),
+            Some(FieldValue::String("B".to_string()))
+        );
+        assert_eq!(archival.object_file("post", "a")?, original);
+        assert!(archival.get_object("post", Some("b")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn subscribers_see_changes() -> Result<()> {
+        let mut fs = MemoryFileSystem::default();
+        fs.write_str(MANIFEST_FILE_NAME, "upload_prefix = \"\"\n".to_string())?;
+        fs.write_str(
+            OBJECT_DEFINITION_FILE_NAME,
+            "[post]\ntemplate = \"post\"\ntitle = \"string\"\nbody = \"string\"\n".to_string(),
+        )?;
+        fs.write_str("objects/post/a.toml", "title = \"A\"\n".to_string())?;
+        fs.write_str("pages/post.liquid", "{{ post.title }}".to_string())?;
+        let archival = Archival::new(fs)?;
+        let changes = archival.subscribe();
+        let path = |p: &str| ValuePath::from_string(p);
+
+        archival.send_event(
+            ArchivalEvent::EditField(EditFieldEvent {
+                object: "post".to_string(),
+                filename: "a".to_string(),
+                path: ValuePath::empty(),
+                field: "title".to_string(),
+                value: Some(FieldValue::String("B".to_string())),
+                source: None,
+            }),
+            Some(BuildOptions::default()),
+        )?;
+        assert_eq!(
+            changes.try_recv()?,
+            changes::Change::FieldChanged {
+                object: "post".to_string(),
+                filename: "a".to_string(),
+                path: path("title"),
+            }
+        );
+        let changes::Change::BuildCompleted { changed, .. } = changes.try_recv()? else {
+            panic!("expected a build");
+        };
+        assert_eq!(
+            changed
+                .iter()
+                .map(|file| file.path.clone())
+                .collect::<Vec<_>>(),
+            vec![PathBuf::from("post/a.html")]
+        );
+
+        // Edits from outside are diffed against what was loaded.
+        archival.get_object("post", Some("a"))?;
+        archival.fs_mutex.with_fs(|fs| {
+            fs.write_str(
+                "objects/post/a.toml",
+                "title = \"B\"\nbody = \"new\"\n".to_string(),
+            )?;
+            fs.delete("objects/post/b.toml")
+        })?;
+        archival.files_changed([
+            PathBuf::from("objects/post/a.toml"),
+            PathBuf::from("objects/post/b.toml"),
+            PathBuf::from(OBJECT_DEFINITION_FILE_NAME),
+        ])?;
+        assert_eq!(
+            changes.try_iter().collect::<Vec<_>>(),
+            vec![
+                changes::Change::FieldChanged {
+                    object: "post".to_string(),
+                    filename: "a".to_string(),
+                    path: path("body"),
+                },
+                changes::Change::ObjectRemoved {
+                    object: "post".to_string(),
+                    filename: "b".to_string(),
+                },
+                changes::Change::DefinitionsReloaded,
+            ]
+        );
+        Ok(())
+    }
+
+    fn subscribed_site() -> Result<(
+        Archival<MemoryFileSystem>,
+        std::sync::mpsc::Receiver<changes::Change>,
+    )> {
+        let mut fs = MemoryFileSystem::default();
+        fs.write_str(MANIFEST_FILE_NAME, "upload_prefix = \"\"\n".to_string())?;
+        fs.write_str(
+            OBJECT_DEFINITION_FILE_NAME,
+            "[post]\ntitle = \"string\"\nbody = \"string\"\n".to_string(),
+        )?;
+        fs.write_str("objects/post/a.toml", "title = \"A\"\n".to_string())?;
+        fs.write_str("objects/post/b.toml", "title = \"B\"\n".to_string())?;
+        let archival = Archival::new(fs)?;
+        let changes = archival.subscribe();
+        Ok((archival, changes))
+    }
+
+    #[test]
+    fn written_files_notify_subscribers() -> Result<()> {
+        let (archival, changes) = subscribed_site()?;
+        archival.write_file("post", "a", "title = \"A\"\nbody = \"new\"\n".to_string())?;
+        archival.write_file("post", "c", "title = \"C\"\n".to_string())?;
+        assert_eq!(
+            changes.try_iter().collect::<Vec<_>>(),
+            vec![
+                changes::Change::FieldChanged {
+                    object: "post".to_string(),
+                    filename: "a".to_string(),
+                    path: ValuePath::from_string("body"),
+                },
+                changes::Change::ObjectAdded {
+                    object: "post".to_string(),
+                    filename: "c".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            archival.get_object("post", Some("a"))?.values.get("body"),
+            Some(&FieldValue::String("new".to_string()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn modified_manifests_notify_subscribers() -> Result<()> {
+        let (archival, changes) = subscribed_site()?;
+        archival.modify_manifest(|manifest| manifest.site_url = Some("https://a.b".to_string()))?;
+        assert_eq!(
+            changes.try_iter().collect::<Vec<_>>(),
+            vec![changes::Change::DefinitionsReloaded]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn deleted_objects_notify_subscribers() -> Result<()> {
+        let (archival, changes) = subscribed_site()?;
+        archival.delete_objects(["post"], Some(vec![ValuePath::from_string("post.b")]))?;
+        assert_eq!(
+            changes.try_iter().collect::<Vec<_>>(),
+            vec![changes::Change::ObjectRemoved {
+                object: "post".to_string(),
+                filename: "a".to_string(),
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn changed_definitions_reload_the_site() -> Result<()> {
+        let mut fs = MemoryFileSystem::default();
+        fs.write_str(MANIFEST_FILE_NAME, "upload_prefix = \"\"\n".to_string())?;
+        fs.write_str(
+            OBJECT_DEFINITION_FILE_NAME,
+            "[post]\ntitle = \"string\"\n".to_string(),
+        )?;
+        let archival = Archival::new(fs)?;
+        let before = archival.site();
+        archival.fs_mutex.with_fs(|fs| {
+            fs.write_str(
+                OBJECT_DEFINITION_FILE_NAME,
+                "[post]\ntitle = \"string\"\n[note]\nbody = \"string\"\n".to_string(),
+            )
+        })?;
+        archival.files_changed([PathBuf::from(OBJECT_DEFINITION_FILE_NAME)])?;
+        assert!(archival.get_object_definition("note").is_ok());
+        // Anyone still holding the old site keeps a consistent view of it.
+        assert!(!before.object_definitions.contains_key("note"));
+
+        // A definitions file that no longer parses keeps the loaded site.
+        archival
+            .fs_mutex
+            .with_fs(|fs| fs.write_str(OBJECT_DEFINITION_FILE_NAME, "[post\n".to_string()))?;
+        assert!(archival
+            .files_changed([PathBuf::from(OBJECT_DEFINITION_FILE_NAME)])
+            .is_err());
+        assert!(archival.get_object_definition("note").is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn stale_events_conflict() -> Result<()> {
+        let mut fs = MemoryFileSystem::default();
+        fs.write_str(MANIFEST_FILE_NAME, "upload_prefix = \"\"\n".to_string())?;
+        fs.write_str(
+            OBJECT_DEFINITION_FILE_NAME,
+            "[post]\ntitle = \"string\"\nbody = \"string\"\n[post.links]\nurl = \"string\"\n"
+                .to_string(),
+        )?;
+        fs.write_str("objects/post/a.toml", "title = \"A\"\n".to_string())?;
+        let archival = Archival::new(fs)?;
+        let edit = |field: &str, value: &str| {
+            ArchivalEvent::EditField(EditFieldEvent {
+                object: "post".to_string(),
+                filename: "a".to_string(),
+                path: ValuePath::empty(),
+                field: field.to_string(),
+                value: Some(FieldValue::String(value.to_string())),
+                source: None,
+            })
+        };
+        let send = |event: ArchivalEvent, expected: ExpectedVersion| {
+            archival.send_checked_event(CheckedEvent { event, expected }, None)
+        };
+        // Every read carries the object's version.
+        let version = archival.get_object("post", Some("a"))?.version();
+        assert_eq!(
+            archival.query("post").objects()?[0].version(),
+            version.clone()
+        );
+        send(
+            edit("title", "B"),
+            ExpectedVersion::Version(version.clone()),
+        )?;
+
+        // The first editor's version is now stale.
+        let error = send(
+            edit("title", "C"),
+            ExpectedVersion::Version(version.clone()),
+        )
+        .unwrap_err()
+        .downcast::<ConflictError>()?;
+        assert_eq!(
+            error,
+            ConflictError {
+                object: "post".to_string(),
+                filename: "a".to_string(),
+                path: ValuePath::from_string("title"),
+                current_version: archival.get_object("post", Some("a"))?.version(),
+                current_value: Some(FieldValue::String("B".to_string())),
+            }
+        );
+        // ...for every kind of event.
+        let reorder = ArchivalEvent::EditOrder(EditOrderEvent {
+            object: "post".to_string(),
+            filename: "a".to_string(),
+            order: Some(1.),
+            source: None,
+        });
+        assert!(send(reorder.clone(), ExpectedVersion::Version(version)).is_err());
+        let add_link = ArchivalEvent::AddChild(AddChildEvent {
+            object: "post".to_string(),
+            filename: "a".to_string(),
+            path: ValuePath::from_string("links"),
+            values: vec![],
+            index: None,
+        });
+        let no_links = || ExpectedVersion::Value(Some(FieldValue::Objects(vec![])));
+        send(add_link.clone(), no_links())?;
+        assert!(send(add_link, no_links()).is_err());
+        // Only events that change a value can expect one.
+        assert!(send(reorder, ExpectedVersion::Value(None)).is_err());
+
+        // Expecting a value only conflicts with changes to that value.
+        let expect = |value: Option<&str>| {
+            ExpectedVersion::Value(value.map(|v| FieldValue::String(v.to_string())))
+        };
+        assert!(send(edit("title", "C"), expect(Some("A"))).is_err());
+        send(edit("body", "text"), expect(None))?;
+        send(edit("title", "C"), expect(Some("B")))?;
+        assert_eq!(
+            archival.get_object("post", Some("a"))?.values.get("title"),
+            Some(&FieldValue::String("C".to_string()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn merges_object_files() -> Result<()> {
+        let mut fs = MemoryFileSystem::default();
+        fs.write_str(MANIFEST_FILE_NAME, "upload_prefix = \"\"\n".to_string())?;
+        fs.write_str(
+            OBJECT_DEFINITION_FILE_NAME,
+            "[post]\ntitle = \"string\"\nbody = \"string\"\n[post.links]\nurl = \"string\"\n"
+                .to_string(),
+        )?;
+        let archival = Archival::new(fs)?;
+        let base = "title = \"A\"\n\n[[links]]\nurl = \"one\"\n";
+        let ours = "title = \"B\"\n\n[[links]]\nurl = \"one\"\n";
+        let theirs = "title = \"A\"\nbody = \"x\"\n\n[[links]]\nurl = \"uno\"\n";
+        let merged = archival.merge_object_files("post", "a", base, ours, theirs)?;
+        assert!(merged.conflicts.is_empty());
+        let table: toml::Table = toml::from_str(&merged.contents)?;
+        assert_eq!(table["title"].as_str(), Some("B"));
+        assert_eq!(table["body"].as_str(), Some("x"));
+        assert_eq!(table["links"][0]["url"].as_str(), Some("uno"));
+
+        assert!(!merged.contents.contains("CONFLICT"));
+
+        let merged = archival.merge_object_files("post", "a", "", ours, "title = \"C\"\n")?;
+        assert_eq!(merged.conflicts.len(), 1);
+        assert_eq!(merged.conflicts[0].path.to_string(), "title");
+        // Conflicts are listed at the top, and the file still loads.
+        assert!(merged.contents.starts_with("# MERGE CONFLICTS"));
+        assert!(merged.contents.contains("#   theirs: C\n"));
+        let table: toml::Table = toml::from_str(&merged.contents)?;
+        assert_eq!(table["title"].as_str(), Some("B"));
+        Ok(())
+    }
+
+    #[test]
+    fn search_follows_events() -> Result<()> {
+        let mut fs = MemoryFileSystem::default();
+        fs.write_str(MANIFEST_FILE_NAME, "upload_prefix = \"\"\n".to_string())?;
+        fs.write_str(
+            OBJECT_DEFINITION_FILE_NAME,
+            "[post]\ntitle = \"string\"\nbody = \"markdown\"\n[post.links]\nlabel = \"string\"\n"
+                .to_string(),
+        )?;
+        fs.write_str(
+            "objects/post/a.toml",
+            "title = \"Tomato soup\"\nbody = \"Simmer the tomatoes.\"\n".to_string(),
+        )?;
+        fs.write_str(
+            "objects/post/b.toml",
+            "title = \"Bread\"\n[[links]]\nlabel = \"Goes well with soup\"\n".to_string(),
+        )?;
+        let archival = Archival::new(fs)?;
+        let found = |query: &str| -> Result<Vec<String>> {
+            Ok(archival
+                .search(query)?
+                .into_iter()
+                .map(|r| format!("{}.{}", r.filename, r.path))
+                .collect())
+        };
+        assert_eq!(found("soup")?, vec!["a.title", "b.links.0.label"]);
+        assert_eq!(found("tomat")?, vec!["a.title", "a.body"]);
+
+        archival.send_event(
+            ArchivalEvent::EditField(EditFieldEvent {
+                object: "post".to_string(),
+                filename: "b".to_string(),
+                path: ValuePath::from_string("links.0"),
+                field: "label".to_string(),
+                value: Some(FieldValue::String("Goes well with butter".to_string())),
+                source: None,
+            }),
+            None,
+        )?;
+        assert_eq!(found("soup")?, vec!["a.title"]);
+        archival.send_event(
+            ArchivalEvent::RenameObject(RenameObjectEvent {
+                object: "post".to_string(),
+                from: "a".to_string(),
+                to: "c".to_string(),
+            }),
+            None,
+        )?;
+        assert_eq!(found("soup")?, vec!["c.title"]);
+        archival.send_event(
+            ArchivalEvent::DeleteObject(DeleteObjectEvent {
+                object: "post".to_string(),
+                filename: "c".to_string(),
+                source: None,
+            }),
+            None,
+        )?;
+        assert!(found("soup")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn objects_and_children_use_their_declared_sort() -> Result<()> {
+        use liquid::ValueView;
+        let mut fs = MemoryFileSystem::default();
+        fs.write_str(MANIFEST_FILE_NAME, "upload_prefix = \"\"\n".to_string())?;
+        fs.write_str(
+            OBJECT_DEFINITION_FILE_NAME,
+            r#"
+            [post]
+            sort = ["-rating", "title"]
+            title = "string"
+            rating = "number"
+            [post.links]
+            sort = ["url"]
+            url = "string"
+            "#
+            .to_string(),
+        )?;
+        fs.write_str(
+            "objects/post/a.toml",
+            "title = \"b\"\nrating = 1\n[[links]]\nurl = \"z\"\n[[links]]\nurl = \"y\"\n"
+                .to_string(),
+        )?;
+        fs.write_str(
+            "objects/post/b.toml",
+            "title = \"a\"\nrating = 1\n".to_string(),
+        )?;
+        fs.write_str("objects/post/c.toml", "title = \"c\"\n".to_string())?;
+        fs.write_str(
+            "objects/post/d.toml",
+            "title = \"a\"\nrating = 1\n".to_string(),
+        )?;
+        let archival = Archival::new(fs)?;
+        let filenames = |objects: &ObjectMap| -> Vec<String> {
+            objects
+                .get("post")
+                .unwrap()
+                .into_iter()
+                .map(|o| o.filename.clone())
+                .collect()
+        };
+        assert_eq!(
+            filenames(&archival.get_objects()?),
+            vec!["b", "d", "a", "c"]
+        );
+        // An ad-hoc sort falls back to the declared one for ties.
+        let by_rating = archival.get_objects_sorted(|a, b| {
+            a.values
+                .contains_key("rating")
+                .cmp(&b.values.contains_key("rating"))
+        })?;
+        assert_eq!(filenames(&by_rating), vec!["c", "b", "d", "a"]);
+
+        // Children are stored as written, but render in their declared order.
+        let post = archival.get_object("post", Some("a"))?;
+        let definition = archival.get_object_definition("post")?;
+        assert_eq!(
+            ValuePath::from_string("links.0.url").get_in_object(&post),
+            Some(&FieldValue::String("z".to_string()))
+        );
+        let liquid = post.liquid_object(&definition, &archival.site().field_config);
+        let urls: Vec<String> = liquid
+            .as_object()
+            .unwrap()
+            .get("links")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .values()
+            .map(|link| {
+                link.as_object()
+                    .unwrap()
+                    .get("url")
+                    .unwrap()
+                    .to_kstr()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(urls, vec!["y", "z"]);
+        Ok(())
+    }
+
+    #[test]
+    fn remove_child() -> Result<()> {
+        let mut fs = MemoryFileSystem::default();
+        let zip = include_bytes!("../tests/fixtures/archival-website.zip");
+        unpack_zip(zip.to_vec(), &mut fs)?;
+        let archival = Archival::new(fs)?;
+        archival
+            .send_event(
+                ArchivalEvent::RemoveChild(RemoveChildEvent {
+                    object: "post".to_string(),
+                    filename: "a-post".to_string(),
+                    path: ValuePath::default()
+                        .append(ValuePath::key("links"))
+                        .append(ValuePath::index(0)),
+                    source: None,
+                }),
+                Some(BuildOptions::default()),
+            )
+            .unwrap();
+        // Sending an event should result in an updated fs
+        let post_html = archival
+            .fs_mutex
+            .with_fs(|fs| {
+                fs.read_to_string(
+                    archival
+                        .site()
+                        .manifest
+                        .build_dir
+                        .join(Path::new("post/a-post.html")),
+                )
+            })?
+            .unwrap();
+        println!("post: {}", post_html);
+        let rendered_links: Vec<_> = post_html.match_indices("<a href=").collect();
+        println!("LINKS: {:?}", rendered_links);
+        assert_eq!(rendered_links.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn modify_manifest() -> Result<()> {
+        let mut fs = MemoryFileSystem::default();
+        let zip = include_bytes!("../tests/fixtures/archival-website.zip");
+        unpack_zip(zip.to_vec(), &mut fs)?;
+        let archival = Archival::new(fs)?;
+        archival.modify_manifest(|m| {
+            m.site_url = Some("test.com".to_string());
+            m.prebuild = vec!["test".to_string()];
+        })?;
+        let output = archival.site().manifest.to_toml()?;
+        println!("{}", output);
+        assert!(output.contains("site_url = \"test.com\""));
+        // Doesn't fill defaults
+        assert!(!output.contains("objects_dir"));
+        assert!(!output.contains("objects"));
+        // Does show non-defaults
+        assert!(output.contains("prebuild = [\"test\"]"));
+        Ok(())
+    }
+
+    #[test]
+    fn bulk_delete_objects() -> Result<()> {
+        let mut fs = MemoryFileSystem::default();
+        let zip = include_bytes!("../tests/fixtures/archival-website.zip");
+        unpack_zip(zip.to_vec(), &mut fs)?;
+        let archival = Archival::new(fs)?;
+        archival.delete_objects(
+            vec!["section", "site"],
+            Some(vec![ValuePath::from_string("section.second")]),
+        )?;
+        // This should result in the relevant files being missing
+        let sections_dir = archival.site().manifest.objects_dir.join("section");
+        let sections = archival.fs_mutex.with_fs(|fs| {
+            fs.walk_dir(&sections_dir, false)
+                .map(|d| d.collect::<Vec<PathBuf>>())
+        })?;
+        println!("SECTIONS: {:?}", sections);
+        assert_eq!(sections.len(), 1);
+        let site_file_exists = archival
+            .fs_mutex
+            .with_fs(|fs| fs.exists(archival.site().manifest.objects_dir.join("site.toml")))?;
+        assert!(!site_file_exists);
+        Ok(())
+    }
+
+    #[test]
+    fn edit_enum() -> Result<()> {
+        let mut fs = MemoryFileSystem::default();
+        let zip = include_bytes!("../tests/fixtures/archival-website.zip");
+        unpack_zip(zip.to_vec(), &mut fs)?;
+        let archival = Archival::new(fs)?;
+        archival.send_event(
+            ArchivalEvent::EditField(EditFieldEvent {
+                object: "post".to_string(),
+                filename: "a-post".to_string(),
+                path: ValuePath::empty(),
+                field: "state".to_string(),
+                value: Some(FieldValue::Enum("draft".to_string())),
+                source: None,
+            }),
+            Some(BuildOptions::default()),
+        )?;
+        let post_html = archival
+            .fs_mutex
+            .with_fs(|fs| {
+                fs.read_to_string(
+                    archival
+                        .site()
+                        .manifest
+                        .build_dir
+                        .join(Path::new("post/a-post.html")),
+                )
+            })?
+            .unwrap();
+        println!("post: {}", post_html);
+        assert!(post_html.contains("State: draft"));
+        Ok(())
+    }
+    #[test]
+    fn edit_enum_fails_when_invalid() -> Result<()> {
+        let mut fs = MemoryFileSystem::default();
+        let zip = include_bytes!("../tests/fixtures/archival-website.zip");
+        unpack_zip(zip.to_vec(), &mut fs)?;
+        let archival = Archival::new(fs)?;
+        assert!(archival
+            .send_event(
+                ArchivalEvent::EditField(EditFieldEvent {
+                    object: "post".to_string(),
+                    filename: "a-post".to_string(),
+                    path: ValuePath::empty(),
+                    field: "state".to_string(),
+                    value: Some(FieldValue::Enum("poopoo".to_string())),
+                    source: None,
+                }),
+                Some(BuildOptions::default()),
+            )
+            .is_err());
+        Ok(())
+    }
 
     fn named_site_fs(manifest: &str, objects: &str, site_url: &str) -> Result<MemoryFileSystem> {
         let mut fs = MemoryFileSystem::default();
@@ -1413,13 +3386,13 @@ mod lib {
             LEGACY_OBJECT_DEFINITION_FILE_NAME,
             "legacy.example",
         )?;
-        let mut archival = Archival::new(fs)?;
+        let archival = Archival::new(fs)?;
         assert_eq!(
-            archival.site.manifest.site_url.as_deref(),
+            archival.site().manifest.site_url.as_deref(),
             Some("legacy.example")
         );
         assert_eq!(
-            archival.site.manifest.object_definition_file,
+            archival.site().manifest.object_definition_file,
             Path::new(LEGACY_OBJECT_DEFINITION_FILE_NAME).to_path_buf()
         );
         // Writes go back to the file the site already has rather than creating
@@ -1458,13 +3431,16 @@ mod lib {
             LEGACY_OBJECT_DEFINITION_FILE_NAME,
             "[legacy_only]\nname = \"string\"\n".to_string(),
         )?;
-        let mut archival = Archival::new(fs)?;
+        let archival = Archival::new(fs)?;
         assert_eq!(
-            archival.site.manifest.site_url.as_deref(),
+            archival.site().manifest.site_url.as_deref(),
             Some("canonical.example")
         );
-        assert!(archival.site.object_definitions.contains_key("site"));
-        assert!(!archival.site.object_definitions.contains_key("legacy_only"));
+        assert!(archival.site().object_definitions.contains_key("site"));
+        assert!(!archival
+            .site()
+            .object_definitions
+            .contains_key("legacy_only"));
         archival.modify_manifest(|m| m.site_url = Some("canonical-edited.example".to_string()))?;
         archival.fs_mutex.with_fs(|fs| {
             let legacy = fs
@@ -1501,11 +3477,11 @@ mod lib {
         )?;
         let archival = Archival::new(fs)?;
         assert_eq!(
-            archival.site.manifest.object_definition_file,
+            archival.site().manifest.object_definition_file,
             Path::new("custom_objects.toml").to_path_buf()
         );
-        assert!(archival.site.object_definitions.contains_key("custom"));
-        let written = archival.site.manifest.to_toml()?;
+        assert!(archival.site().object_definitions.contains_key("custom"));
+        let written = archival.site().manifest.to_toml()?;
         assert!(
             written.contains("object_file = \"custom_objects.toml\""),
             "{}",
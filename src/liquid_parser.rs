@@ -1,8 +1,11 @@
+use crate::filters;
 use crate::liquid_rewrite::rewrite_template;
 use crate::tags::include::IncludeTag;
 use crate::tags::output::{OutputContext, OutputTag};
 use crate::tags::render::RenderTag;
-use crate::{page::TemplateType, tags::layout::LayoutTag, util::path_to_slash, FileSystemAPI};
+use crate::{
+    page::TemplateType, tags::layout::LayoutTag, util::path_to_slash, ArchivalError, FileSystemAPI,
+};
 use anyhow::Result;
 use liquid_core::partials::{EagerCompiler, PartialCompiler, PartialSource};
 use liquid_core::runtime::PartialStore;
@@ -10,7 +13,11 @@ use liquid_core::Language;
 use once_cell::sync::Lazy;
 use regex::Regex;
 use std::sync::Arc;
-use std::{borrow::Cow, collections::HashMap, path::Path};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 #[cfg(feature = "verbose-logging")]
 use tracing::debug;
 use tracing::{error, warn};
@@ -20,6 +27,17 @@ pub static PARTIAL_FILE_NAME_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^_(.+)\
 #[derive(Default, Debug, Clone)]
 pub(crate) struct ArchivalPartialSource {
     partials: HashMap<String, String>,
+    /// Plugin modules travel with the partials because they are part of the
+    /// parser too: a changed plugin has to invalidate it the same way a changed
+    /// partial does.
+    pub(crate) plugins: Vec<PluginSource>,
+}
+
+/// The bytes of a plugin module (see `crate::plugins`), named by its path.
+#[derive(Debug, Clone)]
+pub(crate) struct PluginSource {
+    pub name: String,
+    pub bytes: Vec<u8>,
 }
 
 /// The name a template file is included by: its path relative to the directory
@@ -78,7 +96,24 @@ impl ArchivalPartialSource {
         if let Some(path) = pages_path {
             add(path, true)?;
         }
-        Ok(Self { partials })
+        Ok(Self {
+            partials,
+            plugins: vec![],
+        })
+    }
+
+    /// Reads the plugin modules listed in the manifest.
+    pub fn with_plugins(mut self, plugins: &[PathBuf], fs: &impl FileSystemAPI) -> Result<Self> {
+        for path in plugins {
+            let bytes = fs.read(path)?.ok_or_else(|| {
+                ArchivalError::new(&format!("plugin {} does not exist", path.display()))
+            })?;
+            self.plugins.push(PluginSource {
+                name: path_to_slash(path),
+                bytes,
+            });
+        }
+        Ok(self)
     }
 }
 
@@ -97,6 +132,12 @@ impl ArchivalPartialSource {
             hasher.write(self.partials[key].as_bytes());
             hasher.write(&[0]);
         }
+        for plugin in &self.plugins {
+            hasher.write(plugin.name.as_bytes());
+            hasher.write(&[0]);
+            hasher.write(&plugin.bytes);
+            hasher.write(&[0]);
+        }
         hasher.finish()
     }
 }
@@ -137,15 +178,17 @@ pub fn get(
     build_with_partials(source)
 }
 
-/// Reads all partial/layout sources and returns their combined hash. Reading
-/// and hashing sources is much cheaper than compiling them, so builds use this
-/// to decide whether a cached parser can be reused.
+/// Reads all partial/layout sources and plugin modules and returns their
+/// combined hash. Reading and hashing sources is much cheaper than compiling
+/// them, so builds use this to decide whether a cached parser can be reused.
 pub(crate) fn partials_hash(
     pages_path: Option<&Path>,
     layout_path: Option<&Path>,
+    plugins: &[PathBuf],
     fs: &impl FileSystemAPI,
 ) -> Result<(ArchivalPartialSource, u64)> {
-    let source = ArchivalPartialSource::new(pages_path, layout_path, fs)?;
+    let source =
+        ArchivalPartialSource::new(pages_path, layout_path, fs)?.with_plugins(plugins, fs)?;
     let hash = source.source_hash();
     Ok((source, hash))
 }
@@ -160,17 +203,35 @@ pub(crate) fn build_with_partials(source: ArchivalPartialSource) -> Result<liqui
 pub(crate) fn build_with_output_context(
     source: ArchivalPartialSource,
 ) -> Result<(liquid::Parser, Arc<OutputContext>)> {
+    #[cfg(feature = "plugins")]
+    let plugins = crate::plugins::load(&source.plugins)?;
+    #[cfg(not(feature = "plugins"))]
+    if !source.plugins.is_empty() {
+        return Err(ArchivalError::new(
+            "this site uses plugins, but archival was built without the `plugins` feature",
+        )
+        .into());
+    }
     let ctx = Arc::new(OutputContext::default());
     let partials = LanguageCapturingCompiler {
         inner: EagerCompiler::new(source),
         ctx: Arc::clone(&ctx),
     };
-    let parser = liquid::ParserBuilder::with_stdlib()
+    let parser = filters::archival_filters(&ctx)
+        .into_iter()
+        .fold(liquid::ParserBuilder::with_stdlib(), |parser, filter| {
+            parser.filter(filter)
+        })
         .tag(LayoutTag)
         .tag(IncludeTag)
         .tag(RenderTag)
-        .tag(OutputTag::new(Arc::clone(&ctx)))
-        .partials(partials);
+        .tag(OutputTag::new(Arc::clone(&ctx)));
+    // Plugins are registered last, so a plugin can replace a built-in filter.
+    #[cfg(feature = "plugins")]
+    let parser = plugins
+        .iter()
+        .fold(parser, |parser, plugin| plugin.register(parser));
+    let parser = parser.partials(partials);
     Ok((parser.build()?, ctx))
 }
 
@@ -0,0 +1,549 @@
+//! Completion in liquid templates: filter names after a `|` (see
+//! [`super::filters`]), partial names in `include`, `render` and `layout`, and
+//! otherwise the variables a page is rendered with.
+//!
+//! Variables mirror the context `crate::page::build_context` builds: every
+//! object type under `objects`, each also as a global named by its plural (or
+//! singular, for a root object), and the fields, children and file properties
+//! of their instances. Template pages also see the object they render.
+
+use super::documents::Document;
+use super::filters;
+use super::workspace::SiteState;
+use crate::fields::{markdown::meta_field_name, FieldType};
+use crate::file_system_stdlib::NativeFileSystem;
+use crate::liquid_parser::ArchivalPartialSource;
+use crate::object_definition::{ObjectDefinition, ObjectDefinitions};
+use crate::util::path_to_slash;
+use liquid_core::partials::PartialSource;
+use lsp_types::{
+    CompletionItem, CompletionItemKind, Documentation, MarkupContent, MarkupKind, Position,
+};
+use once_cell::sync::Lazy;
+use pluralizer::pluralize;
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::Path;
+use tracing::warn;
+
+/// The derived properties of a markdown field, see [`crate::fields::markdown`].
+const MARKDOWN_PROPERTIES: [(&str, &str); 4] = [
+    ("toc", "headings, each with level, title, id and children"),
+    ("excerpt", "string"),
+    ("word_count", "number"),
+    ("reading_time", "number"),
+];
+
+/// The properties of a file field, as [`crate::fields::File`] renders them.
+const FILE_PROPERTIES: [&str; 7] = [
+    "url",
+    "sha",
+    "filename",
+    "mime",
+    "name",
+    "description",
+    "display_type",
+];
+
+/// The name of the partial being typed, up to the cursor.
+static PARTIAL_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"\{%-?\s*(?:include|render|layout)\s+["']([^"'%]*)$"#).unwrap());
+
+/// A loop that binds a name to each item of a variable.
+static LOOP_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{%-?\s*(?:for|tablerow)\s+(\w+)\s+in\s+([\w.]+)").unwrap());
+
+/// What a template in a site can see, besides filters and partials.
+pub(crate) struct Scope<'a> {
+    definitions: &'a ObjectDefinitions,
+    /// The object types stored as a list rather than as a single root object.
+    lists: HashSet<&'a str>,
+    /// The definition this file is the template page of.
+    template: Option<&'a ObjectDefinition>,
+    has_data: bool,
+}
+
+impl<'a> Scope<'a> {
+    /// The scope of `file`, or `None` when its site did not load.
+    pub fn new(state: &'a SiteState, file: &Path) -> Option<Self> {
+        let site = state.site.as_ref()?;
+        let manifest = &site.manifest;
+        let objects_dir = state.root.join(&manifest.objects_dir);
+        // Like builds, a type with neither a directory nor a root file is an
+        // empty list.
+        let lists = site
+            .object_definitions
+            .keys()
+            .filter(|name| {
+                objects_dir.join(name).is_dir()
+                    || !objects_dir.join(format!("{name}.toml")).exists()
+            })
+            .map(|name| &name[..])
+            .collect();
+        let template = file
+            .strip_prefix(state.root.join(&manifest.pages_dir))
+            .ok()
+            .and_then(|relative| {
+                let name = path_to_slash(relative);
+                let name = name.strip_suffix(".liquid")?;
+                site.object_definitions
+                    .values()
+                    .find(|definition| definition.template.as_deref() == Some(name))
+            });
+        Some(Self {
+            definitions: &site.object_definitions,
+            lists,
+            template,
+            has_data: state.root.join(&manifest.data_dir).is_dir(),
+        })
+    }
+}
+
+/// A value in a template context, as far as completion needs to know it.
+#[derive(Clone, Copy)]
+enum Shape<'a> {
+    Root,
+    Objects,
+    /// An object, or a list of them when `list` is set. Top level objects also
+    /// have an `order`, and the object a template page renders also has an
+    /// `object_name`.
+    Instance {
+        definition: &'a ObjectDefinition,
+        list: bool,
+        top_level: bool,
+        template: bool,
+    },
+    File,
+    /// The derived properties beside a markdown field.
+    MarkdownMeta,
+}
+
+struct Member<'a> {
+    name: String,
+    kind: CompletionItemKind,
+    detail: Option<String>,
+    description: Option<&'a str>,
+    /// What the member is, when it has members of its own.
+    shape: Option<Shape<'a>>,
+}
+
+impl<'a> Member<'a> {
+    fn new(name: impl Into<String>, kind: CompletionItemKind) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            detail: None,
+            description: None,
+            shape: None,
+        }
+    }
+
+    fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    fn shape(mut self, shape: Shape<'a>) -> Self {
+        self.shape = Some(shape);
+        self
+    }
+
+    fn instance(definition: &'a ObjectDefinition, list: bool, top_level: bool) -> Shape<'a> {
+        Shape::Instance {
+            definition,
+            list,
+            top_level,
+            template: false,
+        }
+    }
+
+    fn item(self) -> CompletionItem {
+        CompletionItem {
+            label: self.name,
+            kind: Some(self.kind),
+            detail: self.detail,
+            documentation: self.description.map(|description| {
+                Documentation::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: description.to_string(),
+                })
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+fn list_detail(definition: &ObjectDefinition, list: bool) -> String {
+    match list {
+        true => format!("list of {}", definition.name),
+        false => definition.name.clone(),
+    }
+}
+
+impl<'a> Scope<'a> {
+    /// Every member of a value of `shape`. Loop variables are members of the
+    /// root, bound to the items of what they loop over.
+    fn members(&self, shape: Shape<'a>, loops: &[(&str, &str)]) -> Vec<Member<'a>> {
+        let mut members = vec![];
+        match shape {
+            Shape::Root => {
+                members.push(
+                    Member::new("objects", CompletionItemKind::MODULE)
+                        .detail("every object, by type")
+                        .shape(Shape::Objects),
+                );
+                for definition in self.definitions.values() {
+                    let list = self.lists.contains(&definition.name[..]);
+                    let name = pluralize(&definition.name, if list { 2 } else { 1 }, false);
+                    members.push(Member {
+                        description: definition.description.as_deref(),
+                        ..Member::new(name, CompletionItemKind::VARIABLE)
+                            .detail(list_detail(definition, list))
+                            .shape(Member::instance(definition, list, true))
+                    });
+                }
+                if let Some(definition) = self.template {
+                    members.push(Member {
+                        description: definition.description.as_deref(),
+                        ..Member::new(&definition.name, CompletionItemKind::VARIABLE)
+                            .detail(format!("the {} this page renders", definition.name))
+                            .shape(Shape::Instance {
+                                definition,
+                                list: false,
+                                top_level: true,
+                                template: true,
+                            })
+                    });
+                }
+                members.push(
+                    Member::new("page", CompletionItemKind::VARIABLE)
+                        .detail("the name of the page being rendered"),
+                );
+                members.push(
+                    Member::new("site_url", CompletionItemKind::VARIABLE).detail("the site's url"),
+                );
+                if self.has_data {
+                    members.push(
+                        Member::new("data", CompletionItemKind::MODULE).detail("the data files"),
+                    );
+                }
+                for (at, (name, path)) in loops.iter().enumerate() {
+                    // Each loop only sees the ones before it, which also keeps a
+                    // loop over itself from recursing.
+                    let item = self
+                        .resolve(path, &loops[..at])
+                        .and_then(|shape| match shape {
+                            Shape::Instance {
+                                definition,
+                                list: true,
+                                top_level,
+                                ..
+                            } => Some(Member::instance(definition, false, top_level)),
+                            _ => None,
+                        });
+                    let mut member = Member::new(*name, CompletionItemKind::VARIABLE)
+                        .detail(format!("in {path}"));
+                    member.shape = item;
+                    members.push(member);
+                }
+            }
+            Shape::Objects => {
+                for definition in self.definitions.values() {
+                    let list = self.lists.contains(&definition.name[..]);
+                    members.push(Member {
+                        description: definition.description.as_deref(),
+                        ..Member::new(&definition.name, CompletionItemKind::VARIABLE)
+                            .detail(list_detail(definition, list))
+                            .shape(Member::instance(definition, list, true))
+                    });
+                }
+            }
+            Shape::Instance {
+                definition,
+                list: true,
+                top_level,
+                ..
+            } => {
+                for end in ["first", "last"] {
+                    members.push(
+                        Member::new(end, CompletionItemKind::PROPERTY)
+                            .detail(definition.name.clone())
+                            .shape(Member::instance(definition, false, top_level)),
+                    );
+                }
+                members.push(Member::new("size", CompletionItemKind::PROPERTY).detail("number"));
+            }
+            Shape::Instance {
+                definition,
+                list: false,
+                top_level,
+                template,
+            } => {
+                for (name, field) in &definition.fields {
+                    // Secret fields are never added to template contexts.
+                    if field.r#type.is_secret() {
+                        continue;
+                    }
+                    let mut member = Member {
+                        description: field.description.as_deref(),
+                        ..Member::new(name, CompletionItemKind::FIELD).detail(field.r#type.as_str())
+                    };
+                    if matches!(
+                        field.r#type.base_type(),
+                        FieldType::Image | FieldType::Video | FieldType::Audio | FieldType::Upload
+                    ) {
+                        member.shape = Some(Shape::File);
+                    }
+                    members.push(member);
+                    let meta = meta_field_name(name);
+                    if matches!(field.r#type.base_type(), FieldType::Markdown)
+                        && !definition.fields.contains_key(&meta)
+                        && !definition.children.contains_key(&meta)
+                    {
+                        members.push(
+                            Member::new(meta, CompletionItemKind::PROPERTY)
+                                .detail(format!("derived from {name}"))
+                                .shape(Shape::MarkdownMeta),
+                        );
+                    }
+                }
+                for (name, child) in &definition.children {
+                    members.push(Member {
+                        description: child.description.as_deref(),
+                        ..Member::new(name, CompletionItemKind::FIELD)
+                            .detail(list_detail(child, true))
+                            .shape(Member::instance(child, true, false))
+                    });
+                }
+                if top_level {
+                    members
+                        .push(Member::new("path", CompletionItemKind::PROPERTY).detail("string"));
+                    members
+                        .push(Member::new("order", CompletionItemKind::PROPERTY).detail("number"));
+                }
+                if template {
+                    members.push(
+                        Member::new("object_name", CompletionItemKind::PROPERTY).detail("string"),
+                    );
+                }
+            }
+            Shape::MarkdownMeta => {
+                for (property, detail) in MARKDOWN_PROPERTIES {
+                    members
+                        .push(Member::new(property, CompletionItemKind::PROPERTY).detail(detail));
+                }
+            }
+            Shape::File => {
+                for property in FILE_PROPERTIES {
+                    members
+                        .push(Member::new(property, CompletionItemKind::PROPERTY).detail("string"));
+                }
+            }
+        }
+        members
+    }
+
+    /// What the dotted `path` refers to, if completion knows its members.
+    fn resolve(&self, path: &str, loops: &[(&str, &str)]) -> Option<Shape<'a>> {
+        let mut shape = Shape::Root;
+        for segment in path.split('.') {
+            shape = self
+                .members(shape, loops)
+                .into_iter()
+                // Later loop variables shadow earlier names.
+                .rfind(|member| member.name == segment)?
+                .shape?;
+        }
+        Some(shape)
+    }
+}
+
+/// The dotted variable being typed at the end of `before`, if the cursor is in
+/// an expression (`{{ post.ti`, `{% if post.ti`) rather than in a tag name.
+fn variable_prefix(before: &str) -> Option<&str> {
+    let open = before.rfind('{')?;
+    if before[open..].contains('}') {
+        return None;
+    }
+    let start = before
+        .rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+        .map_or(0, |at| at + 1);
+    if start <= open {
+        return None;
+    }
+    let markup = &before[open + 1..start];
+    if let Some(tag) = markup.strip_prefix('%') {
+        if tag.trim_start_matches('-').trim().is_empty() {
+            return None;
+        }
+    }
+    Some(&before[start..])
+}
+
+pub(crate) fn completions(
+    doc: &Document,
+    position: Position,
+    site: Option<&SiteState>,
+    file: &Path,
+) -> Vec<CompletionItem> {
+    let offset = doc.index.offset(&doc.text, position);
+    if filters::filter_prefix(&doc.text, offset).is_some() {
+        return filters::completions(doc, position);
+    }
+    let before = &doc.text[..offset];
+    let Some(site) = site else {
+        return vec![];
+    };
+    if let Some(partial) = PARTIAL_RE.captures(before) {
+        return partials(site)
+            .into_iter()
+            .filter(|name| name.starts_with(&partial[1]))
+            .map(|name| Member::new(name, CompletionItemKind::FILE).item())
+            .collect();
+    }
+    let (Some(typed), Some(scope)) = (variable_prefix(before), Scope::new(site, file)) else {
+        return vec![];
+    };
+    let loops: Vec<(&str, &str)> = LOOP_RE
+        .captures_iter(before)
+        .map(|captures| {
+            let (_, [name, path]) = captures.extract();
+            (name, path)
+        })
+        .collect();
+    let (shape, prefix) = match typed.rsplit_once('.') {
+        Some((path, prefix)) => match scope.resolve(path, &loops) {
+            Some(shape) => (shape, prefix),
+            None => return vec![],
+        },
+        None => (Shape::Root, typed),
+    };
+    let mut seen = HashSet::new();
+    let mut members = scope.members(shape, &loops);
+    // A loop variable replaces whatever it shadows.
+    members.reverse();
+    let mut items: Vec<CompletionItem> = members
+        .into_iter()
+        .filter(|member| member.name.starts_with(prefix) && seen.insert(member.name.clone()))
+        .map(Member::item)
+        .collect();
+    items.reverse();
+    items
+}
+
+/// The names of the partials and layouts in `site`, read afresh because they
+/// change as the site is edited.
+fn partials(site: &SiteState) -> Vec<String> {
+    let Some(manifest) = site.manifest() else {
+        return vec![];
+    };
+    let fs = NativeFileSystem::new(&site.root);
+    let source = match ArchivalPartialSource::new(
+        Some(&manifest.pages_dir),
+        Some(&manifest.layout_dir),
+        &fs,
+    ) {
+        Ok(source) => source,
+        Err(err) => {
+            warn!("{}: could not read partials: {err}", site.root.display());
+            return vec![];
+        }
+    };
+    let mut names: Vec<String> = source.names().into_iter().map(str::to_string).collect();
+    names.sort();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::workspace::Workspace;
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fixture() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/website")
+    }
+
+    fn labels_in(file: &str, text: &str) -> Vec<String> {
+        let file = fixture().join(file);
+        let mut workspace = Workspace::default();
+        let site = workspace.site_for(&file);
+        let doc = Document::new(text.to_string());
+        let end = doc.index.position(&doc.text, text.len());
+        completions(&doc, end, site, &file)
+            .into_iter()
+            .map(|c| c.label)
+            .collect()
+    }
+
+    fn labels(text: &str) -> Vec<String> {
+        labels_in("pages/index.liquid", text)
+    }
+
+    #[test]
+    fn completes_globals_and_object_types() {
+        let globals = labels("{{ ");
+        for name in ["objects", "sections", "subpages", "page", "site_url"] {
+            assert!(globals.contains(&name.to_string()), "{name} missing");
+        }
+        assert_eq!(labels("{% if sec"), ["sections"]);
+        assert_eq!(labels("{{ objects."), ["section", "subpage"]);
+        assert_eq!(labels("{{ objects.sections.first."), Vec::<String>::new());
+        assert_eq!(labels("{{ objects.section."), ["first", "last", "size"]);
+    }
+
+    #[test]
+    fn completes_fields_through_loops() {
+        assert_eq!(
+            labels("{% for s in sections %}{{ s."),
+            ["name", "body", "body_meta", "path", "order"]
+        );
+        assert_eq!(
+            labels("{{ sections.first.body_meta."),
+            ["toc", "excerpt", "word_count", "reading_time"]
+        );
+        assert_eq!(labels("{{ sections.first.na"), ["name"]);
+        assert!(labels("{% for s in nothing %}{{ s.").is_empty());
+        // Tag names are not variables.
+        assert!(labels("{% fo").is_empty());
+    }
+
+    #[test]
+    fn completes_the_object_a_template_page_renders() {
+        let labels = labels_in("pages/subpage.liquid", "{{ subpage.");
+        assert_eq!(labels, ["name", "path", "order", "object_name"]);
+    }
+
+    #[test]
+    fn completes_partial_names() {
+        assert_eq!(labels("{% layout 'th"), ["theme"]);
+        assert_eq!(labels("{% include \""), ["theme"]);
+        assert!(labels("{% render \"nope").is_empty());
+    }
+
+    #[test]
+    fn completes_children_and_file_properties() {
+        let definitions = ObjectDefinition::from_source(
+            "[post]\ncover = \"image\"\nsecret = \"secret\"\n[post.links]\nurl = \"string\"\n",
+            &Default::default(),
+        )
+        .unwrap();
+        let scope = Scope {
+            definitions: &definitions,
+            lists: HashSet::from(["post"]),
+            template: None,
+            has_data: false,
+        };
+        let names = |path: &str| -> Vec<String> {
+            let shape = scope.resolve(path, &[]).unwrap();
+            scope
+                .members(shape, &[])
+                .into_iter()
+                .map(|m| m.name)
+                .collect()
+        };
+        assert_eq!(names("posts.first"), ["cover", "links", "path", "order"]);
+        assert_eq!(names("posts.first.cover"), FILE_PROPERTIES);
+        assert_eq!(names("posts.first.links.last"), ["url"]);
+    }
+}
@@ -0,0 +1,156 @@
+//! Completion of filter names after a `|`.
+//!
+//! The catalog is read from the reflection of the filters a build registers
+//! (see `crate::filters`), so names, signatures and descriptions are always
+//! those of the archival this server was built with.
+
+use super::documents::Document;
+use crate::filters;
+use crate::tags::output::OutputContext;
+use liquid_core::parser::ParameterReflection;
+use lsp_types::{
+    CompletionItem, CompletionItemKind, Documentation, MarkupContent, MarkupKind, Position,
+};
+use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+pub(crate) struct FilterDoc {
+    pub name: String,
+    pub description: String,
+    pub positional: &'static [ParameterReflection],
+    pub keyword: &'static [ParameterReflection],
+}
+
+impl FilterDoc {
+    /// How the filter is called, e.g. `truncate_html: [length], [ellipsis]`.
+    pub fn signature(&self) -> String {
+        let params: Vec<String> = self
+            .positional
+            .iter()
+            .map(|p| p.name.to_string())
+            .chain(self.keyword.iter().map(|p| format!("{}: …", p.name)))
+            .zip(self.positional.iter().chain(self.keyword))
+            .map(|(text, p)| match p.is_optional {
+                true => format!("[{text}]"),
+                false => text,
+            })
+            .collect();
+        match params.is_empty() {
+            true => self.name.clone(),
+            false => format!("{}: {}", self.name, params.join(", ")),
+        }
+    }
+
+    fn documentation(&self) -> String {
+        let mut doc = self.description.clone();
+        for p in self.positional.iter().chain(self.keyword) {
+            doc.push_str(&format!("\n- `{}`: {}", p.name, p.description));
+        }
+        doc
+    }
+}
+
+/// Every filter, by name. Archival's filters replace stdlib filters of the
+/// same name, as they do in the parser.
+pub(crate) static CATALOG: Lazy<BTreeMap<String, FilterDoc>> = Lazy::new(|| {
+    // Only reflection is read, so the filters never see this context.
+    let ctx = Arc::new(OutputContext::default());
+    filters::stdlib_filters()
+        .into_iter()
+        .chain(filters::archival_filters(&ctx))
+        .map(|filter| {
+            let reflection = filter.reflection();
+            let doc = FilterDoc {
+                name: reflection.name().to_string(),
+                description: reflection.description().to_string(),
+                positional: reflection.positional_parameters(),
+                keyword: reflection.keyword_parameters(),
+            };
+            (doc.name.clone(), doc)
+        })
+        .collect()
+});
+
+/// The partial filter name being typed at `offset`, if the cursor is in the
+/// filter position of a liquid expression (`{{ x | da`).
+pub(super) fn filter_prefix(text: &str, offset: usize) -> Option<&str> {
+    let before = &text[..offset];
+    let open = before.rfind(['{'])?;
+    if before[open..].contains('}') {
+        return None;
+    }
+    let pipe = before.rfind('|').filter(|pipe| *pipe > open)?;
+    let typed = before[pipe + 1..].trim_start();
+    typed
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        .then_some(typed)
+}
+
+pub(crate) fn completions(doc: &Document, position: Position) -> Vec<CompletionItem> {
+    let offset = doc.index.offset(&doc.text, position);
+    let Some(prefix) = filter_prefix(&doc.text, offset) else {
+        return vec![];
+    };
+    CATALOG
+        .values()
+        .filter(|filter| filter.name.starts_with(prefix))
+        .map(|filter| CompletionItem {
+            label: filter.name.clone(),
+            kind: Some(CompletionItemKind::FUNCTION),
+            detail: Some(filter.signature()),
+            documentation: Some(Documentation::MarkupContent(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: filter.documentation(),
+            })),
+            ..Default::default()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(text: &str) -> Vec<String> {
+        let doc = Document::new(text.to_string());
+        let end = doc.index.position(&doc.text, text.len());
+        completions(&doc, end)
+            .into_iter()
+            .map(|c| c.label)
+            .collect()
+    }
+
+    #[test]
+    fn catalog_includes_stdlib_and_archival_filters() {
+        for name in ["upcase", "where", "where_exp", "sort_by", "date_to_rfc822"] {
+            assert!(CATALOG.contains_key(name), "{name} missing");
+        }
+        assert_eq!(
+            CATALOG["truncate_html"].signature(),
+            "truncate_html: [length], [ellipsis]"
+        );
+        assert_eq!(CATALOG["sort_by"].signature(), "sort_by: keys");
+        // Stdlib filters keep their names and behavior.
+        assert!(!CATALOG["strip_html"].description.contains("whitespace"));
+        assert!(CATALOG["html_to_text"].description.contains("whitespace"));
+    }
+
+    #[test]
+    fn completes_filter_names_after_a_pipe() {
+        assert_eq!(
+            labels("{{ post.date | date_to"),
+            ["date_to_rfc822", "date_to_xmlschema"]
+        );
+        assert!(labels("{% assign x = a | ").contains(&"slugify".to_string()));
+        assert!(labels("{{ a | upcase | sl").contains(&"slugify".to_string()));
+    }
+
+    #[test]
+    fn does_not_complete_outside_filters() {
+        assert!(labels("{{ post.da").is_empty());
+        assert!(labels("{{ a | b }} text | sl").is_empty());
+        assert!(labels("{{ a | truncate: 5").is_empty());
+    }
+}
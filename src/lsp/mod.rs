@@ -2,8 +2,10 @@
 //! `archival lsp`.
 
 mod builtin;
+mod completion;
 mod diagnostics;
 mod documents;
+mod filters;
 mod objects;
 mod server;
 mod toml_diag;
@@ -2,8 +2,10 @@
 //! instance of.
 
 use super::documents::Document;
-use super::toml_diag::{child_tables, diagnostic, error_at_key, error_at_named_key, syntax_error};
-use crate::fields::FieldType;
+use super::toml_diag::{
+    child_tables, diagnostic, error_at_key, error_at_named_key, error_at_path, syntax_error,
+};
+use crate::fields::{FieldType, FieldValueValidationError};
 use crate::manifest::EditorTypes;
 use crate::object::Object;
 use crate::object_definition::ObjectDefinition;
@@ -13,7 +15,7 @@ use std::path::Path;
 use toml_edit::Table;
 
 /// Checks `doc` against `definition`, reporting whatever would stop archival
-/// reading it.
+/// reading it, and values that break their field's constraints.
 pub(crate) fn validate(
     doc: &Document,
     path: &Path,
@@ -28,16 +30,47 @@ pub(crate) fn validate(
     let mut found = unknown_fields(doc, definition, custom_types);
     found.extend(bad_order(doc, &table));
     // Archival stops at the first bad value, so this adds at most one more.
-    if let Err(err) = Object::from_table(definition, path, &table, custom_types, false) {
-        found.push(error_at_named_key(
+    match Object::from_table(definition, path, &table, custom_types, false) {
+        Ok(_) => {}
+        // Reported along with every other broken constraint, below.
+        Err(err)
+            if matches!(
+                err.downcast_ref::<FieldValueValidationError>(),
+                Some(FieldValueValidationError::ConstraintViolation(..))
+            ) => {}
+        Err(err) => found.push(error_at_named_key(
             doc,
             err.to_string(),
             DiagnosticSeverity::ERROR,
-        ));
+        )),
+    }
+    if let Ok(object) = Object::from_table(definition, path, &table, custom_types, true) {
+        found.extend(broken_constraints(doc, definition, &object));
     }
     found
 }
 
+/// Values that break their field's constraints. Archival still builds with
+/// these, but won't save them.
+fn broken_constraints(
+    doc: &Document,
+    definition: &ObjectDefinition,
+    object: &Object,
+) -> Vec<Diagnostic> {
+    definition
+        .constraint_violations(&object.values)
+        .into_iter()
+        .map(|(path, violation)| {
+            error_at_path(
+                doc,
+                &path,
+                format!("`{path}` {violation}."),
+                DiagnosticSeverity::WARNING,
+            )
+        })
+        .collect()
+}
+
 /// `order` sorts objects within their directory, and archival ignores it when
 /// it is not a number, leaving the object in filename order instead.
 fn bad_order(doc: &Document, table: &toml::Table) -> Option<Diagnostic> {
@@ -203,6 +236,47 @@ label = "string"
         assert_eq!(found[0].severity, Some(DiagnosticSeverity::ERROR));
     }
 
+    const CONSTRAINED: &str = r#"
+[post]
+title = { required = true, max_length = 5 }
+rating = { type = "number", min = 0, max = 5 }
+
+[post.tags]
+label = { required = true }
+"#;
+
+    #[test]
+    fn reports_broken_constraints() {
+        let found = check(
+            CONSTRAINED,
+            "title = \"too long\"\nrating = 9\n\n[[tags]]\nlabel = \"x\"\n\n[[tags]]\n",
+        );
+        let messages: Vec<_> = found.iter().map(|d| &d.message[..]).collect();
+        assert_eq!(
+            messages,
+            [
+                "`title` must be at most 5 characters long (found 8).",
+                "`rating` must be at most 5.",
+                "`tags.1.label` is required.",
+            ],
+            "{found:#?}"
+        );
+        assert_eq!(found[0].severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(found[0].range.start.line, 0);
+        assert_eq!(found[1].range.start.line, 1);
+        // A missing value points at the list it is missing from.
+        assert_eq!(found[2].range.start.line, 3);
+    }
+
+    #[test]
+    fn reports_a_missing_required_field_at_the_top() {
+        let found = check(CONSTRAINED, "rating = 1\n");
+        assert_eq!(found.len(), 1, "{found:#?}");
+        assert_eq!(found[0].message, "`title` is required.");
+        assert_eq!(found[0].range.start.line, 0);
+        assert_eq!(found[0].range.end.line, 0);
+    }
+
     #[test]
     fn reports_a_toml_syntax_error() {
         let found = check(POST, "title = \n");
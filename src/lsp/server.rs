@@ -2,17 +2,19 @@
 
 use super::documents::Documents;
 use super::workspace::Workspace;
-use super::{builtin, diagnostics, objects};
+use super::{builtin, completion, diagnostics, objects};
 use crate::binary::command::ExitStatus;
 use anyhow::Result;
-use lsp_server::{Connection, Message, Notification};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
 use lsp_types::notification::{
     DidChangeTextDocument, DidChangeWatchedFiles, DidCloseTextDocument, DidOpenTextDocument,
     Notification as _, PublishDiagnostics,
 };
+use lsp_types::request::{Completion, Request as _};
 use lsp_types::{
-    InitializeParams, OneOf, PublishDiagnosticsParams, ServerCapabilities,
-    TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+    CompletionOptions, CompletionParams, CompletionResponse, InitializeParams, OneOf,
+    PublishDiagnosticsParams, ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind,
+    Url,
 };
 use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -50,6 +52,10 @@ fn capabilities() -> ServerCapabilities {
             TextDocumentSyncKind::INCREMENTAL,
         )),
         definition_provider: Some(OneOf::Left(false)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec!["|".into(), ".".into(), "\"".into(), "'".into()]),
+            ..Default::default()
+        }),
         ..Default::default()
     }
 }
@@ -74,7 +80,7 @@ impl Server {
                     if connection.handle_shutdown(&request)? {
                         return Ok(ExitStatus::Ok);
                     }
-                    debug!("unhandled request {}", request.method);
+                    self.requested(connection, request);
                 }
                 Ok(Message::Notification(notification)) => {
                     if notification.method == lsp_types::notification::Exit::METHOD {
@@ -92,6 +98,46 @@ impl Server {
         }
     }
 
+    fn requested(&mut self, connection: &Connection, request: Request) {
+        let response = match &request.method[..] {
+            Completion::METHOD => {
+                let params: CompletionParams = match serde_json::from_value(request.params) {
+                    Ok(params) => params,
+                    Err(err) => {
+                        warn!("malformed {} request: {err}", Completion::METHOD);
+                        let response = Response::new_err(
+                            request.id,
+                            ErrorCode::InvalidParams as i32,
+                            err.to_string(),
+                        );
+                        let _ = connection.sender.send(response.into());
+                        return;
+                    }
+                };
+                let position = params.text_document_position;
+                let uri = &position.text_document.uri;
+                // Only liquid templates have anything to complete, and one
+                // outside a site still has its filters.
+                let path = uri.to_file_path().unwrap_or_default();
+                let items = match self.documents.get(uri) {
+                    Some(doc) if path.extension().is_some_and(|ext| ext == "liquid") => {
+                        let site = self.workspace.site_for(&path);
+                        completion::completions(doc, position.position, site, &path)
+                    }
+                    _ => vec![],
+                };
+                Response::new_ok(request.id, CompletionResponse::Array(items))
+            }
+            method => {
+                debug!("unhandled request {method}");
+                return;
+            }
+        };
+        if connection.sender.send(response.into()).is_err() {
+            warn!("client disconnected while responding");
+        }
+    }
+
     fn notified(&mut self, connection: &Connection, notification: Notification) {
         match &notification.method[..] {
             DidOpenTextDocument::METHOD => {
@@ -373,4 +419,60 @@ mod tests {
         assert_eq!(sent.len(), 1);
         assert!(diagnostics_in(&sent[0]).diagnostics.is_empty());
     }
+
+    /// The labels completed at `character` on the first line of `text`,
+    /// opened as `uri`.
+    fn completion_labels(uri: &str, text: &str, character: u32) -> Vec<String> {
+        let request = Message::Request(Request::new(
+            1.into(),
+            Completion::METHOD.to_string(),
+            CompletionParams {
+                text_document_position: lsp_types::TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: Url::parse(uri).unwrap(),
+                    },
+                    position: lsp_types::Position { line: 0, character },
+                },
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+                context: None,
+            },
+        ));
+        let sent = exchange(vec![did_open(uri, text), request]);
+        let Message::Response(response) = &sent[1] else {
+            panic!("expected a response, got {:?}", sent[1]);
+        };
+        let items: Vec<lsp_types::CompletionItem> =
+            serde_json::from_value(response.result.clone().unwrap()).unwrap();
+        items.into_iter().map(|i| i.label).collect()
+    }
+
+    #[test]
+    fn completes_filter_names() {
+        let labels = completion_labels("file:///a.liquid", "{{ a | slu }}", 10);
+        assert_eq!(labels, ["slugify"]);
+    }
+
+    #[test]
+    fn only_completes_liquid_files() {
+        for uri in ["file:///a.html", "file:///a.toml", "file:///a"] {
+            assert!(
+                completion_labels(uri, "{{ a | slu }}", 10).is_empty(),
+                "{uri}"
+            );
+        }
+    }
+
+    #[test]
+    fn completes_variables_from_the_site_definitions() {
+        let page =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/website/pages/index.liquid");
+        let uri = Url::from_file_path(page).unwrap();
+        assert_eq!(
+            completion_labels(uri.as_str(), "{{ objects.sub }}", 14),
+            ["subpage"]
+        );
+        // Outside a site, there is nothing to complete but filters.
+        assert!(completion_labels("file:///a.liquid", "{{ objects.", 11).is_empty());
+    }
 }
@@ -2,6 +2,7 @@
 //! the key it is about.
 
 use super::documents::Document;
+use crate::value_path::{ValuePath, ValuePathComponent};
 use lsp_types::{Diagnostic, DiagnosticSeverity};
 use std::ops::Range;
 use toml_edit::{Item, Table};
@@ -58,6 +59,51 @@ pub(super) fn error_at_key(
     diagnostic(doc, span, message, severity)
 }
 
+/// An error about the value at `path`. When that value is missing, it points at
+/// the closest key that is there - the child list it belongs to - or else at
+/// the start of the document.
+pub(super) fn error_at_path(
+    doc: &Document,
+    path: &ValuePath,
+    message: String,
+    severity: DiagnosticSeverity,
+) -> Diagnostic {
+    let span = toml_edit::Document::<&str>::parse(&doc.text)
+        .ok()
+        .and_then(|parsed| span_of_path(parsed.as_table(), path))
+        .unwrap_or(0..0);
+    diagnostic(doc, span, message, severity)
+}
+
+fn span_of_path(root: &Table, path: &ValuePath) -> Option<Range<usize>> {
+    let mut table = root;
+    let mut closest = None;
+    let mut components = path.clone().into_iter().peekable();
+    while let Some(component) = components.next() {
+        let ValuePathComponent::Key(name) = component else {
+            return closest;
+        };
+        let Some((key, item)) = table.get_key_value(&name) else {
+            return closest;
+        };
+        closest = key.span();
+        let index = match components.peek() {
+            None => return closest,
+            Some(ValuePathComponent::Index(index)) => *index,
+            Some(ValuePathComponent::Key(_)) => return closest,
+        };
+        components.next();
+        table = match item {
+            Item::ArrayOfTables(tables) => match tables.get(index) {
+                Some(entry) => entry,
+                None => return closest,
+            },
+            _ => return closest,
+        };
+    }
+    closest
+}
+
 fn span_of_key(source: &str, key: &str) -> Option<Range<usize>> {
     if key.is_empty() {
         return None;
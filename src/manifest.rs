@@ -17,7 +17,7 @@ use crate::{
 };
 
 use super::constants::{
-    BUILD_DIR_NAME, LEGACY_MANIFEST_FILE_NAME, LEGACY_OBJECT_DEFINITION_FILE_NAME,
+    BUILD_DIR_NAME, DATA_DIR_NAME, LEGACY_MANIFEST_FILE_NAME, LEGACY_OBJECT_DEFINITION_FILE_NAME,
     MANIFEST_FILE_NAME, OBJECTS_DIR_NAME, OBJECT_DEFINITION_FILE_NAME, PAGES_DIR_NAME,
     STATIC_DIR_NAME,
 };
@@ -240,6 +240,11 @@ pub struct Manifest {
     pub build_dir: PathBuf,
     pub static_dir: PathBuf,
     pub layout_dir: PathBuf,
+    /// JSON, TOML and CSV files exposed to templates as `data.<filename>`.
+    pub data_dir: PathBuf,
+    /// WebAssembly modules providing extra liquid filters and tags, see
+    /// `crate::plugins`.
+    pub plugins: Vec<PathBuf>,
     pub uploads_url: Option<String>,
     #[cfg_attr(feature = "typescript", type_def(type_of = "typedefs::EditorTypesDef"))]
     pub editor_types: EditorTypes,
@@ -267,6 +272,8 @@ pub enum ManifestField {
     StaticDir,
     SchemasDir,
     LayoutDir,
+    DataDir,
+    Plugins,
     UploadsUrl,
     EditorTypes,
     Metadata,
@@ -288,6 +295,8 @@ impl ManifestField {
             ManifestField::StaticDir => "static_dir",
             ManifestField::SchemasDir => "schemas_dir",
             ManifestField::LayoutDir => "layout_dir",
+            ManifestField::DataDir => "data_dir",
+            ManifestField::Plugins => "plugins",
             ManifestField::UploadsUrl => "uploads_url",
             ManifestField::EditorTypes => "editor_types",
             ManifestField::Metadata => "metadata",
@@ -418,6 +427,8 @@ impl Manifest {
             build_dir: root.join(BUILD_DIR_NAME),
             static_dir: root.join(STATIC_DIR_NAME),
             layout_dir: root.join(LAYOUT_DIR_NAME),
+            data_dir: root.join(DATA_DIR_NAME),
+            plugins: vec![],
             editor_types: EditorTypes::new(),
             metadata: None,
         }
@@ -425,7 +436,7 @@ impl Manifest {
     fn is_default(&self, field: &ManifestField) -> bool {
         let str_value = self.field_as_string(field);
         match field {
-            ManifestField::Prebuild => str_value == "[]",
+            ManifestField::Prebuild | ManifestField::Plugins => str_value == "[]",
             ManifestField::ObjectDefinitionFile => {
                 // The legacy name counts as a default too, so formatting a
                 // site that still uses objects.toml doesn't write out an
@@ -459,6 +470,7 @@ impl Manifest {
             ManifestField::SchemasDir => {
                 str_value == self.root.join(SCHEMAS_DIR_NAME).to_string_lossy()
             }
+            ManifestField::DataDir => str_value == self.root.join(DATA_DIR_NAME).to_string_lossy(),
             _ => str_value.is_empty(),
         }
     }
@@ -514,6 +526,18 @@ impl Manifest {
                 "static_dir" => manifest.static_dir = path_or_err(value, "static_dir")?,
                 "schemas_dir" => manifest.schemas_dir = path_or_err(value, "schemas_dir")?,
                 "layout_dir" => manifest.layout_dir = path_or_err(value, "layout_dir")?,
+                "data_dir" => manifest.data_dir = path_or_err(value, "data_dir")?,
+                "plugins" => {
+                    manifest.plugins = match value {
+                        Value::Array(paths) => paths
+                            .into_iter()
+                            .map(|path| path_or_err(path, "plugins"))
+                            .collect::<Result<_, _>>()?,
+                        _ => {
+                            return Err(InvalidManifestError::BadType("plugins".to_string()).into())
+                        }
+                    }
+                }
                 "object_file" => {
                     manifest.object_definition_file = path_or_err(value, "object_file")?
                 }
@@ -577,6 +601,15 @@ impl Manifest {
             ManifestField::LayoutDir => {
                 Some(Value::String(self.layout_dir.to_string_lossy().to_string()))
             }
+            ManifestField::DataDir => {
+                Some(Value::String(self.data_dir.to_string_lossy().to_string()))
+            }
+            ManifestField::Plugins => Some(Value::Array(
+                self.plugins
+                    .iter()
+                    .map(|p| Value::String(p.to_string_lossy().to_string()))
+                    .collect(),
+            )),
             ManifestField::Metadata => self.metadata.as_ref().map(|metadata| {
                 let mut map = toml::map::Map::new();
                 for (key, v) in metadata {
@@ -724,6 +757,10 @@ impl Manifest {
             ManifestField::StaticDir => self.static_dir = PathBuf::from(value),
             ManifestField::SchemasDir => self.schemas_dir = PathBuf::from(value),
             ManifestField::LayoutDir => self.layout_dir = PathBuf::from(value),
+            ManifestField::DataDir => self.data_dir = PathBuf::from(value),
+            ManifestField::Plugins => {
+                panic!("Plugins are not modifiable via events")
+            }
             ManifestField::Metadata => {
                 panic!("Metadata is not modifiable via events")
             }
@@ -760,6 +797,8 @@ impl Manifest {
             ManifestField::ObjectsDir,
             ManifestField::LayoutDir,
             ManifestField::SchemasDir,
+            ManifestField::DataDir,
+            ManifestField::Plugins,
             ManifestField::EditorTypes,
             ManifestField::Metadata,
         ]
@@ -785,8 +824,10 @@ impl Manifest {
             &self.pages_dir,
             &self.static_dir,
             &self.layout_dir,
+            &self.data_dir,
         ]
-        .iter()
+        .into_iter()
+        .chain(&self.plugins)
         .map(|p| p.to_string_lossy().into_owned())
         .collect()
     }
@@ -813,6 +854,8 @@ pages = "m_pages"
 objects = "m_objects"
 layout_dir = "m_layout"
 schemas_dir = "m_schemas"
+data_dir = "m_data"
+plugins = ["m_plugins/sku.wasm"]
 
 [editor_types.day]
 type = "date"
@@ -853,6 +896,11 @@ baz = "hello!"
         assert_eq!(m.static_dir, Path::new("m_public").to_path_buf());
         assert_eq!(m.layout_dir, Path::new("m_layout").to_path_buf());
         assert_eq!(m.schemas_dir, Path::new("m_schemas").to_path_buf());
+        assert_eq!(m.data_dir, Path::new("m_data").to_path_buf());
+        assert_eq!(
+            m.plugins,
+            vec![Path::new("m_plugins/sku.wasm").to_path_buf()]
+        );
         assert_eq!(m.site_name, Some("jesse's site".to_string()));
         assert_eq!(
             m.uploads_url,
@@ -0,0 +1,402 @@
+//! Three-way merges of object files, e.g. for `archival merge-driver`.
+//!
+//! Each version of a file is parsed with its object's definition and merged
+//! value by value rather than line by line, so branches that edit different
+//! fields (or different children) of the same object merge cleanly. Children
+//! are matched with the ancestor's by content first, so moved children are
+//! still recognised, and then by position, so edited ones are. A conflict is
+//! only reported where both sides changed the same value differently, and the
+//! merged object keeps our side of it. Merged files list their conflicts in a
+//! comment at the top, so they can't be committed unnoticed.
+
+use crate::{
+    fields::{FieldValue, ObjectValues},
+    object::Object,
+    reserved_fields,
+    value_path::ValuePath,
+};
+use serde::Serialize;
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+};
+
+/// A value both sides changed differently. The path is as in our version.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MergeConflict {
+    pub path: ValuePath,
+    pub base: Option<FieldValue>,
+    pub ours: Option<FieldValue>,
+    pub theirs: Option<FieldValue>,
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |value: &Option<FieldValue>| match value {
+            Some(value) => value.to_string(),
+            None => "(removed)".to_string(),
+        };
+        write!(
+            f,
+            "{}: ours {}, theirs {}",
+            self.path,
+            show(&self.ours),
+            show(&self.theirs)
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Merge {
+    pub object: Object,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// A merged object file, as returned by
+/// [`crate::Archival::merge_object_files`].
+#[derive(Debug, Clone)]
+pub struct MergedFile {
+    /// The merged file, starting with a [`conflict_comment`] if there were
+    /// conflicts.
+    pub contents: String,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// A toml comment listing `conflicts`, each with both sides and the base, to
+/// put at the top of a merged file. Empty if there are none.
+pub fn conflict_comment(conflicts: &[MergeConflict]) -> String {
+    if conflicts.is_empty() {
+        return String::new();
+    }
+    let show = |value: &Option<FieldValue>| match value {
+        Some(value) => value.to_string(),
+        None => "(unset)".to_string(),
+    };
+    let mut lines = vec![
+        "MERGE CONFLICTS: both sides changed these values. This file keeps".to_string(),
+        "ours; resolve each one, then delete this comment.".to_string(),
+    ];
+    for conflict in conflicts {
+        lines.push(String::new());
+        lines.push(format!("{}:", conflict.path));
+        for (side, value) in [
+            ("ours", &conflict.ours),
+            ("theirs", &conflict.theirs),
+            ("base", &conflict.base),
+        ] {
+            let mut value = show(value).lines().map(str::to_string).collect::<Vec<_>>();
+            if value.is_empty() {
+                value.push(String::new());
+            }
+            lines.push(format!("  {side}: {}", value[0]));
+            lines.extend(value[1..].iter().map(|line| format!("    {line}")));
+        }
+    }
+    lines
+        .iter()
+        .map(|line| format!("# {line}").trim_end().to_string() + "\n")
+        .collect::<String>()
+        + "\n"
+}
+
+/// Merges the changes `ours` and `theirs` each made to `base`.
+pub fn merge_objects(base: &Object, ours: &Object, theirs: &Object) -> Merge {
+    let mut merger = Merger::default();
+    let order_path = ValuePath::empty().append(ValuePath::key(reserved_fields::ORDER));
+    let order = |object: &Object| object.order.map(FieldValue::Number);
+    let order = merger
+        .merge_value(
+            order(base).as_ref(),
+            order(ours).as_ref(),
+            order(theirs).as_ref(),
+            &order_path,
+        )
+        .and_then(|order| match order {
+            FieldValue::Number(n) => Some(n),
+            _ => None,
+        });
+    let values = merger.merge_values(
+        &base.values,
+        &ours.values,
+        &theirs.values,
+        &ValuePath::empty(),
+    );
+    Merge {
+        object: Object {
+            filename: ours.filename.clone(),
+            object_name: ours.object_name.clone(),
+            order,
+            values,
+        },
+        conflicts: merger.conflicts,
+    }
+}
+
+/// Unset values and explicit nulls are the same thing to a merge.
+fn present(value: Option<&FieldValue>) -> Option<&FieldValue> {
+    value.filter(|value| !matches!(value, FieldValue::Null))
+}
+
+/// For each of `side`'s children, the index of the `base` child it is a
+/// version of, if any.
+fn match_children(base: &[ObjectValues], side: &[ObjectValues]) -> Vec<Option<usize>> {
+    let mut used = vec![false; base.len()];
+    let mut matches = vec![None; side.len()];
+    // Unchanged children match wherever they've moved to...
+    for (idx, child) in side.iter().enumerate() {
+        if let Some(b) = (0..base.len()).find(|&b| !used[b] && base[b] == *child) {
+            used[b] = true;
+            matches[idx] = Some(b);
+        }
+    }
+    // ...and changed ones are taken to be where they were.
+    for (idx, matched) in matches.iter_mut().enumerate() {
+        if matched.is_none() && idx < base.len() && !used[idx] {
+            used[idx] = true;
+            *matched = Some(idx);
+        }
+    }
+    matches
+}
+
+#[derive(Default)]
+struct Merger {
+    conflicts: Vec<MergeConflict>,
+}
+
+impl Merger {
+    fn conflict(
+        &mut self,
+        path: &ValuePath,
+        base: Option<&FieldValue>,
+        ours: Option<&FieldValue>,
+        theirs: Option<&FieldValue>,
+    ) {
+        self.conflicts.push(MergeConflict {
+            path: path.clone(),
+            base: base.cloned(),
+            ours: ours.cloned(),
+            theirs: theirs.cloned(),
+        });
+    }
+
+    fn merge_values(
+        &mut self,
+        base: &ObjectValues,
+        ours: &ObjectValues,
+        theirs: &ObjectValues,
+        path: &ValuePath,
+    ) -> ObjectValues {
+        let keys: BTreeSet<&String> = base
+            .keys()
+            .chain(ours.keys())
+            .chain(theirs.keys())
+            .collect();
+        let mut merged = ObjectValues::new();
+        for key in keys {
+            let path = path.clone().append(ValuePath::key(key));
+            let value = self.merge_value(
+                present(base.get(key)),
+                present(ours.get(key)),
+                present(theirs.get(key)),
+                &path,
+            );
+            if let Some(value) = value {
+                merged.insert(key.clone(), value);
+            }
+        }
+        merged
+    }
+
+    fn merge_value(
+        &mut self,
+        base: Option<&FieldValue>,
+        ours: Option<&FieldValue>,
+        theirs: Option<&FieldValue>,
+        path: &ValuePath,
+    ) -> Option<FieldValue> {
+        if ours == theirs || theirs == base {
+            return ours.cloned();
+        }
+        if ours == base {
+            return theirs.cloned();
+        }
+        if let (Some(FieldValue::Objects(o)), Some(FieldValue::Objects(t))) = (ours, theirs) {
+            let b = match base {
+                Some(FieldValue::Objects(b)) => b.as_slice(),
+                _ => &[],
+            };
+            return Some(FieldValue::Objects(self.merge_children(b, o, t, path)));
+        }
+        self.conflict(path, base, ours, theirs);
+        ours.cloned()
+    }
+
+    fn merge_children(
+        &mut self,
+        base: &[ObjectValues],
+        ours: &[ObjectValues],
+        theirs: &[ObjectValues],
+        path: &ValuePath,
+    ) -> Vec<ObjectValues> {
+        let ours_matches = match_children(base, ours);
+        let theirs_matches = match_children(base, theirs);
+        let theirs_by_base: HashMap<usize, usize> = theirs_matches
+            .iter()
+            .enumerate()
+            .filter_map(|(t, b)| b.map(|b| (b, t)))
+            .collect();
+        let child = |values: &ObjectValues| FieldValue::Objects(vec![values.clone()]);
+
+        // Our children, in our order, merged with their versions of them.
+        let mut merged: Vec<(Option<usize>, ObjectValues)> = vec![];
+        for (idx, (values, matched)) in ours.iter().zip(&ours_matches).enumerate() {
+            let child_path = path.clone().append(ValuePath::index(idx));
+            let Some(b) = *matched else {
+                merged.push((None, values.clone()));
+                continue;
+            };
+            match theirs_by_base.get(&b) {
+                Some(&t) => {
+                    let values = self.merge_values(&base[b], values, &theirs[t], &child_path);
+                    merged.push((Some(b), values));
+                }
+                // Removed on their side.
+                None if *values == base[b] => {}
+                None => {
+                    self.conflict(
+                        &child_path,
+                        Some(&child(&base[b])),
+                        Some(&child(values)),
+                        None,
+                    );
+                    merged.push((Some(b), values.clone()));
+                }
+            }
+        }
+        // Children we removed that they changed.
+        for (b, base_values) in base.iter().enumerate() {
+            if ours_matches.contains(&Some(b)) {
+                continue;
+            }
+            if let Some(&t) = theirs_by_base.get(&b) {
+                if theirs[t] != *base_values {
+                    self.conflict(
+                        &path.clone().append(ValuePath::index(b)),
+                        Some(&child(base_values)),
+                        None,
+                        Some(&child(&theirs[t])),
+                    );
+                }
+            }
+        }
+        // Children they added, after the child they follow on their side.
+        let mut cursor = 0;
+        for (values, matched) in theirs.iter().zip(&theirs_matches) {
+            match matched {
+                Some(b) => {
+                    if let Some(pos) = merged.iter().position(|(m, _)| *m == Some(*b)) {
+                        cursor = pos + 1;
+                    }
+                }
+                None => {
+                    // Added on both sides.
+                    if merged.iter().any(|(m, v)| m.is_none() && v == values) {
+                        continue;
+                    }
+                    merged.insert(cursor, (None, values.clone()));
+                    cursor += 1;
+                }
+            }
+        }
+        merged.into_iter().map(|(_, values)| values).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(s: &str) -> FieldValue {
+        FieldValue::String(s.to_string())
+    }
+
+    fn link(url: &str) -> ObjectValues {
+        ObjectValues::from([("url".to_string(), string(url))])
+    }
+
+    fn object(title: &str, body: &str, links: &[&str]) -> Object {
+        Object {
+            filename: "a".to_string(),
+            object_name: "post".to_string(),
+            order: None,
+            values: ObjectValues::from([
+                ("title".to_string(), string(title)),
+                ("body".to_string(), string(body)),
+                (
+                    "links".to_string(),
+                    FieldValue::Objects(links.iter().map(|url| link(url)).collect()),
+                ),
+            ]),
+        }
+    }
+
+    #[test]
+    fn merges_different_fields() {
+        let base = object("A", "x", &[]);
+        let merge = merge_objects(&base, &object("B", "x", &[]), &object("A", "y", &[]));
+        assert!(merge.conflicts.is_empty());
+        assert_eq!(merge.object.values, object("B", "y", &[]).values);
+    }
+
+    #[test]
+    fn merges_children() {
+        let base = object("A", "x", &["one", "two", "three"]);
+        // We edit the first link and remove the last; they add one after the
+        // second.
+        let ours = object("A", "x", &["uno", "two"]);
+        let theirs = object("A", "x", &["one", "two", "two and a half", "three"]);
+        let merge = merge_objects(&base, &ours, &theirs);
+        assert!(merge.conflicts.is_empty());
+        assert_eq!(
+            merge.object.values["links"],
+            FieldValue::Objects(vec![link("uno"), link("two"), link("two and a half")])
+        );
+    }
+
+    #[test]
+    fn conflicts_on_the_same_value() {
+        let base = object("A", "x", &["one"]);
+        let merge = merge_objects(
+            &base,
+            &object("B", "x", &["uno"]),
+            &object("C", "x", &["eins"]),
+        );
+        let paths: Vec<String> = merge.conflicts.iter().map(|c| c.path.to_string()).collect();
+        assert_eq!(paths, vec!["links.0.url", "title"]);
+        assert_eq!(merge.object.values["title"], string("B"));
+    }
+
+    #[test]
+    fn comments_on_conflicts() {
+        let base = object("A", "x", &[]);
+        let merge = merge_objects(
+            &base,
+            &object("B", "x", &[]),
+            &object("A\nsecond line", "x", &[]),
+        );
+        assert_eq!(conflict_comment(&[]), "");
+        assert_eq!(
+            conflict_comment(&merge.conflicts),
+            "# MERGE CONFLICTS: both sides changed these values. This file keeps\n\
+             # ours; resolve each one, then delete this comment.\n\
+             #\n\
+             # title:\n\
+             #   ours: B\n\
+             #   theirs: A\n\
+             #     second line\n\
+             #   base: A\n\
+             \n"
+        );
+    }
+}
@@ -0,0 +1,678 @@
+//! Rewrites object files when their definitions change.
+//!
+//! A [`Migration`] is a list of [`MigrationOp`]s, usually proposed by diffing
+//! the definitions an archival site's objects were written against with its
+//! current ones. Ops are applied to each object file's raw TOML, since a stale
+//! file often won't parse with the new definitions, and the result is then
+//! parsed with the new definitions and written back out through
+//! [`Object::to_toml`].
+
+use crate::{
+    fields::{FieldType, FieldValue},
+    object_definition::{FieldDefinition, ObjectDefinition, ObjectDefinitions},
+    read_toml::read_toml,
+    site::Site,
+    FileSystemAPI, Object,
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+use toml::Table;
+
+#[derive(Error, Debug)]
+pub enum MigrationError {
+    #[error("{file}: can't convert {field} from {from} to {to} ({error})")]
+    Coercion {
+        file: PathBuf,
+        field: String,
+        from: String,
+        to: String,
+        error: String,
+    },
+    #[error("{file} is invalid after migrating: {error}")]
+    InvalidObject { file: PathBuf, error: String },
+    #[error("{0} is not a table")]
+    NotATable(String),
+}
+
+/// One change to the values of every instance of an object.
+///
+/// `object` names the definition the op applies to: an object's name, or a
+/// path through its children such as `post.links`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum MigrationOp {
+    /// Renames a field, or a child.
+    RenameField {
+        object: String,
+        from: String,
+        to: String,
+    },
+    /// Converts a field's values from one type to another. Values that can't
+    /// be converted fail the migration.
+    ChangeType {
+        object: String,
+        field: String,
+        from: FieldType,
+        to: FieldType,
+    },
+    /// Moves a field into the first entry of one of the object's children,
+    /// adding an entry if there are none.
+    MoveToChild {
+        object: String,
+        field: String,
+        child: String,
+    },
+    /// Removes a field, or a child, and its values.
+    DeleteField { object: String, field: String },
+}
+
+impl fmt::Display for MigrationOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RenameField { object, from, to } => {
+                write!(f, "{object}: rename {from} to {to}")
+            }
+            Self::ChangeType {
+                object,
+                field,
+                from,
+                to,
+            } => write!(f, "{object}: change {field} from {from} to {to}"),
+            Self::MoveToChild {
+                object,
+                field,
+                child,
+            } => write!(f, "{object}: move {field} to {child}"),
+            Self::DeleteField { object, field } => write!(f, "{object}: delete {field}"),
+        }
+    }
+}
+
+impl MigrationOp {
+    fn object(&self) -> &str {
+        match self {
+            Self::RenameField { object, .. }
+            | Self::ChangeType { object, .. }
+            | Self::MoveToChild { object, .. }
+            | Self::DeleteField { object, .. } => object,
+        }
+    }
+
+    /// Applies this op to one table of the object's values, describing what
+    /// changed in `changes`. Tables without the field are left alone.
+    fn apply(&self, file: &Path, table: &mut Table, changes: &mut Vec<String>) -> Result<()> {
+        match self {
+            Self::RenameField { object, from, to } => {
+                if let Some(value) = table.remove(from) {
+                    table.insert(to.clone(), value);
+                    changes.push(format!("{object}: renamed {from} to {to}"));
+                }
+            }
+            Self::ChangeType {
+                object,
+                field,
+                from,
+                to,
+            } => {
+                if let Some(value) = table.get(field) {
+                    if let Some(converted) = convert(file, field, value, from, to)? {
+                        changes.push(format!("{object}: {field} {value} -> {converted}"));
+                        table.insert(field.clone(), converted);
+                    }
+                }
+            }
+            Self::MoveToChild {
+                object,
+                field,
+                child,
+            } => {
+                if let Some(value) = table.remove(field) {
+                    let entries = table
+                        .entry(child.clone())
+                        .or_insert_with(|| toml::Value::Array(vec![]))
+                        .as_array_mut()
+                        .ok_or_else(|| MigrationError::NotATable(child.clone()))?;
+                    if entries.is_empty() {
+                        entries.push(toml::Value::Table(Table::new()));
+                    }
+                    entries[0]
+                        .as_table_mut()
+                        .ok_or_else(|| MigrationError::NotATable(format!("{child}.0")))?
+                        .insert(field.clone(), value);
+                    changes.push(format!("{object}: moved {field} to {child}"));
+                }
+            }
+            Self::DeleteField { object, field } => {
+                if table.remove(field).is_some() {
+                    changes.push(format!("{object}: deleted {field}"));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Migration {
+    pub ops: Vec<MigrationOp>,
+    /// Changes [`Migration::propose`] couldn't decide on, and so proposed as
+    /// deletes. Not part of the ops file.
+    #[serde(skip)]
+    pub ambiguities: Vec<String>,
+}
+
+impl Migration {
+    /// Proposes the ops that take objects written against `old` to `new`.
+    ///
+    /// A field that disappears is assumed moved when a child gains a field of
+    /// the same name and type, and renamed when it's the only field of its
+    /// type to disappear and exactly one new field of that type appears.
+    /// Otherwise it's deleted, and anything that could have been a rename is
+    /// listed in [`ambiguities`](Self::ambiguities). Objects that were added or
+    /// removed outright need no ops.
+    pub fn propose(old: &ObjectDefinitions, new: &ObjectDefinitions) -> Self {
+        let mut migration = Self::default();
+        for (name, new_def) in new {
+            if let Some(old_def) = old.get(name) {
+                propose_for(name, old_def, new_def, &mut migration);
+            }
+        }
+        migration
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+fn stored_fields(def: &ObjectDefinition) -> impl Iterator<Item = (&String, &FieldType)> {
+    def.fields
+        .iter()
+        .filter(|(_, field)| !field.is_computed())
+        .map(|(name, field)| (name, &field.r#type))
+}
+
+fn propose_for(
+    path: &str,
+    old: &ObjectDefinition,
+    new: &ObjectDefinition,
+    migration: &mut Migration,
+) {
+    let added: Vec<(&String, &FieldType)> = stored_fields(new)
+        .filter(|(name, _)| {
+            old.fields
+                .get(*name)
+                .is_none_or(FieldDefinition::is_computed)
+        })
+        .collect();
+    let kept = |name: &String| new.fields.get(name).is_some_and(|f| !f.is_computed());
+    let moved_to = |name: &String, old_type: &FieldType| {
+        new.children.iter().find_map(|(child, def)| {
+            let gained = def.field_type(name) == Some(old_type)
+                && old
+                    .children
+                    .get(child)
+                    .is_none_or(|old_child| old_child.field_type(name).is_none());
+            gained.then_some(child)
+        })
+    };
+    // Moves keep the field's name, so they're never mistaken for renames.
+    let removed: Vec<&FieldType> = stored_fields(old)
+        .filter(|(name, old_type)| !kept(name) && moved_to(name, old_type).is_none())
+        .map(|(_, old_type)| old_type)
+        .collect();
+    for (name, old_type) in stored_fields(old) {
+        if let Some(field) = new.fields.get(name).filter(|_| kept(name)) {
+            if field.r#type != *old_type {
+                migration.ops.push(MigrationOp::ChangeType {
+                    object: path.to_string(),
+                    field: name.clone(),
+                    from: old_type.clone(),
+                    to: field.r#type.clone(),
+                });
+            }
+        } else if let Some(child) = moved_to(name, old_type) {
+            migration.ops.push(MigrationOp::MoveToChild {
+                object: path.to_string(),
+                field: name.clone(),
+                child: child.clone(),
+            });
+        } else {
+            let candidates: Vec<&String> = added
+                .iter()
+                .filter(|(_, t)| *t == old_type)
+                .map(|(added, _)| *added)
+                .collect();
+            let alike = removed.iter().filter(|t| **t == old_type).count();
+            rename_or_delete(path, name, &candidates, alike, migration);
+        }
+    }
+    let added_children: Vec<&String> = new
+        .children
+        .keys()
+        .filter(|name| !old.children.contains_key(*name))
+        .collect();
+    let removed_children: Vec<&ObjectDefinition> = old
+        .children
+        .iter()
+        .filter(|(name, _)| !new.children.contains_key(*name))
+        .map(|(_, def)| def)
+        .collect();
+    for (name, old_child) in &old.children {
+        if let Some(new_child) = new.children.get(name) {
+            propose_for(&format!("{path}.{name}"), old_child, new_child, migration);
+            continue;
+        }
+        let candidates: Vec<&String> = added_children
+            .iter()
+            .filter(|added| new.children[**added].fields == old_child.fields)
+            .copied()
+            .collect();
+        let alike = removed_children
+            .iter()
+            .filter(|removed| removed.fields == old_child.fields)
+            .count();
+        rename_or_delete(path, name, &candidates, alike, migration);
+    }
+}
+
+/// Renames `name` when exactly one `candidate` appeared in its place and it
+/// was the only one of its kind (of `alike`) to disappear. Anything less
+/// certain is a delete, noting the candidates so they can be renamed by hand.
+fn rename_or_delete(
+    path: &str,
+    name: &String,
+    candidates: &[&String],
+    alike: usize,
+    migration: &mut Migration,
+) {
+    if let ([to], 1) = (candidates, alike) {
+        migration.ops.push(MigrationOp::RenameField {
+            object: path.to_string(),
+            from: name.clone(),
+            to: (*to).clone(),
+        });
+        return;
+    }
+    if !candidates.is_empty() {
+        let candidates: Vec<&str> = candidates.iter().map(|c| c.as_str()).collect();
+        migration.ambiguities.push(format!(
+            "{path}: {name} may have been renamed to {}",
+            candidates.join(" or ")
+        ));
+    }
+    migration.ops.push(MigrationOp::DeleteField {
+        object: path.to_string(),
+        field: name.clone(),
+    });
+}
+
+/// Converts a value written as `from` to `to`. Returns `None` when the value
+/// is already valid as `to`, which makes migrations safe to re-run.
+fn convert(
+    file: &Path,
+    field: &String,
+    value: &toml::Value,
+    from: &FieldType,
+    to: &FieldType,
+) -> Result<Option<toml::Value>> {
+    if FieldValue::from_toml(field, to, value).is_ok() {
+        return Ok(None);
+    }
+    let error = |error: String| MigrationError::Coercion {
+        file: file.to_path_buf(),
+        field: field.clone(),
+        from: from.to_string(),
+        to: to.to_string(),
+        error,
+    };
+    let old = FieldValue::from_toml(field, from, value).map_err(|e| error(e.to_string()))?;
+    let new = coerce(field, &old, to).map_err(error)?;
+    Ok(Option::<toml::Value>::from(&new))
+}
+
+fn coerce(field: &String, value: &FieldValue, to: &FieldType) -> Result<FieldValue, String> {
+    match (value, to.base_type()) {
+        (FieldValue::List(items), FieldType::List(item_type)) => Ok(FieldValue::List(
+            items
+                .iter()
+                .map(|item| coerce(field, item, item_type))
+                .collect::<Result<_, _>>()?,
+        )),
+        (_, FieldType::List(item_type)) => {
+            Ok(FieldValue::List(vec![coerce(field, value, item_type)?]))
+        }
+        (FieldValue::List(items), _) => match &items[..] {
+            [item] => coerce(field, item, to),
+            _ => Err(format!(
+                "a list of {} values has no single value",
+                items.len()
+            )),
+        },
+        (
+            FieldValue::File(file),
+            FieldType::Image | FieldType::Video | FieldType::Audio | FieldType::Upload,
+        ) => Ok(FieldValue::File(file.clone())),
+        _ => {
+            let string = match value {
+                FieldValue::String(s)
+                | FieldValue::Markdown(s)
+                | FieldValue::Secret(s)
+                | FieldValue::Enum(s) => s.clone(),
+                FieldValue::Number(n) => n.to_string(),
+                FieldValue::Boolean(b) => b.to_string(),
+                FieldValue::Date(d) => d.as_liquid_datetime().to_string(),
+                _ => return Err("only single values can be converted".to_string()),
+            };
+            FieldValue::from_string(field, to, string).map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// The result of migrating one object file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileMigration {
+    pub path: PathBuf,
+    /// What the migration's ops changed. Empty when the file is only being
+    /// reformatted.
+    pub changes: Vec<String>,
+    /// The file's new contents.
+    pub contents: String,
+}
+
+/// Every object file a migration changes, and how.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MigrationPlan {
+    pub files: Vec<FileMigration>,
+}
+
+impl MigrationPlan {
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// Applies `migration` to every object file in `site`, without writing
+/// anything. Fails if any file can't be migrated.
+pub(crate) fn plan(
+    site: &Site,
+    fs: &impl FileSystemAPI,
+    migration: &Migration,
+) -> Result<MigrationPlan> {
+    let mut plan = MigrationPlan::default();
+    for (name, definition) in &site.object_definitions {
+        let files = site.object_files(name, fs)?;
+        let ops: Vec<&MigrationOp> = migration
+            .ops
+            .iter()
+            .filter(|op| op.object().split('.').next() == Some(name))
+            .collect();
+        for path in files {
+            if let Some(file) = migrate_file(site, fs, &path, definition, &ops)? {
+                plan.files.push(file);
+            }
+        }
+    }
+    Ok(plan)
+}
+
+fn migrate_file(
+    site: &Site,
+    fs: &impl FileSystemAPI,
+    path: &Path,
+    definition: &ObjectDefinition,
+    ops: &[&MigrationOp],
+) -> Result<Option<FileMigration>> {
+    let mut table = read_toml(path, fs)?;
+    let mut changes = vec![];
+    for op in ops {
+        let children: Vec<&str> = op.object().split('.').skip(1).collect();
+        for_each_table(&mut table, &children, &mut |table| {
+            op.apply(path, table, &mut changes)
+        })?;
+    }
+    let invalid = |error: anyhow::Error| MigrationError::InvalidObject {
+        file: path.to_path_buf(),
+        error: error.to_string(),
+    };
+    let object = Object::from_table(
+        definition,
+        Path::new(path.with_extension("").file_name().unwrap()),
+        &table,
+        &site.manifest.editor_types,
+        true,
+    )
+    .map_err(invalid)?;
+    let contents = object.to_toml(definition)?;
+    if changes.is_empty() && fs.read_to_string(path)?.as_ref() == Some(&contents) {
+        return Ok(None);
+    }
+    Ok(Some(FileMigration {
+        path: path.to_path_buf(),
+        changes,
+        contents,
+    }))
+}
+
+/// Calls `f` with every table of values at `children`, a path through an
+/// object's children. An empty path is just the object's own table.
+pub(crate) fn for_each_table(
+    table: &mut Table,
+    children: &[&str],
+    f: &mut dyn FnMut(&mut Table) -> Result<()>,
+) -> Result<()> {
+    let Some((child, rest)) = children.split_first() else {
+        return f(table);
+    };
+    let Some(entries) = table.get_mut(*child) else {
+        return Ok(());
+    };
+    let entries = entries
+        .as_array_mut()
+        .ok_or_else(|| MigrationError::NotATable(child.to_string()))?;
+    for entry in entries {
+        let entry = entry
+            .as_table_mut()
+            .ok_or_else(|| MigrationError::NotATable(child.to_string()))?;
+        for_each_table(entry, rest, f)?;
+    }
+    Ok(())
+}
+
+/// Writes a planned migration's files.
+pub(crate) fn apply(site: &Site, fs: &mut impl FileSystemAPI, plan: &MigrationPlan) -> Result<()> {
+    for file in &plan.files {
+        fs.write_str(&file.path, file.contents.clone())?;
+        site.invalidate_file(&file.path);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::{MANIFEST_FILE_NAME, OBJECT_DEFINITION_FILE_NAME},
+        Archival, MemoryFileSystem,
+    };
+    use ordermap::OrderMap;
+
+    fn definitions(source: &str) -> ObjectDefinitions {
+        ObjectDefinition::from_source(source, &OrderMap::new()).unwrap()
+    }
+
+    #[test]
+    fn proposes_renames_type_changes_moves_and_deletes() {
+        let old = definitions(
+            r#"
+            [post]
+            title = "string"
+            rating = "string"
+            url = "string"
+            legacy = "boolean"
+            [post.links]
+            label = "string"
+            [post.old_tags]
+            name = "string"
+            "#,
+        );
+        let new = definitions(
+            r#"
+            [post]
+            name = "string"
+            rating = "number"
+            [post.links]
+            label = "string"
+            url = "string"
+            [post.tags]
+            name = "string"
+            "#,
+        );
+        let ops = Migration::propose(&old, &new).ops;
+        let expected: Vec<String> = vec![
+            "post: rename title to name".to_string(),
+            "post: change rating from string to number".to_string(),
+            "post: move url to links".to_string(),
+            "post: delete legacy".to_string(),
+            "post: rename old_tags to tags".to_string(),
+        ];
+        assert_eq!(
+            ops.iter().map(|op| op.to_string()).collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[test]
+    fn only_proposes_unambiguous_renames() {
+        let old = definitions(
+            r#"
+            [post]
+            title = "string"
+            subtitle = "string"
+            rating = "number"
+            [post.old_links]
+            url = "string"
+            [post.old_images]
+            url = "string"
+            "#,
+        );
+        let new = definitions(
+            r#"
+            [post]
+            headline = "string"
+            score = "number"
+            [post.links]
+            url = "string"
+            "#,
+        );
+        let migration = Migration::propose(&old, &new);
+        assert_eq!(
+            migration
+                .ops
+                .iter()
+                .map(|op| op.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "post: delete title".to_string(),
+                "post: delete subtitle".to_string(),
+                "post: rename rating to score".to_string(),
+                "post: delete old_links".to_string(),
+                "post: delete old_images".to_string(),
+            ]
+        );
+        assert_eq!(
+            migration.ambiguities,
+            vec![
+                "post: title may have been renamed to headline".to_string(),
+                "post: subtitle may have been renamed to headline".to_string(),
+                "post: old_links may have been renamed to links".to_string(),
+                "post: old_images may have been renamed to links".to_string(),
+            ]
+        );
+        // The ops file is just the ops.
+        assert_eq!(
+            serde_json::to_value(&migration).unwrap(),
+            serde_json::json!({ "ops": serde_json::to_value(&migration.ops).unwrap() })
+        );
+    }
+
+    #[test]
+    fn coerces_between_types() {
+        let field = "field".to_string();
+        let convert = |value: toml::Value, from: FieldType, to: FieldType| {
+            convert(Path::new("a.toml"), &field, &value, &from, &to)
+        };
+        assert_eq!(
+            convert("4.5".into(), FieldType::String, FieldType::Number).unwrap(),
+            Some(toml::Value::Float(4.5))
+        );
+        assert_eq!(
+            convert(3.into(), FieldType::Number, FieldType::String).unwrap(),
+            Some("3".into())
+        );
+        assert_eq!(
+            convert(
+                "a".into(),
+                FieldType::String,
+                FieldType::List(Box::new(FieldType::String))
+            )
+            .unwrap(),
+            Some(toml::Value::Array(vec!["a".into()]))
+        );
+        // Already converted
+        assert_eq!(
+            convert(3.into(), FieldType::String, FieldType::Number).unwrap(),
+            None
+        );
+        assert!(convert("nope".into(), FieldType::String, FieldType::Number).is_err());
+    }
+
+    #[test]
+    fn migrates_object_files() -> Result<()> {
+        let old = definitions(
+            r#"
+            [post]
+            title = "string"
+            rating = "string"
+            "#,
+        );
+        let mut fs = MemoryFileSystem::default();
+        fs.write_str(MANIFEST_FILE_NAME, "upload_prefix = \"\"\n".to_string())?;
+        fs.write_str(
+            OBJECT_DEFINITION_FILE_NAME,
+            "[post]\nname = \"string\"\nrating = \"number\"\n".to_string(),
+        )?;
+        fs.write_str(
+            "objects/post/a.toml",
+            "title = \"A\"\nrating = \"4\"\n".to_string(),
+        )?;
+        fs.write_str("objects/post/b.toml", "title = \"B\"\n".to_string())?;
+        let archival = Archival::new(fs)?;
+        let migration = Migration::propose(&old, &archival.site().object_definitions);
+        let plan = archival.plan_migration(&migration)?;
+        assert_eq!(plan.files.len(), 2);
+        assert_eq!(
+            plan.files[0].changes,
+            vec![
+                "post: renamed title to name".to_string(),
+                "post: rating \"4\" -> 4.0".to_string()
+            ]
+        );
+        // Planning writes nothing, so the stale file still fails to load.
+        assert!(archival.get_object("post", Some("a")).is_err());
+
+        archival.apply_migration(&plan)?;
+        let a = archival.get_object("post", Some("a"))?;
+        assert_eq!(a.values.get("name"), Some(&FieldValue::String("A".into())));
+        assert_eq!(a.values.get("rating"), Some(&FieldValue::Number(4.)));
+        // Migrations can be re-run safely
+        assert!(archival.plan_migration(&migration)?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,261 @@
+//! Computed fields: fields whose value is a liquid expression over the rest of
+//! the object, declared as `slug = { computed = "title | slugify" }`.
+//!
+//! An expression is rendered as `{{ <expression> }}` with the object's own
+//! values as its context, so it can use every filter a template can (other than
+//! those provided by plugins). Expressions that already contain liquid markup,
+//! such as `"{{ year }}-{{ title }}"`, are rendered as written.
+
+use super::to_liquid::ToLiquidOptions;
+use crate::{
+    liquid_parser::{self, ArchivalPartialSource},
+    FieldConfig, FieldValue, ObjectDefinition,
+};
+use anyhow::Result;
+use liquid::model::KString;
+use once_cell::sync::Lazy;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+use tracing::warn;
+
+static PARSER: Lazy<liquid::Parser> = Lazy::new(|| {
+    liquid_parser::build_with_partials(ArchivalPartialSource::default())
+        .expect("failed building computed field parser")
+});
+
+/// Expressions are parsed once when definitions are loaded, then rendered for
+/// every object, so parsed templates are kept for the life of the process.
+static TEMPLATES: Lazy<RwLock<HashMap<String, Arc<liquid::Template>>>> =
+    Lazy::new(Default::default);
+
+fn template_source(expression: &str) -> String {
+    if expression.contains("{{") || expression.contains("{%") {
+        expression.to_string()
+    } else {
+        format!("{{{{ {} }}}}", expression)
+    }
+}
+
+pub(crate) fn parse(expression: &str) -> Result<Arc<liquid::Template>, liquid_core::Error> {
+    if let Some(template) = TEMPLATES.read().unwrap().get(expression) {
+        return Ok(template.clone());
+    }
+    let template = Arc::new(liquid_parser::parse(&PARSER, &template_source(expression))?);
+    TEMPLATES
+        .write()
+        .unwrap()
+        .insert(expression.to_string(), template.clone());
+    Ok(template)
+}
+
+/// Evaluates `definition`'s computed fields and inserts them into `object`,
+/// the liquid form of one of its instances. Fields are evaluated in definition
+/// order, so a computed field can use the ones declared before it.
+///
+/// Nothing here can fail a render, so a field that fails to evaluate is logged
+/// and left empty.
+pub(crate) fn insert_computed(
+    object: &mut liquid::Object,
+    definition: &ObjectDefinition,
+    field_config: &FieldConfig,
+    options: ToLiquidOptions,
+) {
+    for (key, field) in &definition.fields {
+        let Some(expression) = &field.computed else {
+            continue;
+        };
+        let value = match evaluate(key, expression, &field.r#type, object) {
+            Ok(value) => value.to_liquid_with(field_config, options),
+            Err(e) => {
+                warn!("{}.{}: {}", definition.name, key, e);
+                liquid::model::Value::Nil
+            }
+        };
+        object.insert(KString::from_ref(key), value);
+    }
+}
+
+fn evaluate(
+    key: &String,
+    expression: &str,
+    field_type: &crate::FieldType,
+    object: &liquid::Object,
+) -> Result<FieldValue> {
+    let output = parse(expression)?.render(object)?;
+    Ok(FieldValue::from_string(
+        key,
+        field_type,
+        output.trim().to_string(),
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        events::{ArchivalEvent, EditFieldEvent},
+        fields::{FieldValueValidationError, InvalidFieldError},
+        object::{Object, ValuePath},
+        Archival, FieldConfig, FieldType, FieldValue, FileSystemAPI, MemoryFileSystem,
+        ObjectDefinition, MANIFEST_FILE_NAME, OBJECT_DEFINITION_FILE_NAME,
+    };
+    use liquid::ValueView;
+    use ordermap::OrderMap;
+    use std::path::Path;
+    use toml::Table;
+
+    fn definitions() -> crate::ObjectDefinitions {
+        ObjectDefinition::from_source(
+            r#"
+            [post]
+            title = "string"
+            date = "date"
+            # The url-safe title.
+            slug = { computed = "title | slugify" }
+            year = { computed = "date | date: '%Y'", type = "number" }
+            label = { computed = "{{ year }}/{{ slug }}" }
+            [post.links]
+            type = "string"
+            url = "string"
+            host = { computed = "url | split: '/' | slice: 2" }
+            "#,
+            &OrderMap::new(),
+        )
+        .unwrap()
+    }
+
+    fn post() -> (ObjectDefinition, Object) {
+        let definition = definitions().get("post").unwrap().clone();
+        let table: Table = toml::from_str(
+            r#"
+            title = "Hello World"
+            date = 2024-03-01T00:00:00
+            slug = "stale"
+            [[links]]
+            type = "external"
+            url = "https://archival.dev/docs"
+            "#,
+        )
+        .unwrap();
+        let object = Object::from_table(
+            &definition,
+            Path::new("hello"),
+            &table,
+            &OrderMap::new(),
+            false,
+        )
+        .unwrap();
+        (definition, object)
+    }
+
+    #[test]
+    fn computed_fields_are_parsed() {
+        let defs = definitions();
+        let post = defs.get("post").unwrap();
+        let slug = post.fields.get("slug").unwrap();
+        assert_eq!(slug.r#type, FieldType::String);
+        assert_eq!(slug.computed.as_deref(), Some("title | slugify"));
+        assert_eq!(slug.description.as_deref(), Some("The url-safe title."));
+        assert_eq!(post.field_type("year"), Some(&FieldType::Number));
+        assert!(!post.fields.get("title").unwrap().is_computed());
+        // A table with only a `type` is still a child.
+        let links = post.children.get("links").unwrap();
+        assert_eq!(links.field_type("type"), Some(&FieldType::String));
+        assert!(links.fields.get("host").unwrap().is_computed());
+    }
+
+    #[test]
+    fn invalid_computed_fields_are_rejected() {
+        for source in [
+            "[post]\nslug = { computed = \"title | \" }",
+            "[post]\nslug = { computed = 3 }",
+            "[post]\nslug = { computed = \"title\", type = \"image\" }",
+        ] {
+            let err = ObjectDefinition::from_source(source, &OrderMap::new()).unwrap_err();
+            assert!(
+                matches!(
+                    err.downcast_ref::<InvalidFieldError>(),
+                    Some(InvalidFieldError::InvalidComputed { .. })
+                ),
+                "{source}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn computed_fields_are_never_stored() {
+        let (definition, object) = post();
+        assert!(!object.values.contains_key("slug"));
+        let written = object.to_toml(&definition).unwrap();
+        assert!(!written.contains("slug"), "{}", written);
+        assert!(!written.contains("host"), "{}", written);
+    }
+
+    #[test]
+    fn computed_fields_are_evaluated_in_liquid() {
+        let (definition, object) = post();
+        let liquid = object.liquid_object(&definition, &FieldConfig::default());
+        let liquid = liquid.as_object().unwrap();
+        assert_eq!(liquid.get("slug").unwrap().to_kstr(), "hello-world");
+        assert_eq!(
+            liquid.get("year").unwrap().as_scalar().unwrap().to_float(),
+            Some(2024.)
+        );
+        assert_eq!(liquid.get("label").unwrap().to_kstr(), "2024/hello-world");
+        let links = liquid.get("links").unwrap().as_array().unwrap();
+        let link = links.first().unwrap();
+        assert_eq!(
+            link.as_object().unwrap().get("host").unwrap().to_kstr(),
+            "archival.dev"
+        );
+    }
+
+    #[test]
+    fn computed_fields_cannot_be_set() {
+        let (definition, _) = post();
+        let err = FieldValue::String("nope".to_string())
+            .validate(
+                &ValuePath::from_string("slug"),
+                &definition,
+                &OrderMap::new(),
+            )
+            .unwrap_err();
+        assert!(matches!(err, FieldValueValidationError::ComputedField(_)));
+    }
+
+    #[test]
+    fn edit_field_rejects_computed_fields() -> anyhow::Result<()> {
+        let mut fs = MemoryFileSystem::default();
+        fs.write_str(MANIFEST_FILE_NAME, "upload_prefix = \"\"\n".to_string())?;
+        fs.write_str(
+            OBJECT_DEFINITION_FILE_NAME,
+            "[post]\ntitle = \"string\"\nslug = { computed = \"title | slugify\" }".to_string(),
+        )?;
+        fs.write_str("objects/post/hello.toml", "title = \"Hello\"".to_string())?;
+        let archival = Archival::new(fs)?;
+        for value in [Some(FieldValue::String("nope".to_string())), None] {
+            let err = archival
+                .send_event(
+                    ArchivalEvent::EditField(EditFieldEvent {
+                        object: "post".to_string(),
+                        filename: "hello".to_string(),
+                        path: ValuePath::empty(),
+                        field: "slug".to_string(),
+                        value,
+                        source: None,
+                    }),
+                    None,
+                )
+                .unwrap_err();
+            assert!(
+                matches!(
+                    err.downcast_ref::<FieldValueValidationError>(),
+                    Some(FieldValueValidationError::ComputedField(_))
+                ),
+                "{err}"
+            );
+        }
+        Ok(())
+    }
+}
@@ -23,9 +23,12 @@ use std::{
 use to_liquid::{object_to_liquid_with, ToLiquidOptions};
 use toml::Table;
 use tracing::{instrument, warn};
+pub(crate) mod computed;
 mod object_entry;
+pub(crate) mod sort;
 pub(crate) mod to_liquid;
 pub use object_entry::{ObjectEntry, RenderedObjectEntry};
+pub use sort::SortKey;
 
 pub type ObjectMap = OrderMap<String, ObjectEntry>;
 pub type RenderedObjectMap = OrderMap<String, RenderedObjectEntry>;
@@ -102,6 +105,13 @@ impl Object {
     pub fn url_path(&self) -> String {
         path_to_slash(self.path())
     }
+    /// A token for this object's contents, which changes whenever the object
+    /// does. Sending it back with a [`crate::events::CheckedEvent`] makes sure
+    /// the event isn't overwriting changes its sender hasn't seen.
+    pub fn version(&self) -> String {
+        // Objects only hold values that serialize, so this can't fail.
+        crate::sha_for_data(&serde_json::to_vec(self).unwrap_or_default())
+    }
 }
 
 impl Hash for Object {
@@ -150,7 +160,18 @@ impl Object {
                 .get(type_name)
                 .map(|i| &i.alias_of)
                 .unwrap_or(type_name);
-            if let Some(field_type) = definition.field_type(def_key) {
+            if definition
+                .fields
+                .get(def_key)
+                .is_some_and(|f| f.is_computed())
+            {
+                // Computed fields are evaluated, never read.
+                warn!(
+                    "{}: ignoring value for computed field {}",
+                    file.display(),
+                    def_key
+                );
+            } else if let Some(field_type) = definition.field_type(def_key) {
                 // Values
                 let field_value = FieldValue::from_toml(def_key, field_type, value)?;
                 if !skip_validation {
@@ -234,14 +255,11 @@ impl Object {
         order: Option<f64>,
         defaults: Vec<AddObjectValue>,
     ) -> Result<Self> {
-        let path = Path::new(&definition.name).join(filename);
-        let values =
-            Object::values_from_table(&path, &Table::new(), definition, &OrderMap::new(), false)?;
         let mut object = Self {
             filename: filename.to_owned(),
             object_name: definition.name.clone(),
             order,
-            values,
+            values: definition.default_object(),
         };
         for default in defaults {
             default
@@ -0,0 +1,278 @@
+//! Default sort orders, declared per object (and per child) with
+//! `sort = ["-date", "title"]` in `archival_objects.toml`.
+//!
+//! Each key names a field to sort by, ascending unless prefixed with `-`.
+//! `order` may be used as a key too. Unset values sort last in either
+//! direction, and objects that tie on every key are ordered by filename so the
+//! order never depends on the file system.
+
+use super::Object;
+use crate::{
+    fields::{FieldType, FieldValue, InvalidFieldError, ObjectValues},
+    object_definition::FieldsMap,
+    reserved_fields,
+};
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, fmt};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
+pub struct SortKey {
+    pub field: String,
+    pub descending: bool,
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.descending {
+            write!(f, "-{}", self.field)
+        } else {
+            write!(f, "{}", self.field)
+        }
+    }
+}
+
+impl SortKey {
+    /// Parses the `sort` array of the object named `object`, checking each key
+    /// against its fields.
+    pub fn from_toml(
+        object: &str,
+        value: &toml::Value,
+        fields: &FieldsMap,
+    ) -> Result<Vec<SortKey>, InvalidFieldError> {
+        let invalid = |error: String| InvalidFieldError::InvalidSort {
+            object: object.to_string(),
+            error,
+        };
+        let keys = value
+            .as_array()
+            .ok_or_else(|| invalid(format!("expected an array of field names, got {value}")))?;
+        keys.iter()
+            .map(|key| {
+                let key = key
+                    .as_str()
+                    .ok_or_else(|| invalid(format!("expected a field name, got {key}")))?;
+                let (field, descending) = match key.strip_prefix('-') {
+                    Some(field) => (field, true),
+                    None => (key, false),
+                };
+                if field != reserved_fields::ORDER {
+                    let definition = fields
+                        .get(field)
+                        .ok_or_else(|| invalid(format!("no field named {field:?}")))?;
+                    if definition.is_computed() {
+                        return Err(invalid(format!(
+                            "{field:?} is computed, and can't be sorted by"
+                        )));
+                    }
+                    if !is_sortable(&definition.r#type) {
+                        return Err(invalid(format!(
+                            "{field:?} is a {} field, and can't be sorted by",
+                            definition.r#type
+                        )));
+                    }
+                }
+                Ok(SortKey {
+                    field: field.to_string(),
+                    descending,
+                })
+            })
+            .collect()
+    }
+}
+
+fn is_sortable(field_type: &FieldType) -> bool {
+    matches!(
+        field_type.base_type(),
+        FieldType::String
+            | FieldType::Markdown
+            | FieldType::Enum(_)
+            | FieldType::Number
+            | FieldType::Date
+            | FieldType::Boolean
+    )
+}
+
+/// Orders two objects by `keys`, then by filename. Without any keys objects
+/// are ordered by their `order`, falling back to filename.
+pub(crate) fn compare_objects(keys: &[SortKey], a: &Object, b: &Object) -> Ordering {
+    if keys.is_empty() {
+        return legacy_order(a)
+            .cmp(&legacy_order(b))
+            .then_with(|| a.filename.cmp(&b.filename));
+    }
+    keys.iter()
+        .map(|key| {
+            if key.field == reserved_fields::ORDER {
+                compare_present(
+                    a.order.map(FieldValue::Number).as_ref(),
+                    b.order.map(FieldValue::Number).as_ref(),
+                    key.descending,
+                )
+            } else {
+                compare_present(
+                    a.values.get(&key.field),
+                    b.values.get(&key.field),
+                    key.descending,
+                )
+            }
+        })
+        .find(|o| o.is_ne())
+        .unwrap_or_else(|| a.filename.cmp(&b.filename))
+}
+
+/// Orders two children by `keys`. Children have no filename, so ties keep the
+/// order they were written in.
+pub(crate) fn compare_children(keys: &[SortKey], a: &ObjectValues, b: &ObjectValues) -> Ordering {
+    keys.iter()
+        .map(|key| compare_present(a.get(&key.field), b.get(&key.field), key.descending))
+        .find(|o| o.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+// Objects without an order have always sorted by filename, with ordered
+// objects compared as zero-padded strings.
+fn legacy_order(obj: &Object) -> String {
+    if let Some(order) = obj.order {
+        format!("{:0>10}", order)
+    } else {
+        obj.filename.to_owned()
+    }
+}
+
+pub(crate) fn compare_present(
+    a: Option<&FieldValue>,
+    b: Option<&FieldValue>,
+    descending: bool,
+) -> Ordering {
+    match (present(a), present(b)) {
+        (Some(a), Some(b)) => {
+            let ordering = compare_values(a, b);
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        }
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn present(value: Option<&FieldValue>) -> Option<&FieldValue> {
+    value.filter(|v| !matches!(v, FieldValue::Null))
+}
+
+fn compare_values(a: &FieldValue, b: &FieldValue) -> Ordering {
+    partial_compare(a, b).unwrap_or(Ordering::Equal)
+}
+
+/// Orders two values of comparable types. String, markdown and enum values
+/// all compare as strings.
+pub(crate) fn partial_compare(a: &FieldValue, b: &FieldValue) -> Option<Ordering> {
+    match (a, b) {
+        (FieldValue::Number(a), FieldValue::Number(b)) => a.partial_cmp(b),
+        (FieldValue::Date(a), FieldValue::Date(b)) => {
+            a.as_liquid_datetime().partial_cmp(&b.as_liquid_datetime())
+        }
+        (FieldValue::Boolean(a), FieldValue::Boolean(b)) => Some(a.cmp(b)),
+        (
+            FieldValue::String(a) | FieldValue::Markdown(a) | FieldValue::Enum(a),
+            FieldValue::String(b) | FieldValue::Markdown(b) | FieldValue::Enum(b),
+        ) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fields::DateTime, ObjectDefinition};
+    use ordermap::OrderMap;
+
+    fn definition(source: &str) -> Result<ObjectDefinition, anyhow::Error> {
+        let defs = ObjectDefinition::from_source(source, &OrderMap::new())?;
+        Ok(defs.get("post").unwrap().clone())
+    }
+
+    fn post(filename: &str, date: Option<&str>, title: &str) -> Object {
+        let mut values = ObjectValues::new();
+        if let Some(date) = date {
+            values.insert(
+                "date".to_string(),
+                FieldValue::Date(DateTime::from(&format!("{date} 00:00:00")).unwrap()),
+            );
+        }
+        values.insert("title".to_string(), FieldValue::String(title.to_string()));
+        Object {
+            filename: filename.to_string(),
+            object_name: "post".to_string(),
+            order: None,
+            values,
+        }
+    }
+
+    #[test]
+    fn sorts_by_keys_then_filename() {
+        let def = definition(
+            r#"[post]
+            sort = ["-date", "title"]
+            date = "date"
+            title = "string"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            def.sort,
+            vec![
+                SortKey {
+                    field: "date".to_string(),
+                    descending: true
+                },
+                SortKey {
+                    field: "title".to_string(),
+                    descending: false
+                }
+            ]
+        );
+        let mut posts = vec![
+            post("d", None, "a"),
+            post("c", Some("2024-01-01"), "b"),
+            post("b", Some("2024-02-01"), "z"),
+            post("a", Some("2024-01-01"), "b"),
+            post("e", Some("2024-01-01"), "a"),
+        ];
+        def.sort_objects(&mut posts);
+        let filenames: Vec<&str> = posts.iter().map(|p| p.filename.as_str()).collect();
+        assert_eq!(filenames, vec!["b", "e", "a", "c", "d"]);
+    }
+
+    #[test]
+    fn invalid_sort_keys_are_rejected() {
+        for source in [
+            "[post]\nsort = [\"missing\"]\ntitle = \"string\"",
+            "[post]\nsort = [\"cover\"]\ncover = \"image\"",
+            "[post]\nsort = [\"slug\"]\ntitle = \"string\"\nslug = { computed = \"title\" }",
+        ] {
+            let err = definition(source).unwrap_err();
+            assert!(
+                matches!(
+                    err.downcast_ref::<InvalidFieldError>(),
+                    Some(InvalidFieldError::InvalidSort { .. })
+                ),
+                "{source}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn sort_is_a_field_unless_it_lists_field_names() {
+        let def = definition("[post]\nsort = \"number\"\ntitle = \"string\"").unwrap();
+        assert!(def.sort.is_empty());
+        assert_eq!(def.field_type("sort"), Some(&crate::FieldType::Number));
+        let def = definition("[post]\nsort = { type = \"string\", required = true }").unwrap();
+        assert!(def.sort.is_empty());
+        assert!(def.fields.contains_key("sort"));
+    }
+}
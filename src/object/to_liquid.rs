@@ -1,5 +1,9 @@
+use super::computed::insert_computed;
 use crate::{
-    fields::{File, ObjectValues},
+    fields::{
+        markdown::{meta_field_name, rendered_markdown_cached},
+        File, ObjectValues,
+    },
     FieldConfig, FieldValue, ObjectDefinition,
 };
 use liquid::model::{KString, ObjectIndex};
@@ -21,13 +25,20 @@ pub fn object_to_liquid_with(
         .iter()
         // Secret fields are never added to template contexts.
         .filter(|(_, field)| options.include_secrets || !field.r#type.is_secret())
-        .map(|(k, _)| {
+        .map(|(k, field)| {
             (
                 KString::from_ref(k.as_index()),
                 object_values
                     .get(k)
                     .map(|v| v.to_liquid_with(field_config, options))
-                    .unwrap_or_else(|| Value::Nil),
+                    .unwrap_or_else(|| {
+                        // Like children, an unset list is just an empty one.
+                        if field.r#type.list_item().is_some() {
+                            Value::Array(vec![])
+                        } else {
+                            Value::Nil
+                        }
+                    }),
             )
         })
         .collect();
@@ -54,9 +65,34 @@ pub fn object_to_liquid_with(
             }
         })
         .collect();
+    // Markdown fields stay html strings, with what is derived from them
+    // beside them (see `fields::markdown`). Parsed definitions can't use
+    // that name, but ones built in code might, and their fields win.
+    let mut markdown_meta: Vec<(KString, Value)> = definition
+        .fields
+        .keys()
+        .filter_map(|k| match object_values.get(k) {
+            Some(FieldValue::Markdown(m)) => Some((meta_field_name(k), m)),
+            _ => None,
+        })
+        .filter(|(name, _)| {
+            !definition.fields.contains_key(name) && !definition.children.contains_key(name)
+        })
+        .map(|(name, m)| {
+            (
+                KString::from_string(name),
+                rendered_markdown_cached(m).meta_liquid(),
+            )
+        })
+        .collect();
     values.append(&mut meta_values);
+    values.append(&mut markdown_meta);
     values.append(&mut child_values);
-    values.into_iter().collect()
+    let mut object = values.into_iter().collect();
+    if definition.has_computed_fields() {
+        insert_computed(&mut object, definition, field_config, options);
+    }
+    object
 }
 
 impl FieldValue {
@@ -88,6 +124,12 @@ impl FieldValue {
                 .into(),
                 None => liquid::model::Value::Nil,
             },
+            FieldValue::List(items) => liquid::model::Value::Array(
+                items
+                    .iter()
+                    .map(|item| item.to_liquid_with(field_config, options))
+                    .collect(),
+            ),
             _ => self.to_value(),
         }
     }
@@ -1,8 +1,11 @@
-#[cfg(feature = "json-schema")]
-use crate::object::ValuePath;
+use crate::object::{sort, Object, SortKey, ValuePath};
 use crate::{
     definition_comments::{extract_comments, DefinitionComments},
-    fields::{field_type::InvalidFieldError, FieldType, ObjectValues, OneofOption},
+    fields::{
+        constraints::CONSTRAINT_OPTIONS, field_type::InvalidFieldError, markdown::meta_field_name,
+        ConstraintViolation, FieldConstraints, FieldType, FieldValueValidationError, ObjectValues,
+        OneofOption,
+    },
     manifest::EditorTypes,
     reserved_fields::{self, is_reserved_field, reserved_field_from_str, ReservedFieldError},
     FieldValue,
@@ -12,7 +15,7 @@ use ordermap::OrderMap;
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "json-schema")]
 use serde_json::json;
-use std::fmt::Debug;
+use std::{cmp::Ordering, collections::HashMap, fmt::Debug, hash::Hash};
 use toml::Table;
 use tracing::instrument;
 
@@ -67,12 +70,40 @@ pub type FieldsMap = OrderMap<String, FieldDefinition>;
 /// `description` comes from the comment above the field in
 /// `archival_objects.toml` (see [`crate::definition_comments`]) and exists so
 /// editors and generated types can explain a field rather than just naming it.
-#[derive(Debug, Serialize, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Serialize, Clone, PartialEq)]
 #[cfg_attr(feature = "typescript", derive(typescript_type_def::TypeDef))]
 pub struct FieldDefinition {
     pub r#type: FieldType,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    /// A liquid expression over the object's other fields. Computed fields are
+    /// never stored: they are evaluated whenever the object is converted to
+    /// liquid (see [`crate::object::computed`]), and can't be set by events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub computed: Option<String>,
+    /// The value new objects and children get for this field when they are
+    /// created without one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<FieldValue>,
+    /// Rules a value must follow to be saved, see [`crate::fields::constraints`].
+    #[serde(default, skip_serializing_if = "FieldConstraints::is_empty")]
+    pub constraints: FieldConstraints,
+}
+
+impl Hash for FieldDefinition {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.r#type.hash(state);
+        self.description.hash(state);
+        self.computed.hash(state);
+        // FieldValue's own hash only special-cases numbers; defaults are
+        // always scalars, so their toml form is a stable stand-in.
+        self.default
+            .as_ref()
+            .and_then(Option::<toml::Value>::from)
+            .map(|v| v.to_string())
+            .hash(state);
+        self.constraints.hash(state);
+    }
 }
 
 impl FieldDefinition {
@@ -80,16 +111,196 @@ impl FieldDefinition {
         Self {
             r#type,
             description,
+            computed: None,
+            default: None,
+            constraints: FieldConstraints::default(),
         }
     }
+
+    pub fn is_computed(&self) -> bool {
+        self.computed.is_some()
+    }
+
+    /// Parses a field written as an inline table of options, such as
+    /// `slug = { computed = "title | slugify" }`. Returns `None` when the table
+    /// is a child definition instead: a field table only ever contains the keys
+    /// in [`FIELD_OPTIONS`], and at least one of them other than `type`, since
+    /// a child with a single `type` field is a perfectly good child.
+    ///
+    /// Tables written under a header (`[object.child]`) never reach this, so a
+    /// child whose fields happen to be named `min` and `max` stays a child.
+    fn from_table(
+        key: &str,
+        table: &Table,
+        description: Option<String>,
+        editor_types: &EditorTypes,
+    ) -> Result<Option<Self>> {
+        if !table.keys().all(|k| {
+            FIELD_OPTIONS.contains(&k.as_str()) || CONSTRAINT_OPTIONS.contains(&k.as_str())
+        }) || !table.keys().any(|k| k != FIELD_TYPE)
+        {
+            return Ok(None);
+        }
+        let values = match table.get(FIELD_VALUES) {
+            Some(values) => Some(enum_values(values)?),
+            None => None,
+        };
+        let r#type = match (table.get(FIELD_TYPE), values) {
+            // Lists: `type = ["string"]`, or `type = ["enum"]` alongside values.
+            (Some(toml::Value::Array(item)), values) => match (&item[..], values) {
+                ([toml::Value::String(item)], Some(values)) if item == "enum" => {
+                    FieldType::List(Box::new(FieldType::Enum(values)))
+                }
+                (_, Some(_)) => {
+                    return Err(InvalidFieldError::InvalidEnum(format!(
+                        "{key}: only enum fields have values, but this is a list"
+                    ))
+                    .into())
+                }
+                (item, None) => {
+                    FieldType::list_from_toml(item, editor_types)?.ok_or_else(|| {
+                        InvalidFieldError::UnrecognizedType(format!("{key}: {item:?}"))
+                    })?
+                }
+            },
+            (Some(t), values) => {
+                let type_name = t
+                    .as_str()
+                    .ok_or_else(|| InvalidFieldError::UnrecognizedType(format!("{key}: {t}")))?;
+                match (type_name, values) {
+                    ("enum", Some(values)) => FieldType::Enum(values),
+                    ("enum", None) => {
+                        return Err(InvalidFieldError::InvalidEnum(format!(
+                            "{key}: enum fields must list their values"
+                        ))
+                        .into())
+                    }
+                    (type_name, None) => FieldType::from_str(type_name, editor_types)?,
+                    (type_name, Some(_)) => {
+                        return Err(InvalidFieldError::InvalidEnum(format!(
+                            "{key}: only enum fields have values, but this is a {type_name}"
+                        ))
+                        .into())
+                    }
+                }
+            }
+            (None, Some(values)) => FieldType::Enum(values),
+            (None, None) => FieldType::String,
+        };
+        let mut field = Self::new(r#type, description);
+        if let Some(computed) = table.get(FIELD_COMPUTED) {
+            let expression =
+                computed
+                    .as_str()
+                    .ok_or_else(|| InvalidFieldError::InvalidComputed {
+                        field: key.to_string(),
+                        error: format!("expected a string, got {computed}"),
+                    })?;
+            if !field.r#type.base_type().is_computable() {
+                return Err(InvalidFieldError::InvalidComputed {
+                    field: key.to_string(),
+                    error: format!("{} fields can't be computed", field.r#type),
+                }
+                .into());
+            }
+            crate::object::computed::parse(expression).map_err(|e| {
+                InvalidFieldError::InvalidComputed {
+                    field: key.to_string(),
+                    error: e.to_string(),
+                }
+            })?;
+            field.computed = Some(expression.to_string());
+        }
+        field.constraints = FieldConstraints::from_table(key, &field.r#type, table)?;
+        if field.is_computed() && !field.constraints.is_empty() {
+            return Err(InvalidFieldError::InvalidConstraint {
+                field: key.to_string(),
+                error: "computed fields can't have constraints".to_string(),
+            }
+            .into());
+        }
+        if let Some(default) = table.get(FIELD_DEFAULT) {
+            let invalid = |error: String| InvalidFieldError::InvalidDefault {
+                field: key.to_string(),
+                error,
+            };
+            if field.is_computed() {
+                return Err(invalid("computed fields can't have a default".to_string()).into());
+            }
+            if !field.r#type.base_type().is_scalar() {
+                return Err(
+                    invalid(format!("{} fields can't have a default", field.r#type)).into(),
+                );
+            }
+            let value = FieldValue::from_toml(&key.to_string(), &field.r#type, default)
+                .map_err(|e| invalid(e.to_string()))?;
+            field
+                .constraints
+                .check(Some(&value))
+                .map_err(|e| invalid(e.to_string()))?;
+            field.default = Some(value);
+        }
+        Ok(Some(field))
+    }
+}
+
+const FIELD_TYPE: &str = "type";
+const FIELD_COMPUTED: &str = "computed";
+const FIELD_VALUES: &str = "values";
+const FIELD_DEFAULT: &str = "default";
+/// The keys a field written as a table may contain, along with
+/// [`CONSTRAINT_OPTIONS`].
+const FIELD_OPTIONS: [&str; 4] = [FIELD_TYPE, FIELD_COMPUTED, FIELD_VALUES, FIELD_DEFAULT];
+
+/// The tables in a definitions file that were written under a header of their
+/// own (`[object.child]`, or a dotted key) rather than inline, keyed by name.
+///
+/// `toml` reads both spellings as the same table, but only an inline table can
+/// be a field's options - anything with a header is a child definition, even
+/// if all of its field names are also option names.
+#[derive(Debug, Default)]
+struct HeaderTables(HashMap<String, HeaderTables>);
+
+impl HeaderTables {
+    fn from_source(source: &str) -> Result<Self, toml_edit::TomlError> {
+        let doc = toml_edit::Document::<&str>::parse(source)?;
+        Ok(Self::from_table(doc.as_table()))
+    }
+
+    fn from_table(table: &toml_edit::Table) -> Self {
+        Self(
+            table
+                .iter()
+                .filter_map(|(name, item)| {
+                    let table = item.as_table()?;
+                    Some((name.to_string(), Self::from_table(table)))
+                })
+                .collect(),
+        )
+    }
+
+    /// The headers beneath `name`, or `None` if it wasn't written with one.
+    fn child(&self, name: &str) -> Option<&HeaderTables> {
+        self.0.get(name)
+    }
+}
+
+/// The values of a string enum, written as an array of strings.
+fn enum_values(value: &toml::Value) -> Result<Vec<String>, InvalidFieldError> {
+    value
+        .as_array()
+        .and_then(|values| {
+            values
+                .iter()
+                .map(|v| v.as_str().map(|s| s.to_string()))
+                .collect()
+        })
+        .ok_or_else(|| InvalidFieldError::InvalidEnum(format!("{value:?}")))
 }
 
 impl From<FieldType> for FieldDefinition {
     fn from(r#type: FieldType) -> Self {
-        Self {
-            r#type,
-            description: None,
-        }
+        Self::new(r#type, None)
     }
 }
 
@@ -100,11 +311,18 @@ impl<'de> Deserialize<'de> for FieldDefinition {
     fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
         #[derive(Deserialize)]
         #[serde(untagged)]
+        #[allow(clippy::large_enum_variant)]
         enum Repr {
             Described {
                 r#type: FieldType,
                 #[serde(default)]
                 description: Option<String>,
+                #[serde(default)]
+                computed: Option<String>,
+                #[serde(default)]
+                default: Option<FieldValue>,
+                #[serde(default)]
+                constraints: FieldConstraints,
             },
             Bare(FieldType),
         }
@@ -112,9 +330,15 @@ impl<'de> Deserialize<'de> for FieldDefinition {
             Repr::Described {
                 r#type,
                 description,
+                computed,
+                default,
+                constraints,
             } => Self {
                 r#type,
                 description,
+                computed,
+                default,
+                constraints,
             },
             Repr::Bare(r#type) => Self::from(r#type),
         })
@@ -135,6 +359,11 @@ pub struct ObjectDefinition {
     /// The comment above this object's `[header]` in `archival_objects.toml`.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+
+    /// The default order of this object's instances (or of this child's
+    /// entries), from `sort = ["-date", "title"]`. See [`SortKey`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sort: Vec<SortKey>,
 }
 
 impl ObjectDefinition {
@@ -148,7 +377,9 @@ impl ObjectDefinition {
         // `toml` just accepted - both crates track the same TOML spec version,
         // so disagreement here would be a bug rather than an authoring error.
         let comments = extract_comments(source).unwrap_or_default();
-        Self::from_table(&table, &comments, editor_types)
+        // Same reasoning: without it, every table is read as if written inline.
+        let headers = HeaderTables::from_source(source).unwrap_or_default();
+        Self::from_table_with_headers(&table, &comments, &headers, editor_types)
     }
 
     pub fn new(
@@ -156,6 +387,22 @@ impl ObjectDefinition {
         definition: &Table,
         comments: &DefinitionComments,
         editor_types: &EditorTypes,
+    ) -> Result<ObjectDefinition> {
+        Self::new_with_headers(
+            name,
+            definition,
+            comments,
+            &HeaderTables::default(),
+            editor_types,
+        )
+    }
+
+    fn new_with_headers(
+        name: &str,
+        definition: &Table,
+        comments: &DefinitionComments,
+        headers: &HeaderTables,
+        editor_types: &EditorTypes,
     ) -> Result<ObjectDefinition> {
         if is_reserved_field(name) {
             return Err(InvalidFieldError::ReservedObjectNameError(name.to_string()).into());
@@ -166,8 +413,20 @@ impl ObjectDefinition {
             template: None,
             children: ObjectDefinitions::new(),
             description: comments.own.clone(),
+            sort: vec![],
         };
+        let mut sort = None;
         for (key, m_value) in definition {
+            if key == reserved_fields::SORT
+                && m_value
+                    .as_array()
+                    .is_some_and(|keys| keys.iter().all(toml::Value::is_str))
+            {
+                // Sort keys name fields, so they're checked once every field
+                // has been parsed. Any other `sort` is an ordinary field.
+                sort = Some(m_value);
+                continue;
+            }
             if is_reserved_field(key)
                 && !(m_value.as_str().is_some() && key == reserved_fields::TEMPLATE)
             {
@@ -177,12 +436,34 @@ impl ObjectDefinition {
                 .into());
             }
             if let Some(child_table) = m_value.as_table() {
+                let child_headers = headers.child(key);
+                if child_headers.is_none() {
+                    if let Some(field) = FieldDefinition::from_table(
+                        key,
+                        child_table,
+                        comments.child(key).own.clone(),
+                        editor_types,
+                    )? {
+                        obj_def.fields.insert(key.clone(), field);
+                        continue;
+                    }
+                }
                 obj_def.children.insert(
                     key.clone(),
-                    ObjectDefinition::new(key, child_table, comments.child(key), editor_types)?,
+                    ObjectDefinition::new_with_headers(
+                        key,
+                        child_table,
+                        comments.child(key),
+                        child_headers.unwrap_or(&HeaderTables::default()),
+                        editor_types,
+                    )?,
                 );
             } else if let Some(value) = m_value.as_array() {
-                if let Some(tables) = value
+                if let Some(list) = FieldType::list_from_toml(value, editor_types)? {
+                    obj_def
+                        .fields
+                        .insert(key.clone(), FieldDefinition::new(list, comments.field(key)));
+                } else if let Some(tables) = value
                     .iter()
                     .map(|v| v.as_table())
                     .collect::<Option<Vec<_>>>()
@@ -241,25 +522,81 @@ impl ObjectDefinition {
                 }
             }
         }
+        if let Some(sort) = sort {
+            obj_def.sort = SortKey::from_toml(name, sort, &obj_def.fields)?;
+        }
+        for (key, field) in &obj_def.fields {
+            if field.r#type.base_type() != &FieldType::Markdown {
+                continue;
+            }
+            let meta_name = meta_field_name(key);
+            if obj_def.fields.contains_key(&meta_name) || obj_def.children.contains_key(&meta_name)
+            {
+                return Err(InvalidFieldError::MarkdownMetaCollision {
+                    field: meta_name,
+                    markdown: key.clone(),
+                }
+                .into());
+            }
+        }
         Ok(obj_def)
     }
     pub fn from_table(
         table: &Table,
         comments: &DefinitionComments,
         editor_types: &EditorTypes,
+    ) -> Result<ObjectDefinitions> {
+        Self::from_table_with_headers(table, comments, &HeaderTables::default(), editor_types)
+    }
+
+    fn from_table_with_headers(
+        table: &Table,
+        comments: &DefinitionComments,
+        headers: &HeaderTables,
+        editor_types: &EditorTypes,
     ) -> Result<ObjectDefinitions> {
         let mut objects = ObjectDefinitions::new();
         for (name, m_def) in table.into_iter() {
             if let Some(def) = m_def.as_table() {
                 objects.insert(
                     name.clone(),
-                    ObjectDefinition::new(name, def, comments.child(name), editor_types)?,
+                    ObjectDefinition::new_with_headers(
+                        name,
+                        def,
+                        comments.child(name),
+                        headers.child(name).unwrap_or(&HeaderTables::default()),
+                        editor_types,
+                    )?,
                 );
             }
         }
         Ok(objects)
     }
 
+    /// Whether this object has any computed fields. Children are not included,
+    /// since they are converted to liquid separately.
+    pub fn has_computed_fields(&self) -> bool {
+        self.fields.values().any(FieldDefinition::is_computed)
+    }
+
+    /// Orders two instances of this object by its [`sort`](Self::sort), then by
+    /// filename. Objects without a declared sort are ordered by `order`.
+    pub fn compare_objects(&self, a: &Object, b: &Object) -> Ordering {
+        sort::compare_objects(&self.sort, a, b)
+    }
+
+    /// Sorts instances of this object into their default order.
+    pub fn sort_objects(&self, objects: &mut [Object]) {
+        objects.sort_by(|a, b| self.compare_objects(a, b));
+    }
+
+    /// Orders two entries of this child by its [`sort`](Self::sort). Entries
+    /// that tie keep the order they were written in, as do all entries of a
+    /// child without a declared sort.
+    pub fn compare_children(&self, a: &ObjectValues, b: &ObjectValues) -> Ordering {
+        sort::compare_children(&self.sort, a, b)
+    }
+
     /// The type of a field on this object, ignoring its description.
     pub fn field_type(&self, key: &str) -> Option<&FieldType> {
         self.fields.get(key).map(|field| &field.r#type)
@@ -273,6 +610,103 @@ impl ObjectDefinition {
         }
         values
     }
+
+    /// The values a newly created object or child starts with: an
+    /// [`empty_object`](Self::empty_object) plus every field's declared
+    /// default.
+    pub fn default_object(&self) -> ObjectValues {
+        let mut values = self.empty_object();
+        for (name, field) in &self.fields {
+            if let Some(default) = &field.default {
+                values.insert(name.to_owned(), default.clone());
+            }
+        }
+        values
+    }
+
+    /// Every constraint `values` breaks, anywhere in this object or its
+    /// children. Computed fields are never stored, so are never checked.
+    pub fn constraint_violations(
+        &self,
+        values: &ObjectValues,
+    ) -> Vec<(ValuePath, ConstraintViolation)> {
+        let mut found = vec![];
+        self.collect_violations(values, &ValuePath::empty(), &mut found);
+        found
+    }
+
+    fn collect_violations(
+        &self,
+        values: &ObjectValues,
+        path: &ValuePath,
+        found: &mut Vec<(ValuePath, ConstraintViolation)>,
+    ) {
+        for (name, field) in &self.fields {
+            if field.is_computed() {
+                continue;
+            }
+            if let Err(violation) = field.constraints.check(values.get(name)) {
+                found.push((path.clone().append(ValuePath::key(name)), violation));
+            }
+        }
+        for (name, child_def) in &self.children {
+            if let Some(FieldValue::Objects(children)) = values.get(name) {
+                for (index, child) in children.iter().enumerate() {
+                    let child_path = path
+                        .clone()
+                        .append(ValuePath::key(name))
+                        .append(ValuePath::index(index));
+                    child_def.collect_violations(child, &child_path, found);
+                }
+            }
+        }
+    }
+
+    /// Like [`constraint_violations`](Self::constraint_violations), but stops
+    /// at the first one. `path` is prepended to the reported path, for values
+    /// that are themselves a child.
+    #[allow(clippy::result_large_err)]
+    pub fn check_constraints(
+        &self,
+        values: &ObjectValues,
+        path: &ValuePath,
+    ) -> Result<(), FieldValueValidationError> {
+        self.first_violation(values, path, |_| true)
+    }
+
+    /// Like [`check_constraints`](Self::check_constraints), for values that
+    /// are still being created. Objects and children are added before they're
+    /// filled in, so only values that are present and wrong are rejected.
+    #[allow(clippy::result_large_err)]
+    pub fn check_new_constraints(
+        &self,
+        values: &ObjectValues,
+        path: &ValuePath,
+    ) -> Result<(), FieldValueValidationError> {
+        self.first_violation(values, path, |violation| !violation.is_incomplete())
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn first_violation(
+        &self,
+        values: &ObjectValues,
+        path: &ValuePath,
+        applies: impl Fn(&ConstraintViolation) -> bool,
+    ) -> Result<(), FieldValueValidationError> {
+        match self
+            .constraint_violations(values)
+            .into_iter()
+            .find(|(_, violation)| applies(violation))
+        {
+            Some((violation_path, violation)) => {
+                Err(FieldValueValidationError::ConstraintViolation(
+                    path.clone().concat(violation_path),
+                    violation,
+                ))
+            }
+            None => Ok(()),
+        }
+    }
 }
 
 #[cfg(feature = "json-schema")]
@@ -297,10 +731,17 @@ impl ObjectDefinition {
             // Fall back to the field name when the schema author didn't
             // describe it - that's all this had to go on before descriptions.
             let description = field_def.description.as_deref().unwrap_or(field);
-            let field_props =
+            let mut field_props =
                 field_def
                     .r#type
                     .to_json_schema_property(description, &field_path, options);
+            if field_def.is_computed() {
+                field_props.insert("readOnly".into(), true.into());
+            }
+            if let Some(default) = field_def.default.as_ref().and_then(default_json) {
+                field_props.insert("default".into(), default);
+            }
+            constraints_json(&field_def.r#type, &field_def.constraints, &mut field_props);
             properties.insert(field.into(), field_props.into());
         }
         if !is_child {
@@ -326,9 +767,9 @@ impl ObjectDefinition {
             let mut child_items_type = serde_json::Map::new();
             child_items_type.insert("type".into(), "object".into());
             child_items_type.insert("additionalProperties".into(), false.into());
-            if options.all_fields_required {
-                let keys: Vec<String> = child_properties.keys().map(|k| k.to_string()).collect();
-                child_items_type.insert("required".into(), keys.into());
+            let required = crate::json_schema::required_for(definition, &child_properties, options);
+            if options.all_fields_required || !required.is_empty() {
+                child_items_type.insert("required".into(), required.into());
             }
             child_items_type.insert("properties".into(), child_properties.into());
             child.insert("items".into(), child_items_type.into());
@@ -338,6 +779,67 @@ impl ObjectDefinition {
     }
 }
 
+/// A field default as JSON. Defaults are always scalars (see
+/// `FieldType::is_scalar`).
+#[cfg(feature = "json-schema")]
+fn default_json(value: &FieldValue) -> Option<serde_json::Value> {
+    match value {
+        FieldValue::String(s)
+        | FieldValue::Secret(s)
+        | FieldValue::Enum(s)
+        | FieldValue::Markdown(s) => Some(s.as_str().into()),
+        FieldValue::Number(n) => Some((*n).into()),
+        FieldValue::Boolean(b) => Some((*b).into()),
+        FieldValue::Date(_) => Some(value.to_string().into()),
+        _ => None,
+    }
+}
+
+/// Adds the JSON schema keywords for `constraints` to a field's schema.
+/// `required` is not one of them - it belongs to the object the field is in.
+/// Date bounds use the non-standard `formatMinimum`/`formatMaximum` keywords.
+#[cfg(feature = "json-schema")]
+fn constraints_json(
+    field_type: &FieldType,
+    constraints: &FieldConstraints,
+    field_props: &mut crate::json_schema::ObjectSchema,
+) {
+    for (bound, number_keyword, date_keyword) in [
+        (&constraints.min, "minimum", "formatMinimum"),
+        (&constraints.max, "maximum", "formatMaximum"),
+    ] {
+        match bound {
+            Some(FieldValue::Number(n)) => {
+                field_props.insert(number_keyword.into(), (*n).into());
+            }
+            // Date fields are `"format": "date"`, so their bounds are too.
+            // JSON schema can only bound numbers: `formatMinimum` and
+            // `formatMaximum` are an extension (from ajv-formats), which other
+            // validators ignore as unknown keywords.
+            Some(FieldValue::Date(date)) => {
+                let (year, month, day) = date.as_liquid_datetime().to_calendar_date();
+                field_props.insert(
+                    date_keyword.into(),
+                    format!("{:04}-{:02}-{:02}", year, month as u8, day).into(),
+                );
+            }
+            _ => {}
+        }
+    }
+    // The length of a list is how many items it has.
+    let (min_keyword, max_keyword) = if field_type.list_item().is_some() {
+        ("minItems", "maxItems")
+    } else {
+        ("minLength", "maxLength")
+    };
+    if let Some(min_length) = constraints.min_length {
+        field_props.insert(min_keyword.into(), min_length.into());
+    }
+    if let Some(max_length) = constraints.max_length {
+        field_props.insert(max_keyword.into(), max_length.into());
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
 
@@ -551,4 +1053,221 @@ pub mod tests {
         let json = serde_json::to_string(&FieldDefinition::from(FieldType::String)).unwrap();
         assert_eq!(json, r#"{"type":"String"}"#);
     }
+
+    #[test]
+    fn field_defaults_are_parsed() {
+        let defs = ObjectDefinition::from_source(
+            r#"
+            [post]
+            status = { type = "enum", values = ["draft", "published"], default = "draft" }
+            kind = { values = ["essay", "note"] }
+            views = { type = "number", default = 0 }
+            title = "string"
+            [post.links]
+            label = { default = "Read more" }
+            "#,
+            &OrderMap::new(),
+        )
+        .unwrap();
+        let post = defs.get("post").unwrap();
+        let draft_or_published = FieldType::Enum(vec!["draft".into(), "published".into()]);
+        assert_eq!(post.field_type("status"), Some(&draft_or_published));
+        assert_eq!(
+            post.fields.get("status").unwrap().default,
+            Some(FieldValue::Enum("draft".to_string()))
+        );
+        assert_eq!(
+            post.field_type("kind"),
+            Some(&FieldType::Enum(vec!["essay".into(), "note".into()]))
+        );
+        assert_eq!(post.fields.get("kind").unwrap().default, None);
+        assert_eq!(
+            post.fields.get("views").unwrap().default,
+            Some(FieldValue::Number(0.))
+        );
+
+        let values = post.default_object();
+        assert_eq!(
+            values.get("status"),
+            Some(&FieldValue::Enum("draft".to_string()))
+        );
+        assert_eq!(values.get("views"), Some(&FieldValue::Number(0.)));
+        assert!(!values.contains_key("title"));
+        assert_eq!(values.get("links"), Some(&FieldValue::Objects(vec![])));
+        let links = post.children.get("links").unwrap();
+        assert_eq!(
+            links.default_object().get("label"),
+            Some(&FieldValue::String("Read more".to_string()))
+        );
+    }
+
+    #[test]
+    fn invalid_field_defaults_are_rejected() {
+        for source in [
+            "[post]\nstatus = { values = [\"a\", \"b\"], default = \"c\" }",
+            "[post]\nviews = { type = \"number\", default = \"many\" }",
+            "[post]\ncover = { type = \"image\", default = \"cover.png\" }",
+            "[post]\nslug = { computed = \"title\", default = \"slug\" }",
+        ] {
+            let err = ObjectDefinition::from_source(source, &OrderMap::new()).unwrap_err();
+            assert!(
+                matches!(
+                    err.downcast_ref::<InvalidFieldError>(),
+                    Some(InvalidFieldError::InvalidDefault { .. })
+                ),
+                "{source}: {err}"
+            );
+        }
+        for source in [
+            "[post]\nstatus = { type = \"enum\", default = \"a\" }",
+            "[post]\nstatus = { type = \"string\", values = [\"a\"] }",
+            "[post]\nstatus = { values = [1, 2] }",
+        ] {
+            let err = ObjectDefinition::from_source(source, &OrderMap::new()).unwrap_err();
+            assert!(
+                matches!(
+                    err.downcast_ref::<InvalidFieldError>(),
+                    Some(InvalidFieldError::InvalidEnum(_))
+                ),
+                "{source}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn field_constraints_are_parsed() {
+        let defs = ObjectDefinition::from_source(
+            r#"
+            [post]
+            title = { required = true, max_length = 80 }
+            rating = { type = "number", min = 0, max = 5, default = 3 }
+            [post.links]
+            url = { type = "string", required = true }
+            "#,
+            &OrderMap::new(),
+        )
+        .unwrap();
+        let post = defs.get("post").unwrap();
+        let title = &post.fields.get("title").unwrap().constraints;
+        assert!(title.required);
+        assert_eq!(title.max_length, Some(80));
+        let rating = &post.fields.get("rating").unwrap().constraints;
+        assert_eq!(rating.min, Some(FieldValue::Number(0.)));
+        assert_eq!(rating.max, Some(FieldValue::Number(5.)));
+        let links = post.children.get("links").unwrap();
+        assert!(links.fields.get("url").unwrap().constraints.required);
+
+        for (source, expected) in [
+            (
+                "[post]\nrating = { type = \"number\", max = 5, default = 6 }",
+                "invalid default",
+            ),
+            (
+                "[post]\nslug = { computed = \"title\", required = true }",
+                "invalid constraint",
+            ),
+        ] {
+            let err = ObjectDefinition::from_source(source, &OrderMap::new()).unwrap_err();
+            assert!(err.to_string().starts_with(expected), "{source}: {err}");
+        }
+    }
+
+    #[test]
+    fn children_named_like_options_stay_children() {
+        let defs = ObjectDefinition::from_source(
+            r#"
+            [product]
+            name = "string"
+            price = { type = "number", min = 0, max = 100 }
+            [product.price_range]
+            min = "number"
+            max = "number"
+            "#,
+            &OrderMap::new(),
+        )
+        .unwrap();
+        let product = defs.get("product").unwrap();
+        assert!(product.fields.contains_key("price"));
+        assert!(!product.fields.contains_key("price_range"));
+        let range = product.children.get("price_range").unwrap();
+        assert_eq!(range.field_type("min"), Some(&FieldType::Number));
+        assert_eq!(range.field_type("max"), Some(&FieldType::Number));
+    }
+
+    #[test]
+    fn list_fields_are_parsed() {
+        let defs = ObjectDefinition::from_source(
+            r#"
+            [post]
+            tags = ["string"]
+            moods = [["happy", "sad"]]
+            gallery = { type = ["image"], max_length = 10 }
+            kinds = { type = ["enum"], values = ["a", "b"] }
+            state = ["published"]
+            "#,
+            &OrderMap::new(),
+        )
+        .unwrap();
+        let post = defs.get("post").unwrap();
+        let list_of = |t: FieldType| FieldType::List(Box::new(t));
+        assert_eq!(post.field_type("tags"), Some(&list_of(FieldType::String)));
+        assert_eq!(
+            post.field_type("moods"),
+            Some(&list_of(FieldType::Enum(vec![
+                "happy".to_string(),
+                "sad".to_string()
+            ])))
+        );
+        assert_eq!(post.field_type("gallery"), Some(&list_of(FieldType::Image)));
+        assert_eq!(
+            post.fields.get("gallery").unwrap().constraints.max_length,
+            Some(10)
+        );
+        assert_eq!(
+            post.field_type("kinds"),
+            Some(&list_of(FieldType::Enum(vec![
+                "a".to_string(),
+                "b".to_string()
+            ])))
+        );
+        // A single value that isn't a type is still an enum.
+        assert_eq!(
+            post.field_type("state"),
+            Some(&FieldType::Enum(vec!["published".to_string()]))
+        );
+
+        for source in [
+            "[post]\nflags = [\"boolean\"]",
+            "[post]\nnotes = { type = [\"markdown\"] }",
+            "[post]\ntags = { type = [\"string\"], values = [\"a\"] }",
+        ] {
+            assert!(
+                ObjectDefinition::from_source(source, &OrderMap::new()).is_err(),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn fields_cant_take_markdown_property_names() {
+        for source in [
+            "[post]\nbody = \"markdown\"\nbody_meta = \"string\"",
+            "[post]\nbody = \"markdown\"\n[post.body_meta]\ntext = \"string\"",
+        ] {
+            let err = ObjectDefinition::from_source(source, &OrderMap::new()).unwrap_err();
+            assert!(
+                matches!(
+                    err.downcast_ref::<InvalidFieldError>(),
+                    Some(InvalidFieldError::MarkdownMetaCollision { .. })
+                ),
+                "{source}: {err}"
+            );
+        }
+        // Only markdown fields have properties.
+        assert!(ObjectDefinition::from_source(
+            "[post]\ntitle = \"string\"\ntitle_meta = \"string\"",
+            &OrderMap::new()
+        )
+        .is_ok());
+    }
 }
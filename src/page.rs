@@ -109,11 +109,37 @@ impl fmt::Debug for PageTemplate<'_> {
 #[derive(Debug)]
 pub struct RenderGlobals<'a> {
     pub site_url: Cow<'a, str>,
+    /// The site's data files, see `crate::data_files`.
+    pub data: liquid::Object,
 }
 
+/// The global the site's data files are exposed as.
+pub const DATA_GLOBAL: &str = "data";
+
 impl RenderGlobals<'_> {
     fn inject(&self, object: &mut liquid::Object) {
         object.insert("site_url".into(), Value::scalar(self.site_url.to_string()));
+        // Sites without data files keep the name free for an object type.
+        if !self.data.is_empty() {
+            object.insert(DATA_GLOBAL.into(), Value::Object(self.data.clone()));
+        }
+    }
+
+    /// The object type whose global (see [`build_context`]) would be shadowed
+    /// by the data files, if there is one. Either form of its name counts,
+    /// since which one it takes depends on whether it is a list.
+    pub fn shadowed_object<'d>(&self, definitions: &'d ObjectDefinitions) -> Option<&'d str> {
+        if self.data.is_empty() {
+            return None;
+        }
+        definitions
+            .keys()
+            .find(|name| {
+                [1, 2]
+                    .into_iter()
+                    .any(|count| pluralize(name, count, false) == DATA_GLOBAL)
+            })
+            .map(|name| &name[..])
     }
 }
 
@@ -147,7 +173,13 @@ pub fn build_context(
             .unwrap_or_else(|| panic!("missing object definition {}", name));
         let values = match obj_entry {
             ObjectEntry::List(l) => {
-                Value::array(l.iter().map(|o| o.liquid_object(definition, field_config)))
+                let mut sorted: Vec<&Object> = l.iter().collect();
+                sorted.sort_by(|a, b| definition.compare_objects(a, b));
+                Value::array(
+                    sorted
+                        .into_iter()
+                        .map(|o| o.liquid_object(definition, field_config)),
+                )
             }
             ObjectEntry::Object(o) => o.liquid_object(definition, field_config),
         };
@@ -615,6 +647,7 @@ here is a liquid variable: {{site_url}}
                     template: None,
                     children: ObjectDefinitions::new(),
                     description: None,
+                    sort: vec![],
                 },
             ),
             (
@@ -625,6 +658,7 @@ here is a liquid variable: {{site_url}}
                     template: None,
                     children: ObjectDefinitions::new(),
                     description: None,
+                    sort: vec![],
                 },
             ),
         ]);
@@ -634,6 +668,7 @@ here is a liquid variable: {{site_url}}
             template: Some("artist".to_string()),
             children: artist_children,
             description: None,
+            sort: vec![],
         }
     }
 
@@ -660,9 +695,11 @@ here is a liquid variable: {{site_url}}
                             template: None,
                             children: ObjectDefinitions::new(),
                             description: None,
+                            sort: vec![],
                         },
                     )]),
                     description: None,
+                    sort: vec![],
                 },
             ),
         ])
@@ -701,6 +738,7 @@ here is a liquid variable: {{site_url}}
         let liquid_parser = liquid_parser::get(None, None, &MemoryFileSystem::default())?;
         let globals = RenderGlobals {
             site_url: "https://foo.bar".into(),
+            data: liquid::Object::new(),
         };
         let field_config = FieldConfig {
             uploads_url: "https://uploads.foo.bar".into(),
@@ -754,6 +792,7 @@ here is a liquid variable: {{site_url}}
     fn template_page() -> Result<()> {
         let globals = RenderGlobals {
             site_url: "https://foo.bar".into(),
+            data: liquid::Object::new(),
         };
         let field_config = FieldConfig {
             uploads_url: "https://uploads.foo.bar".into(),
@@ -802,6 +841,7 @@ here is a liquid variable: {{site_url}}
         let liquid_parser = liquid_parser::get(None, None, &MemoryFileSystem::default())?;
         let globals = RenderGlobals {
             site_url: "https://foo.bar".into(),
+            data: liquid::Object::new(),
         };
         let field_config = FieldConfig::default();
         let objects_map = ObjectMap::from([("c".to_string(), ObjectEntry::from_vec(objects))]);
@@ -813,6 +853,7 @@ here is a liquid variable: {{site_url}}
                 template: None,
                 children: ObjectDefinitions::new(),
                 description: None,
+                sort: vec![],
             },
         )]);
         let base_context = build_context(&objects_map, &definition_map, &field_config, &globals);
@@ -860,6 +901,33 @@ here is a liquid variable: {{site_url}}
         Ok(())
     }
 
+    #[test]
+    fn markdown_fields_expose_derived_properties() -> Result<()> {
+        let rendered = render_with_objects(
+            FieldsMap::from([("content".to_string(), FieldType::Markdown.into())]),
+            vec![c_object(
+                "home",
+                ObjectValues::from([(
+                    "content".to_string(),
+                    FieldValue::Markdown(
+                        "# Hello\n\nThe intro.\n\n<!-- more -->\n\n## Next\n\nRest."
+                            .to_string(),
+                    ),
+                )]),
+            )],
+            "{% assign c = objects.c | first %}\
+             {% for h in c.content_meta.toc %}{{ h.id }}>{% for s in h.children %}{{ s.id }}{% endfor %}{% endfor %}|\
+             {{ c.content_meta.excerpt }}|{{ c.content_meta.word_count }}|{{ c.content_meta.reading_time }}|\
+             {% if c.content contains \"intro.\" %}string{% endif %}|\
+             {{ c.content | strip_html | strip_newlines }}",
+        )?;
+        assert_eq!(
+            rendered,
+            "hello>next|Hello The intro.|5|1|string|HelloThe intro.NextRest."
+        );
+        Ok(())
+    }
+
     #[test]
     fn loop_variables_are_visible_in_field_content() -> Result<()> {
         let entry = |name: &str| {
@@ -918,6 +986,7 @@ here is a liquid variable: {{site_url}}
     fn secret_fields_cannot_be_used_in_templates() -> Result<()> {
         let globals = RenderGlobals {
             site_url: "https://foo.bar".into(),
+            data: liquid::Object::new(),
         };
         let field_config = FieldConfig::default();
         let definition_map = get_definition_map();
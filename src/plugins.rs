@@ -0,0 +1,640 @@
+//! Site-provided liquid filters and block tags, compiled to WebAssembly.
+//!
+//! A site lists modules under `plugins` in its manifest. They run in a sandbox:
+//! a module may not import anything, so it has no access to the host beyond the
+//! values it is handed, and every call is metered and memory-limited. Each call
+//! gets a fresh instance, so a plugin cannot carry state from one render into
+//! another.
+//!
+//! # Interface
+//!
+//! Values cross the boundary as JSON, written into and read out of the
+//! module's exported `memory`. A module exports:
+//!
+//! - `archival_abi_version() -> i32`, which must return [`PLUGIN_ABI_VERSION`].
+//! - `archival_alloc(len: i32) -> i32`, returning a pointer to `len` writable
+//!   bytes.
+//! - `archival_plugin() -> i64`, returning the module's [`PluginManifest`].
+//! - `archival_filter(ptr: i32, len: i32) -> i64`, called with a
+//!   [`FilterRequest`] and returning a [`PluginResponse`], if the module
+//!   declares filters.
+//! - `archival_tag(ptr: i32, len: i32) -> i64`, called with a [`TagRequest`]
+//!   and returning a [`PluginResponse`] whose value is a string, if the module
+//!   declares tags.
+//!
+//! Results are JSON written into the module's memory, returned as its pointer
+//! in the high 32 bits and its length in the low 32 bits.
+
+use crate::filters::liquid_to_json;
+use crate::liquid_parser::PluginSource;
+use liquid::ParserBuilder;
+use liquid_core::error::{ResultLiquidExt, ResultLiquidReplaceExt};
+use liquid_core::parser::{FilterArguments, FilterReflection, ParameterReflection};
+use liquid_core::{
+    BlockReflection, Expression, Filter, Language, ParseBlock, ParseFilter, Renderable, Runtime,
+    TagBlock, TagTokenIter, Template, Value, ValueView,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Write;
+use std::sync::Arc;
+use thiserror::Error;
+use wasmi::{Config, Engine, Instance, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+/// The version of the interface described in the module docs. Bumped whenever
+/// a change would break existing modules.
+pub const PLUGIN_ABI_VERSION: i32 = 1;
+
+/// Fuel each call may burn before it is stopped. Roughly one unit per
+/// instruction, so this bounds a call to well under a second.
+const FUEL_PER_CALL: u64 = 200_000_000;
+
+/// The most linear memory a plugin instance may grow to.
+const MAX_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Error, Debug)]
+pub enum PluginError {
+    #[error("Plugin {0} could not be loaded: {1}")]
+    Load(String, String),
+    #[error("Plugin {0} implements plugin interface version {1}, but this version of archival requires version {2}.")]
+    UnsupportedVersion(String, i32, i32),
+    #[error("Plugin {0} failed: {1}")]
+    Call(String, String),
+    #[error("Plugin {0} returned invalid JSON: {1}")]
+    InvalidJson(String, String),
+}
+
+/// What a module provides, returned by its `archival_plugin` export.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PluginManifest {
+    #[serde(default)]
+    pub filters: Vec<PluginItem>,
+    #[serde(default)]
+    pub tags: Vec<PluginItem>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PluginItem {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+/// Sent to `archival_filter` for `{{ input | name: args..., key: value }}`.
+#[derive(Debug, Serialize)]
+pub struct FilterRequest<'a> {
+    pub name: &'a str,
+    pub input: serde_json::Value,
+    pub args: Vec<serde_json::Value>,
+    pub options: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Sent to `archival_tag` for `{% name args... %}body{% endname %}`, where
+/// `body` is the already rendered contents of the block.
+#[derive(Debug, Serialize)]
+pub struct TagRequest<'a> {
+    pub body: String,
+    pub name: &'a str,
+    pub args: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginResponse {
+    Ok(serde_json::Value),
+    Error(String),
+}
+
+/// A compiled plugin module. Cheap to clone.
+#[derive(Clone)]
+pub struct Plugin {
+    name: Arc<str>,
+    engine: Engine,
+    module: Arc<Module>,
+    manifest: Arc<PluginManifest>,
+}
+
+impl fmt::Debug for Plugin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Plugin")
+            .field("name", &self.name)
+            .field("manifest", &self.manifest)
+            .finish()
+    }
+}
+
+impl Plugin {
+    pub fn new(name: &str, bytes: &[u8]) -> Result<Self, PluginError> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, bytes)
+            .map_err(|e| PluginError::Load(name.to_string(), e.to_string()))?;
+        let mut plugin = Self {
+            name: name.into(),
+            engine,
+            module: Arc::new(module),
+            manifest: Default::default(),
+        };
+        let (mut store, instance) = plugin
+            .instantiate()
+            .map_err(|e| PluginError::Load(name.to_string(), e))?;
+        let version = instance
+            .get_typed_func::<(), i32>(&store, "archival_abi_version")
+            .and_then(|f| f.call(&mut store, ()).map_err(Into::into))
+            .map_err(|e| PluginError::Load(name.to_string(), e.to_string()))?;
+        if version != PLUGIN_ABI_VERSION {
+            return Err(PluginError::UnsupportedVersion(
+                name.to_string(),
+                version,
+                PLUGIN_ABI_VERSION,
+            ));
+        }
+        let manifest = plugin.call_export(&mut store, instance, "archival_plugin", None)?;
+        plugin.manifest = Arc::new(
+            serde_json::from_slice(&manifest)
+                .map_err(|e| PluginError::InvalidJson(name.to_string(), e.to_string()))?,
+        );
+        Ok(plugin)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn manifest(&self) -> &PluginManifest {
+        &self.manifest
+    }
+
+    /// Registers every filter and tag the plugin provides.
+    pub fn register(&self, mut parser: ParserBuilder) -> ParserBuilder {
+        for filter in &self.manifest.filters {
+            parser = parser.filter(PluginFilter {
+                plugin: self.clone(),
+                item: filter.clone(),
+            });
+        }
+        for tag in &self.manifest.tags {
+            parser = parser.block(PluginBlock {
+                plugin: self.clone(),
+                end_tag: format!("end{}", tag.name),
+                item: tag.clone(),
+            });
+        }
+        parser
+    }
+
+    fn instantiate(&self) -> Result<(Store<StoreLimits>, Instance), String> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(MAX_MEMORY_BYTES)
+            .instances(1)
+            .build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store
+            .add_fuel(FUEL_PER_CALL)
+            .expect("fuel metering is enabled");
+        // Nothing is linked in: a module that imports anything cannot load.
+        let instance = Linker::new(&self.engine)
+            .instantiate(&mut store, &self.module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(|e| e.to_string())?;
+        Ok((store, instance))
+    }
+
+    /// Calls `export`, passing `input` (if any) as a pointer and length, and
+    /// returns the bytes its packed result points to.
+    fn call_export(
+        &self,
+        store: &mut Store<StoreLimits>,
+        instance: Instance,
+        export: &str,
+        input: Option<&[u8]>,
+    ) -> Result<Vec<u8>, PluginError> {
+        let failed = |e: String| PluginError::Call(self.name.to_string(), format!("{export}: {e}"));
+        let memory = instance
+            .get_memory(&*store, "memory")
+            .ok_or_else(|| failed("no exported memory".to_string()))?;
+        let packed = match input {
+            Some(input) => {
+                let len = i32::try_from(input.len()).map_err(|e| failed(e.to_string()))?;
+                let ptr = instance
+                    .get_typed_func::<i32, i32>(&*store, "archival_alloc")
+                    .and_then(|alloc| alloc.call(&mut *store, len).map_err(Into::into))
+                    .map_err(|e| failed(e.to_string()))?;
+                memory
+                    .write(&mut *store, ptr as u32 as usize, input)
+                    .map_err(|e| failed(e.to_string()))?;
+                instance
+                    .get_typed_func::<(i32, i32), i64>(&*store, export)
+                    .and_then(|f| f.call(&mut *store, (ptr, len)).map_err(Into::into))
+            }
+            None => instance
+                .get_typed_func::<(), i64>(&*store, export)
+                .and_then(|f| f.call(&mut *store, ()).map_err(Into::into)),
+        }
+        .map_err(|e| failed(e.to_string()))?;
+        let (ptr, len) = ((packed as u64 >> 32) as usize, packed as u32 as usize);
+        // The length is the plugin's to choose, so it is checked against the
+        // memory it could have written before anything is allocated for it.
+        let output = ptr
+            .checked_add(len)
+            .and_then(|end| memory.data(&*store).get(ptr..end))
+            .ok_or_else(|| {
+                failed(format!(
+                    "returned {len} bytes at {ptr}, outside of its memory"
+                ))
+            })?;
+        Ok(output.to_vec())
+    }
+
+    /// Makes one call in a fresh instance.
+    pub fn call(&self, export: &str, request: &impl Serialize) -> Result<Value, PluginError> {
+        let request = serde_json::to_vec(request)
+            .map_err(|e| PluginError::Call(self.name.to_string(), e.to_string()))?;
+        let (mut store, instance) = self
+            .instantiate()
+            .map_err(|e| PluginError::Call(self.name.to_string(), e))?;
+        let output = self.call_export(&mut store, instance, export, Some(&request))?;
+        match serde_json::from_slice(&output)
+            .map_err(|e| PluginError::InvalidJson(self.name.to_string(), e.to_string()))?
+        {
+            PluginResponse::Ok(value) => liquid_core::model::to_value(&value)
+                .map_err(|e| PluginError::InvalidJson(self.name.to_string(), e.to_string())),
+            PluginResponse::Error(message) => {
+                Err(PluginError::Call(self.name.to_string(), message))
+            }
+        }
+    }
+}
+
+/// Compiles every plugin a site uses.
+pub(crate) fn load(sources: &[PluginSource]) -> Result<Vec<Plugin>, PluginError> {
+    sources
+        .iter()
+        .map(|source| Plugin::new(&source.name, &source.bytes))
+        .collect()
+}
+
+fn liquid_error(err: PluginError) -> liquid_core::Error {
+    liquid_core::Error::with_msg(err.to_string())
+}
+
+#[derive(Clone)]
+struct PluginFilter {
+    plugin: Plugin,
+    item: PluginItem,
+}
+
+impl FilterReflection for PluginFilter {
+    fn name(&self) -> &str {
+        &self.item.name
+    }
+
+    fn description(&self) -> &str {
+        &self.item.description
+    }
+
+    fn positional_parameters(&self) -> &'static [ParameterReflection] {
+        &[]
+    }
+
+    fn keyword_parameters(&self) -> &'static [ParameterReflection] {
+        &[]
+    }
+}
+
+impl ParseFilter for PluginFilter {
+    fn parse(&self, arguments: FilterArguments) -> liquid_core::Result<Box<dyn Filter>> {
+        Ok(Box::new(PluginFilterCall {
+            plugin: self.plugin.clone(),
+            name: self.item.name.clone(),
+            args: arguments.positional.collect(),
+            options: arguments
+                .keyword
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        }))
+    }
+
+    fn reflection(&self) -> &dyn FilterReflection {
+        self
+    }
+}
+
+#[derive(Debug)]
+struct PluginFilterCall {
+    plugin: Plugin,
+    name: String,
+    args: Vec<Expression>,
+    options: Vec<(String, Expression)>,
+}
+
+impl fmt::Display for PluginFilterCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|a| a.to_string())
+            .chain(self.options.iter().map(|(k, v)| format!("{k}: {v}")))
+            .collect();
+        write!(f, "{}: {}", self.name, args.join(", "))
+    }
+}
+
+impl Filter for PluginFilterCall {
+    fn evaluate(&self, input: &dyn ValueView, runtime: &dyn Runtime) -> liquid_core::Result<Value> {
+        let request = FilterRequest {
+            name: &self.name,
+            input: liquid_to_json(&input.to_value()),
+            args: self
+                .args
+                .iter()
+                .map(|arg| Ok(liquid_to_json(&arg.evaluate(runtime)?.to_value())))
+                .collect::<liquid_core::Result<_>>()?,
+            options: self
+                .options
+                .iter()
+                .map(|(key, arg)| {
+                    Ok((
+                        key.clone(),
+                        liquid_to_json(&arg.evaluate(runtime)?.to_value()),
+                    ))
+                })
+                .collect::<liquid_core::Result<_>>()?,
+        };
+        self.plugin
+            .call("archival_filter", &request)
+            .map_err(liquid_error)
+    }
+}
+
+#[derive(Clone)]
+struct PluginBlock {
+    plugin: Plugin,
+    item: PluginItem,
+    end_tag: String,
+}
+
+impl BlockReflection for PluginBlock {
+    fn start_tag(&self) -> &str {
+        &self.item.name
+    }
+
+    fn end_tag(&self) -> &str {
+        &self.end_tag
+    }
+
+    fn description(&self) -> &str {
+        &self.item.description
+    }
+}
+
+impl ParseBlock for PluginBlock {
+    fn parse(
+        &self,
+        arguments: TagTokenIter<'_>,
+        mut tokens: TagBlock<'_, '_>,
+        options: &Language,
+    ) -> liquid_core::Result<Box<dyn Renderable>> {
+        let mut args = vec![];
+        for token in arguments {
+            if token.as_str() == "," {
+                continue;
+            }
+            args.push(token.expect_value().into_result()?);
+        }
+        let body = Template::new(
+            tokens
+                .parse_all(options)
+                .trace_with(|| format!("{{% {} %}}", self.item.name).into())?,
+        );
+        tokens.assert_empty();
+        Ok(Box::new(PluginTag {
+            plugin: self.plugin.clone(),
+            name: self.item.name.clone(),
+            args,
+            body,
+        }))
+    }
+
+    fn reflection(&self) -> &dyn BlockReflection {
+        self
+    }
+}
+
+#[derive(Debug)]
+struct PluginTag {
+    plugin: Plugin,
+    name: String,
+    args: Vec<Expression>,
+    body: Template,
+}
+
+impl Renderable for PluginTag {
+    fn render_to(&self, writer: &mut dyn Write, runtime: &dyn Runtime) -> liquid_core::Result<()> {
+        let trace = || format!("{{% {} %}}", self.name).into();
+        let mut body = Vec::new();
+        self.body.render_to(&mut body, runtime).trace_with(trace)?;
+        let request = TagRequest {
+            body: String::from_utf8(body).expect("render only writes UTF-8"),
+            name: &self.name,
+            args: self
+                .args
+                .iter()
+                .map(|arg| Ok(liquid_to_json(&arg.evaluate(runtime)?.to_value())))
+                .collect::<liquid_core::Result<_>>()?,
+        };
+        let output = self
+            .plugin
+            .call("archival_tag", &request)
+            .map_err(liquid_error)
+            .trace_with(trace)?;
+        let Some(output) = output.as_scalar() else {
+            return liquid_core::Error::with_msg("plugin tags must return a string")
+                .trace(trace())
+                .into_err();
+        };
+        write!(writer, "{}", output.to_kstr()).replace("Failed to render")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::liquid_parser::{self, ArchivalPartialSource};
+
+    /// A module written against the interface by hand. Its filter answers with
+    /// the request it was sent, and its `box` tag with the rendered body in
+    /// brackets — which it finds by skipping the `{"body":"` that requests
+    /// start with and the suffix a `box` tag without arguments ends with.
+    fn plugin_wat(filter_body: &str) -> String {
+        let manifest = r#"{"filters":[{"name":"echo","description":"Echoes its request"}],"tags":[{"name":"box"}]}"#;
+        let tag_suffix = r#"","name":"box","args":[]}"#;
+        format!(
+            r#"(module
+              (memory (export "memory") 1)
+              (global $heap (mut i32) (i32.const 4096))
+              (data (i32.const 0) "{manifest}")
+              (data (i32.const 512) "{{\"ok\":")
+              (data (i32.const 520) "{{\"ok\":\"[")
+              (data (i32.const 540) "}}")
+              (data (i32.const 544) "]\22}}")
+              (data (i32.const 600) "{error}")
+              (func (export "archival_abi_version") (result i32) (i32.const 1))
+              (func $alloc (export "archival_alloc") (param $len i32) (result i32)
+                (local $ptr i32)
+                (local.set $ptr (global.get $heap))
+                (global.set $heap (i32.add (global.get $heap) (local.get $len)))
+                (if (i32.gt_u (global.get $heap) (i32.mul (memory.size) (i32.const 65536)))
+                  (then (drop (memory.grow (i32.add (i32.shr_u (local.get $len) (i32.const 16)) (i32.const 1))))))
+                (local.get $ptr))
+              (func $pack (param $ptr i32) (param $len i32) (result i64)
+                (i64.or
+                  (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+                  (i64.extend_i32_u (local.get $len))))
+              (func $wrap (param $prefix i32) (param $prefix_len i32) (param $src i32) (param $src_len i32) (param $suffix i32) (param $suffix_len i32) (result i64)
+                (local $out i32) (local $total i32)
+                (local.set $total (i32.add (i32.add (local.get $prefix_len) (local.get $src_len)) (local.get $suffix_len)))
+                (local.set $out (call $alloc (local.get $total)))
+                (memory.copy (local.get $out) (local.get $prefix) (local.get $prefix_len))
+                (memory.copy (i32.add (local.get $out) (local.get $prefix_len)) (local.get $src) (local.get $src_len))
+                (memory.copy (i32.add (i32.add (local.get $out) (local.get $prefix_len)) (local.get $src_len)) (local.get $suffix) (local.get $suffix_len))
+                (call $pack (local.get $out) (local.get $total)))
+              (func (export "archival_plugin") (result i64)
+                (call $pack (i32.const 0) (i32.const {manifest_len})))
+              (func (export "archival_filter") (param $ptr i32) (param $len i32) (result i64)
+                {filter_body})
+              (func (export "archival_tag") (param $ptr i32) (param $len i32) (result i64)
+                (call $wrap (i32.const 520) (i32.const 8)
+                  (i32.add (local.get $ptr) (i32.const 9))
+                  (i32.sub (local.get $len) (i32.const {tag_trim}))
+                  (i32.const 544) (i32.const 3))))"#,
+            manifest = manifest.replace('"', "\\\""),
+            manifest_len = manifest.len(),
+            error = ERROR.replace('"', "\\\""),
+            tag_trim = 9 + tag_suffix.len(),
+        )
+    }
+
+    const ECHO: &str =
+        "(call $wrap (i32.const 512) (i32.const 6) (local.get $ptr) (local.get $len) (i32.const 540) (i32.const 1))";
+    const ERROR: &str = r#"{"error":"unknown sku"}"#;
+    const FAIL: &str = "(call $pack (i32.const 600) (i32.const 23))";
+
+    fn module(filter_body: &str) -> Vec<u8> {
+        wat::parse_str(plugin_wat(filter_body)).unwrap()
+    }
+
+    fn render(bytes: Vec<u8>, template: &str) -> Result<String, String> {
+        let mut source = ArchivalPartialSource::default();
+        source.plugins.push(PluginSource {
+            name: "test.wasm".to_string(),
+            bytes,
+        });
+        let parser = liquid_parser::build_with_partials(source).map_err(|e| e.to_string())?;
+        liquid_parser::parse(&parser, template)
+            .and_then(|t| t.render(&liquid::object!({ "price": 1250 })))
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn changing_a_plugin_invalidates_the_parser() {
+        use crate::{FileSystemAPI, MemoryFileSystem};
+        use std::path::PathBuf;
+        let mut fs = MemoryFileSystem::default();
+        let plugins = [PathBuf::from("plugins/sku.wasm")];
+        let hash = |fs: &MemoryFileSystem| {
+            liquid_parser::partials_hash(None, None, &plugins, fs)
+                .unwrap()
+                .1
+        };
+        fs.write(&plugins[0], module(ECHO)).unwrap();
+        let before = hash(&fs);
+        assert_eq!(before, hash(&fs));
+        fs.write(&plugins[0], module(FAIL)).unwrap();
+        assert_ne!(before, hash(&fs));
+    }
+
+    #[test]
+    fn missing_plugins_are_an_error() {
+        let err = liquid_parser::partials_hash(
+            None,
+            None,
+            &[std::path::PathBuf::from("nope.wasm")],
+            &crate::MemoryFileSystem::default(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("nope.wasm"), "{err}");
+    }
+
+    #[test]
+    fn reads_the_plugin_manifest() {
+        let plugin = Plugin::new("test.wasm", &module(ECHO)).unwrap();
+        assert_eq!(plugin.manifest().filters[0].name, "echo");
+        assert_eq!(plugin.manifest().tags[0].name, "box");
+    }
+
+    #[test]
+    fn filters_receive_their_input_and_arguments_as_json() {
+        let output = render(
+            module(ECHO),
+            "{% assign r = 'sku' | echo: price, 'x', currency: 'usd' %}{{ r.name }}|{{ r.input }}|{{ r.args[0] }}|{{ r.args[1] }}|{{ r.options.currency }}",
+        )
+        .unwrap();
+        assert_eq!(output, "echo|sku|1250|x|usd");
+    }
+
+    #[test]
+    fn tags_receive_their_rendered_body() {
+        let output = render(module(ECHO), "{% box %}${{ price }}{% endbox %}").unwrap();
+        assert_eq!(output, "[$1250]");
+    }
+
+    #[test]
+    fn outputs_outside_plugin_memory_are_errors() {
+        // A length of u32::MAX, which must not be allocated up front.
+        let huge = "(call $pack (i32.const 0) (i32.const -1))";
+        let err = render(module(huge), "{{ 'a' | echo }}").unwrap_err();
+        assert!(err.contains("outside of its memory"), "{err}");
+    }
+
+    #[test]
+    fn plugin_errors_fail_the_render() {
+        let err = render(module(FAIL), "{{ 'a' | echo }}").unwrap_err();
+        assert!(err.contains("unknown sku"), "{err}");
+    }
+
+    #[test]
+    fn runaway_plugins_are_stopped() {
+        let spin = "(loop $forever (br $forever)) (unreachable)";
+        let err = render(module(spin), "{{ 'a' | echo }}").unwrap_err();
+        assert!(err.contains("test.wasm"), "{err}");
+    }
+
+    #[test]
+    fn plugins_cannot_import_host_functions() {
+        let wat = plugin_wat(ECHO).replacen(
+            "(module",
+            r#"(module (import "env" "read_file" (func (param i32)))"#,
+            1,
+        );
+        let err = Plugin::new("test.wasm", &wat::parse_str(wat).unwrap()).unwrap_err();
+        assert!(matches!(err, PluginError::Load(_, _)), "{err}");
+    }
+
+    #[test]
+    fn rejects_other_interface_versions() {
+        let wat = plugin_wat(ECHO).replacen(
+            r#"(result i32) (i32.const 1))"#,
+            r#"(result i32) (i32.const 2))"#,
+            1,
+        );
+        let err = Plugin::new("test.wasm", &wat::parse_str(wat).unwrap()).unwrap_err();
+        assert!(
+            matches!(err, PluginError::UnsupportedVersion(_, 2, 1)),
+            "{err}"
+        );
+    }
+}
@@ -5,8 +5,10 @@ pub mod archival_proto {
 use crate::events::ArchivalEvent;
 use crate::fields::field_type::OneofOption;
 use crate::fields::meta::Meta;
-use crate::fields::{DateTime, DisplayType, FieldType, FieldValue, File, MetaValue, RenderedFile};
-use crate::object::{Object, ObjectEntry, ObjectMap};
+use crate::fields::{
+    DateTime, DisplayType, FieldConstraints, FieldType, FieldValue, File, MetaValue, RenderedFile,
+};
+use crate::object::{Object, ObjectEntry, ObjectMap, SortKey};
 use crate::object_definition::{FieldDefinition, FieldsMap, ObjectDefinition};
 use crate::value_path::{ValuePath, ValuePathComponent};
 use ordermap::OrderMap;
@@ -210,6 +212,9 @@ impl From<archival_proto::FieldValue> for FieldValue {
             Some(archival_proto::field_value::Value::Oneof(val)) => {
                 FieldValue::Oneof((val.name, Box::new(val.value.map(|f| FieldValue::from(*f)))))
             }
+            Some(archival_proto::field_value::Value::List(list)) => {
+                FieldValue::List(list.values.into_iter().map(|v| v.into()).collect())
+            }
             Some(archival_proto::field_value::Value::Boolean(b)) => FieldValue::Boolean(b),
             Some(archival_proto::field_value::Value::File(f)) => FieldValue::File(f.into()),
             Some(archival_proto::field_value::Value::Meta(m)) => FieldValue::Meta(m.into()),
@@ -248,6 +253,11 @@ impl From<FieldValue> for archival_proto::FieldValue {
                     value: val.map(|f| Box::new(archival_proto::FieldValue::from(f))),
                 }))
             }
+            FieldValue::List(items) => {
+                archival_proto::field_value::Value::List(archival_proto::ListValue {
+                    values: items.into_iter().map(|v| v.into()).collect(),
+                })
+            }
             FieldValue::Boolean(b) => archival_proto::field_value::Value::Boolean(b),
             FieldValue::File(file) => archival_proto::field_value::Value::File(file.into()),
             FieldValue::Meta(m) => archival_proto::field_value::Value::Meta(m.into()),
@@ -298,6 +308,7 @@ impl From<Object> for archival_proto::Object {
             Some(o) => Some(archival_proto::object::Order::Some(o)),
         };
         archival_proto::Object {
+            version: obj.version(),
             filename: obj.filename,
             object_name: obj.object_name,
             order,
@@ -306,6 +317,18 @@ impl From<Object> for archival_proto::Object {
     }
 }
 
+impl From<events::ConflictError> for archival_proto::ConflictError {
+    fn from(error: events::ConflictError) -> Self {
+        archival_proto::ConflictError {
+            object: error.object,
+            filename: error.filename,
+            path: Some(error.path.into()),
+            current_version: error.current_version,
+            current_value: error.current_value.map(|v| v.into()),
+        }
+    }
+}
+
 // ObjectEntry
 impl From<archival_proto::ObjectEntry> for ObjectEntry {
     fn from(proto: archival_proto::ObjectEntry) -> Self {
@@ -463,6 +486,11 @@ impl From<archival_proto::FieldType> for FieldType {
                     })
                     .collect(),
             ),
+            Some(archival_proto::field_type::Type::List(list)) => FieldType::List(Box::new(
+                list.r#type
+                    .map(|t| (*t).into())
+                    .unwrap_or(FieldType::String),
+            )),
             Some(archival_proto::field_type::Type::Image(())) => FieldType::Image,
             Some(archival_proto::field_type::Type::Video(())) => FieldType::Video,
             Some(archival_proto::field_type::Type::Upload(())) => FieldType::Upload,
@@ -483,6 +511,30 @@ impl From<archival_proto::FieldType> for FieldType {
 }
 
 // FieldsMap from proto
+impl From<archival_proto::FieldConstraints> for FieldConstraints {
+    fn from(constraints: archival_proto::FieldConstraints) -> Self {
+        Self {
+            required: constraints.required,
+            min: constraints.min.map(|v| v.into()),
+            max: constraints.max.map(|v| v.into()),
+            min_length: constraints.min_length.map(|l| l as usize),
+            max_length: constraints.max_length.map(|l| l as usize),
+        }
+    }
+}
+
+impl From<FieldConstraints> for archival_proto::FieldConstraints {
+    fn from(constraints: FieldConstraints) -> Self {
+        Self {
+            required: constraints.required,
+            min: constraints.min.map(|v| v.into()),
+            max: constraints.max.map(|v| v.into()),
+            min_length: constraints.min_length.map(|l| l as u64),
+            max_length: constraints.max_length.map(|l| l as u64),
+        }
+    }
+}
+
 impl From<archival_proto::FieldsMap> for FieldsMap {
     fn from(proto: archival_proto::FieldsMap) -> Self {
         let mut map = FieldsMap::new();
@@ -490,7 +542,12 @@ impl From<archival_proto::FieldsMap> for FieldsMap {
             let field_type = field.r#type.map(|t| t.into()).unwrap_or(FieldType::String);
             map.insert(
                 field.name,
-                FieldDefinition::new(field_type, non_empty(field.description)),
+                FieldDefinition {
+                    computed: non_empty(field.computed),
+                    default: field.default.map(|d| d.into()),
+                    constraints: field.constraints.map(|c| c.into()).unwrap_or_default(),
+                    ..FieldDefinition::new(field_type, non_empty(field.description))
+                },
             );
         }
         map
@@ -506,6 +563,9 @@ impl From<FieldsMap> for archival_proto::FieldsMap {
                 name,
                 r#type: Some(field.r#type.into()),
                 description: field.description.unwrap_or_default(),
+                computed: field.computed.unwrap_or_default(),
+                default: field.default.map(|d| d.into()),
+                constraints: (!field.constraints.is_empty()).then(|| field.constraints.into()),
             })
             .collect();
         archival_proto::FieldsMap { fields }
@@ -543,6 +603,9 @@ impl From<FieldType> for archival_proto::FieldType {
                     })
                     .collect(),
             }),
+            FieldType::List(item) => Type::List(Box::new(archival_proto::ListFieldType {
+                r#type: Some(Box::new(archival_proto::FieldType::from(*item))),
+            })),
             FieldType::Image => Type::Image(()),
             FieldType::Video => Type::Video(()),
             FieldType::Upload => Type::Upload(()),
@@ -570,16 +633,8 @@ impl From<archival_proto::ObjectDefinition> for ObjectDefinition {
         let mut children = OrderMap::new();
         if let Some(cd) = proto.children {
             for child in cd.children {
-                let child_def: ObjectDefinition = child
-                    .definition
-                    .unwrap_or_else(|| archival_proto::ObjectDefinition {
-                        name: String::new(),
-                        fields: None,
-                        template: String::new(),
-                        children: None,
-                        description: String::new(),
-                    })
-                    .into();
+                // Children convert like any definition, sort included.
+                let child_def: ObjectDefinition = child.definition.unwrap_or_default().into();
                 children.insert(child.name, child_def);
             }
         }
@@ -590,6 +645,14 @@ impl From<archival_proto::ObjectDefinition> for ObjectDefinition {
             template: non_empty(proto.template),
             children,
             description: non_empty(proto.description),
+            sort: proto
+                .sort
+                .into_iter()
+                .map(|key| SortKey {
+                    field: key.field,
+                    descending: key.descending,
+                })
+                .collect(),
         }
     }
 }
@@ -626,6 +689,14 @@ impl From<ObjectDefinition> for archival_proto::ObjectDefinition {
             template: def.template.unwrap_or_default(),
             children,
             description: def.description.unwrap_or_default(),
+            sort: def
+                .sort
+                .into_iter()
+                .map(|key| archival_proto::SortKey {
+                    field: key.field,
+                    descending: key.descending,
+                })
+                .collect(),
         }
     }
 }
@@ -674,6 +745,15 @@ impl From<archival_proto::ArchivalEvent> for ArchivalEvent {
                 archival_proto::archival_event::Event::RemoveChild(remove_child_event) => {
                     ArchivalEvent::RemoveChild(remove_child_event.into())
                 }
+                archival_proto::archival_event::Event::AddListValue(add_list_value_event) => {
+                    ArchivalEvent::AddListValue(add_list_value_event.into())
+                }
+                archival_proto::archival_event::Event::RemoveListValue(remove_list_value_event) => {
+                    ArchivalEvent::RemoveListValue(remove_list_value_event.into())
+                }
+                archival_proto::archival_event::Event::MoveListValue(move_list_value_event) => {
+                    ArchivalEvent::MoveListValue(move_list_value_event.into())
+                }
             })
             .unwrap_or_else(|| panic!("Invalid archival event proto: missing event field"))
     }
@@ -813,6 +893,51 @@ impl From<archival_proto::RemoveChildEvent> for events::RemoveChildEvent {
         }
     }
 }
+impl From<archival_proto::AddListValueEvent> for events::AddListValueEvent {
+    fn from(value: archival_proto::AddListValueEvent) -> Self {
+        events::AddListValueEvent {
+            object: value.object,
+            filename: value.filename,
+            path: value
+                .path
+                .map(|p| p.into())
+                .unwrap_or_else(ValuePath::empty),
+            value: value.value.map(|v| v.into()).unwrap_or(FieldValue::Null),
+            index: match value.index {
+                Some(archival_proto::add_list_value_event::Index::Some(i)) => Some(i as usize),
+                Some(archival_proto::add_list_value_event::Index::None(_)) | None => None,
+            },
+        }
+    }
+}
+impl From<archival_proto::RemoveListValueEvent> for events::RemoveListValueEvent {
+    fn from(value: archival_proto::RemoveListValueEvent) -> Self {
+        events::RemoveListValueEvent {
+            object: value.object,
+            filename: value.filename,
+            path: value
+                .path
+                .map(|p| p.into())
+                .unwrap_or_else(ValuePath::empty),
+            source: non_empty(value.source),
+        }
+    }
+}
+impl From<archival_proto::MoveListValueEvent> for events::MoveListValueEvent {
+    fn from(value: archival_proto::MoveListValueEvent) -> Self {
+        events::MoveListValueEvent {
+            object: value.object,
+            filename: value.filename,
+            path: value
+                .path
+                .map(|p| p.into())
+                .unwrap_or_else(ValuePath::empty),
+            from: value.from as usize,
+            to: value.to as usize,
+            source: non_empty(value.source),
+        }
+    }
+}
 
 // ArchivalEvent -> proto
 impl From<ArchivalEvent> for archival_proto::ArchivalEvent {
@@ -842,11 +967,75 @@ impl From<ArchivalEvent> for archival_proto::ArchivalEvent {
             ArchivalEvent::RemoveChild(remove_child_event) => {
                 archival_proto::archival_event::Event::RemoveChild(remove_child_event.into())
             }
+            ArchivalEvent::AddListValue(add_list_value_event) => {
+                archival_proto::archival_event::Event::AddListValue(add_list_value_event.into())
+            }
+            ArchivalEvent::RemoveListValue(remove_list_value_event) => {
+                archival_proto::archival_event::Event::RemoveListValue(
+                    remove_list_value_event.into(),
+                )
+            }
+            ArchivalEvent::MoveListValue(move_list_value_event) => {
+                archival_proto::archival_event::Event::MoveListValue(move_list_value_event.into())
+            }
         };
         archival_proto::ArchivalEvent { event: Some(event) }
     }
 }
 
+// CheckedEvent
+impl From<archival_proto::CheckedEvent> for events::CheckedEvent {
+    fn from(value: archival_proto::CheckedEvent) -> Self {
+        use archival_proto::checked_event::Expected;
+        events::CheckedEvent {
+            event: value
+                .event
+                .unwrap_or_else(|| panic!("Invalid checked event proto: missing event field"))
+                .into(),
+            expected: match value.expected {
+                Some(Expected::ExpectedVersion(version)) => {
+                    events::ExpectedVersion::Version(version)
+                }
+                Some(Expected::ExpectedValue(value)) => {
+                    events::ExpectedVersion::Value(Some(value.into()))
+                }
+                Some(Expected::ExpectedUnset(())) | None => events::ExpectedVersion::Value(None),
+            },
+        }
+    }
+}
+
+impl From<events::CheckedEvent> for archival_proto::CheckedEvent {
+    fn from(value: events::CheckedEvent) -> Self {
+        use archival_proto::checked_event::Expected;
+        archival_proto::CheckedEvent {
+            event: Some(value.event.into()),
+            expected: Some(match value.expected {
+                events::ExpectedVersion::Version(version) => Expected::ExpectedVersion(version),
+                events::ExpectedVersion::Value(Some(value)) => {
+                    Expected::ExpectedValue(value.into())
+                }
+                events::ExpectedVersion::Value(None) => Expected::ExpectedUnset(()),
+            }),
+        }
+    }
+}
+
+// ArchivalEventBatch
+impl From<archival_proto::ArchivalEventBatch> for Vec<ArchivalEvent> {
+    fn from(value: archival_proto::ArchivalEventBatch) -> Self {
+        value.events.into_iter().map(|event| event.into()).collect()
+    }
+}
+
+impl From<Vec<ArchivalEvent>> for archival_proto::ArchivalEventBatch {
+    fn from(value: Vec<ArchivalEvent>) -> Self {
+        archival_proto::ArchivalEventBatch {
+            events: value.into_iter().map(|event| event.into()).collect(),
+        }
+    }
+}
+
 // Event Types (rust -> proto)
 impl From<events::AddObjectValue> for archival_proto::AddObjectValue {
     fn from(value: events::AddObjectValue) -> Self {
@@ -947,6 +1136,43 @@ impl From<events::RemoveChildEvent> for archival_proto::RemoveChildEvent {
         }
     }
 }
+impl From<events::AddListValueEvent> for archival_proto::AddListValueEvent {
+    fn from(value: events::AddListValueEvent) -> Self {
+        let index = match value.index {
+            None => Some(archival_proto::add_list_value_event::Index::None(())),
+            Some(i) => Some(archival_proto::add_list_value_event::Index::Some(i as u32)),
+        };
+        archival_proto::AddListValueEvent {
+            object: value.object,
+            filename: value.filename,
+            path: Some(value.path.into()),
+            value: Some(value.value.into()),
+            index,
+        }
+    }
+}
+impl From<events::RemoveListValueEvent> for archival_proto::RemoveListValueEvent {
+    fn from(value: events::RemoveListValueEvent) -> Self {
+        archival_proto::RemoveListValueEvent {
+            object: value.object,
+            filename: value.filename,
+            path: Some(value.path.into()),
+            source: value.source.unwrap_or_default(),
+        }
+    }
+}
+impl From<events::MoveListValueEvent> for archival_proto::MoveListValueEvent {
+    fn from(value: events::MoveListValueEvent) -> Self {
+        archival_proto::MoveListValueEvent {
+            object: value.object,
+            filename: value.filename,
+            path: Some(value.path.into()),
+            from: value.from as u32,
+            to: value.to as u32,
+            source: value.source.unwrap_or_default(),
+        }
+    }
+}
 
 #[cfg(test)]
 mod proto_tests {
@@ -988,6 +1214,14 @@ mod proto_tests {
         events::ArchivalEvent::AddChild(events::AddChildEvent { object: "parent".to_string(), filename: "parent_file".to_string(), path: object::ValuePath::from_string("children/0"), values: vec![], index: Some(0) });
         events::ArchivalEvent::RemoveChild(events::RemoveChildEvent { object: "parent".to_string(), filename: "parent_file".to_string(), path: object::ValuePath::from_string("children/0"), source: Some("user".to_string()) });
         events::ArchivalEvent::RenameObject(events::RenameObjectEvent { object: "object".to_string(), from: "old_name".to_string(), to: "new_name".to_string() });
+        events::ArchivalEvent::AddListValue(events::AddListValueEvent { object: "object".to_string(), filename: "foo".to_string(), path: object::ValuePath::from_string("tags"), value: FieldValue::String("new".to_string()), index: Some(1) });
+        events::ArchivalEvent::RemoveListValue(events::RemoveListValueEvent { object: "object".to_string(), filename: "foo".to_string(), path: object::ValuePath::from_string("tags.1"), source: None });
+        events::ArchivalEvent::MoveListValue(events::MoveListValueEvent { object: "object".to_string(), filename: "foo".to_string(), path: object::ValuePath::from_string("tags"), from: 0, to: 2, source: Some("user".to_string()) });
+    });
+    proto_test!(archival_proto::CheckedEvent => events::CheckedEvent, checked_event_test {
+        events::CheckedEvent { event: events::ArchivalEvent::EditOrder(events::EditOrderEvent { object: "object".to_string(), filename: "foo".to_string(), order: None, source: None }), expected: events::ExpectedVersion::Version("abc123".to_string()) };
+        events::CheckedEvent { event: events::ArchivalEvent::RemoveListValue(events::RemoveListValueEvent { object: "object".to_string(), filename: "foo".to_string(), path: object::ValuePath::from_string("tags.1"), source: None }), expected: events::ExpectedVersion::Value(Some(FieldValue::String("a".to_string()))) };
+        events::CheckedEvent { event: events::ArchivalEvent::EditField(events::EditFieldEvent { object: "object".to_string(), filename: "foo".to_string(), path: object::ValuePath::empty(), field: "title".to_string(), value: None, source: None }), expected: events::ExpectedVersion::Value(None) };
     });
     proto_test!(archival_proto::File => fields::File, file_test {
         fields::File::download();
@@ -1016,6 +1250,7 @@ mod proto_tests {
     proto_test!(archival_proto::FieldValue => FieldValue, field_value_test {
         FieldValue::String("Test".to_string());
         FieldValue::Markdown("**test**".to_string());
+        FieldValue::List(vec![FieldValue::Number(1.), FieldValue::Number(2.)]);
     });
 
     proto_test!(archival_proto::Object => object::Object, object_test {
@@ -1053,6 +1288,7 @@ mod proto_tests {
         fields::FieldType::Number;
         fields::FieldType::Enum(vec!["sjdklasd".to_string(), "blue".to_string()]);
         fields::FieldType::Alias(Box::new((fields::FieldType::Number, "numeric".to_string())));
+        fields::FieldType::List(Box::new(fields::FieldType::Enum(vec!["a".to_string()])));
         fields::FieldType::Oneof(vec![
             fields::OneofOption {
                 name: "test".to_string(),
@@ -1081,9 +1317,17 @@ mod proto_tests {
                     template: None,
                     children: ObjectDefinitions::new(),
                     description: Some("A child object.".to_string()),
+                    sort: vec![object::SortKey {
+                        field: "something".to_string(),
+                        descending: false,
+                    }],
                 })
             ]),
             description: None,
+            sort: vec![object::SortKey {
+                field: "something".to_string(),
+                descending: true,
+            }],
         };
     });
 }
@@ -0,0 +1,395 @@
+//! Queries over a site's objects.
+//!
+//! ```ignore
+//! let recent = archival
+//!     .query("post")
+//!     .filter(ValuePath::from_string("tags"), FilterOp::Contains, FieldValue::String("rust".into()))
+//!     .sort_by("-date")
+//!     .limit(10)
+//!     .objects()?;
+//! ```
+//!
+//! Paths are [`ValuePath`]s, and may lead into children: `links.0.url` is the
+//! url of an object's first link, while `links.url` is the url of any of
+//! them. Queries are evaluated against the site's object cache, so only the
+//! objects they return are cloned. The liquid `query` filter applies the same
+//! filters to arrays in templates.
+
+use crate::{
+    fields::{FieldValue, ObjectValues},
+    object::{sort, Object, Renderable, RenderedObject},
+    value_path::{ValuePath, ValuePathComponent},
+    Archival, FileSystemAPI,
+};
+use anyhow::Result;
+use std::{cmp::Ordering, fmt, fmt::Debug, str::FromStr};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone)]
+#[error("unknown query operator {0:?} (expected one of {ops})", ops = FilterOp::ALL.map(|op| op.to_string()).join(", "))]
+pub struct UnknownFilterOp(pub String);
+
+/// How a [`QueryFilter`] compares the values at its path with its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    /// A list holds the value, or a string contains it.
+    Contains,
+    /// A string starts with the value.
+    StartsWith,
+}
+
+impl FilterOp {
+    pub const ALL: [FilterOp; 8] = [
+        Self::Eq,
+        Self::Ne,
+        Self::Lt,
+        Self::Lte,
+        Self::Gt,
+        Self::Gte,
+        Self::Contains,
+        Self::StartsWith,
+    ];
+
+    /// Whether two values ordered `ordering` pass a comparison op. Values that
+    /// don't compare only pass `Ne`.
+    pub(crate) fn holds(&self, ordering: Option<Ordering>) -> bool {
+        match (self, ordering) {
+            (Self::Ne, ordering) => ordering != Some(Ordering::Equal),
+            (_, None) => false,
+            (Self::Eq, Some(o)) => o.is_eq(),
+            (Self::Lt, Some(o)) => o.is_lt(),
+            (Self::Lte, Some(o)) => o.is_le(),
+            (Self::Gt, Some(o)) => o.is_gt(),
+            (Self::Gte, Some(o)) => o.is_ge(),
+            (Self::Contains | Self::StartsWith, _) => false,
+        }
+    }
+}
+
+impl fmt::Display for FilterOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Eq => "==",
+                Self::Ne => "!=",
+                Self::Lt => "<",
+                Self::Lte => "<=",
+                Self::Gt => ">",
+                Self::Gte => ">=",
+                Self::Contains => "contains",
+                Self::StartsWith => "starts_with",
+            }
+        )
+    }
+}
+
+impl FromStr for FilterOp {
+    type Err = UnknownFilterOp;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|op| op.to_string() == s)
+            .ok_or_else(|| UnknownFilterOp(s.to_string()))
+    }
+}
+
+/// Matches objects with any value at `path` that passes `op` against `value`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryFilter {
+    pub path: ValuePath,
+    pub op: FilterOp,
+    pub value: FieldValue,
+}
+
+impl QueryFilter {
+    pub fn matches(&self, object: &Object) -> bool {
+        let values = values_at(&object.values, self.path.components());
+        if self.op == FilterOp::Ne {
+            return !values.iter().any(|v| self.test(v, FilterOp::Eq));
+        }
+        values.iter().any(|v| self.test(v, self.op))
+    }
+
+    fn test(&self, value: &FieldValue, op: FilterOp) -> bool {
+        match (op, value) {
+            (FilterOp::Contains, FieldValue::List(items)) => items
+                .iter()
+                .any(|item| FilterOp::Eq.holds(sort::partial_compare(item, &self.value))),
+            (FilterOp::Contains | FilterOp::StartsWith, value) => {
+                match (string_value(value), string_value(&self.value)) {
+                    (Some(s), Some(needle)) if op == FilterOp::Contains => s.contains(needle),
+                    (Some(s), Some(prefix)) => s.starts_with(prefix),
+                    _ => false,
+                }
+            }
+            (op, value) => op.holds(sort::partial_compare(value, &self.value)),
+        }
+    }
+}
+
+fn string_value(value: &FieldValue) -> Option<&str> {
+    match value {
+        FieldValue::String(s) | FieldValue::Markdown(s) | FieldValue::Enum(s) => Some(s),
+        _ => None,
+    }
+}
+
+/// The value at a path, which may lead into children or end at a list item.
+pub(crate) fn value_at<'a>(object: &'a Object, path: &ValuePath) -> Option<&'a FieldValue> {
+    values_at(&object.values, path.components())
+        .into_iter()
+        .next()
+}
+
+/// Every value at `path`. A key naming a child without an index leads into
+/// each of its entries.
+pub(crate) fn values_at<'a>(
+    values: &'a ObjectValues,
+    path: &[ValuePathComponent],
+) -> Vec<&'a FieldValue> {
+    let Some((ValuePathComponent::Key(key), rest)) = path.split_first() else {
+        return vec![];
+    };
+    let Some(value) = values.get(key) else {
+        return vec![];
+    };
+    match (value, rest.split_first()) {
+        (_, None) => vec![value],
+        (FieldValue::Objects(children), Some((ValuePathComponent::Index(idx), rest))) => children
+            .get(*idx)
+            .map(|child| values_at(child, rest))
+            .unwrap_or_default(),
+        (FieldValue::Objects(children), Some(_)) => children
+            .iter()
+            .flat_map(|child| values_at(child, rest))
+            .collect(),
+        (FieldValue::List(items), Some((ValuePathComponent::Index(idx), []))) => {
+            items.get(*idx).into_iter().collect()
+        }
+        _ => vec![],
+    }
+}
+
+/// A query over one type of object, see the [module docs](self). Build one
+/// with [`Archival::query`].
+pub struct Query<'a, F: FileSystemAPI + Clone + Debug> {
+    archival: &'a Archival<F>,
+    object: String,
+    filters: Vec<QueryFilter>,
+    sort: Vec<(ValuePath, bool)>,
+    limit: Option<usize>,
+    offset: usize,
+}
+
+impl<'a, F: FileSystemAPI + Clone + Debug> Query<'a, F> {
+    pub(crate) fn new(archival: &'a Archival<F>, object: &str) -> Self {
+        Self {
+            archival,
+            object: object.to_string(),
+            filters: vec![],
+            sort: vec![],
+            limit: None,
+            offset: 0,
+        }
+    }
+
+    /// Keeps only the objects with a value at `path` that passes `op`.
+    pub fn filter(mut self, path: ValuePath, op: FilterOp, value: FieldValue) -> Self {
+        self.filters.push(QueryFilter { path, op, value });
+        self
+    }
+
+    /// Sorts by the value at a path, descending when prefixed with `-`, as in
+    /// an object definition's `sort`. Each call adds a less significant key,
+    /// and objects that tie on every key keep their default order.
+    pub fn sort_by(mut self, key: &str) -> Self {
+        let (path, descending) = match key.strip_prefix('-') {
+            Some(path) => (path, true),
+            None => (key, false),
+        };
+        self.sort.push((ValuePath::from_string(path), descending));
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    fn compare(&self, a: &Object, b: &Object) -> Ordering {
+        self.sort
+            .iter()
+            .map(|(path, descending)| {
+                sort::compare_present(
+                    values_at(&a.values, path.components()).first().copied(),
+                    values_at(&b.values, path.components()).first().copied(),
+                    *descending,
+                )
+            })
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+
+    /// Calls `f` with the matching objects, in order. Only `count` sees
+    /// every match; everything else sees the page `limit` and `offset` pick.
+    fn run<R>(&self, paginate: bool, f: impl FnOnce(Vec<&Object>) -> R) -> Result<R> {
+        let definition = self.archival.get_object_definition(&self.object)?;
+        self.archival.fs_mutex.with_fs(|fs| {
+            self.archival
+                .site()
+                .with_objects(&self.object, fs, |objects| {
+                    let mut matches: Vec<&Object> = objects
+                        .into_iter()
+                        .filter(|o| self.filters.iter().all(|filter| filter.matches(o)))
+                        .collect();
+                    if !paginate {
+                        return f(matches);
+                    }
+                    matches.sort_by(|a, b| {
+                        self.compare(a, b)
+                            .then_with(|| definition.compare_objects(a, b))
+                    });
+                    f(matches
+                        .into_iter()
+                        .skip(self.offset)
+                        .take(self.limit.unwrap_or(usize::MAX))
+                        .collect())
+                })
+        })
+    }
+
+    /// The matching objects.
+    pub fn objects(&self) -> Result<Vec<Object>> {
+        self.run(true, |objects| objects.into_iter().cloned().collect())
+    }
+
+    /// The matching objects, rendered.
+    pub fn rendered(&self) -> Result<Vec<RenderedObject>> {
+        let field_config = &self.archival.site().field_config;
+        self.run(true, |objects| {
+            objects
+                .into_iter()
+                .map(|o| o.clone().rendered(field_config))
+                .collect()
+        })
+    }
+
+    /// How many objects match, ignoring `limit` and `offset`.
+    pub fn count(&self) -> Result<usize> {
+        self.run(false, |objects| objects.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::{MANIFEST_FILE_NAME, OBJECT_DEFINITION_FILE_NAME},
+        MemoryFileSystem,
+    };
+
+    fn archival() -> Result<Archival<MemoryFileSystem>> {
+        let mut fs = MemoryFileSystem::default();
+        fs.write_str(MANIFEST_FILE_NAME, "upload_prefix = \"\"\n".to_string())?;
+        fs.write_str(
+            OBJECT_DEFINITION_FILE_NAME,
+            "[post]\ntitle = \"string\"\nrating = \"number\"\ntags = [\"string\"]\n[post.links]\nurl = \"string\"\n"
+                .to_string(),
+        )?;
+        fs.write_str(
+            "objects/post/a.toml",
+            "title = \"A\"\nrating = 3\ntags = [\"rust\"]\n[[links]]\nurl = \"https://a.com\"\n"
+                .to_string(),
+        )?;
+        fs.write_str(
+            "objects/post/b.toml",
+            "title = \"B\"\nrating = 5\ntags = [\"web\"]\n".to_string(),
+        )?;
+        fs.write_str(
+            "objects/post/c.toml",
+            "title = \"C\"\nrating = 4\ntags = [\"rust\", \"web\"]\n[[links]]\nurl = \"https://x.com\"\n[[links]]\nurl = \"https://a.com/c\"\n"
+                .to_string(),
+        )?;
+        Archival::new(fs)
+    }
+
+    fn filenames(objects: Vec<Object>) -> Vec<String> {
+        objects.into_iter().map(|o| o.filename).collect()
+    }
+
+    #[test]
+    fn filters_sorts_and_pages() -> Result<()> {
+        let archival = archival()?;
+        let rust = || {
+            archival.query("post").filter(
+                ValuePath::from_string("tags"),
+                FilterOp::Contains,
+                FieldValue::String("rust".to_string()),
+            )
+        };
+        assert_eq!(filenames(rust().objects()?), vec!["a", "c"]);
+        assert_eq!(
+            filenames(rust().sort_by("-rating").objects()?),
+            vec!["c", "a"]
+        );
+        let all = || archival.query("post").sort_by("-rating");
+        assert_eq!(filenames(all().objects()?), vec!["b", "c", "a"]);
+        assert_eq!(filenames(all().offset(1).limit(1).objects()?), vec!["c"]);
+        assert_eq!(all().offset(1).limit(1).count()?, 3);
+        let rendered = all().limit(1).rendered()?;
+        assert_eq!(rendered[0].filename, "b");
+        Ok(())
+    }
+
+    #[test]
+    fn paths_lead_into_children() -> Result<()> {
+        let archival = archival()?;
+        let links = |path: &str, op: FilterOp| {
+            archival
+                .query("post")
+                .filter(
+                    ValuePath::from_string(path),
+                    op,
+                    FieldValue::String("https://a.com".to_string()),
+                )
+                .objects()
+        };
+        assert_eq!(
+            filenames(links("links.url", FilterOp::StartsWith)?),
+            vec!["a", "c"]
+        );
+        assert_eq!(
+            filenames(links("links.0.url", FilterOp::StartsWith)?),
+            vec!["a"]
+        );
+        assert_eq!(filenames(links("links.url", FilterOp::Ne)?), vec!["b", "c"]);
+        Ok(())
+    }
+
+    #[test]
+    fn parses_operators() {
+        for op in FilterOp::ALL {
+            assert_eq!(op.to_string().parse::<FilterOp>().unwrap(), op);
+        }
+        assert!("~".parse::<FilterOp>().is_err());
+    }
+
+    #[test]
+    fn unknown_objects_are_errors() -> Result<()> {
+        assert!(archival()?.query("nope").objects().is_err());
+        Ok(())
+    }
+}
@@ -9,6 +9,10 @@ pub const OBJECT_NAME: &str = "object_name";
 pub const PAGE: &str = "page";
 pub const PAGE_NAME: &str = "page_name";
 
+/// An array of strings with this key in an object definition is its default
+/// sort order. Any other value is an ordinary field, so `sort` isn't reserved.
+pub const SORT: &str = "sort";
+
 /// The canonical set of reserved names. objects.schema.json forbids these as
 /// object and field names, and the schema_files tests assert the two agree.
 pub const RESERVED_FIELDS: [&str; 6] = [OBJECT_NAME, ORDER, OBJECTS, PAGE_NAME, PAGE, TEMPLATE];
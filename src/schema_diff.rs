@@ -0,0 +1,506 @@
+//! Compares two sets of object definitions, classifying each change by what it
+//! breaks.
+//!
+//! A change can break a site's existing content (object files that no longer
+//! load, or lose values), editors built against the old definitions (events
+//! they send that the new definitions reject), and consumers of
+//! `archival types` output (properties that disappear, or change shape).
+
+use crate::{
+    fields::FieldType,
+    migrate::for_each_table,
+    object_definition::{FieldDefinition, ObjectDefinition, ObjectDefinitions},
+    read_toml::read_toml,
+    site::Site,
+    FileSystemAPI,
+};
+use anyhow::Result;
+use serde::Serialize;
+use std::fmt;
+
+/// Something a schema change can break.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Breaks {
+    Content,
+    Editor,
+    Types,
+}
+
+/// One difference between two sets of definitions. `object` is an object's
+/// name, or a path through its children such as `post.links`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SchemaChange {
+    ObjectAdded {
+        object: String,
+    },
+    ObjectRemoved {
+        object: String,
+    },
+    FieldAdded {
+        object: String,
+        field: String,
+        /// Required fields without a default are missing from every existing
+        /// object.
+        required: bool,
+    },
+    FieldRemoved {
+        object: String,
+        field: String,
+    },
+    TypeChanged {
+        object: String,
+        field: String,
+        from: FieldType,
+        to: FieldType,
+    },
+    EnumNarrowed {
+        object: String,
+        field: String,
+        removed: Vec<String>,
+    },
+    EnumWidened {
+        object: String,
+        field: String,
+        added: Vec<String>,
+    },
+    ChildAdded {
+        object: String,
+        child: String,
+    },
+    ChildRemoved {
+        object: String,
+        child: String,
+    },
+    /// A child was removed, and one with identical fields added.
+    ChildRenamed {
+        object: String,
+        from: String,
+        to: String,
+    },
+    TemplateChanged {
+        object: String,
+        from: Option<String>,
+        to: Option<String>,
+    },
+}
+
+impl fmt::Display for SchemaChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ObjectAdded { object } => write!(f, "{object}: object added"),
+            Self::ObjectRemoved { object } => write!(f, "{object}: object removed"),
+            Self::FieldAdded {
+                object,
+                field,
+                required,
+            } => write!(
+                f,
+                "{object}: {} field {field} added",
+                if *required { "required" } else { "optional" }
+            ),
+            Self::FieldRemoved { object, field } => write!(f, "{object}: field {field} removed"),
+            Self::TypeChanged {
+                object,
+                field,
+                from,
+                to,
+            } => write!(f, "{object}: {field} changed from {from} to {to}"),
+            Self::EnumNarrowed {
+                object,
+                field,
+                removed,
+            } => write!(
+                f,
+                "{object}: {field} no longer allows {}",
+                removed.join(", ")
+            ),
+            Self::EnumWidened {
+                object,
+                field,
+                added,
+            } => write!(f, "{object}: {field} now allows {}", added.join(", ")),
+            Self::ChildAdded { object, child } => write!(f, "{object}: child {child} added"),
+            Self::ChildRemoved { object, child } => write!(f, "{object}: child {child} removed"),
+            Self::ChildRenamed { object, from, to } => {
+                write!(f, "{object}: child {from} renamed to {to}")
+            }
+            Self::TemplateChanged { object, from, to } => write!(
+                f,
+                "{object}: template changed from {} to {}",
+                from.as_deref().unwrap_or("none"),
+                to.as_deref().unwrap_or("none")
+            ),
+        }
+    }
+}
+
+impl SchemaChange {
+    pub fn breaks(&self) -> Vec<Breaks> {
+        match self {
+            Self::ObjectAdded { .. } | Self::ChildAdded { .. } | Self::TemplateChanged { .. } => {
+                vec![]
+            }
+            Self::FieldAdded { required, .. } => {
+                if *required {
+                    vec![Breaks::Content]
+                } else {
+                    vec![]
+                }
+            }
+            Self::ObjectRemoved { .. }
+            | Self::FieldRemoved { .. }
+            | Self::TypeChanged { .. }
+            | Self::ChildRemoved { .. }
+            | Self::ChildRenamed { .. } => vec![Breaks::Content, Breaks::Editor, Breaks::Types],
+            Self::EnumNarrowed { .. } => vec![Breaks::Content, Breaks::Editor],
+            // Readers that handle every value of an enum don't handle the new
+            // ones.
+            Self::EnumWidened { .. } => vec![Breaks::Types],
+        }
+    }
+
+    pub fn object(&self) -> &str {
+        match self {
+            Self::ObjectAdded { object }
+            | Self::ObjectRemoved { object }
+            | Self::FieldAdded { object, .. }
+            | Self::FieldRemoved { object, .. }
+            | Self::TypeChanged { object, .. }
+            | Self::EnumNarrowed { object, .. }
+            | Self::EnumWidened { object, .. }
+            | Self::ChildAdded { object, .. }
+            | Self::ChildRemoved { object, .. }
+            | Self::ChildRenamed { object, .. }
+            | Self::TemplateChanged { object, .. } => object,
+        }
+    }
+
+    /// Whether this change affects the given table of an object's values.
+    fn affects(&self, table: &toml::Table) -> bool {
+        match self {
+            Self::ObjectAdded { .. } | Self::ChildAdded { .. } => false,
+            Self::ObjectRemoved { .. } | Self::TemplateChanged { .. } => true,
+            Self::FieldAdded {
+                field, required, ..
+            } => *required && !table.contains_key(field),
+            Self::FieldRemoved { field, .. }
+            | Self::TypeChanged { field, .. }
+            | Self::ChildRemoved { child: field, .. }
+            | Self::ChildRenamed { from: field, .. } => table.contains_key(field),
+            Self::EnumNarrowed { field, removed, .. } => {
+                let removed =
+                    |v: &toml::Value| v.as_str().is_some_and(|v| removed.iter().any(|r| r == v));
+                match table.get(field) {
+                    Some(toml::Value::Array(items)) => items.iter().any(removed),
+                    Some(value) => removed(value),
+                    None => false,
+                }
+            }
+            Self::EnumWidened { .. } => false,
+        }
+    }
+}
+
+/// A change, what it breaks, and how many existing objects it affects.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClassifiedChange {
+    #[serde(flatten)]
+    pub change: SchemaChange,
+    pub breaks: Vec<Breaks>,
+    pub affected_objects: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SchemaDiff {
+    pub changes: Vec<ClassifiedChange>,
+}
+
+impl SchemaDiff {
+    /// Diffs two sets of definitions. Affected objects aren't counted, see
+    /// [`crate::Archival::schema_diff`].
+    pub fn new(old: &ObjectDefinitions, new: &ObjectDefinitions) -> Self {
+        let mut changes = vec![];
+        for (name, old_def) in old {
+            match new.get(name) {
+                Some(new_def) => diff_definition(name, old_def, new_def, &mut changes),
+                None => changes.push(SchemaChange::ObjectRemoved {
+                    object: name.clone(),
+                }),
+            }
+        }
+        for name in new.keys().filter(|name| !old.contains_key(*name)) {
+            changes.push(SchemaChange::ObjectAdded {
+                object: name.clone(),
+            });
+        }
+        Self {
+            changes: changes
+                .into_iter()
+                .map(|change| ClassifiedChange {
+                    breaks: change.breaks(),
+                    change,
+                    affected_objects: 0,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn is_breaking(&self) -> bool {
+        self.changes.iter().any(|c| !c.breaks.is_empty())
+    }
+
+    /// Counts the objects in `site` each change affects.
+    pub(crate) fn count_affected(&mut self, site: &Site, fs: &impl FileSystemAPI) -> Result<()> {
+        for classified in &mut self.changes {
+            let mut path = classified.change.object().split('.');
+            let name = path.next().unwrap_or_default();
+            let children: Vec<&str> = path.collect();
+            let mut count = 0;
+            for file in site.object_files(name, fs)? {
+                let mut table = read_toml(&file, fs)?;
+                let mut affected = false;
+                for_each_table(&mut table, &children, &mut |table| {
+                    affected |= classified.change.affects(table);
+                    Ok(())
+                })?;
+                if affected {
+                    count += 1;
+                }
+            }
+            classified.affected_objects = count;
+        }
+        Ok(())
+    }
+}
+
+fn enum_values(field_type: &FieldType) -> Option<&Vec<String>> {
+    match field_type.base_type() {
+        FieldType::Enum(values) => Some(values),
+        FieldType::List(item) => match item.base_type() {
+            FieldType::Enum(values) => Some(values),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn diff_field(
+    path: &str,
+    name: &str,
+    old: &FieldDefinition,
+    new: &FieldDefinition,
+    changes: &mut Vec<SchemaChange>,
+) {
+    if let (Some(old_values), Some(new_values)) =
+        (enum_values(&old.r#type), enum_values(&new.r#type))
+    {
+        // An enum that became a list of the same enum (or vice versa) is a
+        // type change, even if its values didn't change.
+        if matches!(old.r#type.base_type(), FieldType::List(_))
+            == matches!(new.r#type.base_type(), FieldType::List(_))
+        {
+            let removed: Vec<String> = old_values
+                .iter()
+                .filter(|v| !new_values.contains(v))
+                .cloned()
+                .collect();
+            let added: Vec<String> = new_values
+                .iter()
+                .filter(|v| !old_values.contains(v))
+                .cloned()
+                .collect();
+            if !removed.is_empty() {
+                changes.push(SchemaChange::EnumNarrowed {
+                    object: path.to_string(),
+                    field: name.to_string(),
+                    removed,
+                });
+            }
+            if !added.is_empty() {
+                changes.push(SchemaChange::EnumWidened {
+                    object: path.to_string(),
+                    field: name.to_string(),
+                    added,
+                });
+            }
+            return;
+        }
+    }
+    if old.r#type != new.r#type {
+        changes.push(SchemaChange::TypeChanged {
+            object: path.to_string(),
+            field: name.to_string(),
+            from: old.r#type.clone(),
+            to: new.r#type.clone(),
+        });
+    }
+}
+
+fn diff_definition(
+    path: &str,
+    old: &ObjectDefinition,
+    new: &ObjectDefinition,
+    changes: &mut Vec<SchemaChange>,
+) {
+    if old.template != new.template {
+        changes.push(SchemaChange::TemplateChanged {
+            object: path.to_string(),
+            from: old.template.clone(),
+            to: new.template.clone(),
+        });
+    }
+    for (name, old_field) in &old.fields {
+        match new.fields.get(name) {
+            Some(new_field) => diff_field(path, name, old_field, new_field, changes),
+            None => changes.push(SchemaChange::FieldRemoved {
+                object: path.to_string(),
+                field: name.clone(),
+            }),
+        }
+    }
+    for (name, field) in new
+        .fields
+        .iter()
+        .filter(|(n, _)| !old.fields.contains_key(*n))
+    {
+        changes.push(SchemaChange::FieldAdded {
+            object: path.to_string(),
+            field: name.clone(),
+            required: field.constraints.required && field.default.is_none() && !field.is_computed(),
+        });
+    }
+    let mut added_children: Vec<&String> = new
+        .children
+        .keys()
+        .filter(|name| !old.children.contains_key(*name))
+        .collect();
+    for (name, old_child) in &old.children {
+        if let Some(new_child) = new.children.get(name) {
+            diff_definition(&format!("{path}.{name}"), old_child, new_child, changes);
+        } else if let Some(idx) = added_children
+            .iter()
+            .position(|added| new.children[*added].fields == old_child.fields)
+        {
+            changes.push(SchemaChange::ChildRenamed {
+                object: path.to_string(),
+                from: name.clone(),
+                to: added_children.remove(idx).clone(),
+            });
+        } else {
+            changes.push(SchemaChange::ChildRemoved {
+                object: path.to_string(),
+                child: name.clone(),
+            });
+        }
+    }
+    for child in added_children {
+        changes.push(SchemaChange::ChildAdded {
+            object: path.to_string(),
+            child: child.clone(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::{MANIFEST_FILE_NAME, OBJECT_DEFINITION_FILE_NAME},
+        Archival, MemoryFileSystem,
+    };
+    use ordermap::OrderMap;
+
+    fn definitions(source: &str) -> ObjectDefinitions {
+        ObjectDefinition::from_source(source, &OrderMap::new()).unwrap()
+    }
+
+    const OLD: &str = r#"
+        [post]
+        template = "post"
+        title = "string"
+        status = ["draft", "published", "archived"]
+        rating = "string"
+        [post.links]
+        url = "string"
+        [about]
+        body = "markdown"
+        "#;
+
+    const NEW: &str = r#"
+        [post]
+        template = "article"
+        status = ["draft", "published", "scheduled"]
+        rating = "number"
+        summary = "string"
+        author = { type = "string", required = true }
+        [post.references]
+        url = "string"
+        [tag]
+        name = "string"
+        "#;
+
+    #[test]
+    fn classifies_changes() {
+        let diff = SchemaDiff::new(&definitions(OLD), &definitions(NEW));
+        let described: Vec<String> = diff
+            .changes
+            .iter()
+            .map(|c| format!("{} {:?}", c.change, c.breaks))
+            .collect();
+        assert_eq!(
+            described,
+            vec![
+                "post: template changed from post to article []",
+                "post: field title removed [Content, Editor, Types]",
+                "post: status no longer allows archived [Content, Editor]",
+                "post: status now allows scheduled [Types]",
+                "post: rating changed from string to number [Content, Editor, Types]",
+                "post: optional field summary added []",
+                "post: required field author added [Content]",
+                "post: child links renamed to references [Content, Editor, Types]",
+                "about: object removed [Content, Editor, Types]",
+                "tag: object added []",
+            ]
+        );
+        assert!(diff.is_breaking());
+        assert!(!SchemaDiff::new(&definitions(OLD), &definitions(OLD)).is_breaking());
+    }
+
+    #[test]
+    fn counts_affected_objects() -> Result<()> {
+        let mut fs = MemoryFileSystem::default();
+        fs.write_str(MANIFEST_FILE_NAME, "upload_prefix = \"\"\n".to_string())?;
+        fs.write_str(OBJECT_DEFINITION_FILE_NAME, OLD.to_string())?;
+        fs.write_str(
+            "objects/post/a.toml",
+            "title = \"A\"\nstatus = \"archived\"\n[[links]]\nurl = \"x\"\n".to_string(),
+        )?;
+        fs.write_str(
+            "objects/post/b.toml",
+            "title = \"B\"\nstatus = \"draft\"\n".to_string(),
+        )?;
+        fs.write_str("objects/about.toml", "body = \"hi\"\n".to_string())?;
+        let archival = Archival::new(fs)?;
+        let diff = archival.schema_diff(&definitions(OLD), &definitions(NEW))?;
+        let affected: Vec<(String, usize)> = diff
+            .changes
+            .iter()
+            .map(|c| (c.change.to_string(), c.affected_objects))
+            .collect();
+        let count = |change: &str| {
+            affected
+                .iter()
+                .find(|(c, _)| c == change)
+                .map(|(_, count)| *count)
+        };
+        assert_eq!(count("post: field title removed"), Some(2));
+        assert_eq!(count("post: status no longer allows archived"), Some(1));
+        assert_eq!(count("post: required field author added"), Some(2));
+        assert_eq!(count("post: optional field summary added"), Some(0));
+        assert_eq!(count("post: child links renamed to references"), Some(1));
+        assert_eq!(count("about: object removed"), Some(1));
+        Ok(())
+    }
+}
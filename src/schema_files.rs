@@ -105,6 +105,8 @@ fn all_manifest_fields() -> Vec<ManifestField> {
         ManifestField::StaticDir,
         ManifestField::SchemasDir,
         ManifestField::LayoutDir,
+        ManifestField::DataDir,
+        ManifestField::Plugins,
         ManifestField::UploadsUrl,
         ManifestField::EditorTypes,
         ManifestField::Metadata,
@@ -123,6 +125,8 @@ fn all_manifest_fields() -> Vec<ManifestField> {
             | ManifestField::StaticDir
             | ManifestField::SchemasDir
             | ManifestField::LayoutDir
+            | ManifestField::DataDir
+            | ManifestField::Plugins
             | ManifestField::UploadsUrl
             | ManifestField::EditorTypes
             | ManifestField::Metadata => {}
@@ -132,9 +136,9 @@ fn all_manifest_fields() -> Vec<ManifestField> {
 }
 
 /// The archival types a user may name as a bare string in archival_objects.toml
-/// or as an editor type's `type` in archival.toml. Enum, oneof and alias field types have
-/// no spelling of their own (enums and oneofs are written as TOML arrays, and an
-/// alias is spelled as the custom type's name), so they contribute nothing here
+/// or as an editor type's `type` in archival.toml. Enum, oneof, list and alias field types
+/// have no spelling of their own (enums, oneofs and lists are written as TOML arrays, and
+/// an alias is spelled as the custom type's name), so they contribute nothing here
 /// — but the match is exhaustive, so a new variant forces a decision.
 fn archival_field_type_names() -> BTreeSet<String> {
     let all = [
@@ -164,7 +168,8 @@ fn archival_field_type_names() -> BTreeSet<String> {
             | FieldType::Upload
             | FieldType::Meta => {}
             // Not nameable as a bare string: see above.
-            FieldType::Enum(_) | FieldType::Oneof(_) | FieldType::Alias(_) => {}
+            FieldType::Enum(_) | FieldType::Oneof(_) | FieldType::List(_) | FieldType::Alias(_) => {
+            }
         }
     }
     all.iter().map(|t| t.as_str().to_string()).collect()
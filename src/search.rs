@@ -0,0 +1,389 @@
+//! Full-text search over the string and markdown fields of a site's objects,
+//! including those of their children.
+//!
+//! The index is built the first time [`crate::Archival::search`] is called, so
+//! sites that never search never pay for it. After that it is kept in step
+//! with [`crate::site::Site::invalidate_file`]: invalidated files are dropped
+//! from the index and re-read before the next search.
+
+use crate::{
+    fields::{FieldValue, ObjectValues},
+    object::Object,
+    value_path::ValuePath,
+};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+/// Bytes of context kept before the first match in a snippet.
+const SNIPPET_CONTEXT: usize = 40;
+/// The longest a snippet gets, before ellipses.
+const SNIPPET_LENGTH: usize = 160;
+const ELLIPSIS: &str = "…";
+
+/// A field that contains every term of a search.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchResult {
+    pub object: String,
+    pub filename: String,
+    /// The path of the field in the object, e.g. `links.0.title`.
+    pub path: ValuePath,
+    /// An excerpt of the field around its first match.
+    pub snippet: String,
+    /// The byte ranges of `snippet` that matched a search term.
+    pub highlights: Vec<Range<usize>>,
+    pub score: f64,
+}
+
+impl SearchResult {
+    /// The snippet with each highlight wrapped in `open` and `close`, e.g.
+    /// `<mark>` and `</mark>`.
+    pub fn highlighted(&self, open: &str, close: &str) -> String {
+        let mut out = String::with_capacity(self.snippet.len());
+        let mut last = 0;
+        for range in &self.highlights {
+            out.push_str(&self.snippet[last..range.start]);
+            out.push_str(open);
+            out.push_str(&self.snippet[range.clone()]);
+            out.push_str(close);
+            last = range.end;
+        }
+        out.push_str(&self.snippet[last..]);
+        out
+    }
+}
+
+/// Splits text into lowercased words, with the byte range each came from.
+fn tokenize(text: &str) -> Vec<(String, Range<usize>)> {
+    let mut tokens = vec![];
+    let mut start = None;
+    for (idx, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(idx),
+            (false, Some(s)) => {
+                tokens.push((text[s..idx].to_lowercase(), s..idx));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    tokens
+}
+
+#[derive(Debug)]
+struct Document {
+    object: String,
+    filename: String,
+    fields: Vec<(ValuePath, String)>,
+}
+
+/// Collects the searchable fields of `values`, leading into children.
+fn collect_fields(values: &ObjectValues, path: &ValuePath, fields: &mut Vec<(ValuePath, String)>) {
+    for (key, value) in values {
+        let path = path.clone().append(ValuePath::key(key));
+        match value {
+            FieldValue::String(s) | FieldValue::Markdown(s) => fields.push((path, s.clone())),
+            FieldValue::List(items) => {
+                for (idx, item) in items.iter().enumerate() {
+                    if let FieldValue::String(s) | FieldValue::Markdown(s) = item {
+                        fields.push((path.clone().append(ValuePath::index(idx)), s.clone()));
+                    }
+                }
+            }
+            FieldValue::Objects(children) => {
+                for (idx, child) in children.iter().enumerate() {
+                    collect_fields(child, &path.clone().append(ValuePath::index(idx)), fields);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// An inverted index from words to the object fields they appear in.
+#[derive(Debug, Default)]
+pub(crate) struct SearchIndex {
+    documents: HashMap<PathBuf, Document>,
+    /// For each word, the fields of each file it appears in, once per
+    /// appearance.
+    terms: BTreeMap<String, HashMap<PathBuf, Vec<usize>>>,
+    stale: HashSet<PathBuf>,
+}
+
+impl SearchIndex {
+    pub(crate) fn insert(&mut self, path: &Path, object: &Object) {
+        self.remove(path);
+        let mut fields = vec![];
+        collect_fields(&object.values, &ValuePath::empty(), &mut fields);
+        for (idx, (_, text)) in fields.iter().enumerate() {
+            for (term, _) in tokenize(text) {
+                self.terms
+                    .entry(term)
+                    .or_default()
+                    .entry(path.to_path_buf())
+                    .or_default()
+                    .push(idx);
+            }
+        }
+        self.documents.insert(
+            path.to_path_buf(),
+            Document {
+                object: object.object_name.clone(),
+                filename: object.filename.clone(),
+                fields,
+            },
+        );
+    }
+
+    fn remove(&mut self, path: &Path) {
+        let Some(document) = self.documents.remove(path) else {
+            return;
+        };
+        let terms: HashSet<String> = document
+            .fields
+            .iter()
+            .flat_map(|(_, text)| tokenize(text).into_iter().map(|(term, _)| term))
+            .collect();
+        for term in terms {
+            if let Some(postings) = self.terms.get_mut(&term) {
+                postings.remove(path);
+                if postings.is_empty() {
+                    self.terms.remove(&term);
+                }
+            }
+        }
+    }
+
+    /// Drops a file from the index until it is re-read.
+    pub(crate) fn invalidate(&mut self, path: &Path) {
+        self.remove(path);
+        self.stale.insert(path.to_path_buf());
+    }
+
+    /// The files invalidated since the last call, which should be re-read
+    /// with [`Self::insert`] if they still exist.
+    pub(crate) fn take_stale(&mut self) -> Vec<PathBuf> {
+        self.stale.drain().collect()
+    }
+
+    /// The indexed words matching a query word. The last word of a query
+    /// matches as a prefix, so results keep up with someone typing.
+    fn matching_terms<'a>(
+        &'a self,
+        word: &'a str,
+        prefix: bool,
+    ) -> impl Iterator<Item = (&'a String, &'a HashMap<PathBuf, Vec<usize>>)> + 'a {
+        self.terms
+            .range(word.to_string()..)
+            .take_while(move |(term, _)| {
+                if prefix {
+                    term.starts_with(word)
+                } else {
+                    *term == word
+                }
+            })
+    }
+
+    /// Finds the fields containing every word of `query`, best first. Words
+    /// score by how often they appear in a field and how rare they are across
+    /// the site, and short fields outrank long ones.
+    pub(crate) fn search(&self, query: &str) -> Vec<SearchResult> {
+        let words: Vec<String> = tokenize(query).into_iter().map(|(word, _)| word).collect();
+        let documents = self.documents.len() as f64;
+        let mut scores: HashMap<(&PathBuf, usize), (f64, usize)> = HashMap::new();
+        for (idx, word) in words.iter().enumerate() {
+            let prefix = idx == words.len() - 1;
+            let mut word_scores: HashMap<(&PathBuf, usize), f64> = HashMap::new();
+            for (_, postings) in self.matching_terms(word, prefix) {
+                let idf = (documents / postings.len() as f64).ln() + 1.0;
+                for (path, fields) in postings {
+                    for field in fields {
+                        *word_scores.entry((path, *field)).or_default() += idf;
+                    }
+                }
+            }
+            for (field, score) in word_scores {
+                let entry = scores.entry(field).or_default();
+                entry.0 += score;
+                entry.1 += 1;
+            }
+        }
+        let mut results: Vec<SearchResult> = scores
+            .into_iter()
+            .filter(|(_, (_, matched))| *matched == words.len())
+            .map(|((path, field), (score, _))| {
+                let document = &self.documents[path];
+                let (value_path, text) = &document.fields[field];
+                let (snippet, highlights) = snippet(text, &words);
+                SearchResult {
+                    object: document.object.clone(),
+                    filename: document.filename.clone(),
+                    path: value_path.clone(),
+                    snippet,
+                    highlights,
+                    score: score / (1.0 + (tokenize(text).len() as f64).ln()),
+                }
+            })
+            .collect();
+        results.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.object.cmp(&b.object))
+                .then_with(|| a.filename.cmp(&b.filename))
+                .then_with(|| a.path.to_string().cmp(&b.path.to_string()))
+        });
+        results
+    }
+}
+
+fn floor_char_boundary(text: &str, mut idx: usize) -> usize {
+    while !text.is_char_boundary(idx) {
+        idx -= 1;
+    }
+    idx
+}
+
+/// An excerpt of `text` around the first word matching `words`, and the
+/// ranges in it of every match.
+fn snippet(text: &str, words: &[String]) -> (String, Vec<Range<usize>>) {
+    let matches: Vec<Range<usize>> = tokenize(text)
+        .into_iter()
+        .filter(|(token, _)| {
+            words.iter().enumerate().any(|(idx, word)| {
+                token == word || (idx == words.len() - 1 && token.starts_with(word.as_str()))
+            })
+        })
+        .map(|(_, range)| range)
+        .collect();
+    let first = matches.first().map(|m| m.start).unwrap_or_default();
+    let mut start = floor_char_boundary(text, first.saturating_sub(SNIPPET_CONTEXT));
+    // Start on a word rather than partway through one.
+    if start > 0 {
+        if let Some(space) = text[start..first].find(char::is_whitespace) {
+            start += space + 1;
+        }
+    }
+    let end = floor_char_boundary(text, (start + SNIPPET_LENGTH).min(text.len()));
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push_str(ELLIPSIS);
+    }
+    let offset = snippet.len();
+    // Newlines in markdown would break up a one-line excerpt.
+    snippet.push_str(&text[start..end].replace(['\n', '\r', '\t'], " "));
+    if end < text.len() {
+        snippet.push_str(ELLIPSIS);
+    }
+    let highlights = matches
+        .into_iter()
+        .filter(|m| m.start >= start && m.end <= end)
+        .map(|m| m.start - start + offset..m.end - start + offset)
+        .collect();
+    (snippet, highlights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(filename: &str, values: &[(&str, FieldValue)]) -> Object {
+        Object {
+            filename: filename.to_string(),
+            object_name: "post".to_string(),
+            order: None,
+            values: values
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect(),
+        }
+    }
+
+    fn string(s: &str) -> FieldValue {
+        FieldValue::String(s.to_string())
+    }
+
+    fn index() -> SearchIndex {
+        let mut index = SearchIndex::default();
+        index.insert(
+            Path::new("objects/post/a.toml"),
+            &object(
+                "a",
+                &[
+                    ("title", string("Writing Rust")),
+                    (
+                        "body",
+                        FieldValue::Markdown(
+                            "A long post about many things.\n\nRust comes up once.".to_string(),
+                        ),
+                    ),
+                ],
+            ),
+        );
+        index.insert(
+            Path::new("objects/post/b.toml"),
+            &object(
+                "b",
+                &[
+                    ("title", string("Gardening")),
+                    (
+                        "links",
+                        FieldValue::Objects(vec![ObjectValues::from([(
+                            "label".to_string(),
+                            string("Rusty tools"),
+                        )])]),
+                    ),
+                ],
+            ),
+        );
+        index
+    }
+
+    #[test]
+    fn finds_and_ranks_fields() {
+        let results = index().search("rust");
+        let found: Vec<(String, String)> = results
+            .iter()
+            .map(|r| (r.filename.clone(), r.path.to_string()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("a".to_string(), "title".to_string()),
+                ("b".to_string(), "links.0.label".to_string()),
+                ("a".to_string(), "body".to_string()),
+            ]
+        );
+        assert_eq!(results[0].highlighted("[", "]"), "Writing [Rust]");
+    }
+
+    #[test]
+    fn every_word_must_match_a_field() {
+        assert_eq!(index().search("writing rust").len(), 1);
+        assert!(index().search("gardening rust").is_empty());
+        assert!(index().search("").is_empty());
+    }
+
+    #[test]
+    fn snippets_are_excerpts_around_the_first_match() {
+        let text = format!("{} needle {}", "hay ".repeat(30), "stack ".repeat(40));
+        let (snippet, highlights) = snippet(&text, &["needle".to_string()]);
+        assert!(snippet.starts_with(ELLIPSIS) && snippet.ends_with(ELLIPSIS));
+        assert_eq!(&snippet[highlights[0].clone()], "needle");
+        assert!(snippet.len() <= SNIPPET_LENGTH + 2 * ELLIPSIS.len());
+    }
+
+    #[test]
+    fn invalidated_files_leave_the_index() {
+        let mut index = index();
+        index.invalidate(Path::new("objects/post/a.toml"));
+        assert_eq!(index.search("rust").len(), 1);
+        assert_eq!(
+            index.take_stale(),
+            vec![PathBuf::from("objects/post/a.toml")]
+        );
+        assert!(!index.terms.contains_key("writing"));
+    }
+}
@@ -3,12 +3,14 @@ use crate::json_schema;
 use crate::{
     check_compatibility,
     constants::MANIFEST_FILE_NAME,
+    data_files,
     liquid_parser::{self, PARTIAL_FILE_NAME_RE},
     manifest::Manifest,
     object::{Object, ObjectEntry, Renderable, RenderedObject, RenderedObjectMap},
     object_definition::{ObjectDefinition, ObjectDefinitions},
     page::{build_context, Page, RenderGlobals, TemplateType},
     read_toml::read_toml,
+    search::{SearchIndex, SearchResult},
     tags::layout,
     util::path_to_slash,
     ArchivalError, BuildOptions, FieldConfig, FileSystemAPI, ObjectMap,
@@ -53,6 +55,8 @@ pub enum BuildError {
     TemplateRenderError(String, String, String),
     #[error("page {0} failed rendering:\n{1}")]
     PageRenderError(String, String),
+    #[error("object {0} is exposed to templates as `data`, which the site's data files also use; rename the object or remove the data files")]
+    DataShadowsObject(String),
 }
 
 /// Parsing liquid is expensive (a fixed cost to compile partials into a
@@ -99,6 +103,8 @@ pub struct Site {
     cache_generation: AtomicU64,
     #[serde(skip)]
     parser_cache: RwLock<Option<ParserCache>>,
+    #[serde(skip)]
+    search_index: RwLock<Option<SearchIndex>>,
 }
 
 // Site is shared across threads (e.g. the dev server); keep it Send + Sync
@@ -128,14 +134,6 @@ impl std::fmt::Display for Site {
     }
 }
 
-fn get_order(obj: &Object) -> String {
-    if let Some(order) = obj.order {
-        format!("{:0>10}", order)
-    } else {
-        obj.filename.to_owned()
-    }
-}
-
 impl Site {
     #[instrument(skip(fs))]
     pub fn load(fs: &impl FileSystemAPI, upload_prefix: Option<&str>) -> Result<Site> {
@@ -194,6 +192,7 @@ impl Site {
             build_cache: RwLock::new(HashMap::new()),
             cache_generation: AtomicU64::new(0),
             parser_cache: RwLock::new(None),
+            search_index: RwLock::new(None),
         })
     }
 
@@ -207,7 +206,7 @@ impl Site {
     ) -> Result<std::sync::Arc<liquid::Parser>> {
         let _span = trace_span!("get_or_build_parser").entered();
         let (source, partials_hash) =
-            liquid_parser::partials_hash(Some(pages_dir), layout_dir, fs)?;
+            liquid_parser::partials_hash(Some(pages_dir), layout_dir, &self.manifest.plugins, fs)?;
         if let Some(cache) = self.parser_cache.read().unwrap().as_ref() {
             if cache.partials_hash == partials_hash {
                 return Ok(cache.parser.clone());
@@ -351,10 +350,7 @@ impl Site {
 
     #[instrument(skip(fs))]
     pub fn get_objects<T: FileSystemAPI>(&self, fs: &T) -> Result<ObjectMap> {
-        self.get_objects_sorted(
-            fs,
-            Some(|a: &_, b: &_| get_order(a).partial_cmp(&get_order(b)).unwrap()),
-        )
+        self.get_objects_sorted(fs, None::<fn(&Object, &Object) -> Ordering>)
     }
     #[instrument(skip(fs))]
     pub fn get_rendered_objects<T: FileSystemAPI>(&self, fs: &T) -> Result<RenderedObjectMap> {
@@ -388,6 +384,11 @@ impl Site {
         #[cfg(feature = "verbose-logging")]
         debug!("invalidate {}", file.display());
         self.obj_cache.write().unwrap().remove(file);
+        if self.object_name_for_path(file).is_some() {
+            if let Some(index) = self.search_index.write().unwrap().as_mut() {
+                index.invalidate(file);
+            }
+        }
         // Increment cache generation to ensure build_id changes after invalidation.
         // We don't clear build_cache here because it's needed for file cleanup
         // in site.build() - it tracks which output files need to be deleted.
@@ -395,6 +396,22 @@ impl Site {
             .fetch_add(1, atomic::Ordering::Relaxed);
     }
 
+    /// The object read from `file`, if it has been read since it last changed.
+    pub(crate) fn loaded_object(&self, file: &Path) -> Option<Object> {
+        self.obj_cache.read().unwrap().get(file).cloned()
+    }
+
+    /// The hash of each file written by the last build.
+    pub(crate) fn build_hashes(&self) -> HashMap<PathBuf, u64> {
+        self.build_cache.read().unwrap().clone()
+    }
+
+    /// Drops a file from the object cache only, leaving the search index and
+    /// build id alone; see [`crate::Archival::send_events`].
+    pub(crate) fn evict_object(&self, file: &Path) {
+        self.obj_cache.write().unwrap().remove(file);
+    }
+
     #[instrument(skip(fs, modify))]
     pub fn modify_manifest<T: FileSystemAPI>(
         &mut self,
@@ -444,6 +461,8 @@ impl Site {
         }
     }
 
+    /// Lists every object, each type in the default order its definition
+    /// declares. A `sort` reorders them, with ties left in the default order.
     #[instrument(skip(fs, sort))]
     pub fn get_objects_sorted<T: FileSystemAPI>(
         &self,
@@ -476,9 +495,9 @@ impl Site {
                         }
                     }
                 }
-                // Sort objects by order key
+                trace_span!("sort objects");
+                object_def.sort_objects(&mut objects);
                 if let Some(sort) = &sort {
-                    trace_span!("sort objects");
                     objects.sort_by(sort);
                 }
                 all_objects.insert(object_name.clone(), ObjectEntry::from_vec(objects));
@@ -513,6 +532,17 @@ impl Site {
         cache: &mut HashMap<PathBuf, Object>,
         fs: &T,
     ) -> Result<Object> {
+        self.cached_object(path, object_def, cache, fs).cloned()
+    }
+
+    /// Like [`Self::object_for_path`], but borrows the object from the cache.
+    fn cached_object<'c, T: FileSystemAPI>(
+        &self,
+        path: &Path,
+        object_def: &ObjectDefinition,
+        cache: &'c mut HashMap<PathBuf, Object>,
+        fs: &T,
+    ) -> Result<&'c Object> {
         let ext = path
             .extension()
             .ok_or_else(|| InvalidFileError::MissingFileExtension(path.to_path_buf()))?;
@@ -523,9 +553,7 @@ impl Site {
             )
             .into());
         }
-        if let Some(o) = cache.get(path) {
-            Ok(o.clone())
-        } else {
+        if !cache.contains_key(path) {
             #[cfg(feature = "verbose-logging")]
             debug!("parsing {}", path.display());
             let obj_table = read_toml(path, fs)?;
@@ -538,9 +566,131 @@ impl Site {
                 // objects with invalid unset keys
                 true,
             )?;
-            cache.insert(path.to_path_buf(), o.clone());
-            Ok(o)
+            cache.insert(path.to_path_buf(), o);
+        }
+        Ok(&cache[path])
+    }
+
+    /// Calls `f` with every object named `object_name`, borrowed from the
+    /// object cache rather than cloned. Invalid files are skipped, as they are
+    /// by [`Self::get_objects`]. The objects are in no particular order.
+    #[instrument(skip(fs, f))]
+    pub fn with_objects<T: FileSystemAPI, R>(
+        &self,
+        object_name: &str,
+        fs: &T,
+        f: impl FnOnce(Vec<&Object>) -> R,
+    ) -> Result<R> {
+        let object_def = self
+            .object_definitions
+            .get(object_name)
+            .ok_or_else(|| InvalidFileError::UnknownObject(object_name.to_string()))?;
+        let objects_dir = &self.manifest.objects_dir;
+        let object_files_path = objects_dir.join(object_name);
+        let paths = if fs.is_dir(&object_files_path)? {
+            fs.walk_dir(&object_files_path, false)?
+                .map(|file| object_files_path.join(file))
+                .collect()
+        } else {
+            vec![objects_dir.join(format!("{}.toml", object_name))]
+        };
+        let mut cache = self.obj_cache.write().unwrap();
+        let mut loaded = vec![];
+        for path in paths {
+            if !fs.exists(&path)? {
+                continue;
+            }
+            match self.cached_object(&path, object_def, &mut cache, fs) {
+                Ok(_) => loaded.push(path),
+                Err(err) => error!("Invalid file {:?}: {}", path, err),
+            }
+        }
+        Ok(f(loaded.iter().map(|path| &cache[path]).collect()))
+    }
+
+    /// The files holding the objects named `name`: one per instance, or the
+    /// single file of a root object.
+    pub(crate) fn object_files<T: FileSystemAPI>(
+        &self,
+        name: &str,
+        fs: &T,
+    ) -> Result<Vec<PathBuf>> {
+        let objects_dir = &self.manifest.objects_dir;
+        let list_dir = objects_dir.join(name);
+        let root_file = objects_dir.join(format!("{name}.toml"));
+        Ok(if fs.is_dir(&list_dir)? {
+            fs.walk_dir(&list_dir, false)?
+                .map(|file| list_dir.join(file))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+                .collect()
+        } else if fs.exists(&root_file)? {
+            vec![root_file]
+        } else {
+            vec![]
+        })
+    }
+
+    /// The name of the object an object file holds, if `path` is one.
+    pub(crate) fn object_name_for_path(&self, path: &Path) -> Option<&str> {
+        if path.extension().is_none_or(|ext| ext != "toml") {
+            return None;
         }
+        let relative = path.strip_prefix(&self.manifest.objects_dir).ok()?;
+        let mut components = relative.components();
+        let first = Path::new(components.next()?.as_os_str());
+        let name = match components.next() {
+            Some(_) => first.to_str()?,
+            None => first.file_stem()?.to_str()?,
+        };
+        self.object_definitions
+            .get_key_value(name)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Reads an object file into the search index.
+    fn index_object<T: FileSystemAPI>(
+        &self,
+        path: &Path,
+        index: &mut SearchIndex,
+        fs: &T,
+    ) -> Result<()> {
+        let Some(object_def) = self
+            .object_name_for_path(path)
+            .and_then(|name| self.object_definitions.get(name))
+        else {
+            return Ok(());
+        };
+        let mut cache = self.obj_cache.write().unwrap();
+        let object = self.cached_object(path, object_def, &mut cache, fs)?;
+        index.insert(path, object);
+        Ok(())
+    }
+
+    /// Searches the string and markdown fields of every object, see
+    /// [`crate::search`]. Builds the search index on the first call.
+    #[instrument(skip(fs))]
+    pub fn search<T: FileSystemAPI>(&self, query: &str, fs: &T) -> Result<Vec<SearchResult>> {
+        let mut index = self.search_index.write().unwrap();
+        let paths = match index.as_mut() {
+            Some(index) => index.take_stale(),
+            None => {
+                let mut paths = vec![];
+                for name in self.object_definitions.keys() {
+                    paths.extend(self.object_files(name, fs)?);
+                }
+                paths
+            }
+        };
+        let index = index.get_or_insert_with(SearchIndex::default);
+        for path in paths {
+            if !fs.exists(&path)? {
+                continue;
+            }
+            if let Err(err) = self.index_object(&path, index, fs) {
+                error!("Invalid file {:?}: {}", path, err);
+            }
+        }
+        Ok(index.search(query))
     }
 
     #[instrument(skip(fs))]
@@ -603,13 +753,18 @@ impl Site {
             layout_dir,
             pages_dir,
             build_dir,
+            data_dir,
             site_url,
             ..
         } = &self.manifest;
 
         let globals = RenderGlobals {
             site_url: site_url.as_ref().map(|v| v.into()).unwrap_or_default(),
+            data: data_files::load(data_dir, fs)?,
         };
+        if let Some(name) = globals.shadowed_object(&self.object_definitions) {
+            return Err(BuildError::DataShadowsObject(name.to_string()).into());
+        }
 
         let mut built_hashes = HashMap::new();
 
@@ -633,6 +788,18 @@ impl Site {
         }
 
         let all_objects = self.get_objects(fs)?;
+        // Objects are saved as they're filled in, so constraints they don't
+        // meet yet are reported rather than failing the build.
+        for (name, objects) in &all_objects {
+            let Some(definition) = self.object_definitions.get(name) else {
+                continue;
+            };
+            for object in objects {
+                for (path, violation) in definition.constraint_violations(&object.values) {
+                    warn!("{name}/{}: `{path}` {violation}", object.filename);
+                }
+            }
+        }
 
         // for (n, os) in &all_objects {
         //     debug!("{}", n);
@@ -1209,6 +1376,34 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn data_files_cannot_shadow_an_object() -> Result<()> {
+        let mut fs = MemoryFileSystem::default();
+        fs.write_str(
+            Path::new(OBJECT_DEFINITION_FILE_NAME),
+            "[data]\nname = \"string\"\n".to_string(),
+        )?;
+        fs.write_str(
+            Path::new("objects/data/a.toml"),
+            "name = \"A\"\n".to_string(),
+        )?;
+        fs.write_str(Path::new("pages/index.liquid"), "{{ data }}".to_string())?;
+        let site = Site::load(&fs, Some("test"))?;
+        // Without data files, the name is the object's.
+        site.build(&mut fs, BuildOptions::default())?;
+
+        fs.write_str(Path::new("data/site.json"), "{\"a\": 1}".to_string())?;
+        let err = site.build(&mut fs, BuildOptions::default()).unwrap_err();
+        assert!(
+            matches!(
+                err.downcast_ref::<BuildError>(),
+                Some(BuildError::DataShadowsObject(name)) if name == "data"
+            ),
+            "{err}"
+        );
+        Ok(())
+    }
+
     #[test]
     fn object_paths_are_url_paths() {
         let object = Object {
@@ -103,12 +103,51 @@ impl OutputContext {
         Ok(template)
     }
 
+    /// Parses a liquid condition (anything `{% if %}` accepts) into a template
+    /// that renders `true` when the condition holds. Used by filters that take
+    /// an expression, e.g. `where_exp`.
+    ///
+    /// The expression is checked to be a condition and nothing else before it
+    /// is wrapped, so that it cannot close the `if` tag and add markup of its
+    /// own.
+    pub(crate) fn condition(&self, expression: &str) -> Result<Arc<runtime::Template>> {
+        if let Some(markup) = markup_outside_strings(expression) {
+            return Err(Error::with_msg("Expected a condition, found markup")
+                .context("markup", markup.to_string()));
+        }
+        self.nested_template(&format!("{{% if {expression} %}}true{{% endif %}}"))
+    }
+
     #[cfg(test)]
     pub(crate) fn nested_len(&self) -> usize {
         self.nested.read().unwrap().len()
     }
 }
 
+/// The first liquid delimiter in `expression` that is not inside a string
+/// literal. Liquid strings have no escapes, so a quote always ends the string
+/// it opened.
+fn markup_outside_strings(expression: &str) -> Option<&str> {
+    let mut quote = None;
+    for (at, c) in expression.char_indices() {
+        match quote {
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None => {
+                let rest = &expression[at..];
+                if let Some(delimiter) = ["{%", "%}", "{{", "}}"]
+                    .into_iter()
+                    .find(|delimiter| rest.starts_with(delimiter))
+                {
+                    return Some(delimiter);
+                }
+            }
+        }
+    }
+    None
+}
+
 #[derive(Clone, Debug)]
 pub(crate) struct OutputTag {
     ctx: Arc<OutputContext>,
@@ -151,6 +151,8 @@ fn field_type(field: &FieldType) -> String {
             format!("{} | null", FILE_TYPE)
         }
         FieldType::Meta => format!("{} | null", META_TYPE),
+        // Unset lists render as empty ones, and items are never null.
+        FieldType::List(item) => format!("Array<{}>", field_type(item).trim_end_matches(" | null")),
         FieldType::Oneof(options) => {
             if options.is_empty() {
                 return "null".to_string();
@@ -202,7 +204,14 @@ fn emit_object(
             members.push(doc_comment(description, "  "));
         }
         members.push(format!(
-            "  {}: {};",
+            "  {}{}: {};",
+            // Computed fields are derived from the others, see
+            // `crate::object::computed`.
+            if field_definition.is_computed() {
+                "readonly "
+            } else {
+                ""
+            },
             property_name(field),
             field_type(&field_definition.r#type)
         ));
@@ -361,6 +370,23 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn computed_fields_are_readonly() -> Result<()> {
+        let out = generate(
+            r#"
+            [posts]
+            title = "string"
+            slug = { computed = "title | slugify" }
+            year = { computed = "date | date: '%Y'", type = "number" }
+            "#,
+            &[],
+        )?;
+        assert!(out.contains("  title: string | null;"), "{}", out);
+        assert!(out.contains("  readonly slug: string | null;"), "{}", out);
+        assert!(out.contains("  readonly year: number | null;"), "{}", out);
+        Ok(())
+    }
+
     #[test]
     fn injects_path_and_order_on_top_level_objects_only() -> Result<()> {
         let out = generate(
@@ -418,6 +444,23 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn lists_become_arrays() -> Result<()> {
+        let out = generate(
+            r#"
+            [posts]
+            tags = ["string"]
+            moods = [["happy", "sad"]]
+            gallery = ["image"]
+            "#,
+            &[],
+        )?;
+        assert!(out.contains("tags: Array<string>;"), "{}", out);
+        assert!(out.contains(r#"moods: Array<"happy" | "sad">;"#), "{}", out);
+        assert!(out.contains("gallery: Array<ArchivalFile>;"), "{}", out);
+        Ok(())
+    }
+
     #[test]
     fn files_and_meta() -> Result<()> {
         let out = generate(
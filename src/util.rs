@@ -28,6 +28,22 @@ pub fn path_to_native(path: &Path) -> Cow<'_, Path> {
     }
 }
 
+/// A url-safe version of `text`: lowercased, with every run of characters that
+/// are not letters or numbers collapsed into a single `-`.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let trimmed = slug.trim_end_matches('-').len();
+    slug.truncate(trimmed);
+    slug
+}
+
 pub fn integer_decode(val: f64) -> (u64, i16, i8) {
     let bits: u64 = val.to_bits();
     let sign: i8 = if bits >> 63 == 0 { 1 } else { -1 };
@@ -52,6 +68,13 @@ mod tests {
         assert_eq!(path_to_slash(&native), "artist/tormenta-rey");
     }
 
+    #[test]
+    fn slugs_collapse_punctuation_and_whitespace() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("  --Ünïcode  stays__ok-- "), "ünïcode-stays-ok");
+        assert_eq!(slugify("!!!"), "");
+    }
+
     #[test]
     fn paths_convert_to_native_separators() {
         let native: PathBuf = ["pages", "post", "single.liquid"].iter().collect();
@@ -1,6 +1,7 @@
 use crate::{
     fields::{meta::Meta, FieldType, FieldValue, MetaValue, ObjectValues},
     object::Object,
+    object_definition::FieldDefinition,
     ObjectDefinition,
 };
 use liquid::ValueView;
@@ -165,6 +166,9 @@ impl ValuePath {
         }
         Self(vpv)
     }
+    pub(crate) fn components(&self) -> &[ValuePathComponent] {
+        &self.0
+    }
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
@@ -220,7 +224,7 @@ impl ValuePath {
         ValuePath(self.0[1..].to_vec())
     }
     pub fn without_last(&self) -> ValuePath {
-        ValuePath(self.0[..1].to_vec())
+        ValuePath(self.0[..self.0.len().saturating_sub(1)].to_vec())
     }
 
     pub fn pop(&mut self) -> Option<ValuePathComponent> {
@@ -334,6 +338,15 @@ impl ValuePath {
                             }
                         }
                     }
+                } else if let Some(FieldValue::List(items)) = last_val {
+                    // List items are values themselves, so are the end of
+                    // the path.
+                    if let ValuePathComponent::Index(index) = cmp {
+                        if i_path.next().is_none() {
+                            return items.get(index);
+                        }
+                    }
+                    return None;
                 }
             }
             break;
@@ -403,11 +416,20 @@ impl ValuePath {
         &self,
         def: &'a ObjectDefinition,
     ) -> Result<&'a FieldType, ValuePathError> {
+        self.get_field(def).map(|field| &field.r#type)
+    }
+
+    /// Like [`Self::get_field_definition`], but returns the whole field rather
+    /// than just its type.
+    pub fn get_field<'a>(
+        &self,
+        def: &'a ObjectDefinition,
+    ) -> Result<&'a FieldDefinition, ValuePathError> {
         let mut current_def = def;
         for cmp in self.0.iter() {
             match cmp {
                 ValuePathComponent::Key(k) => {
-                    if let Some(field) = current_def.field_type(k) {
+                    if let Some(field) = current_def.fields.get(k) {
                         return Ok(field);
                     } else if let Some(child) = current_def.children.get(k) {
                         current_def = child;
@@ -559,6 +581,11 @@ impl ValuePath {
                         return ValuePath::from(path).set_in_tree(child, value);
                     }
                 }
+            } else if let Some(FieldValue::List(items)) = child.get_mut(&key) {
+                if let [ValuePathComponent::Index(idx)] = path[..] {
+                    return set_list_item(items, idx, value)
+                        .ok_or_else(|| ValuePathError::NotFound(self.clone(), key));
+                }
             }
         }
         Err(ValuePathError::NotFound(
@@ -607,12 +634,85 @@ impl ValuePath {
                         ValuePath::from(i_path.collect::<Vec<ValuePathComponent>>())
                             .set_in_tree(child, value)?;
                     }
+                } else if let Some(FieldValue::List(items)) = last_val {
+                    if let (ValuePathComponent::Index(index), 0) = (cmp, i_path.len()) {
+                        set_list_item(items, index, value).ok_or_else(|| {
+                            ValuePathError::NotFound(self.clone(), format!("{:?}", object))
+                        })?;
+                    }
                 }
             }
             break;
         }
         Ok(())
     }
+
+    /// Calls `modify` with the list this path points to, creating it first if
+    /// the object doesn't have one yet. The path must point at a list field,
+    /// either at the root or inside a child.
+    pub fn modify_list<R>(
+        &self,
+        object: &mut Object,
+        definition: &ObjectDefinition,
+        modify: impl FnOnce(&mut Vec<FieldValue>) -> R,
+    ) -> Result<R, ValuePathError> {
+        let filename = object.filename.clone();
+        let not_found = || ValuePathError::NotFound(self.clone(), filename.clone());
+        if self.get_field_definition(definition)?.list_item().is_none() {
+            return Err(ValuePathError::NotFound(
+                self.clone(),
+                format!("{:?}", definition),
+            ));
+        }
+        let mut parent_path = self.clone();
+        let Some(ValuePathComponent::Key(key)) = parent_path.pop() else {
+            return Err(not_found());
+        };
+        let mut values = &mut object.values;
+        let mut i_path = parent_path.0.into_iter();
+        while let Some(cmp) = i_path.next() {
+            let (ValuePathComponent::Key(k), Some(ValuePathComponent::Index(index))) =
+                (cmp, i_path.next())
+            else {
+                return Err(not_found());
+            };
+            match values.get_mut(&k) {
+                Some(FieldValue::Objects(children)) if index < children.len() => {
+                    values = &mut children[index];
+                }
+                _ => return Err(not_found()),
+            }
+        }
+        let list = values
+            .entry(key)
+            .or_insert_with(|| FieldValue::List(vec![]));
+        if matches!(list, FieldValue::Null) {
+            *list = FieldValue::List(vec![]);
+        }
+        match list {
+            FieldValue::List(items) => Ok(modify(items)),
+            _ => Err(not_found()),
+        }
+    }
+}
+
+/// Replaces, or with no value removes, an item of a list. Returns `None` when
+/// there's no item at `index`.
+fn set_list_item(
+    items: &mut Vec<FieldValue>,
+    index: usize,
+    value: Option<FieldValue>,
+) -> Option<()> {
+    if index >= items.len() {
+        return None;
+    }
+    match value {
+        Some(value) => items[index] = value,
+        None => {
+            items.remove(index);
+        }
+    }
+    Some(())
 }
 
 impl From<&str> for ValuePath {
@@ -770,4 +870,11 @@ pub mod tests {
             serde_json::from_str(&json_string).expect("deserialization failed.");
         assert_eq!(holder, deserialized);
     }
+
+    #[test]
+    fn without_last() {
+        let path = ValuePath::from_string("children.1.name");
+        assert_eq!(path.without_last(), ValuePath::from_string("children.1"));
+        assert_eq!(ValuePath::empty().without_last(), ValuePath::empty());
+    }
 }